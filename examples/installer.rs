@@ -1,17 +1,22 @@
+//! A small installer written against the typed standard library.
+//!
+//! This is ordinary Rust (`cargo check -p bashrs-std --examples`) and
+//! transpiles with `bashrs build examples/installer.rs -o install.sh`.
+
+use bashrs_std::{fs, log};
+
 fn main() {
     let prefix = "/usr/local";
     let version = "1.0.0";
-    
-    echo("Installing version");
-    echo(version);
-    
-    mkdir(prefix);
-    
-    let install_path = concat(prefix, "/bin/tool");
-    touch(install_path);
-}
 
-fn echo(msg: &str) {}
-fn mkdir(path: &str) {}  
-fn touch(path: &str) {}
-fn concat(a: &str, b: &str) -> &str { a }
\ No newline at end of file
+    log::info("Installing version");
+    log::info(version);
+
+    fs::mkdir_p(prefix);
+
+    let bin_dir = "/usr/local/bin";
+    fs::mkdir_p(bin_dir);
+    if fs::dir_exists(bin_dir) {
+        log::info("Installation directory ready");
+    }
+}
@@ -15,7 +15,7 @@ fn main() -> Result<()> {
 
     // Generate Rust code to embed the runtime
     let escaped_runtime = minified.replace('\\', "\\\\").replace('"', "\\\"");
-    let output = format!(
+    let mut output = format!(
         r#"/// Embedded Rash runtime library
 pub const RUNTIME_LIBRARY: &str = "{escaped_runtime}";
 
@@ -25,6 +25,22 @@ pub fn get_runtime() -> &'static str {{
 "#
     );
 
+    // Every function on its own, so that scripts carry only the ones they call
+    let helpers = split_functions(&runtime_content);
+    output.push_str("\n/// The functions of the runtime library, in library order\n");
+    output.push_str("pub const HELPERS: &[Helper] = &[\n");
+    for (name, definition) in &helpers {
+        let dependencies: Vec<&str> = helpers
+            .iter()
+            .map(|(other, _)| other.as_str())
+            .filter(|other| other != name && calls(definition, other))
+            .collect();
+        output.push_str(&format!(
+            "    Helper {{ name: {name:?}, definition: {definition:?}, dependencies: &{dependencies:?} }},\n"
+        ));
+    }
+    output.push_str("];\n");
+
     // Write to output file
     let out_dir = std::env::var("OUT_DIR")?;
     let dest_path = Path::new(&out_dir).join("runtime.rs");
@@ -36,6 +52,41 @@ pub fn get_runtime() -> &'static str {{
     Ok(())
 }
 
+/// The `rash_*` function definitions of the library, with their names
+fn split_functions(content: &str) -> Vec<(String, String)> {
+    let mut functions = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+    for line in content.lines() {
+        match &mut current {
+            None => {
+                if let Some(name) = line.strip_suffix("() {") {
+                    if name.starts_with("rash_") && !name.contains(char::is_whitespace) {
+                        current = Some((name.to_string(), vec![line]));
+                    }
+                }
+            }
+            Some((_, lines)) => {
+                lines.push(line);
+                if line == "}" {
+                    let (name, lines) = current.take().expect("function");
+                    functions.push((name, lines.join("\n")));
+                }
+            }
+        }
+    }
+    functions
+}
+
+/// Whether `definition` calls the function `name`, i.e. mentions it as a word
+fn calls(definition: &str, name: &str) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    definition.match_indices(name).any(|(start, _)| {
+        let before = definition[..start].chars().next_back();
+        let after = definition[start + name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
 #[allow(dead_code)]
 fn validate_shell_syntax(content: &str) -> Result<()> {
     let mut validator = SyntaxValidator::new();
@@ -1 +1,46 @@
+//! The shell runtime library of Rash-generated scripts
+//!
+//! `src/lib.sh` defines the `rash_*` functions. The build script embeds it
+//! whole, as [`RUNTIME_LIBRARY`], and function by function, as [`HELPERS`],
+//! so that the transpiler can give each script only the functions it calls.
+
+/// A function of the runtime library
+#[derive(Debug)]
+pub struct Helper {
+    pub name: &'static str,
+    /// The definition as written in `lib.sh`
+    pub definition: &'static str,
+    /// The other library functions its body calls
+    pub dependencies: &'static [&'static str],
+}
+
 include!(concat!(env!("OUT_DIR"), "/runtime.rs"));
+
+/// The library function named `name`, if any
+pub fn helper(name: &str) -> Option<&'static Helper> {
+    HELPERS.iter().find(|helper| helper.name == name)
+}
+
+/// The library functions among `names` together with every function they
+/// call, directly or not, in library order. Other names are ignored.
+pub fn helpers_for<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<&'static Helper> {
+    let mut needed = std::collections::HashSet::new();
+    let mut pending: Vec<&'static Helper> = names.into_iter().filter_map(helper).collect();
+    while let Some(helper) = pending.pop() {
+        if needed.insert(helper.name) {
+            pending.extend(
+                helper
+                    .dependencies
+                    .iter()
+                    .filter_map(|name| self::helper(name)),
+            );
+        }
+    }
+    HELPERS
+        .iter()
+        .filter(|helper| needed.contains(helper.name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
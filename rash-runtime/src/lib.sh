@@ -1,18 +1,22 @@
 #!/bin/sh
 # Rash Runtime Library v1.0.0
 # POSIX-compliant shell functions for Rash-generated scripts
+#
+# Strict POSIX subset: no `local` (arguments stay in $1, $2, ...; other
+# scratch variables are prefixed `_rash_`), and `printf` rather than `echo`
+# for anything that is not a literal.
 
 # Error handling
 rash_require() {
     if ! "$@"; then
-        echo "FATAL: Requirement failed: $*" >&2
+        printf 'FATAL: Requirement failed: %s\n' "$*" >&2
         exit 1
     fi
 }
 
 rash_assert() {
     if ! "$@"; then
-        echo "ASSERTION FAILED: $*" >&2
+        printf 'ASSERTION FAILED: %s\n' "$*" >&2
         exit 2
     fi
 }
@@ -26,6 +30,10 @@ rash_dir_exists() {
     test -d "$1"
 }
 
+rash_mkdir_p() {
+    mkdir -p "$1"
+}
+
 rash_is_writable() {
     test -w "$1"
 }
@@ -35,68 +43,76 @@ rash_is_readable() {
 }
 
 rash_atomic_write() {
-    local content="$1"
-    local target="$2"
-    local temp="${target}.tmp.$$"
-    
-    echo "$content" > "$temp"
-    mv "$temp" "$target"
+    # $1: content, $2: target
+    printf '%s\n' "$1" > "$2.tmp.$$"
+    mv "$2.tmp.$$" "$2"
 }
 
 # Network operations with verification
 rash_download_verified() {
-    local url="$1"
-    local dst="$2"
-    local checksum="$3"
-    
+    # $1: URL, $2: destination, $3: SHA-256 checksum
     # Try curl first, then wget
     if command -v curl >/dev/null 2>&1; then
-        curl -fsSL --proto '=https' --tlsv1.2 "$url" -o "$dst"
+        curl -fsSL --proto '=https' --tlsv1.2 "$1" -o "$2"
     elif command -v wget >/dev/null 2>&1; then
-        wget -qO "$dst" "$url"
+        wget -qO "$2" "$1"
     else
         echo "FATAL: Neither curl nor wget found" >&2
         return 1
     fi
-    
+
     # Verify checksum
     if command -v sha256sum >/dev/null 2>&1; then
-        echo "$checksum  $dst" | sha256sum -c >/dev/null
+        printf '%s  %s\n' "$3" "$2" | sha256sum -c >/dev/null
     elif command -v shasum >/dev/null 2>&1; then
-        echo "$checksum  $dst" | shasum -a 256 -c >/dev/null
+        printf '%s  %s\n' "$3" "$2" | shasum -a 256 -c >/dev/null
     else
         echo "FATAL: No checksum utility found" >&2
         return 1
     fi
 }
 
+# Lists
+# Print the arguments as one word of quoted elements, which
+# `eval "set -- $list"` unpacks
+rash_list() {
+    for _rash_word in "$@"; do
+        _rash_quoted=
+        while :; do
+            case "$_rash_word" in
+                (*\'*)
+                    _rash_quoted="$_rash_quoted${_rash_word%%\'*}'\\''"
+                    _rash_word="${_rash_word#*\'}"
+                    ;;
+                (*)
+                    break
+                    ;;
+            esac
+        done
+        printf "'%s' " "$_rash_quoted$_rash_word"
+    done
+}
+
 # Archive operations
 rash_extract_tar() {
-    local archive="$1"
-    local dest="$2"
-    
+    # $1: archive, $2: destination
     # Security: extract with restricted permissions
-    tar -xf "$archive" -C "$dest" --no-same-owner --no-same-permissions
+    tar -xf "$1" -C "$2" --no-same-owner --no-same-permissions
 }
 
 rash_extract_tar_gz() {
-    local archive="$1"
-    local dest="$2"
-    
+    # $1: archive, $2: destination
     if command -v gzip >/dev/null 2>&1; then
-        gzip -dc "$archive" | tar -xf - -C "$dest" --no-same-owner --no-same-permissions
+        gzip -dc "$1" | tar -xf - -C "$2" --no-same-owner --no-same-permissions
     else
         # Try with built-in gz support
-        tar -xzf "$archive" -C "$dest" --no-same-owner --no-same-permissions
+        tar -xzf "$1" -C "$2" --no-same-owner --no-same-permissions
     fi
 }
 
 # Platform detection
 rash_detect_arch() {
-    local arch
-    arch="$(uname -m)"
-    
-    case "$arch" in
+    case "$(uname -m)" in
         x86_64|amd64)
             echo "x86_64"
             ;;
@@ -117,10 +133,7 @@ rash_detect_arch() {
 }
 
 rash_detect_os() {
-    local os
-    os="$(uname -s)"
-    
-    case "$os" in
+    case "$(uname -s)" in
         Linux)
             echo "linux"
             ;;
@@ -144,28 +157,27 @@ rash_detect_os() {
 }
 
 rash_detect_platform() {
-    local os arch
-    os="$(rash_detect_os)" || return 1
-    arch="$(rash_detect_arch)" || return 1
-    echo "${arch}-${os}"
+    _rash_os="$(rash_detect_os)" || return 1
+    _rash_arch="$(rash_detect_arch)" || return 1
+    printf '%s-%s\n' "$_rash_arch" "$_rash_os"
 }
 
 # Logging
 rash_log_info() {
-    echo "INFO: $*" >&2
+    printf 'INFO: %s\n' "$*" >&2
 }
 
 rash_log_warn() {
-    echo "WARN: $*" >&2
+    printf 'WARN: %s\n' "$*" >&2
 }
 
 rash_log_error() {
-    echo "ERROR: $*" >&2
+    printf 'ERROR: %s\n' "$*" >&2
 }
 
 rash_log_debug() {
     if [ "${RASH_DEBUG:-}" = "1" ]; then
-        echo "DEBUG: $*" >&2
+        printf 'DEBUG: %s\n' "$*" >&2
     fi
 }
 
@@ -175,40 +187,34 @@ rash_has_command() {
 }
 
 rash_require_command() {
-    local cmd="$1"
-    if ! rash_has_command "$cmd"; then
-        echo "FATAL: Required command '$cmd' not found" >&2
+    if ! rash_has_command "$1"; then
+        printf "FATAL: Required command '%s' not found\n" "$1" >&2
         exit 1
     fi
 }
 
 # Temporary directory management
 rash_mktemp_dir() {
-    local template="${1:-rash.XXXXXX}"
-    local tmpdir="${TMPDIR:-/tmp}"
-    
+    # $1: template, rash.XXXXXX by default
+    set -- "${TMPDIR:-/tmp}/${1:-rash.XXXXXX}"
     if command -v mktemp >/dev/null 2>&1; then
-        mktemp -d "$tmpdir/$template"
+        mktemp -d "$1"
     else
         # Fallback for systems without mktemp
-        local dir="$tmpdir/$template.$$"
-        mkdir -p "$dir"
-        echo "$dir"
+        mkdir -p "$1.$$"
+        printf '%s\n' "$1.$$"
     fi
 }
 
 # Permission management
 rash_chmod() {
-    local perms="$1"
-    local file="$2"
-    
     # Validate permissions format (octal)
-    case "$perms" in
-        [0-7][0-7][0-7])
-            chmod "$perms" "$file"
+    case "$1" in
+        ([0-7][0-7][0-7])
+            chmod "$1" "$2"
             ;;
-        *)
-            echo "ERROR: Invalid permission format: $perms" >&2
+        (*)
+            printf 'ERROR: Invalid permission format: %s\n' "$1" >&2
             return 1
             ;;
     esac
@@ -216,11 +222,9 @@ rash_chmod() {
 
 # String operations
 rash_str_contains() {
-    local string="$1"
-    local substring="$2"
-    
-    case "$string" in
-        *"$substring"*)
+    # $1: string, $2: substring
+    case "$1" in
+        *"$2"*)
             return 0
             ;;
         *)
@@ -230,11 +234,9 @@ rash_str_contains() {
 }
 
 rash_str_starts_with() {
-    local string="$1"
-    local prefix="$2"
-    
-    case "$string" in
-        "$prefix"*)
+    # $1: string, $2: prefix
+    case "$1" in
+        "$2"*)
             return 0
             ;;
         *)
@@ -244,11 +246,9 @@ rash_str_starts_with() {
 }
 
 rash_str_ends_with() {
-    local string="$1"
-    local suffix="$2"
-    
-    case "$string" in
-        *"$suffix")
+    # $1: string, $2: suffix
+    case "$1" in
+        *"$2")
             return 0
             ;;
         *)
@@ -257,22 +257,46 @@ rash_str_ends_with() {
     esac
 }
 
+# Sanitizers
+# Print the argument if it is safe to remove recursively: a non-empty path
+# of letters, digits and `_./@%+=:,-` that is not the root, not an option
+# and has no `..` component. Otherwise stop the script.
+rash_sanitize_path() {
+    case "$1" in
+        ("" | -* | / | //* | /. | /./* | .. | ../* | */.. | */../* | *[!A-Za-z0-9_./@%+=:,-]*)
+            printf 'FATAL: Unsafe path: %s\n' "$1" >&2
+            exit 1
+            ;;
+    esac
+    printf '%s\n' "$1"
+}
+
+# Print the argument if it is safe as a command name or in code the shell
+# evaluates: a non-empty word of letters, digits and `_.+-` that is not an
+# option. Otherwise stop the script.
+rash_sanitize_word() {
+    case "$1" in
+        ("" | -* | *[!A-Za-z0-9_.+-]*)
+            printf 'FATAL: Unsafe word: %s\n' "$1" >&2
+            exit 1
+            ;;
+    esac
+    printf '%s\n' "$1"
+}
+
 # Version comparison
 rash_version_compare() {
-    local version1="$1"
-    local operator="$2"
-    local version2="$3"
-    
+    # $1: version, $2: operator, $3: version
     # Simple version comparison - can be enhanced
-    case "$operator" in
+    case "$2" in
         "="|"==")
-            [ "$version1" = "$version2" ]
+            [ "$1" = "$3" ]
             ;;
         "!="|"<>")
-            [ "$version1" != "$version2" ]
+            [ "$1" != "$3" ]
             ;;
         *)
-            echo "ERROR: Unsupported version operator: $operator" >&2
+            printf 'ERROR: Unsupported version operator: %s\n' "$2" >&2
             return 1
             ;;
     esac
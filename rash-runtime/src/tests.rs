@@ -0,0 +1,280 @@
+use super::*;
+
+#[test]
+fn test_every_function_is_split_out() {
+    let defined = RUNTIME_LIBRARY
+        .lines()
+        .filter(|line| line.starts_with("rash_") && line.ends_with("() {"))
+        .count();
+    assert_eq!(HELPERS.len(), defined);
+    for helper in HELPERS {
+        assert!(helper
+            .definition
+            .starts_with(&format!("{}() {{\n", helper.name)));
+        assert!(helper.definition.ends_with("\n}"));
+    }
+}
+
+#[test]
+fn test_helpers_for_includes_dependencies() {
+    let names: Vec<&str> = helpers_for(["rash_require_command", "echo"])
+        .iter()
+        .map(|helper| helper.name)
+        .collect();
+    assert_eq!(names, ["rash_has_command", "rash_require_command"]);
+    assert!(helpers_for(["rash_log_info"])
+        .iter()
+        .all(|helper| helper.dependencies.is_empty()));
+}
+
+#[test]
+fn test_library_is_strict_posix() {
+    for helper in HELPERS {
+        for line in helper.definition.lines() {
+            let line = line.trim_start();
+            assert!(!line.starts_with("local "), "{}: {line}", helper.name);
+            assert!(!line.contains("[["), "{}: {line}", helper.name);
+            // `echo` mangles backslashes on some shells, so only literals go through it
+            if let Some(argument) = line.strip_prefix("echo ") {
+                assert!(!argument.contains('$'), "{}: {line}", helper.name);
+            }
+        }
+    }
+}
+
+/// The shells the library must work under, with the flags that make them POSIX
+const SHELLS: &[(&str, &[&str])] = &[
+    ("sh", &[]),
+    ("dash", &[]),
+    ("bash", &["--posix"]),
+    ("busybox", &["ash"]),
+];
+
+/// A script calling `helper`, run in an empty directory that is also `TMPDIR`
+struct Case {
+    helper: &'static str,
+    script: &'static str,
+    stdout: &'static str,
+    stderr: &'static str,
+    status: i32,
+}
+
+const fn case(
+    helper: &'static str,
+    script: &'static str,
+    stdout: &'static str,
+    stderr: &'static str,
+    status: i32,
+) -> Case {
+    Case {
+        helper,
+        script,
+        stdout,
+        stderr,
+        status,
+    }
+}
+
+/// The downloads go through a stand-in `curl` that copies a local file; the
+/// checksum is that of "hello\n"
+const CASES: &[Case] = &[
+    case("rash_require", "rash_require true; echo ok", "ok\n", "", 0),
+    case(
+        "rash_require",
+        r"rash_require test 'a\b' = x",
+        "",
+        "FATAL: Requirement failed: test a\\b = x\n",
+        1,
+    ),
+    case("rash_assert", "rash_assert false", "", "ASSERTION FAILED: false\n", 2),
+    case("rash_file_exists", "touch f; rash_file_exists f", "", "", 0),
+    case("rash_file_exists", "mkdir d; rash_file_exists d", "", "", 1),
+    case("rash_dir_exists", "mkdir d; rash_dir_exists d", "", "", 0),
+    case("rash_mkdir_p", "rash_mkdir_p 'a b/c'; test -d 'a b/c'", "", "", 0),
+    case("rash_is_readable", "touch f; rash_is_readable f", "", "", 0),
+    case("rash_is_writable", "rash_is_writable missing", "", "", 1),
+    case(
+        "rash_atomic_write",
+        r"rash_atomic_write 'a\tb\\c\n-e' out; cat out; ls",
+        "a\\tb\\\\c\\n-e\nout\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_download_verified",
+        "mkdir bin; printf '#!/bin/sh\\ncp \"$5\" \"$7\"\\n' > bin/curl; chmod +x bin/curl
+         echo hello > src; PATH=\"$PWD/bin:$PATH\"
+         rash_download_verified src dst 5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03
+         cat dst",
+        "hello\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_download_verified",
+        "mkdir bin; printf '#!/bin/sh\\ncp \"$5\" \"$7\"\\n' > bin/curl; chmod +x bin/curl
+         echo hullo > src; PATH=\"$PWD/bin:$PATH\"
+         rash_download_verified src dst 5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03 2>/dev/null",
+        "",
+        "",
+        1,
+    ),
+    case("rash_list", r#"rash_list "it's" 'a b' '$x'"#, r"'it'\''s' 'a b' '$x' ", "", 0),
+    case(
+        "rash_extract_tar",
+        "mkdir in out; echo hi > in/f; tar -cf a.tar -C in f; rash_extract_tar a.tar out; cat out/f",
+        "hi\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_extract_tar_gz",
+        "mkdir in out; echo hi > in/f; tar -cf - -C in f | gzip > a.tgz; rash_extract_tar_gz a.tgz out; cat out/f",
+        "hi\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_detect_platform",
+        r#"test "$(rash_detect_platform)" = "$(rash_detect_arch)-$(rash_detect_os)" && echo same"#,
+        "same\n",
+        "",
+        0,
+    ),
+    case("rash_log_info", r"rash_log_info 'a\nb' c", "", "INFO: a\\nb c\n", 0),
+    case("rash_log_warn", "rash_log_warn careful", "", "WARN: careful\n", 0),
+    case("rash_log_error", "rash_log_error -n", "", "ERROR: -n\n", 0),
+    case("rash_log_debug", "rash_log_debug hidden", "", "", 0),
+    case("rash_log_debug", "RASH_DEBUG=1; rash_log_debug shown", "", "DEBUG: shown\n", 0),
+    case("rash_has_command", "rash_has_command sh && echo yes", "yes\n", "", 0),
+    // The failing status of `command -v` varies between shells
+    case(
+        "rash_has_command",
+        "rash_has_command no-such-command || echo no",
+        "no\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_require_command",
+        r"rash_require_command 'no\such'",
+        "",
+        "FATAL: Required command 'no\\such' not found\n",
+        1,
+    ),
+    case(
+        "rash_mktemp_dir",
+        r#"d="$(rash_mktemp_dir)"; test -d "$d"; test "$(dirname "$d")" = "$PWD" && echo ok"#,
+        "ok\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_chmod",
+        "touch f; rash_chmod 640 f; ls -l f | cut -c1-10",
+        "-rw-r-----\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_chmod",
+        "touch f; rash_chmod u+x f",
+        "",
+        "ERROR: Invalid permission format: u+x\n",
+        1,
+    ),
+    case("rash_str_contains", "rash_str_contains abc b", "", "", 0),
+    case("rash_str_contains", "rash_str_contains abc '*'", "", "", 1),
+    case("rash_str_starts_with", "rash_str_starts_with abc ab", "", "", 0),
+    case("rash_str_starts_with", "rash_str_starts_with abc '?b'", "", "", 1),
+    case("rash_str_ends_with", "rash_str_ends_with abc bc", "", "", 0),
+    case("rash_str_ends_with", "rash_str_ends_with abc '[a-c]'", "", "", 1),
+    case(
+        "rash_sanitize_path",
+        r#"p="$(rash_sanitize_path /opt/app-1.2/bin/)"; echo "$p""#,
+        "/opt/app-1.2/bin/\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_sanitize_path",
+        r#"for p in "" / //x /. -rf .. ../x a/.. a/../b 'a b' '$(x)' 'a*'; do
+             (rash_sanitize_path "$p" >/dev/null 2>&1) && echo "accepted $p"
+           done; echo done"#,
+        "done\n",
+        "",
+        0,
+    ),
+    case(
+        "rash_sanitize_path",
+        r"rash_sanitize_path '/tmp/x;rm'; echo unreachable",
+        "",
+        "FATAL: Unsafe path: /tmp/x;rm\n",
+        1,
+    ),
+    case("rash_sanitize_word", "rash_sanitize_word gcc-12.2", "gcc-12.2\n", "", 0),
+    case(
+        "rash_sanitize_word",
+        r#"for w in "" -c a/b 'a b' '$x' 'a;b' '`x`'; do
+             (rash_sanitize_word "$w" >/dev/null 2>&1) && echo "accepted $w"
+           done; echo done"#,
+        "done\n",
+        "",
+        0,
+    ),
+    case("rash_version_compare", "rash_version_compare 1.0 = 1.0", "", "", 0),
+    case("rash_version_compare", "rash_version_compare 1.0 != 1.0", "", "", 1),
+    case(
+        "rash_version_compare",
+        "rash_version_compare 1.0 '<' 2.0",
+        "",
+        "ERROR: Unsupported version operator: <\n",
+        1,
+    ),
+];
+
+fn installed((program, flags): (&str, &[&str])) -> bool {
+    std::process::Command::new(program)
+        .args(flags)
+        .args(["-c", ":"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[test]
+fn test_helpers_behave_the_same_under_every_shell() {
+    for &(program, flags) in SHELLS.iter().filter(|&&shell| installed(shell)) {
+        for case in CASES {
+            let mut script = String::from("set -euf\n");
+            for helper in helpers_for([case.helper]) {
+                script.push_str(helper.definition);
+                script.push('\n');
+            }
+            script.push_str(case.script);
+
+            let dir = tempfile::tempdir().unwrap();
+            let output = std::process::Command::new(program)
+                .args(flags)
+                .arg("-c")
+                .arg(&script)
+                .current_dir(dir.path())
+                .env("TMPDIR", dir.path())
+                .env_remove("RASH_DEBUG")
+                .output()
+                .unwrap();
+
+            let context = format!("{program} {flags:?}: {}", case.script);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                case.stdout,
+                "{context}"
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stderr),
+                case.stderr,
+                "{context}"
+            );
+            assert_eq!(output.status.code(), Some(case.status), "{context}");
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Environment variables and command-line arguments
+
+/// The value of environment variable `name`, or `None` if it is unset or empty
+///
+/// Transpiles to `${NAME:-}`, so `name` must be a string literal.
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// The value of environment variable `name`, or `default` if it is unset or empty
+///
+/// Transpiles to `${NAME:-default}`.
+pub fn var_or(name: &str, default: &str) -> String {
+    var(name).unwrap_or_else(|| default.to_string())
+}
+
+/// The arguments the script was run with, without the program name
+///
+/// Transpiles to `"$@"`.
+pub fn args() -> Vec<String> {
+    std::env::args().skip(1).collect()
+}
+
+/// The `n`th argument the script was run with, counting from 1
+///
+/// Transpiles to `"$1"`, `"$2"`, ...; `n` must be an integer literal.
+pub fn arg(n: usize) -> Option<String> {
+    if n == 0 {
+        return None;
+    }
+    std::env::args().nth(n)
+}
@@ -0,0 +1,50 @@
+//! File system operations
+
+use std::path::Path;
+
+/// Create a directory and any missing parents, like `mkdir -p`
+///
+/// Transpiles to `rash_mkdir_p`. Panics if the directory can't be created.
+pub fn mkdir_p(path: &str) {
+    if let Err(e) = std::fs::create_dir_all(path) {
+        panic!("mkdir -p {path}: {e}");
+    }
+}
+
+/// Whether `path` is a regular file. Transpiles to `rash_file_exists`.
+pub fn file_exists(path: &str) -> bool {
+    Path::new(path).is_file()
+}
+
+/// Whether `path` is a directory. Transpiles to `rash_dir_exists`.
+pub fn dir_exists(path: &str) -> bool {
+    Path::new(path).is_dir()
+}
+
+/// Set the permissions of `path` from a three-digit octal `mode` such as `"755"`
+///
+/// Transpiles to `rash_chmod`. Panics on a malformed mode or when the
+/// permissions can't be changed.
+pub fn chmod(mode: &str, path: &str) {
+    let bits = match mode.as_bytes() {
+        [a, b, c] if [a, b, c].iter().all(|d| (b'0'..=b'7').contains(d)) => {
+            u32::from_str_radix(mode, 8).unwrap_or_default()
+        }
+        _ => panic!("Invalid permission format: {mode}"),
+    };
+    set_mode(path, bits);
+}
+
+#[cfg(unix)]
+fn set_mode(path: &str, bits: u32) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Err(e) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(bits)) {
+        panic!("chmod {bits:o} {path}: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &str, _bits: u32) {
+    // Permission bits only exist on Unix, where the generated scripts run
+}
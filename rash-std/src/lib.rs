@@ -0,0 +1,31 @@
+//! Typed standard library for Rash programs.
+//!
+//! A Rash program that calls these functions is ordinary Rust: it builds with
+//! `cargo check` and its logic can be unit-tested with `cargo test`. When the
+//! program is transpiled, each call becomes the `rash_*` helper of the shell
+//! runtime with the same behaviour. The functions of `env` and `process`
+//! become parameter expansions and commands instead. Those of `sanitize`
+//! clear the taint the transpiler tracks on external input.
+//!
+//! Import the modules and call functions through them, which is the form the
+//! transpiler recognizes:
+//!
+//! ```no_run
+//! use bashrs_std::{fs, log};
+//!
+//! log::info("Creating prefix");
+//! fs::mkdir_p("/opt/tool");
+//! ```
+//!
+//! Failures panic, mirroring the generated script, which exits.
+
+pub mod env;
+pub mod fs;
+pub mod log;
+pub mod net;
+pub mod process;
+pub mod sanitize;
+pub mod sys;
+
+#[cfg(test)]
+mod tests;
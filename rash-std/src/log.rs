@@ -0,0 +1,23 @@
+//! Messages for the person running the script, written to stderr
+
+/// Transpiles to `rash_log_info`
+pub fn info(msg: &str) {
+    eprintln!("INFO: {msg}");
+}
+
+/// Transpiles to `rash_log_warn`
+pub fn warn(msg: &str) {
+    eprintln!("WARN: {msg}");
+}
+
+/// Transpiles to `rash_log_error`
+pub fn error(msg: &str) {
+    eprintln!("ERROR: {msg}");
+}
+
+/// Printed only when `RASH_DEBUG=1`. Transpiles to `rash_log_debug`.
+pub fn debug(msg: &str) {
+    if std::env::var("RASH_DEBUG").as_deref() == Ok("1") {
+        eprintln!("DEBUG: {msg}");
+    }
+}
@@ -0,0 +1,53 @@
+//! Network access
+
+use std::process::Command;
+
+/// Download `url` over HTTPS to `dst` and check it against a SHA-256 `checksum`
+///
+/// Transpiles to `rash_download_verified`. Like the shell helper, this runs
+/// `curl` or `wget` and `sha256sum` or `shasum`, and panics if none is
+/// available, the download fails or the checksum doesn't match.
+pub fn download_verified(url: &str, dst: &str, checksum: &str) {
+    let downloaded = if crate::sys::has_command("curl") {
+        run(Command::new("curl").args(["-fsSL", "--proto", "=https", "--tlsv1.2", url, "-o", dst]))
+    } else if crate::sys::has_command("wget") {
+        run(Command::new("wget").args(["-qO", dst, url]))
+    } else {
+        panic!("Neither curl nor wget found");
+    };
+    if !downloaded {
+        panic!("Download of {url} failed");
+    }
+
+    let line = format!("{checksum}  {dst}");
+    let verified = if crate::sys::has_command("sha256sum") {
+        check(Command::new("sha256sum").arg("-c"), &line)
+    } else if crate::sys::has_command("shasum") {
+        check(Command::new("shasum").args(["-a", "256", "-c"]), &line)
+    } else {
+        panic!("No checksum utility found");
+    };
+    if !verified {
+        panic!("Checksum mismatch for {dst}");
+    }
+}
+
+fn run(command: &mut Command) -> bool {
+    command.status().is_ok_and(|status| status.success())
+}
+
+/// Run a checksum utility with `line` on stdin
+fn check(command: &mut Command, line: &str) -> bool {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let child = command.stdin(Stdio::piped()).stdout(Stdio::null()).spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    let written = child
+        .stdin
+        .take()
+        .is_some_and(|mut stdin| writeln!(stdin, "{line}").is_ok());
+    child.wait().is_ok_and(|status| status.success()) && written
+}
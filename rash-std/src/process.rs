@@ -0,0 +1,45 @@
+//! Running commands for their output or their exit status
+
+use std::ffi::OsStr;
+use std::process::{Command, Stdio};
+
+/// The standard output of `program` run with `args`, without its trailing
+/// newlines
+///
+/// Transpiles to `"$(program args...)"`, which strips every trailing
+/// newline and nothing else; so does this function. Panics if the command
+/// cannot run or fails, as the script exits.
+pub fn capture<I, S>(program: &str, args: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .unwrap_or_else(|e| panic!("cannot run {program}: {e}"));
+    if !output.status.success() {
+        panic!("{program} failed with {}", output.status);
+    }
+    let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    while stdout.ends_with('\n') {
+        stdout.pop();
+    }
+    stdout
+}
+
+/// Whether `program` run with `args` exits with status 0
+///
+/// Transpiles to the command itself, used as a condition. Its output is
+/// not captured; a program that cannot run counts as failing.
+pub fn status<I, S>(program: &str, args: I) -> bool
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new(program)
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
+}
@@ -0,0 +1,45 @@
+//! Checks that make external input safe to use where the shell would
+//! interpret it
+//!
+//! Values read from the environment, the arguments or a command's output are
+//! tainted: the transpiler refuses to let them reach `rm -r`, `eval`, a
+//! command name or an arithmetic expansion. Passing them through one of
+//! these functions first clears the taint.
+
+/// `path` if it is safe to remove recursively: a non-empty path of letters,
+/// digits and `_./@%+=:,-` that is not the root, not an option and has no
+/// `..` component
+///
+/// Transpiles to `rash_sanitize_path`, bound with `let` or assigned. Panics
+/// on any other path, as the script exits.
+pub fn path(path: &str) -> String {
+    let is_root = path.starts_with("//") || path == "/" || path == "/." || path.starts_with("/./");
+    let unsafe_path = path.is_empty()
+        || path.starts_with('-')
+        || is_root
+        || path.split('/').any(|component| component == "..")
+        || !path
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_./@%+=:,-".contains(c));
+    if unsafe_path {
+        panic!("Unsafe path: {path}");
+    }
+    path.to_string()
+}
+
+/// `word` if it is safe as a command name or in code the shell evaluates: a
+/// non-empty word of letters, digits and `_.+-` that is not an option
+///
+/// Transpiles to `rash_sanitize_word`, bound with `let` or assigned. Panics
+/// on any other word, as the script exits.
+pub fn word(word: &str) -> String {
+    let unsafe_word = word.is_empty()
+        || word.starts_with('-')
+        || !word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.+-".contains(c));
+    if unsafe_word {
+        panic!("Unsafe word: {word}");
+    }
+    word.to_string()
+}
@@ -0,0 +1,33 @@
+//! The system the script runs on
+
+use std::path::Path;
+
+/// Whether `name` is a command on `PATH`. Transpiles to `rash_has_command`.
+pub fn has_command(name: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| is_executable(&dir.join(name)))
+}
+
+/// Stop unless `name` is a command on `PATH`
+///
+/// Transpiles to `rash_require_command`. Panics if the command is missing.
+pub fn require_command(name: &str) {
+    if !has_command(name) {
+        panic!("Required command '{name}' not found");
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
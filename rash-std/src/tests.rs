@@ -0,0 +1,77 @@
+use crate::{env, fs, process, sanitize, sys};
+
+#[test]
+fn test_mkdir_p_creates_parents() {
+    let root = tempfile::tempdir().unwrap();
+    let nested = root.path().join("a/b/c");
+    let nested = nested.to_str().unwrap();
+
+    assert!(!fs::dir_exists(nested));
+    fs::mkdir_p(nested);
+    fs::mkdir_p(nested); // Idempotent, like `mkdir -p`
+    assert!(fs::dir_exists(nested));
+    assert!(!fs::file_exists(nested));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_chmod_sets_octal_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    fs::chmod("750", path);
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
+
+#[test]
+#[should_panic(expected = "Invalid permission format")]
+fn test_chmod_rejects_malformed_mode() {
+    fs::chmod("u+x", "unused");
+}
+
+#[test]
+fn test_env_var_is_none_when_unset() {
+    assert_eq!(env::var("BASHRS_STD_TEST_SURELY_UNSET"), None);
+    assert_eq!(
+        env::var_or("BASHRS_STD_TEST_SURELY_UNSET", "/usr/local"),
+        "/usr/local"
+    );
+}
+
+#[test]
+fn test_has_command() {
+    assert!(!sys::has_command("bashrs-std-no-such-command"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_capture_strips_trailing_newlines_only() {
+    assert_eq!(process::capture("printf", ["a\n\nb\n\n\n"]), "a\n\nb");
+    assert!(process::status("test", ["-n", "x"]));
+    assert!(!process::status("test", ["-n", ""]));
+    assert!(!process::status("bashrs-std-no-such-command", [""; 0]));
+}
+
+#[test]
+fn test_sanitize_path_accepts_plain_paths_only() {
+    assert_eq!(sanitize::path("/opt/app-1.2/bin/"), "/opt/app-1.2/bin/");
+    assert_eq!(sanitize::path("build/out"), "build/out");
+    for path in [
+        "", "/", "//x", "/.", "-rf", "..", "../x", "a/..", "a/../b", "a b", "$(x)", "a*",
+    ] {
+        assert!(
+            std::panic::catch_unwind(|| sanitize::path(path)).is_err(),
+            "{path:?}"
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "Unsafe word: a;b")]
+fn test_sanitize_word_rejects_metacharacters() {
+    assert_eq!(sanitize::word("gcc-12.2"), "gcc-12.2");
+    sanitize::word("a;b");
+}
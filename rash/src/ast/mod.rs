@@ -31,11 +31,13 @@
 //!         })
 //!     ],
 //!     return_type: Type::Void,
+//!     effects: None,
 //! };
 //!
 //! let ast = RestrictedAst {
 //!     functions: vec![main_fn],
 //!     entry_point: "main".to_string(),
+//!     constants: vec![],
 //! };
 //!
 //! // Validate the AST
@@ -62,6 +64,7 @@
 //!                 name: "env".to_string(),
 //!                 args: vec![Expr::Literal(Literal::Str("USER".to_string()))],
 //!             },
+//!             mutable: false,
 //!         },
 //!         // Use the variable
 //!         Stmt::Expr(Expr::FunctionCall {
@@ -72,11 +75,13 @@
 //!         })
 //!     ],
 //!     return_type: Type::Void,
+//!     effects: None,
 //! };
 //!
 //! let ast = RestrictedAst {
 //!     functions: vec![main_fn],
 //!     entry_point: "main".to_string(),
+//!     constants: vec![],
 //! };
 //!
 //! // Variables must be valid identifiers
@@ -100,11 +105,13 @@
 //!         Stmt::Expr(Expr::Literal(Literal::Str("test".to_string())))
 //!     ],
 //!     return_type: Type::Void,
+//!     effects: None,
 //! };
 //!
 //! let ast = RestrictedAst {
 //!     functions: vec![invalid_fn],
 //!     entry_point: "main".to_string(),
+//!     constants: vec![],
 //! };
 //!
 //! // This will fail validation
@@ -121,7 +128,7 @@ mod tests;
 #[cfg(test)]
 mod visitor_tests;
 
-pub use restricted::{Expr, Function, RestrictedAst, Stmt, Type};
+pub use restricted::{Constant, EffectBudget, Expr, Function, RestrictedAst, Span, Stmt, Type};
 
 use crate::models::{Error, Result};
 
@@ -140,9 +147,11 @@ use crate::models::{Error, Result};
 ///             params: vec![],
 ///             body: vec![],
 ///             return_type: Type::Void,
+///             effects: None,
 ///         }
 ///     ],
 ///     entry_point: "main".to_string(),
+///     constants: vec![],
 /// };
 ///
 /// // Validate the AST
@@ -151,5 +160,5 @@ use crate::models::{Error, Result};
 /// # }
 /// ```
 pub fn validate(ast: &RestrictedAst) -> Result<()> {
-    ast.validate().map_err(Error::Validation)
+    ast.validate().map_err(Error::from)
 }
@@ -1,3 +1,5 @@
+use crate::ir::Effect;
+use crate::models::Diagnostic;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -5,30 +7,55 @@ use std::collections::HashMap;
 pub struct RestrictedAst {
     pub functions: Vec<Function>,
     pub entry_point: String,
+    /// Top-level `const` and `static` items, in source order
+    #[serde(default)]
+    pub constants: Vec<Constant>,
 }
 
+#[allow(clippy::result_large_err)]
 impl RestrictedAst {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), Diagnostic> {
         // Check for entry point
         if !self.functions.iter().any(|f| f.name == self.entry_point) {
-            return Err(format!(
-                "Entry point function '{}' not found",
-                self.entry_point
-            ));
+            return Err(Diagnostic::new(
+                "E0103",
+                format!("Entry point function '{}' not found", self.entry_point),
+            )
+            .with_help("add a `fn main() { ... }` function"));
         }
 
         // Validate each function
+        let mut seen = std::collections::HashSet::new();
         for function in &self.functions {
+            if !seen.insert(function.name.as_str()) {
+                return Err(Diagnostic::new(
+                    "E0119",
+                    format!("Function '{}' is defined more than once", function.name),
+                )
+                .with_help("rename or remove one of the definitions"));
+            }
             function.validate()?;
         }
 
+        let mut seen = std::collections::HashSet::new();
+        for constant in &self.constants {
+            if !seen.insert(constant.name.as_str()) {
+                return Err(Diagnostic::new(
+                    "E0119",
+                    format!("Constant '{}' is defined more than once", constant.name),
+                )
+                .with_help("rename or remove one of the definitions"));
+            }
+            constant.value.validate()?;
+        }
+
         // Check for recursion
         self.check_no_recursion()?;
 
         Ok(())
     }
 
-    fn check_no_recursion(&self) -> Result<(), String> {
+    fn check_no_recursion(&self) -> Result<(), Diagnostic> {
         let mut call_graph: HashMap<String, Vec<String>> = HashMap::new();
 
         // Build call graph
@@ -44,10 +71,11 @@ impl RestrictedAst {
             let mut rec_stack = std::collections::HashSet::new();
 
             if self.has_cycle(&call_graph, &function.name, &mut visited, &mut rec_stack) {
-                return Err(format!(
-                    "Recursion detected involving function '{}'",
-                    function.name
-                ));
+                return Err(Diagnostic::new(
+                    "E0120",
+                    format!("Recursion detected involving function '{}'", function.name),
+                )
+                .with_help("rewrite the recursion as a bounded loop"));
             }
         }
 
@@ -86,16 +114,47 @@ impl RestrictedAst {
     }
 }
 
+/// A top-level `const` or `static` item, which becomes a readonly global
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constant {
+    pub name: String,
+    pub ty: Type,
+    pub value: Expr,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Function {
     pub name: String,
     pub params: Vec<Parameter>,
     pub return_type: Type,
     pub body: Vec<Stmt>,
+    /// Effects allowed by a `#[rash::effects(...)]` attribute, or the
+    /// effects of the command an external stub stands for
+    #[serde(default)]
+    pub effects: Option<EffectBudget>,
+}
+
+/// The effects a `#[rash::effects(...)]` attribute names
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectBudget {
+    pub effects: Vec<Effect>,
+    /// Span of the attribute
+    pub span: Span,
 }
 
+#[allow(clippy::result_large_err)]
 impl Function {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), Diagnostic> {
+        // Functions become shell functions, so their names must not be shell keywords
+        if is_shell_reserved_word(&self.name) {
+            return Err(Diagnostic::new(
+                "E0121",
+                format!("Function name '{}' is a reserved word in shell", self.name),
+            )
+            .with_help("rename the function"));
+        }
+
         // Empty body is OK for functions
 
         // Validate all statements
@@ -111,6 +170,73 @@ impl Function {
             stmt.collect_function_calls(calls);
         }
     }
+
+    /// Functions with an empty body declare an external command, e.g.
+    /// `fn mkdir(path: &str) {}`, and are not emitted as shell functions.
+    pub fn is_external_stub(&self) -> bool {
+        self.body.is_empty()
+    }
+}
+
+fn is_shell_reserved_word(name: &str) -> bool {
+    matches!(
+        name,
+        "case"
+            | "do"
+            | "done"
+            | "elif"
+            | "else"
+            | "esac"
+            | "fi"
+            | "for"
+            | "function"
+            | "if"
+            | "in"
+            | "select"
+            | "then"
+            | "time"
+            | "until"
+            | "while"
+    )
+}
+
+/// Location of a construct in the Rust source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset just past the last character
+    pub end: usize,
+    /// 1-based line of the first character
+    pub line: usize,
+    /// 1-based column of the first character
+    pub column: usize,
+}
+
+impl Span {
+    /// The span moved `base` bytes further into the source
+    pub fn offset_by(self, base: usize) -> Span {
+        Span {
+            start: self.start + base,
+            end: self.end + base,
+            ..self
+        }
+    }
+
+    /// The span with byte offsets counted from `base`
+    pub fn relative_to(self, base: usize) -> Span {
+        Span {
+            start: self.start.saturating_sub(base),
+            end: self.end.saturating_sub(base),
+            ..self
+        }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,6 +258,10 @@ pub enum Type {
     Option {
         inner_type: Box<Type>,
     },
+    /// `Vec<T>`, `&[T]` or `[T; N]`, lowered to a list of shell words
+    Vec {
+        inner_type: Box<Type>,
+    },
 }
 
 impl Type {
@@ -140,6 +270,10 @@ impl Type {
             Type::Void | Type::Bool | Type::U32 | Type::Str => true,
             Type::Result { ok_type, err_type } => ok_type.is_allowed() && err_type.is_allowed(),
             Type::Option { inner_type } => inner_type.is_allowed(),
+            // Shell words can't hold nested lists
+            Type::Vec { inner_type } => {
+                matches!(**inner_type, Type::Bool | Type::U32 | Type::Str)
+            }
         }
     }
 }
@@ -149,6 +283,13 @@ pub enum Stmt {
     Let {
         name: String,
         value: Expr,
+        /// `let mut`, which later `Assign`s may rebind
+        mutable: bool,
+    },
+    /// `name = value`, also standing for compound assignments such as `name += value`
+    Assign {
+        name: String,
+        value: Expr,
     },
     Expr(Expr),
     Return(Option<Expr>),
@@ -174,12 +315,20 @@ pub enum Stmt {
     },
     Break,
     Continue,
+    /// A bare `{ ... }` block, whose bindings end with it
+    Block(Vec<Stmt>),
+    /// Statement annotated with its location in the Rust source
+    Spanned {
+        span: Span,
+        stmt: Box<Stmt>,
+    },
 }
 
+#[allow(clippy::result_large_err)]
 impl Stmt {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), Diagnostic> {
         match self {
-            Stmt::Let { value, .. } => value.validate(),
+            Stmt::Let { value, .. } | Stmt::Assign { value, .. } => value.validate(),
             Stmt::Expr(expr) => expr.validate(),
             Stmt::Return(Some(expr)) => expr.validate(),
             Stmt::Return(None) => Ok(()),
@@ -201,6 +350,16 @@ impl Stmt {
                 max_iterations,
             } => self.validate_while_stmt(condition, body, *max_iterations),
             Stmt::Break | Stmt::Continue => Ok(()),
+            Stmt::Block(stmts) => self.validate_stmt_block(stmts),
+            Stmt::Spanned { span, stmt } => stmt.validate().map_err(|d| d.at(*span)),
+        }
+    }
+
+    /// The statement without any source location wrappers
+    pub fn unspanned(&self) -> &Stmt {
+        match self {
+            Stmt::Spanned { stmt, .. } => stmt.unspanned(),
+            other => other,
         }
     }
 
@@ -209,7 +368,7 @@ impl Stmt {
         condition: &Expr,
         then_block: &[Stmt],
         else_block: Option<&Vec<Stmt>>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Diagnostic> {
         condition.validate()?;
         self.validate_stmt_block(then_block)?;
         if let Some(else_stmts) = else_block {
@@ -218,7 +377,7 @@ impl Stmt {
         Ok(())
     }
 
-    fn validate_match_stmt(&self, scrutinee: &Expr, arms: &[MatchArm]) -> Result<(), String> {
+    fn validate_match_stmt(&self, scrutinee: &Expr, arms: &[MatchArm]) -> Result<(), Diagnostic> {
         scrutinee.validate()?;
         for arm in arms {
             arm.pattern.validate()?;
@@ -236,7 +395,7 @@ impl Stmt {
         iter: &Expr,
         body: &[Stmt],
         max_iterations: Option<u32>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Diagnostic> {
         self.validate_bounded_iteration(max_iterations, "For")?;
         pattern.validate()?;
         iter.validate()?;
@@ -248,7 +407,7 @@ impl Stmt {
         condition: &Expr,
         body: &[Stmt],
         max_iterations: Option<u32>,
-    ) -> Result<(), String> {
+    ) -> Result<(), Diagnostic> {
         self.validate_bounded_iteration(max_iterations, "While")?;
         condition.validate()?;
         self.validate_stmt_block(body)
@@ -258,16 +417,17 @@ impl Stmt {
         &self,
         max_iterations: Option<u32>,
         loop_type: &str,
-    ) -> Result<(), String> {
+    ) -> Result<(), Diagnostic> {
         if max_iterations.is_none() {
-            return Err(format!(
-                "{loop_type} loops must have bounded iterations for verification"
+            return Err(Diagnostic::new(
+                "E0122",
+                format!("{loop_type} loops must have bounded iterations for verification"),
             ));
         }
         Ok(())
     }
 
-    fn validate_stmt_block(&self, stmts: &[Stmt]) -> Result<(), String> {
+    fn validate_stmt_block(&self, stmts: &[Stmt]) -> Result<(), Diagnostic> {
         for stmt in stmts {
             stmt.validate()?;
         }
@@ -276,7 +436,9 @@ impl Stmt {
 
     pub fn collect_function_calls(&self, calls: &mut Vec<String>) {
         match self {
-            Stmt::Let { value, .. } => value.collect_function_calls(calls),
+            Stmt::Let { value, .. } | Stmt::Assign { value, .. } => {
+                value.collect_function_calls(calls)
+            }
             Stmt::Expr(expr) => expr.collect_function_calls(calls),
             Stmt::Return(Some(expr)) => expr.collect_function_calls(calls),
             Stmt::Return(None) => {}
@@ -321,6 +483,12 @@ impl Stmt {
                 }
             }
             Stmt::Break | Stmt::Continue => {}
+            Stmt::Block(stmts) => {
+                for stmt in stmts {
+                    stmt.collect_function_calls(calls);
+                }
+            }
+            Stmt::Spanned { stmt, .. } => stmt.collect_function_calls(calls),
         }
     }
 }
@@ -356,22 +524,41 @@ pub enum Expr {
         expr: Box<Expr>,
     },
     Block(Vec<Stmt>),
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+    /// `format!`: the literal text and the values of the placeholders, in order
+    Format(Vec<Expr>),
+    /// `print!`/`println!`, or `eprint!`/`eprintln!` when `stderr` is set;
+    /// `message` is an [`Expr::Format`] that includes any trailing newline
+    Print {
+        message: Box<Expr>,
+        stderr: bool,
+    },
 }
 
+#[allow(clippy::result_large_err)]
 impl Expr {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), Diagnostic> {
         // Check nesting depth
         let depth = self.nesting_depth();
         if depth > 30 {
-            return Err(format!(
-                "Expression nesting too deep: {depth} levels (max 30)"
-            ));
+            return Err(Diagnostic::new(
+                "E0123",
+                format!("Expression nesting too deep: {depth} levels (max 30)"),
+            )
+            .with_help("split the expression into intermediate `let` bindings"));
         }
 
         match self {
             Expr::Literal(Literal::Str(s)) => {
                 if s.contains('\0') {
-                    return Err("Null characters not allowed in strings".to_string());
+                    return Err(Diagnostic::new(
+                        "E0124",
+                        "Null characters not allowed in strings",
+                    ));
                 }
                 Ok(())
             }
@@ -395,8 +582,20 @@ impl Expr {
                 }
                 Ok(())
             }
+            Expr::Range { start, end, .. } => {
+                start.validate()?;
+                end.validate()
+            }
+            Expr::Format(parts) => {
+                for part in parts {
+                    part.validate()?;
+                }
+                Ok(())
+            }
+            Expr::Print { message, .. } => message.validate(),
+            Expr::Try { expr } => expr.validate(),
             // Placeholder for new expression types - TODO: implement properly
-            _ => Ok(()), // Array, Index, Try, Block
+            _ => Ok(()), // Array, Index, Block
         }
     }
 
@@ -412,6 +611,9 @@ impl Expr {
                 let args_depth = args.iter().map(|a| a.nesting_depth()).max().unwrap_or(0);
                 1 + receiver_depth.max(args_depth)
             }
+            Expr::Format(parts) => 1 + parts.iter().map(|p| p.nesting_depth()).max().unwrap_or(0),
+            Expr::Print { message, .. } => message.nesting_depth(),
+            Expr::Try { expr } => expr.nesting_depth(),
             _ => 0,
         }
     }
@@ -454,6 +656,16 @@ impl Expr {
                     stmt.collect_function_calls(calls);
                 }
             }
+            Expr::Range { start, end, .. } => {
+                start.collect_function_calls(calls);
+                end.collect_function_calls(calls);
+            }
+            Expr::Format(parts) => {
+                for part in parts {
+                    part.collect_function_calls(calls);
+                }
+            }
+            Expr::Print { message, .. } => message.collect_function_calls(calls),
             _ => {}
         }
     }
@@ -505,13 +717,23 @@ pub enum Pattern {
         name: String,
         fields: Vec<(String, Pattern)>,
     },
+    /// Alternatives separated by `|`
+    Or(Vec<Pattern>),
+    /// `Some(pattern)`, matching an `Option` that holds a value
+    Some(Box<Pattern>),
+    /// `None`, matching an `Option` that holds no value
+    None,
 }
 
+#[allow(clippy::result_large_err)]
 impl Pattern {
-    pub fn validate(&self) -> Result<(), String> {
+    pub fn validate(&self) -> Result<(), Diagnostic> {
         match self {
-            Pattern::Literal(_) | Pattern::Variable(_) | Pattern::Wildcard => Ok(()),
-            Pattern::Tuple(patterns) => {
+            Pattern::Literal(_) | Pattern::Variable(_) | Pattern::Wildcard | Pattern::None => {
+                Ok(())
+            }
+            Pattern::Some(inner) => inner.validate(),
+            Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
                 for pattern in patterns {
                     pattern.validate()?;
                 }
@@ -529,8 +751,11 @@ impl Pattern {
     pub fn binds_variable(&self, name: &str) -> bool {
         match self {
             Pattern::Variable(var_name) => var_name == name,
-            Pattern::Tuple(patterns) => patterns.iter().any(|p| p.binds_variable(name)),
+            Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+                patterns.iter().any(|p| p.binds_variable(name))
+            }
             Pattern::Struct { fields, .. } => fields.iter().any(|(_, p)| p.binds_variable(name)),
+            Pattern::Some(inner) => inner.binds_variable(name),
             _ => false,
         }
     }
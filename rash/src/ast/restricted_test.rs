@@ -16,9 +16,11 @@ mod tests {
                             value: Expr::Literal(Literal::U32(42)),
                         }
                     ],
+                    effects: None,
                 }
             ],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
 
         assert!(ast.validate().is_ok());
@@ -33,13 +35,15 @@ mod tests {
                     params: vec![],
                     return_type: Type::Str,
                     body: vec![],
+                    effects: None,
                 }
             ],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
 
         assert!(ast.validate().is_err());
-        assert!(ast.validate().unwrap_err().contains("Entry point function 'main' not found"));
+        assert!(ast.validate().unwrap_err().message.contains("Entry point function 'main' not found"));
     }
 
     #[test]
@@ -49,10 +53,11 @@ mod tests {
             params: vec![],
             return_type: Type::Str,
             body: vec![],
+            effects: None,
         };
 
         assert!(func.validate().is_err());
-        assert!(func.validate().unwrap_err().contains("empty body"));
+        assert!(func.validate().unwrap_err().message.contains("empty body"));
     }
 
     #[test]
@@ -69,13 +74,15 @@ mod tests {
                             args: vec![],
                         })
                     ],
+                    effects: None,
                 },
             ],
             entry_point: "recursive".to_string(),
+            constants: vec![],
         };
 
         assert!(ast.validate().is_err());
-        assert!(ast.validate().unwrap_err().contains("Recursion detected"));
+        assert!(ast.validate().unwrap_err().message.contains("Recursion detected"));
     }
 
     #[test]
@@ -92,6 +99,7 @@ mod tests {
                             args: vec![],
                         })
                     ],
+                    effects: None,
                 },
                 Function {
                     name: "b".to_string(),
@@ -103,13 +111,15 @@ mod tests {
                             args: vec![],
                         })
                     ],
+                    effects: None,
                 },
             ],
             entry_point: "a".to_string(),
+            constants: vec![],
         };
 
         assert!(ast.validate().is_err());
-        assert!(ast.validate().unwrap_err().contains("Recursion detected"));
+        assert!(ast.validate().unwrap_err().message.contains("Recursion detected"));
     }
 
     #[test]
@@ -188,6 +198,7 @@ mod tests {
                     },
                 },
             ],
+            effects: None,
         };
 
         let mut calls = Vec::new();
@@ -13,9 +13,12 @@ fn test_restricted_ast_validation() {
             body: vec![Stmt::Let {
                 name: "x".to_string(),
                 value: Expr::Literal(restricted::Literal::U32(42)),
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     assert!(ast.validate().is_ok());
@@ -31,15 +34,19 @@ fn test_missing_entry_point() {
             body: vec![Stmt::Let {
                 name: "x".to_string(),
                 value: Expr::Literal(restricted::Literal::U32(1)),
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     assert!(ast.validate().is_err());
     assert!(ast
         .validate()
         .unwrap_err()
+        .message
         .contains("Entry point function 'main' not found"));
 }
 
@@ -50,6 +57,7 @@ fn test_function_validation() {
         params: vec![],
         return_type: Type::Str,
         body: vec![],
+        effects: None,
     };
 
     // Empty function bodies are now allowed
@@ -67,12 +75,18 @@ fn test_recursion_detection() {
                 name: "recursive".to_string(),
                 args: vec![],
             })],
+            effects: None,
         }],
         entry_point: "recursive".to_string(),
+        constants: vec![],
     };
 
     assert!(ast.validate().is_err());
-    assert!(ast.validate().unwrap_err().contains("Recursion detected"));
+    assert!(ast
+        .validate()
+        .unwrap_err()
+        .message
+        .contains("Recursion detected"));
 }
 
 #[test]
@@ -87,6 +101,7 @@ fn test_indirect_recursion_detection() {
                     name: "b".to_string(),
                     args: vec![],
                 })],
+                effects: None,
             },
             Function {
                 name: "b".to_string(),
@@ -96,13 +111,19 @@ fn test_indirect_recursion_detection() {
                     name: "a".to_string(),
                     args: vec![],
                 })],
+                effects: None,
             },
         ],
         entry_point: "a".to_string(),
+        constants: vec![],
     };
 
     assert!(ast.validate().is_err());
-    assert!(ast.validate().unwrap_err().contains("Recursion detected"));
+    assert!(ast
+        .validate()
+        .unwrap_err()
+        .message
+        .contains("Recursion detected"));
 }
 
 #[rstest]
@@ -151,6 +172,7 @@ fn test_statement_validation() {
     let let_stmt = Stmt::Let {
         name: "x".to_string(),
         value: Expr::Literal(restricted::Literal::U32(42)),
+        mutable: false,
     };
     assert!(let_stmt.validate().is_ok());
 
@@ -183,8 +205,10 @@ fn test_function_call_collection() {
                     name: "helper2".to_string(),
                     args: vec![],
                 },
+                mutable: false,
             },
         ],
+        effects: None,
     };
 
     let mut calls = Vec::new();
@@ -313,6 +337,16 @@ fn test_type_is_allowed() {
     };
     assert!(option_type.is_allowed());
 
+    // Lists hold single words, so they can't nest
+    let list_type = Type::Vec {
+        inner_type: Box::new(Type::Str),
+    };
+    assert!(list_type.is_allowed());
+    let nested_list = Type::Vec {
+        inner_type: Box::new(list_type),
+    };
+    assert!(!nested_list.is_allowed());
+
     // Test nested allowed types
     let nested_result = Type::Result {
         ok_type: Box::new(Type::Option {
@@ -342,6 +376,7 @@ fn test_expr_array_try_block_handling() {
     let block_expr = Expr::Block(vec![Stmt::Let {
         name: "x".to_string(),
         value: Expr::Literal(restricted::Literal::U32(42)),
+        mutable: false,
     }]);
     assert!(block_expr.validate().is_ok());
 }
@@ -537,9 +572,12 @@ fn test_validate_public_api() {
             body: vec![Stmt::Let {
                 name: "x".to_string(),
                 value: Expr::Literal(restricted::Literal::U32(42)),
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     // Test the public validate function
@@ -551,10 +589,60 @@ fn test_invalid_ast_returns_validation_error() {
     let ast = RestrictedAst {
         functions: vec![],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     match validate(&ast) {
-        Err(crate::models::Error::Validation(_)) => (), // Expected
+        Err(crate::models::Error::Diagnostic(_)) => (), // Expected
         _ => panic!("Expected validation error"),
     }
 }
+
+#[test]
+fn test_duplicate_function_names_rejected() {
+    let function = Function {
+        name: "helper".to_string(),
+        params: vec![],
+        return_type: Type::Void,
+        body: vec![],
+        effects: None,
+    };
+    let ast = RestrictedAst {
+        functions: vec![
+            Function {
+                name: "main".to_string(),
+                params: vec![],
+                return_type: Type::Void,
+                body: vec![],
+                effects: None,
+            },
+            function.clone(),
+            function,
+        ],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    assert!(ast
+        .validate()
+        .unwrap_err()
+        .message
+        .contains("Function 'helper' is defined more than once"));
+}
+
+#[test]
+fn test_shell_reserved_function_names_rejected() {
+    let func = Function {
+        name: "done".to_string(),
+        params: vec![],
+        return_type: Type::Void,
+        body: vec![],
+        effects: None,
+    };
+
+    assert!(func
+        .validate()
+        .unwrap_err()
+        .message
+        .contains("reserved word"));
+}
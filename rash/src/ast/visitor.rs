@@ -35,12 +35,13 @@ where
     }
 }
 
-fn transform_stmt_exprs<F>(stmt: &mut Stmt, transform: &mut F)
+/// Transform a statement by calling a function on each expression in it
+pub fn transform_stmt_exprs<F>(stmt: &mut Stmt, transform: &mut F)
 where
     F: FnMut(&mut Expr),
 {
     match stmt {
-        Stmt::Let { value, .. } => transform_expr(value, transform),
+        Stmt::Let { value, .. } | Stmt::Assign { value, .. } => transform_expr(value, transform),
         Stmt::Expr(expr) => transform_expr(expr, transform),
         Stmt::Return(Some(expr)) => transform_expr(expr, transform),
         Stmt::Return(None) => {}
@@ -59,8 +60,38 @@ where
                 }
             }
         }
-        // Placeholder for new AST nodes - TODO: implement properly
-        _ => {} // Match, For, While, Break, Continue
+        Stmt::For { iter, body, .. } => {
+            transform_expr(iter, transform);
+            for stmt in body {
+                transform_stmt_exprs(stmt, transform);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        } => {
+            transform_expr(condition, transform);
+            for stmt in body {
+                transform_stmt_exprs(stmt, transform);
+            }
+        }
+        Stmt::Match { scrutinee, arms } => {
+            transform_expr(scrutinee, transform);
+            for arm in arms {
+                if let Some(guard) = &mut arm.guard {
+                    transform_expr(guard, transform);
+                }
+                for stmt in &mut arm.body {
+                    transform_stmt_exprs(stmt, transform);
+                }
+            }
+        }
+        Stmt::Break | Stmt::Continue => {}
+        Stmt::Block(stmts) => {
+            for stmt in stmts {
+                transform_stmt_exprs(stmt, transform);
+            }
+        }
+        Stmt::Spanned { stmt, .. } => transform_stmt_exprs(stmt, transform),
     }
 }
 
@@ -87,6 +118,31 @@ where
                 transform_expr(arg, transform);
             }
         }
+        Expr::Range { start, end, .. } => {
+            transform_expr(start, transform);
+            transform_expr(end, transform);
+        }
+        Expr::Format(parts) => {
+            for part in parts {
+                transform_expr(part, transform);
+            }
+        }
+        Expr::Print { message, .. } => transform_expr(message, transform),
+        Expr::Try { expr } => transform_expr(expr, transform),
+        Expr::Array(elements) => {
+            for element in elements {
+                transform_expr(element, transform);
+            }
+        }
+        Expr::Index { object, index } => {
+            transform_expr(object, transform);
+            transform_expr(index, transform);
+        }
+        Expr::Block(stmts) => {
+            for stmt in stmts {
+                transform_stmt_exprs(stmt, transform);
+            }
+        }
         _ => {}
     }
 
@@ -68,6 +68,9 @@ impl Visitor<()> for ExprTypeVisitor {
             Expr::Array(_) => "Array",
             Expr::Try { .. } => "Try",
             Expr::Block(_) => "Block",
+            Expr::Range { .. } => "Range",
+            Expr::Format(_) => "Format",
+            Expr::Print { .. } => "Print",
         };
         self.types.push(type_name.to_string());
     }
@@ -97,11 +100,14 @@ fn test_counting_visitor() {
                 Stmt::Let {
                     name: "x".to_string(),
                     value: Expr::Literal(Literal::U32(42)),
+                    mutable: false,
                 },
                 Stmt::Return(Some(Expr::Variable("x".to_string()))),
             ],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     walk_ast(&mut visitor, &ast);
@@ -132,9 +138,12 @@ fn test_transform_exprs_literal() {
             body: vec![Stmt::Let {
                 name: "x".to_string(),
                 value: Expr::Literal(Literal::U32(42)),
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -159,8 +168,10 @@ fn test_transform_exprs_function_call() {
                     Expr::Literal(Literal::U32(42)),
                 ],
             })],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -186,9 +197,12 @@ fn test_transform_exprs_binary() {
                     left: Box::new(Expr::Literal(Literal::U32(1))),
                     right: Box::new(Expr::Literal(Literal::U32(2))),
                 },
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -213,9 +227,12 @@ fn test_transform_exprs_unary() {
                     op: UnaryOp::Not,
                     operand: Box::new(Expr::Literal(Literal::Bool(true))),
                 },
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -239,8 +256,10 @@ fn test_transform_exprs_method_call() {
                 method: "method".to_string(),
                 args: vec![Expr::Literal(Literal::U32(1))],
             })],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -263,8 +282,10 @@ fn test_transform_exprs_return_stmt() {
                 Stmt::Return(Some(Expr::Literal(Literal::U32(42)))),
                 Stmt::Return(None),
             ],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -288,14 +309,18 @@ fn test_transform_exprs_if_stmt() {
                 then_block: vec![Stmt::Let {
                     name: "x".to_string(),
                     value: Expr::Literal(Literal::U32(1)),
+                    mutable: false,
                 }],
                 else_block: Some(vec![Stmt::Let {
                     name: "y".to_string(),
                     value: Expr::Literal(Literal::U32(2)),
+                    mutable: false,
                 }]),
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -315,8 +340,10 @@ fn test_transform_exprs_empty_function() {
             params: vec![],
             return_type: Type::Void,
             body: vec![],
+            effects: None,
         }],
         entry_point: "empty".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -347,9 +374,12 @@ fn test_transform_exprs_nested_expressions() {
                         operand: Box::new(Expr::Literal(Literal::U32(2))),
                     }),
                 },
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let mut transform_count = 0;
@@ -1,12 +1,18 @@
 use clap::Parser;
-use bashrs::cli::{execute_command, Cli};
+use bashrs::cli::{execute_command, report_error, Cli};
 use std::error::Error;
 use std::process;
 
 fn main() {
     let cli = Cli::parse();
+    let message_format = cli.message_format;
+    let source_file = cli.command.source_file().map(|path| path.to_path_buf());
 
     if let Err(error) = execute_command(cli) {
+        if report_error(&error, source_file.as_deref(), message_format) {
+            process::exit(1);
+        }
+
         eprintln!("Error: {error}");
 
         // Print error chain if available
@@ -1,7 +1,8 @@
+use crate::ir::{Effect, EffectPolicy};
 use crate::models::{ShellDialect, VerificationLevel};
 use crate::validation::ValidationLevel;
 use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "bashrs")]
@@ -27,16 +28,38 @@ pub struct Cli {
     #[arg(long)]
     pub strict: bool,
 
+    /// The only effects the script may have, e.g. `fs_read,network`
+    #[arg(long, value_delimiter = ',', value_name = "EFFECTS")]
+    pub allow_effects: Option<Vec<Effect>>,
+
+    /// Effects the script must not have, e.g. `fs_write,system`
+    #[arg(long, value_delimiter = ',', value_name = "EFFECTS")]
+    pub deny_effects: Vec<Effect>,
+
     /// Enable verbose output
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Format of compile error messages
+    #[arg(long, value_enum, default_value = "human")]
+    pub message_format: MessageFormat,
+}
+
+impl Cli {
+    /// Effects the script may have, from `--allow-effects` and `--deny-effects`
+    pub fn effect_policy(&self) -> EffectPolicy {
+        EffectPolicy {
+            allow: self.allow_effects.clone(),
+            deny: self.deny_effects.clone(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Transpile Rust source to shell script
     Build {
-        /// Input Rust file
+        /// Input Rust file, or a project directory or Rash.toml naming the entry file
         #[arg(value_name = "FILE")]
         input: PathBuf,
 
@@ -51,11 +74,15 @@ pub enum Commands {
         /// Disable optimizations
         #[arg(long)]
         no_optimize: bool,
+
+        /// Write a source map next to the output, relating script lines to Rust lines
+        #[arg(long)]
+        source_map: bool,
     },
 
     /// Check Rust source for Rash compatibility
     Check {
-        /// Input Rust file
+        /// Input Rust file, or a project directory or Rash.toml naming the entry file
         #[arg(value_name = "FILE")]
         input: PathBuf,
     },
@@ -142,6 +169,28 @@ pub enum Commands {
     },
 }
 
+impl Commands {
+    /// The Rust source file the command compiles, if any
+    pub fn source_file(&self) -> Option<&Path> {
+        match self {
+            Commands::Build { input, .. } | Commands::Check { input } => Some(input),
+            Commands::Verify { rust_source, .. } | Commands::Compile { rust_source, .. } => {
+                Some(rust_source)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Output format for compile errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// Source snippets with underlined spans
+    Human,
+    /// One JSON object per error, for editors
+    Json,
+}
+
 /// Runtime options for compilation
 #[derive(Clone, Debug, ValueEnum)]
 pub enum CompileRuntime {
@@ -206,7 +255,9 @@ impl ValueEnum for ShellDialect {
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
         Some(match self {
             ShellDialect::Posix => clap::builder::PossibleValue::new("posix"),
-            ShellDialect::Bash => clap::builder::PossibleValue::new("bash"),
+            ShellDialect::Bash => {
+                clap::builder::PossibleValue::new("bash").help("GNU Bash 4.4 or later")
+            }
             ShellDialect::Dash => clap::builder::PossibleValue::new("dash"),
             ShellDialect::Ash => clap::builder::PossibleValue::new("ash"),
         })
@@ -232,3 +283,13 @@ impl ValueEnum for ValidationLevel {
         })
     }
 }
+
+impl ValueEnum for Effect {
+    fn value_variants<'a>() -> &'a [Self] {
+        &Effect::DECLARABLE
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(clap::builder::PossibleValue::new(self.name()).help(self.description()))
+    }
+}
@@ -23,9 +23,10 @@ fn test_build_command() {
         optimize: true,
         strict_mode: false,
         validation_level: None,
+        effects: crate::ir::EffectPolicy::default(),
     };
 
-    let result = build_command(&input_path, &output_path, config);
+    let result = build_command(&input_path, &output_path, config, false);
 
     assert!(result.is_ok());
     assert!(output_path.exists());
@@ -64,11 +65,64 @@ fn test_init_command() {
     assert!(project_path.join("Cargo.toml").exists());
     assert!(project_path.join("src").exists());
     assert!(project_path.join("src/main.rs").exists());
-    assert!(project_path.join(".rash.toml").exists());
+    assert!(project_path.join("Rash.toml").exists());
 
     // Check Cargo.toml contains project name
     let cargo_toml = fs::read_to_string(project_path.join("Cargo.toml")).unwrap();
     assert!(cargo_toml.contains("name = \"test_project\""));
+
+    // Rash.toml names the entry file that `bashrs build` starts from
+    let rash_toml = fs::read_to_string(project_path.join("Rash.toml")).unwrap();
+    assert!(rash_toml.contains("entry = \"src/main.rs\""));
+}
+
+#[test]
+fn test_build_command_builds_initialized_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path();
+    init_command(project_path, Some("demo")).unwrap();
+
+    let output_path = project_path.join("install.sh");
+    build_command(project_path, &output_path, Config::default(), true).unwrap();
+
+    let script = fs::read_to_string(&output_path).unwrap();
+    assert!(script.contains("bin_dir() {"), "{script}");
+
+    // Mappings name the module file each statement came from
+    let map: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(project_path.join("install.sh.map")).unwrap())
+            .unwrap();
+    let sources: Vec<&str> = map["mappings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|mapping| mapping["source"].as_str().unwrap())
+        .collect();
+    assert!(sources.iter().any(|source| source.ends_with("paths.rs")));
+    assert!(sources.iter().any(|source| source.ends_with("main.rs")));
+}
+
+#[test]
+fn test_module_file_errors_name_their_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("main.rs");
+    fs::write(&input_path, "mod util;\nfn main() { util::run(); }\n").unwrap();
+    fs::write(
+        temp_dir.path().join("util.rs"),
+        "pub fn run() {\n    let v = (1, 2);\n}\n",
+    )
+    .unwrap();
+
+    let output_path = temp_dir.path().join("install.sh");
+    let error = build_command(&input_path, &output_path, Config::default(), false).unwrap_err();
+    let Error::InFile { path, inner } = &error else {
+        panic!("Expected an error in util.rs, got {error:?}");
+    };
+    assert_eq!(path, &temp_dir.path().join("util.rs"));
+
+    let source = fs::read_to_string(path).unwrap();
+    let human = render_error(inner, path, &source, MessageFormat::Human).unwrap();
+    assert!(human.contains("util.rs:2:13"), "{human}");
 }
 
 #[test]
@@ -87,6 +141,7 @@ fn test_compile_command_self_extracting() {
         optimize: true,
         validation_level: Some(ValidationLevel::Minimal),
         strict_mode: false,
+        effects: crate::ir::EffectPolicy::default(),
     };
 
     // Test self-extracting script
@@ -129,6 +184,7 @@ fn test_verify_command() {
         optimize: true,
         strict_mode: false,
         validation_level: None,
+        effects: crate::ir::EffectPolicy::default(),
     };
 
     let source = fs::read_to_string(&rust_path).unwrap();
@@ -157,6 +213,7 @@ fn test_generate_proof() {
         optimize: false,
         strict_mode: false,
         validation_level: None,
+        effects: crate::ir::EffectPolicy::default(),
     };
 
     let result = generate_proof("fn main() {}", &proof_path, &config);
@@ -200,12 +257,16 @@ fn test_execute_command_integration() {
             output: output_path.clone(),
             emit_proof: false,
             no_optimize: false,
+            source_map: false,
         },
         verify: VerificationLevel::Basic,
         target: ShellDialect::Posix,
         validation: crate::validation::ValidationLevel::Minimal,
         strict: false,
+        allow_effects: None,
+        deny_effects: vec![],
         verbose: false,
+        message_format: crate::cli::MessageFormat::Human,
     };
 
     let result = execute_command(cli);
@@ -229,6 +290,7 @@ fn test_error_handling() {
         &input_path,
         &PathBuf::from("/nonexistent/dir/output.sh"),
         config,
+        false,
     );
     assert!(result.is_err());
 }
@@ -332,3 +394,62 @@ fn test_inspect_command_all_formats() {
         assert!(result.is_ok(), "Failed with format: {format:?}");
     }
 }
+
+#[test]
+fn test_build_command_writes_source_map() {
+    let temp_dir = TempDir::new().unwrap();
+    let input_path = temp_dir.path().join("install.rs");
+    let output_path = temp_dir.path().join("install.sh");
+    fs::write(
+        &input_path,
+        "fn main() {\n    let x = 42;\n    echo(x);\n}\n\nfn echo(v: u32) {}\n",
+    )
+    .unwrap();
+
+    build_command(&input_path, &output_path, Config::default(), true).unwrap();
+
+    let script = fs::read_to_string(&output_path).unwrap();
+    let map: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp_dir.path().join("install.sh.map")).unwrap())
+            .unwrap();
+    let mappings = map["mappings"].as_array().unwrap();
+    assert_eq!(mappings.len(), 2);
+
+    let echo = &mappings[1];
+    assert_eq!(echo["source_line"], 3);
+    assert_eq!(echo["source_column"], 5);
+    let script_line = echo["script_line"].as_u64().unwrap() as usize;
+    assert_eq!(
+        script.lines().nth(script_line - 1).unwrap().trim(),
+        "echo \"$x\""
+    );
+
+    let names = map["names"].as_array().unwrap();
+    let x = names
+        .iter()
+        .find(|name| name["function"] == "main" && name["rust_name"] == "x")
+        .unwrap();
+    assert_eq!(x["shell_name"], "x");
+    assert_eq!(x["source_line"], 2);
+}
+
+#[test]
+fn test_render_error_formats() {
+    let source = "fn main() {\n    let v = (1, 2);\n}\n";
+    let error = crate::transpile(source, Config::default()).unwrap_err();
+    let file = Path::new("install.rs");
+
+    let json = render_error(&error, file, source, MessageFormat::Json).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["code"], "E0110");
+    assert_eq!(value["spans"][0]["line"], 2);
+    assert_eq!(value["spans"][0]["file"], "install.rs");
+
+    let human = render_error(&error, file, source, MessageFormat::Human).unwrap();
+    assert!(human.starts_with("error[E0110]: tuples are not supported"));
+    assert!(human.contains("install.rs:2:13"));
+
+    // Errors unrelated to the source are left to the caller
+    let io = Error::Io(std::io::Error::other("disk full"));
+    assert!(render_error(&io, file, source, MessageFormat::Human).is_none());
+}
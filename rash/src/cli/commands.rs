@@ -1,9 +1,11 @@
-use crate::cli::args::{CompileRuntime, ContainerFormatArg, InspectionFormat};
+use crate::cli::args::{CompileRuntime, ContainerFormatArg, InspectionFormat, MessageFormat};
 use crate::cli::{Cli, Commands};
+use crate::formatter::source_map::SourceMap;
 use crate::models::{Config, Error, Result};
-use crate::{check, transpile};
+use crate::services::modules::{load, project_entry, SourceFiles, MANIFEST};
+use crate::{transpile, transpile_ast_with_source_map};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
 #[cfg(test)]
@@ -22,12 +24,14 @@ pub fn execute_command(cli: Cli) -> Result<()> {
     tracing::subscriber::set_global_default(subscriber)
         .map_err(|e| Error::Internal(format!("Failed to initialize logging: {e}")))?;
 
+    let effect_policy = cli.effect_policy();
     match cli.command {
         Commands::Build {
             input,
             output,
             emit_proof,
             no_optimize,
+            source_map,
         } => {
             info!("Building {} -> {}", input.display(), output.display());
 
@@ -38,9 +42,10 @@ pub fn execute_command(cli: Cli) -> Result<()> {
                 optimize: !no_optimize,
                 validation_level: Some(cli.validation),
                 strict_mode: cli.strict,
+                effects: effect_policy,
             };
 
-            build_command(&input, &output, config)
+            build_command(&input, &output, config, source_map)
         }
 
         Commands::Check { input } => {
@@ -90,6 +95,7 @@ pub fn execute_command(cli: Cli) -> Result<()> {
                 optimize: true,
                 validation_level: Some(cli.validation),
                 strict_mode: cli.strict,
+                effects: effect_policy,
             };
 
             handle_compile(
@@ -115,21 +121,71 @@ pub fn execute_command(cli: Cli) -> Result<()> {
     }
 }
 
-fn build_command(input: &Path, output: &Path, config: Config) -> Result<()> {
-    // Read input file
-    let source = fs::read_to_string(input).map_err(Error::Io)?;
+/// Print a compile error against the source file it came from.
+///
+/// Returns `false` for errors that don't point into Rust source, which the
+/// caller reports itself.
+pub fn report_error(error: &Error, source_file: Option<&Path>, format: MessageFormat) -> bool {
+    // Errors in a project's module files name the file themselves
+    let (file, error) = match error {
+        Error::InFile { path, inner } => (path.as_path(), &**inner),
+        _ => match source_file {
+            Some(file) => (file, error),
+            None => return false,
+        },
+    };
+    let source = fs::read_to_string(file).unwrap_or_default();
+    match render_error(error, file, &source, format) {
+        Some(rendered) => {
+            eprint!("{rendered}");
+            true
+        }
+        None => false,
+    }
+}
+
+/// Render a compile error as a diagnostic in the requested format
+pub fn render_error(
+    error: &Error,
+    file: &Path,
+    source: &str,
+    format: MessageFormat,
+) -> Option<String> {
+    let diagnostic = error.to_diagnostic()?;
+    let file = file.display().to_string();
+    Some(match format {
+        MessageFormat::Human => diagnostic.render(&file, source),
+        MessageFormat::Json => format!("{}\n", diagnostic.to_json(&file)),
+    })
+}
+
+fn build_command(input: &Path, output: &Path, config: Config, source_map: bool) -> Result<()> {
+    // Read the entry file and the module files it declares
+    let entry = project_entry(input)?;
+    let mut files = SourceFiles::default();
+    let ast = load(&entry, &mut files).map_err(|e| files.attribute(e))?;
 
     // Transpile
-    let shell_code = transpile(&source, config.clone())?;
+    let (shell_code, map) =
+        transpile_ast_with_source_map(&ast, config.clone()).map_err(|e| files.attribute(e))?;
 
     // Write output
-    fs::write(output, shell_code).map_err(Error::Io)?;
+    fs::write(output, &shell_code).map_err(Error::Io)?;
 
     info!("Successfully transpiled to {}", output.display());
 
+    if source_map {
+        let mut map_path = output.as_os_str().to_owned();
+        map_path.push(".map");
+        let map_path = PathBuf::from(map_path);
+        write_source_map(&map_path, &entry, &files, output, &shell_code, &map)?;
+        info!("Source map written to {}", map_path.display());
+    }
+
     // Generate proof if requested
     if config.emit_proof {
         let proof_path = output.with_extension("proof");
+        let source = fs::read_to_string(&entry).map_err(Error::Io)?;
         generate_proof(&source, &proof_path, &config)?;
         info!("Proof generated at {}", proof_path.display());
     }
@@ -137,12 +193,81 @@ fn build_command(input: &Path, output: &Path, config: Config) -> Result<()> {
     Ok(())
 }
 
+/// Write a JSON source map with one entry per shell line that starts a Rust
+/// statement, naming the file of each statement, and the shell variable each
+/// Rust binding became
+fn write_source_map(
+    path: &Path,
+    input: &Path,
+    files: &SourceFiles,
+    output: &Path,
+    shell_code: &str,
+    map: &SourceMap,
+) -> Result<()> {
+    let mappings: Vec<_> = map
+        .reverse_mappings()
+        .into_iter()
+        .filter_map(|(script, rust)| {
+            let file = files.file_at(rust.offset())?;
+            let (source_line, source_column) =
+                line_and_column(&file.source, rust.offset() - file.offset);
+            Some(serde_json::json!({
+                "script_line": line_and_column(shell_code, script.offset()).0,
+                "source": file.path.display().to_string(),
+                "source_line": source_line,
+                "source_column": source_column,
+            }))
+        })
+        .collect();
+    let names: Vec<_> = map
+        .names()
+        .iter()
+        .map(|name| {
+            let mut entry = serde_json::json!({
+                "function": name.function,
+                "rust_name": name.rust_name,
+                "shell_name": name.shell_name,
+            });
+            let offset = name.original.map(|pos| pos.offset());
+            if let Some((file, offset)) = offset.and_then(|o| Some((files.file_at(o)?, o))) {
+                let (source_line, source_column) =
+                    line_and_column(&file.source, offset - file.offset);
+                entry["source"] = file.path.display().to_string().into();
+                entry["source_line"] = source_line.into();
+                entry["source_column"] = source_column.into();
+            }
+            entry
+        })
+        .collect();
+
+    let document = serde_json::json!({
+        "version": 1,
+        "source": input.display().to_string(),
+        "script": output.display().to_string(),
+        "mappings": mappings,
+        "names": names,
+    });
+    fs::write(path, serde_json::to_string_pretty(&document)?).map_err(Error::Io)
+}
+
+/// 1-based line and column of a byte offset
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
 fn check_command(input: &Path) -> Result<()> {
-    // Read input file
-    let source = fs::read_to_string(input).map_err(Error::Io)?;
+    // Read the entry file and the module files it declares
+    let entry = project_entry(input)?;
+    let mut files = SourceFiles::default();
+    let ast = load(&entry, &mut files).map_err(|e| files.attribute(e))?;
 
     // Check compatibility
-    check(&source)?;
+    crate::ast::validate(&ast).map_err(|e| files.attribute(e))?;
 
     info!("✓ {} is compatible with Rash", input.display());
     Ok(())
@@ -182,85 +307,36 @@ path = "src/main.rs"
     let src_dir = path.join("src");
     fs::create_dir_all(&src_dir).map_err(Error::Io)?;
 
-    // Create main.rs with example installer
-    let main_rs = r#"/// Example installer script for Rash
-/// This will be transpiled to POSIX-compliant shell script
-use std::env;
-use std::fs;
-use std::path::Path;
-use std::process::{Command, exit};
+    // Create main.rs with an example installer split across modules
+    let main_rs = r#"//! Example installer, transpiled to a POSIX shell script by `bashrs build`
+mod paths;
 
-const VERSION: &str = "0.1.0";
-const BINARY_NAME: &str = "myapp";
+const PREFIX: &str = "/usr/local";
 
 fn main() {
-    println!("{} installer v{}", BINARY_NAME, VERSION);
-    println!("=======================");
-    
-    // Parse arguments
-    let args: Vec<String> = env::args().collect();
-    if args.contains(&"--help".to_string()) {
-        print_help();
-        return;
-    }
-    
-    // Determine installation directory
-    let prefix = env::var("PREFIX").unwrap_or_else(|_| "/usr/local".to_string());
-    let bin_dir = format!("{}/bin", prefix);
-    
-    println!("Installing to: {}", bin_dir);
-    
-    // Create directory
-    if let Err(e) = fs::create_dir_all(&bin_dir) {
-        eprintln!("Failed to create directory: {}", e);
-        exit(1);
-    }
-    
-    // Download binary (example URL)
-    let url = format!(
-        "https://github.com/example/{}/releases/download/v{}/{}-{}.tar.gz",
-        BINARY_NAME, VERSION, BINARY_NAME, detect_platform()
-    );
-    
-    println!("Downloading from: {}", url);
-    
-    // TODO: Add your actual installation logic here
-    // - Download binary
-    // - Verify checksum
-    // - Extract and install
-    // - Set permissions
-    
-    println!("\n✓ {} installed successfully!", BINARY_NAME);
-    println!("\nTo get started, run:");
-    println!("  {} --help", BINARY_NAME);
+    let bin_dir = paths::bin_dir(PREFIX);
+    println!("Installing to {bin_dir}");
 }
+"#;
+
+    fs::write(src_dir.join("main.rs"), main_rs).map_err(Error::Io)?;
+
+    let paths_rs = r#"//! Install locations shared by the installer's steps
 
-fn print_help() {
-    println!("Usage: install.sh [OPTIONS]");
-    println!("\nOptions:");
-    println!("  --help       Show this help message");
-    println!("  --prefix DIR Install to DIR (default: /usr/local)");
+pub fn bin_dir(prefix: &str) -> String {
+    format!("{prefix}/bin")
 }
+"#;
 
-fn detect_platform() -> &'static str {
-    // Simplified platform detection
-    if cfg!(target_os = "linux") {
-        if cfg!(target_arch = "x86_64") {
-            "linux-amd64"
-        } else {
-            "linux-arm64"
-        }
-    } else if cfg!(target_os = "macos") {
-        "darwin-amd64"
-    } else {
-        panic!("Unsupported platform");
-    }
-}"#;
+    fs::write(src_dir.join("paths.rs"), paths_rs).map_err(Error::Io)?;
 
-    fs::write(src_dir.join("main.rs"), main_rs).map_err(Error::Io)?;
+    // Create Rash.toml, which names the entry file for `bashrs build`
+    let rash_toml = format!(
+        r##"[package]
+name = "{project_name}"
+version = "0.1.0"
+entry = "src/main.rs"     # Modules are found from here, as rustc does
 
-    // Create .rash.toml
-    let rash_toml = r##"# Rash configuration file
 [transpiler]
 target = "posix"          # Target shell dialect
 strict_mode = true        # Fail on warnings
@@ -279,13 +355,14 @@ optimize_size = true      # Minimize output script size
 [style]
 indent = "    "           # 4 spaces
 max_line_length = 100     # Wrap long commands
-"##;
+"##
+    );
 
-    fs::write(path.join(".rash.toml"), rash_toml).map_err(Error::Io)?;
+    fs::write(path.join(MANIFEST), rash_toml).map_err(Error::Io)?;
 
     info!("✓ Initialized Rash project '{}'", project_name);
     info!("  Run 'cd {}' to enter the project", path.display());
-    info!("  Run 'rash build src/main.rs' to build");
+    info!("  Run 'bashrs build .' to build");
 
     Ok(())
 }
@@ -308,6 +385,7 @@ fn verify_command(
         optimize: true,
         strict_mode: true,
         validation_level: Some(crate::validation::ValidationLevel::Strict),
+        effects: crate::ir::EffectPolicy::default(),
     };
 
     let generated_shell = transpile(&rust_code, config)?;
@@ -4,5 +4,5 @@ pub mod commands;
 #[cfg(test)]
 mod tests;
 
-pub use args::{Cli, Commands};
-pub use commands::execute_command;
+pub use args::{Cli, Commands, MessageFormat};
+pub use commands::{execute_command, render_error, report_error};
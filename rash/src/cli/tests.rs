@@ -14,11 +14,13 @@ fn test_cli_build_command() {
             output,
             emit_proof,
             no_optimize,
+            source_map,
         } => {
             assert_eq!(input, PathBuf::from("test.rs"));
             assert_eq!(output, PathBuf::from("install.sh"));
             assert!(!emit_proof);
             assert!(!no_optimize);
+            assert!(!source_map);
         }
         _ => panic!("Expected Build command"),
     }
@@ -50,11 +52,13 @@ fn test_cli_build_with_options() {
             output,
             emit_proof,
             no_optimize,
+            source_map,
         } => {
             assert_eq!(input, PathBuf::from("test.rs"));
             assert_eq!(output, PathBuf::from("test.sh"));
             assert!(emit_proof);
             assert!(!no_optimize);
+            assert!(!source_map);
         }
         _ => panic!("Expected Build command"),
     }
@@ -235,11 +239,13 @@ fn test_cli_with_all_options() {
             output,
             emit_proof,
             no_optimize,
+            source_map,
         } => {
             assert_eq!(input, PathBuf::from("complex.rs"));
             assert_eq!(output, PathBuf::from("complex.sh"));
             assert!(emit_proof);
             assert!(no_optimize);
+            assert!(!source_map);
         }
         _ => panic!("Expected Build command"),
     }
@@ -258,3 +264,31 @@ fn test_init_with_path() {
         _ => panic!("Expected Init command"),
     }
 }
+
+#[test]
+fn test_effect_flags() {
+    use crate::ir::Effect;
+
+    let cli = Cli::parse_from(["rash", "build", "test.rs"]);
+    assert!(cli.effect_policy().is_unrestricted());
+
+    let cli = Cli::parse_from([
+        "rash",
+        "--allow-effects",
+        "fs_read,network",
+        "--deny-effects",
+        "network",
+        "build",
+        "test.rs",
+    ]);
+    let policy = cli.effect_policy();
+    assert_eq!(
+        policy.allow,
+        Some(vec![Effect::FileRead, Effect::NetworkAccess])
+    );
+    assert!(policy.permits(&Effect::FileRead));
+    assert!(!policy.permits(&Effect::NetworkAccess));
+    assert!(!policy.permits(&Effect::FileWrite));
+
+    assert!(Cli::try_parse_from(["rash", "--deny-effects", "disk", "build", "t.rs"]).is_err());
+}
@@ -0,0 +1,29 @@
+use super::dialect::Bash;
+use super::{Emitter, PosixEmitter};
+use crate::formatter::source_map::SourceMap;
+use crate::ir::ShellIR;
+use crate::models::{Config, Result};
+
+/// Emits Bash scripts: `[[ ]]` tests, `local` variables, `pipefail` and
+/// native case conversion, on top of the shared lowering
+pub struct BashEmitter {
+    inner: PosixEmitter,
+}
+
+impl BashEmitter {
+    pub fn new(config: Config) -> Self {
+        Self {
+            inner: PosixEmitter::with_dialect(config, Box::new(Bash)),
+        }
+    }
+}
+
+impl Emitter for BashEmitter {
+    fn emit(&self, ir: &ShellIR) -> Result<String> {
+        self.inner.emit(ir)
+    }
+
+    fn emit_with_source_map(&self, ir: &ShellIR) -> Result<(String, SourceMap)> {
+        self.inner.emit_with_source_map(ir)
+    }
+}
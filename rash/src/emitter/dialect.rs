@@ -0,0 +1,213 @@
+//! Syntax that differs between the shells the emitter targets.
+//!
+//! The lowering from IR to shell is shared; a [`Dialect`] only decides how
+//! individual constructs are spelled. Every hook defaults to POSIX sh, so a
+//! dialect overrides just the constructs its shell does better.
+
+use crate::ir::LetterCase;
+use crate::models::ShellDialect;
+
+pub trait Dialect {
+    fn shebang(&self) -> &'static str {
+        "#!/bin/sh"
+    }
+
+    /// One-line description written under the shebang
+    fn description(&self) -> &'static str {
+        "POSIX-compliant shell script"
+    }
+
+    /// Strict-mode options set before anything runs
+    fn shell_options(&self) -> &'static str {
+        "set -euf"
+    }
+
+    /// A test command over already-rendered operands
+    fn test(&self, operands: &str) -> String {
+        format!("[ {operands} ]")
+    }
+
+    /// A test for a non-empty word
+    fn test_non_empty(&self, word: &str) -> String {
+        format!("test -n {word}")
+    }
+
+    /// Bind a function parameter to its positional argument
+    fn parameter(&self, name: &str, position: usize) -> String {
+        format!("{name}=\"${position}\"")
+    }
+
+    /// Whether `local` scopes variables to the function that declares them
+    fn has_local(&self) -> bool {
+        false
+    }
+
+    /// Lines that bind an immutable variable. `value` is a rendered word;
+    /// `is_constant` is false when rendering it runs commands.
+    fn readonly(&self, name: &str, value: &str, _is_constant: bool) -> Vec<String> {
+        vec![format!("readonly {name}={value}")]
+    }
+
+    /// Lines that bind an immutable variable to `value`, a rendered command
+    /// substitution, returning the command's status from the enclosing
+    /// function when it fails
+    fn readonly_or_return(&self, name: &str, value: &str) -> Vec<String> {
+        // `readonly name=$(cmd)` would discard the command's exit status
+        self.readonly_assigned(name, &format!("{name}={value} || return $?"))
+    }
+
+    /// Lines that make `name` an immutable variable set by `assignment`, a
+    /// command that assigns it
+    fn readonly_assigned(&self, name: &str, assignment: &str) -> Vec<String> {
+        vec![assignment.to_string(), format!("readonly {name}")]
+    }
+
+    /// A parameter expansion converting a variable's letters, when the shell has one
+    fn change_case(&self, _name: &str, _case: LetterCase) -> Option<String> {
+        None
+    }
+
+    /// Whether list variables are arrays. Without arrays a list is stored as
+    /// one word of quoted elements, which `eval "set -- $list"` unpacks.
+    fn has_arrays(&self) -> bool {
+        false
+    }
+
+    /// A list variable as one word of quoted elements, as shell functions take lists
+    fn list_argument(&self, name: &str) -> String {
+        format!("\"${name}\"")
+    }
+
+    /// The elements of a list variable as separate words, and a command that
+    /// must run first in the same statement
+    fn list_elements(&self, name: &str) -> (Option<String>, String) {
+        (
+            Some(format!("eval \"set -- ${name}\"")),
+            "\"$@\"".to_string(),
+        )
+    }
+
+    /// The number of elements in a list variable (without quotes)
+    fn list_length(&self, name: &str) -> String {
+        format!("$(eval \"set -- ${name}\"; printf '%s' \"$#\")")
+    }
+
+    /// Element `index` (an arithmetic expression) of a list variable, without
+    /// quotes, and a command that must run first in the same statement. An
+    /// index past the end aborts the script. `temporary` is a free variable name.
+    fn list_item(&self, name: &str, index: &str, temporary: &str) -> (Option<String>, String) {
+        // Positional parameters count from one
+        let position = match index.parse::<u64>() {
+            Ok(index) => (index + 1).to_string(),
+            Err(_) => format!("$(({index} + 1))"),
+        };
+        let prelude = format!(
+            "eval \"set -- ${name}\"; eval \"{temporary}=\\\"\\${{{position}?index out of bounds}}\\\"\""
+        );
+        (Some(prelude), format!("${temporary}"))
+    }
+
+    /// Turn a list parameter back into a list variable, where that takes a command
+    fn unpack_list(&self, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+/// POSIX sh, also used for dash and ash
+pub struct Posix;
+
+impl Dialect for Posix {}
+
+/// GNU Bash 4.4 or later, the first release with the `@Q` expansion that
+/// passes lists to functions
+pub struct Bash;
+
+impl Dialect for Bash {
+    fn shebang(&self) -> &'static str {
+        "#!/bin/bash"
+    }
+
+    fn description(&self) -> &'static str {
+        "Bash script"
+    }
+
+    fn shell_options(&self) -> &'static str {
+        "set -euf -o pipefail"
+    }
+
+    fn test(&self, operands: &str) -> String {
+        format!("[[ {operands} ]]")
+    }
+
+    fn test_non_empty(&self, word: &str) -> String {
+        self.test(&format!("-n {word}"))
+    }
+
+    fn parameter(&self, name: &str, position: usize) -> String {
+        format!("local {name}=\"${position}\"")
+    }
+
+    fn has_local(&self) -> bool {
+        true
+    }
+
+    fn readonly(&self, name: &str, value: &str, is_constant: bool) -> Vec<String> {
+        if is_constant {
+            vec![format!("local -r {name}={value}")]
+        } else {
+            // `local -r name=$(cmd)` would discard the command's exit status
+            vec![
+                format!("local {name}"),
+                format!("{name}={value}"),
+                format!("readonly {name}"),
+            ]
+        }
+    }
+
+    fn readonly_assigned(&self, name: &str, assignment: &str) -> Vec<String> {
+        vec![
+            format!("local {name}"),
+            assignment.to_string(),
+            format!("readonly {name}"),
+        ]
+    }
+
+    fn change_case(&self, name: &str, case: LetterCase) -> Option<String> {
+        Some(match case {
+            LetterCase::Lower => format!("${{{name},,}}"),
+            LetterCase::Upper => format!("${{{name}^^}}"),
+        })
+    }
+
+    fn has_arrays(&self) -> bool {
+        true
+    }
+
+    fn list_argument(&self, name: &str) -> String {
+        format!("\"${{{name}[*]@Q}}\"")
+    }
+
+    fn list_elements(&self, name: &str) -> (Option<String>, String) {
+        (None, format!("\"${{{name}[@]}}\""))
+    }
+
+    fn list_length(&self, name: &str) -> String {
+        format!("${{#{name}[@]}}")
+    }
+
+    fn list_item(&self, name: &str, index: &str, _temporary: &str) -> (Option<String>, String) {
+        (None, format!("${{{name}[{index}]?index out of bounds}}"))
+    }
+
+    fn unpack_list(&self, name: &str) -> Option<String> {
+        Some(format!("eval \"local -a {name}=(${name})\""))
+    }
+}
+
+/// The dialect that emits scripts for `target`
+pub fn for_target(target: ShellDialect) -> Box<dyn Dialect> {
+    match target {
+        ShellDialect::Bash => Box::new(Bash),
+        ShellDialect::Posix | ShellDialect::Dash | ShellDialect::Ash => Box::new(Posix),
+    }
+}
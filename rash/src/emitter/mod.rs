@@ -15,8 +15,9 @@
 //!
 //! The emitter consists of:
 //! - **Escape Module**: Handles string escaping and shell-safe formatting
-//! - **POSIX Emitter**: Generates POSIX-compliant shell code
-//! - **Dialect Support**: Extensible architecture for different shell dialects
+//! - **POSIX Emitter**: Lowers IR to shell code shared by every dialect
+//! - **Dialects**: The [`dialect::Dialect`] trait spells constructs per shell;
+//!   [`BashEmitter`] uses it for `[[ ]]`, `local` and `pipefail`
 //!
 //! ## Examples
 //!
@@ -97,16 +98,39 @@
 //! # }
 //! ```
 
+pub mod bash;
+pub mod dialect;
 pub mod escape;
 pub mod posix;
 
 #[cfg(test)]
 mod tests;
 
+pub use bash::BashEmitter;
 pub use posix::PosixEmitter;
 
+use crate::formatter::source_map::SourceMap;
 use crate::ir::ShellIR;
-use crate::models::{Config, Result};
+use crate::models::{Config, Result, ShellDialect};
+
+/// Generates a complete script for one shell dialect
+pub trait Emitter {
+    fn emit(&self, ir: &ShellIR) -> Result<String>;
+
+    /// Emit the script together with a map from Rust source byte offsets to
+    /// the byte offsets of the shell lines they produced
+    fn emit_with_source_map(&self, ir: &ShellIR) -> Result<(String, SourceMap)>;
+}
+
+/// The emitter for the configured target dialect
+pub fn emitter_for(config: &Config) -> Box<dyn Emitter> {
+    match config.target {
+        ShellDialect::Bash => Box::new(BashEmitter::new(config.clone())),
+        ShellDialect::Posix | ShellDialect::Dash | ShellDialect::Ash => {
+            Box::new(PosixEmitter::new(config.clone()))
+        }
+    }
+}
 
 /// Emit shell code from IR based on target dialect
 ///
@@ -144,20 +168,13 @@ use crate::models::{Config, Result};
 /// # }
 /// ```
 pub fn emit(ir: &ShellIR, config: &Config) -> Result<String> {
-    match config.target {
-        crate::models::config::ShellDialect::Posix => {
-            let emitter = PosixEmitter::new(config.clone());
-            emitter.emit(ir)
-        }
-        crate::models::config::ShellDialect::Bash => {
-            // For now, use POSIX emitter for Bash too
-            let emitter = PosixEmitter::new(config.clone());
-            emitter.emit(ir)
-        }
-        _ => {
-            // Default to POSIX for other dialects
-            let emitter = PosixEmitter::new(config.clone());
-            emitter.emit(ir)
-        }
-    }
+    emitter_for(config).emit(ir)
+}
+
+/// Emit shell code together with a source map back to the Rust input
+///
+/// The map relates byte offsets of Rust statements (original positions) to
+/// byte offsets of the shell lines emitted for them (formatted positions).
+pub fn emit_with_source_map(ir: &ShellIR, config: &Config) -> Result<(String, SourceMap)> {
+    emitter_for(config).emit_with_source_map(ir)
 }
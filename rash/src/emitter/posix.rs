@@ -1,53 +1,149 @@
+use super::dialect::{Dialect, Posix};
 use super::escape::{escape_command_name, escape_shell_string, escape_variable_name};
-use crate::ir::{Command, ShellIR, ShellValue};
-use crate::models::{Config, Result};
+use super::Emitter;
+use crate::formatter::source_map::SourceMap;
+use crate::formatter::types::CharPos;
+use crate::ir::{
+    ArithmeticOp, CaseArm, CasePattern, Command, ComparisonOp, ForIter, LetterCase, LogicalOp,
+    ReturnConvention, ShellIR, ShellValue,
+};
+use crate::models::{Config, Error, Result};
+use std::cell::{Cell, RefCell};
 use std::fmt::Write;
 
+/// Mask applied to wrapping arithmetic so results stay within `u32`
+const U32_MASK: &str = "4294967295";
+
+/// Lowers IR to shell code. The lowering is shared by every target; the
+/// dialect decides how individual constructs are spelled.
 pub struct PosixEmitter {
     #[allow(dead_code)]
     config: Config,
+    dialect: Box<dyn Dialect>,
+    /// Source of unique suffixes for the hidden loop counter variables
+    next_loop_id: Cell<usize>,
+    /// Rust source offset to script offset mapping, when one is requested
+    source_map: RefCell<Option<SourceMap>>,
+    /// Commands that must run before the statement being emitted, such as
+    /// unpacking a list into the positional parameters
+    prelude: RefCell<Vec<String>>,
+    /// The list unpacked into the positional parameters for the statement being emitted
+    expanded_list: RefCell<Option<(String, String)>>,
+    /// Whether the statements being emitted belong to a function other than
+    /// main, which may run more than once
+    in_helper: Cell<bool>,
+    /// Runtime library functions the emitted code calls on its own, such as
+    /// `rash_list` for building lists
+    runtime_calls: RefCell<Vec<&'static str>>,
+    /// Whether some function hands its value back on fd 3, so that the
+    /// header must keep the script's stdout open on fd 4
+    returns_on_fd: Cell<bool>,
 }
 
 impl PosixEmitter {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self::with_dialect(config, Box::new(Posix))
+    }
+
+    pub(crate) fn with_dialect(config: Config, dialect: Box<dyn Dialect>) -> Self {
+        Self {
+            config,
+            dialect,
+            next_loop_id: Cell::new(0),
+            source_map: RefCell::new(None),
+            prelude: RefCell::new(Vec::new()),
+            expanded_list: RefCell::new(None),
+            in_helper: Cell::new(false),
+            runtime_calls: RefCell::new(Vec::new()),
+            returns_on_fd: Cell::new(false),
+        }
+    }
+
+    /// Emit the script together with a map from Rust source byte offsets to
+    /// the byte offsets of the shell lines they produced
+    pub fn emit_with_source_map(&self, ir: &ShellIR) -> Result<(String, SourceMap)> {
+        self.source_map.replace(Some(SourceMap::new()));
+        let script = self.emit(ir);
+        let source_map = self.source_map.take().unwrap_or_default();
+        Ok((script?, source_map))
     }
 
     pub fn emit(&self, ir: &ShellIR) -> Result<String> {
+        // The body comes first, since it decides which runtime functions
+        // the header must define
         let mut output = String::new();
+        self.next_loop_id.set(0);
+        self.runtime_calls.borrow_mut().clear();
+        self.returns_on_fd.set(false);
+
+        // Function definitions are hoisted out of main() so that they exist
+        // before any statement calls them
+        let items = match ir {
+            ShellIR::Sequence(items) => items.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let (definitions, statements): (Vec<&ShellIR>, Vec<&ShellIR>) = items
+            .iter()
+            .partition(|item| matches!(item, ShellIR::Function { .. } | ShellIR::Global { .. }));
+
+        // Globals precede the functions that read them
+        let (globals, functions): (Vec<&ShellIR>, Vec<&ShellIR>) = definitions
+            .into_iter()
+            .partition(|item| matches!(item, ShellIR::Global { .. }));
+        for global in &globals {
+            self.emit_ir(&mut output, global, 0)?;
+        }
+        if !globals.is_empty() {
+            writeln!(output)?;
+        }
 
-        // Write the POSIX shell header
-        self.write_header(&mut output)?;
+        for function in functions {
+            if let ShellIR::Function { name, params, body } = function {
+                self.emit_function_definition(&mut output, name, params, body, "", 0)?;
+            }
+        }
+
+        writeln!(output, "# Main script begins")?;
+        writeln!(output, "main() {{")?;
+        let mut assigned = Vec::new();
+        for statement in &statements {
+            collect_assigned_variables(statement, &mut assigned);
+        }
+        self.write_locals(&mut output, &assigned, "    ")?;
 
         // Write the main content
-        self.emit_ir(&mut output, ir, 0)?;
+        for statement in statements {
+            self.emit_ir(&mut output, statement, 0)?;
+        }
 
         // Write the footer
         self.write_footer(&mut output)?;
 
-        Ok(output)
+        let mut script = String::new();
+        self.write_header(&mut script)?;
+        self.write_runtime(&mut script, ir)?;
+        if let Some(source_map) = self.source_map.borrow_mut().as_mut() {
+            *source_map = shift_formatted_positions(source_map, script.len());
+        }
+        script.push_str(&output);
+        Ok(script)
     }
 
     fn write_header(&self, output: &mut String) -> Result<()> {
-        writeln!(output, "#!/bin/sh")?;
+        writeln!(output, "{}", self.dialect.shebang())?;
         writeln!(output, "# Generated by Rash v{}", env!("CARGO_PKG_VERSION"))?;
-        writeln!(output, "# POSIX-compliant shell script")?;
+        writeln!(output, "# {}", self.dialect.description())?;
         writeln!(output)?;
 
         // Set strict error handling
-        writeln!(output, "set -euf")?;
+        writeln!(output, "{}", self.dialect.shell_options())?;
         writeln!(output, "IFS=' \t\n'")?; // POSIX-compatible IFS setting
         writeln!(output, "export LC_ALL=C")?;
-        writeln!(output)?;
-
-        // Include runtime functions if needed
-        if self.needs_runtime() {
-            self.write_runtime(output)?;
+        if self.returns_on_fd.get() {
+            // Functions write their value to fd 3 and their output to fd 4
+            writeln!(output, "exec 4>&1")?;
         }
-
-        writeln!(output, "# Main script begins")?;
-        writeln!(output, "main() {{")?;
-
+        writeln!(output)?;
         Ok(())
     }
 
@@ -63,95 +159,97 @@ impl PosixEmitter {
         Ok(())
     }
 
-    fn write_runtime(&self, output: &mut String) -> Result<()> {
+    /// The runtime library functions the script calls, directly or through
+    /// one another, each spliced in once
+    fn write_runtime(&self, output: &mut String, ir: &ShellIR) -> Result<()> {
+        let mut commands = Vec::new();
+        ir.collect_commands(&mut commands);
+        let runtime_calls = self.runtime_calls.borrow();
+        let names = commands
+            .iter()
+            .map(String::as_str)
+            .chain(runtime_calls.iter().copied());
+        let helpers = bashrs_runtime::helpers_for(names);
+        if helpers.is_empty() {
+            return Ok(());
+        }
         writeln!(output, "# Rash runtime functions")?;
-        self.write_require_function(output)?;
-        self.write_download_function(output)?;
-        Ok(())
-    }
-
-    fn write_require_function(&self, output: &mut String) -> Result<()> {
-        let lines = [
-            "rash_require() {",
-            "    if ! \"$@\"; then",
-            "        echo \"FATAL: Requirement failed: $*\" >&2",
-            "        exit 1",
-            "    fi",
-            "}",
-            "",
-        ];
-        self.write_shell_lines(output, &lines)
-    }
-
-    fn write_download_function(&self, output: &mut String) -> Result<()> {
-        writeln!(output, "rash_download_verified() {{")?;
-        writeln!(output, "    url=\"$1\"; dst=\"$2\"; checksum=\"$3\"")?;
-        writeln!(output, "    ")?;
-
-        self.write_download_logic(output)?;
-        self.write_checksum_logic(output)?;
-
-        writeln!(output, "}}")?;
-        writeln!(output)?;
-        Ok(())
-    }
-
-    fn write_download_logic(&self, output: &mut String) -> Result<()> {
-        let lines = [
-            "    if command -v curl >/dev/null 2>&1; then",
-            "        curl -fsSL --proto '=https' --tlsv1.2 \"$url\" -o \"$dst\"",
-            "    elif command -v wget >/dev/null 2>&1; then",
-            "        wget -qO \"$dst\" \"$url\"",
-            "    else",
-            "        echo \"FATAL: Neither curl nor wget found\" >&2",
-            "        return 1",
-            "    fi",
-            "    ",
-        ];
-        self.write_shell_lines(output, &lines)
-    }
-
-    fn write_checksum_logic(&self, output: &mut String) -> Result<()> {
-        let lines = [
-            "    if command -v sha256sum >/dev/null 2>&1; then",
-            "        echo \"$checksum  $dst\" | sha256sum -c >/dev/null",
-            "    elif command -v shasum >/dev/null 2>&1; then",
-            "        echo \"$checksum  $dst\" | shasum -a 256 -c >/dev/null",
-            "    else",
-            "        echo \"FATAL: No checksum utility found\" >&2",
-            "        return 1",
-            "    fi",
-        ];
-        self.write_shell_lines(output, &lines)
-    }
-
-    fn write_shell_lines(&self, output: &mut String, lines: &[&str]) -> Result<()> {
-        for line in lines {
-            writeln!(output, "{line}")?;
+        for helper in helpers {
+            writeln!(output, "{}", helper.definition)?;
+            writeln!(output)?;
         }
         Ok(())
     }
 
-    fn needs_runtime(&self) -> bool {
-        // For now, always include runtime - later we can analyze the IR to determine if it's needed
-        true
-    }
-
     fn emit_ir(&self, output: &mut String, ir: &ShellIR, indent: usize) -> Result<()> {
         match ir {
             ShellIR::Let { name, value, .. } => {
                 self.emit_let_statement(output, name, value, indent)
             }
+            ShellIR::Assign { name, value, .. } => {
+                self.emit_assign_statement(output, name, value, indent)
+            }
+            ShellIR::LetOption { name, value, .. } => {
+                self.emit_let_option_statement(output, name, value.as_ref(), indent)
+            }
+            ShellIR::Global { name, value } => self.emit_global(output, name, value, indent),
             ShellIR::Exec { cmd, .. } => self.emit_exec_statement(output, cmd, indent),
+            ShellIR::Try {
+                binding,
+                mutable,
+                call,
+                ..
+            } => self.emit_try_statement(output, binding.as_deref(), *mutable, call, indent),
+            ShellIR::Printf {
+                format,
+                args,
+                stderr,
+                ..
+            } => self.emit_printf_statement(output, format, args, *stderr, indent),
             ShellIR::If {
                 test,
                 then_branch,
                 else_branch,
             } => self.emit_if_statement(output, test, then_branch, else_branch.as_deref(), indent),
+            ShellIR::For {
+                var,
+                iter,
+                body,
+                max_iterations,
+            } => self.emit_for_statement(output, var, iter, body, *max_iterations, indent),
+            ShellIR::While {
+                test,
+                body,
+                max_iterations,
+            } => self.emit_while_statement(output, test, body, *max_iterations, indent),
+            ShellIR::Case { scrutinee, arms } => {
+                self.emit_case_statement(output, scrutinee, arms, indent)
+            }
+            ShellIR::Break => self.emit_keyword(output, "break", indent),
+            ShellIR::Continue => self.emit_keyword(output, "continue", indent),
+            ShellIR::Function { name, params, body } => {
+                let indent_str = "    ".repeat(indent + 1);
+                self.emit_function_definition(output, name, params, body, &indent_str, indent + 1)
+            }
+            ShellIR::UnpackList { name } => {
+                if let Some(line) = self.dialect.unpack_list(&escape_variable_name(name)) {
+                    writeln!(output, "{}{line}", "    ".repeat(indent + 1))?;
+                }
+                Ok(())
+            }
+            ShellIR::Return { value, convention } => {
+                self.emit_return_statement(output, value.as_ref(), *convention, indent)
+            }
             ShellIR::Exit { code, message } => {
                 self.emit_exit_statement(output, (*code).into(), message.as_ref(), indent)
             }
             ShellIR::Sequence(items) => self.emit_sequence(output, items, indent),
+            ShellIR::Spanned { span, inner } => {
+                if let Some(source_map) = self.source_map.borrow_mut().as_mut() {
+                    source_map.add_mapping(CharPos(span.start), CharPos(output.len()));
+                }
+                self.emit_ir(output, inner, indent)
+            }
             ShellIR::Noop => self.emit_noop(output, indent),
         }
     }
@@ -163,20 +261,194 @@ impl PosixEmitter {
         value: &ShellValue,
         indent: usize,
     ) -> Result<()> {
+        if self.readonly_is_unsafe() {
+            return self.emit_assign_statement(output, name, value, indent);
+        }
         let indent_str = "    ".repeat(indent + 1);
         let var_name = escape_variable_name(name);
-        let var_value = self.emit_shell_value(value)?;
-        writeln!(output, "{indent_str}readonly {var_name}={var_value}")?;
+        let lines = if is_status(value) {
+            let assignment = self.emit_status_assignment(&var_name, value)?;
+            self.dialect.readonly_assigned(&var_name, &assignment)
+        } else {
+            let var_value = match value {
+                ShellValue::List(elements) if self.dialect.has_arrays() => {
+                    format!("({})", self.emit_words(elements)?.join(" "))
+                }
+                _ => self.emit_shell_value(value)?,
+            };
+            self.dialect
+                .readonly(&var_name, &var_value, value.is_constant())
+        };
+        for (position, line) in lines.iter().enumerate() {
+            if position == 0 {
+                self.write_statement(output, &indent_str, line)?;
+            } else {
+                writeln!(output, "{indent_str}{line}")?;
+            }
+        }
         Ok(())
     }
 
+    fn emit_assign_statement(
+        &self,
+        output: &mut String,
+        name: &str,
+        value: &ShellValue,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let var_name = escape_variable_name(name);
+        if is_status(value) {
+            let assignment = self.emit_status_assignment(&var_name, value)?;
+            return self.write_statement(output, &indent_str, &assignment);
+        }
+        let var_value = match value {
+            ShellValue::List(elements) if self.dialect.has_arrays() => {
+                format!("({})", self.emit_words(elements)?.join(" "))
+            }
+            _ => self.emit_shell_value(value)?,
+        };
+        let line = format!("{var_name}={var_value}");
+        self.write_statement(output, &indent_str, &line)
+    }
+
+    /// Set a variable to the word `true` or `false` by the status of a
+    /// condition. Unlike a status capture, this leaves the output of the
+    /// commands the condition runs on stdout.
+    fn emit_status_assignment(&self, var_name: &str, test: &ShellValue) -> Result<String> {
+        let test_expr = self.emit_test_expression(test)?;
+        Ok(format!(
+            "if {test_expr}; then {var_name}=true; else {var_name}=false; fi"
+        ))
+    }
+
+    /// Bind an `Option` variable: set when it holds a value, unset otherwise
+    fn emit_let_option_statement(
+        &self,
+        output: &mut String,
+        name: &str,
+        value: Option<&ShellValue>,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let var_name = escape_variable_name(name);
+        let line = match value {
+            None => format!("unset {var_name}"),
+            Some(ShellValue::FunctionCall { name, args, .. }) => {
+                let invocation = self.emit_result_invocation(name, args)?;
+                format!("{var_name}=\"$({invocation})\" || unset {var_name}")
+            }
+            Some(other) => {
+                return Err(Error::Emission(format!(
+                    "Cannot bind {other:?} as an option"
+                )))
+            }
+        };
+        self.write_statement(output, &indent_str, &line)
+    }
+
     fn emit_exec_statement(&self, output: &mut String, cmd: &Command, indent: usize) -> Result<()> {
         let indent_str = "    ".repeat(indent + 1);
         let command_str = self.emit_command(cmd)?;
-        writeln!(output, "{indent_str}{command_str}")?;
+        self.write_statement(output, &indent_str, &command_str)
+    }
+
+    /// A call that returns its status from the enclosing function when it fails
+    fn emit_try_statement(
+        &self,
+        output: &mut String,
+        binding: Option<&str>,
+        mutable: bool,
+        call: &ShellValue,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        // The value of `f()?;` is unused, so it must not reach our stdout
+        let invocation = match (call, binding) {
+            (
+                ShellValue::FunctionCall {
+                    name,
+                    args,
+                    convention: ReturnConvention::Stdout,
+                },
+                Some(_),
+            ) => self.emit_result_invocation(name, args)?,
+            (
+                ShellValue::FunctionCall {
+                    name,
+                    args,
+                    convention: ReturnConvention::Stdout,
+                },
+                None,
+            ) => self.emit_discarded_invocation(name, args)?,
+            (ShellValue::FunctionCall { name, args, .. }, _) => self.emit_invocation(name, args)?,
+            (ShellValue::CommandSubst(cmd), Some(_)) => self.emit_command(cmd)?,
+            (ShellValue::CommandSubst(cmd), None) => {
+                format!("{} >/dev/null", self.emit_command(cmd)?)
+            }
+            _ => return Err(Error::Emission(format!("`?` applied to {call:?}"))),
+        };
+
+        let lines = match binding {
+            Some(binding) if mutable || self.readonly_is_unsafe() => vec![format!(
+                "{}=\"$({invocation})\" || return $?",
+                escape_variable_name(binding)
+            )],
+            Some(binding) => self.dialect.readonly_or_return(
+                &escape_variable_name(binding),
+                &format!("\"$({invocation})\""),
+            ),
+            None => vec![format!("{invocation} || return $?")],
+        };
+        for (position, line) in lines.iter().enumerate() {
+            if position == 0 {
+                self.write_statement(output, &indent_str, line)?;
+            } else {
+                writeln!(output, "{indent_str}{line}")?;
+            }
+        }
         Ok(())
     }
 
+    /// `readonly` at the top level of the script, not a function-local binding
+    fn emit_global(
+        &self,
+        output: &mut String,
+        name: &str,
+        value: &ShellValue,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent);
+        let value = self.emit_shell_value(value)?;
+        let line = format!("readonly {}={value}", escape_variable_name(name));
+        self.write_statement(output, &indent_str, &line)
+    }
+
+    fn emit_printf_statement(
+        &self,
+        output: &mut String,
+        format: &str,
+        args: &[ShellValue],
+        stderr: bool,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let mut line = String::from("printf ");
+        if format.starts_with('-') {
+            // Keep printf from reading the format as an option
+            line.push_str("-- ");
+        }
+        line.push_str(&escape_shell_string(format));
+        for arg in args {
+            line.push(' ');
+            line.push_str(&self.emit_shell_value(arg)?);
+        }
+        if stderr {
+            line.push_str(" >&2");
+        }
+        self.write_statement(output, &indent_str, &line)
+    }
+
     fn emit_if_statement(
         &self,
         output: &mut String,
@@ -187,19 +459,380 @@ impl PosixEmitter {
     ) -> Result<()> {
         let indent_str = "    ".repeat(indent + 1);
         let test_expr = self.emit_test_expression(test)?;
-        writeln!(output, "{indent_str}if {test_expr}; then")?;
+        self.write_statement(output, &indent_str, &format!("if {test_expr}; then"))?;
 
-        self.emit_ir(output, then_branch, indent + 1)?;
+        self.emit_block(output, then_branch, indent + 1)?;
 
         if let Some(else_ir) = else_branch {
             writeln!(output, "{indent_str}else")?;
-            self.emit_ir(output, else_ir, indent + 1)?;
+            self.emit_block(output, else_ir, indent + 1)?;
+        }
+
+        writeln!(output, "{indent_str}fi")?;
+        Ok(())
+    }
+
+    fn emit_for_statement(
+        &self,
+        output: &mut String,
+        var: &str,
+        iter: &ForIter,
+        body: &ShellIR,
+        max_iterations: u32,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let var_name = escape_variable_name(var);
+
+        match iter {
+            ForIter::Words(words) => {
+                let mut word_list = String::new();
+                for word in words {
+                    word_list.push(' ');
+                    word_list.push_str(&self.emit_shell_value(word)?);
+                }
+                let header = format!("for {var_name} in{word_list}; do");
+                self.write_statement(output, &indent_str, &header)?;
+                self.emit_ir(output, body, indent + 1)?;
+                writeln!(output, "{indent_str}done")?;
+            }
+            ForIter::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                // Hidden counters are unique per loop so that nested loops and loops
+                // in called functions never share them; the user variable is assigned
+                // before the body runs so that `continue` cannot skip the increment.
+                let loop_id = self.loop_id();
+                let counter = format!("_rash_i_{loop_id}");
+                let limit = format!("_rash_end_{loop_id}");
+                let test_op = if *inclusive { "-le" } else { "-lt" };
+                let inner_indent = "    ".repeat(indent + 2);
+
+                let start_word = self.emit_shell_value(start)?;
+                self.write_statement(output, &indent_str, &format!("{counter}={start_word}"))?;
+                let end_word = self.emit_shell_value(end)?;
+                self.write_statement(output, &indent_str, &format!("{limit}={end_word}"))?;
+                if !(start.is_constant() && end.is_constant()) {
+                    self.emit_iteration_bound_check(
+                        output,
+                        &counter,
+                        &limit,
+                        *inclusive,
+                        max_iterations,
+                        indent,
+                    )?;
+                }
+                let test = self
+                    .dialect
+                    .test(&format!("\"${counter}\" {test_op} \"${limit}\""));
+                writeln!(output, "{indent_str}while {test}; do")?;
+                writeln!(output, "{inner_indent}{var_name}=\"${counter}\"")?;
+                writeln!(output, "{inner_indent}{counter}=$(({counter} + 1))")?;
+                self.emit_ir(output, body, indent + 1)?;
+                writeln!(output, "{indent_str}done")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_while_statement(
+        &self,
+        output: &mut String,
+        test: &ShellValue,
+        body: &ShellIR,
+        max_iterations: u32,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let inner_indent = "    ".repeat(indent + 2);
+        let guard = format!("_rash_guard_{}", self.loop_id());
+        let test_expr = self.emit_test_expression(test)?;
+        let message =
+            escape_shell_string(&format!("FATAL: loop exceeds {max_iterations} iterations"));
+
+        // The guard is bumped before the body so that `continue` cannot bypass it
+        writeln!(output, "{indent_str}{guard}=0")?;
+        self.write_statement(output, &indent_str, &format!("while {test_expr}; do"))?;
+        writeln!(output, "{inner_indent}{guard}=$(({guard} + 1))")?;
+        let exceeded = self
+            .dialect
+            .test(&format!("\"${guard}\" -gt {max_iterations}"));
+        writeln!(output, "{inner_indent}if {exceeded}; then")?;
+        writeln!(output, "{inner_indent}    printf '%s\\n' {message} >&2")?;
+        writeln!(output, "{inner_indent}    exit 1")?;
+        writeln!(output, "{inner_indent}fi")?;
+        self.emit_ir(output, body, indent + 1)?;
+        writeln!(output, "{indent_str}done")?;
+        Ok(())
+    }
+
+    fn emit_case_statement(
+        &self,
+        output: &mut String,
+        scrutinee: &ShellValue,
+        arms: &[CaseArm],
+        indent: usize,
+    ) -> Result<()> {
+        if arms.iter().all(|arm| arm.guard.is_none()) {
+            return self.emit_case_arms(output, scrutinee, arms, indent);
+        }
+        // Guarded arms compare the scrutinee in every test of an if/elif
+        // chain, so anything more expensive than a plain word is evaluated once
+        if matches!(
+            scrutinee,
+            ShellValue::Variable(_) | ShellValue::String(_) | ShellValue::Bool(_)
+        ) {
+            return self.emit_guarded_arms(output, scrutinee, arms, indent);
+        }
+        let indent_str = "    ".repeat(indent + 1);
+        let temporary = format!("_rash_match_{}", self.loop_id());
+        let value = self.emit_shell_value(scrutinee)?;
+        self.write_statement(output, &indent_str, &format!("{temporary}={value}"))?;
+        self.emit_guarded_arms(output, &ShellValue::Variable(temporary), arms, indent)
+    }
+
+    fn emit_case_arms(
+        &self,
+        output: &mut String,
+        scrutinee: &ShellValue,
+        arms: &[CaseArm],
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let arm_indent = "    ".repeat(indent + 2);
+        let body_indent = "    ".repeat(indent + 3);
+
+        let word = self.emit_shell_value(scrutinee)?;
+        self.write_statement(output, &indent_str, &format!("case {word} in"))?;
+        for arm in arms {
+            let patterns = arm
+                .patterns
+                .iter()
+                .map(|pattern| match pattern {
+                    CasePattern::Literal(text) => escape_shell_string(text),
+                    CasePattern::Wildcard => "*".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(output, "{arm_indent}{patterns})")?;
+            if let Some(binding) = &arm.binding {
+                let value = self.emit_shell_value(scrutinee)?;
+                let assignment = format!("{}={value}", escape_variable_name(binding));
+                self.write_statement(output, &body_indent, &assignment)?;
+            }
+            self.emit_block(output, &arm.body, indent + 2)?;
+            writeln!(output, "{body_indent};;")?;
+        }
+        writeln!(output, "{indent_str}esac")?;
+        Ok(())
+    }
+
+    /// A match with guards, as an if/elif chain that tests each arm in turn,
+    /// so that a failed guard moves on to the next arm and every body is
+    /// emitted once
+    fn emit_guarded_arms(
+        &self,
+        output: &mut String,
+        scrutinee: &ShellValue,
+        arms: &[CaseArm],
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        // Guards may read the bindings
+        for binding in arms.iter().filter_map(|arm| arm.binding.as_ref()) {
+            let value = self.emit_shell_value(scrutinee)?;
+            let assignment = format!("{}={value}", escape_variable_name(binding));
+            self.write_statement(output, &indent_str, &assignment)?;
+        }
+
+        let mut opened = false;
+        for (index, arm) in arms.iter().enumerate() {
+            let Some(test) = arm_test(scrutinee, arm) else {
+                // Every value reaches this arm, and none gets past it
+                if !opened {
+                    return self.emit_ir(output, &arm.body, indent);
+                }
+                if !is_empty_body(&arm.body) {
+                    writeln!(output, "{indent_str}else")?;
+                    self.emit_block(output, &arm.body, indent + 1)?;
+                }
+                break;
+            };
+            let test_expr = self.emit_test_expression(&test)?;
+            if !opened {
+                self.write_statement(output, &indent_str, &format!("if {test_expr}; then"))?;
+                opened = true;
+            } else if self.prelude.borrow().is_empty() && self.expanded_list.borrow().is_none() {
+                writeln!(output, "{indent_str}elif {test_expr}; then")?;
+            } else {
+                // The commands the test depends on cannot precede `elif`
+                self.prelude.take();
+                self.expanded_list.take();
+                writeln!(output, "{indent_str}else")?;
+                self.emit_guarded_arms(output, scrutinee, &arms[index..], indent + 1)?;
+                break;
+            }
+            self.emit_block(output, &arm.body, indent + 1)?;
+        }
+        if opened {
+            writeln!(output, "{indent_str}fi")?;
+        }
+        Ok(())
+    }
+
+    /// Emit a compound command body, which the shell does not allow to be empty
+    fn emit_block(&self, output: &mut String, body: &ShellIR, indent: usize) -> Result<()> {
+        if is_empty_body(body) {
+            writeln!(output, "{}:", "    ".repeat(indent + 1))?;
+            return Ok(());
+        }
+        self.emit_ir(output, body, indent)
+    }
+
+    /// Write a statement line, preceded by the commands its words depend on
+    fn write_statement(&self, output: &mut String, indent_str: &str, line: &str) -> Result<()> {
+        let mut prelude = self.prelude.take();
+        if let Some((_, unpack)) = self.expanded_list.take() {
+            // Runs last: element lookups reuse the positional parameters
+            prelude.push(unpack);
+        }
+        let mut prefix = String::new();
+        for command in prelude {
+            prefix.push_str(&command);
+            prefix.push_str("; ");
         }
+        writeln!(output, "{indent_str}{prefix}{line}")?;
+        Ok(())
+    }
+
+    fn loop_id(&self) -> usize {
+        let id = self.next_loop_id.get();
+        self.next_loop_id.set(id + 1);
+        id
+    }
+
+    fn emit_keyword(&self, output: &mut String, keyword: &str, indent: usize) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        writeln!(output, "{indent_str}{keyword}")?;
+        Ok(())
+    }
+
+    /// Abort before entering a range loop whose runtime trip count exceeds its bound
+    fn emit_iteration_bound_check(
+        &self,
+        output: &mut String,
+        counter: &str,
+        limit: &str,
+        inclusive: bool,
+        max_iterations: u32,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        let inner_indent = "    ".repeat(indent + 2);
+        let trip_count = if inclusive {
+            format!("$(({limit} - {counter} + 1))")
+        } else {
+            format!("$(({limit} - {counter}))")
+        };
+        let message =
+            escape_shell_string(&format!("FATAL: loop exceeds {max_iterations} iterations"));
 
+        let exceeded = self
+            .dialect
+            .test(&format!("\"{trip_count}\" -gt {max_iterations}"));
+        writeln!(output, "{indent_str}if {exceeded}; then")?;
+        writeln!(output, "{inner_indent}printf '%s\\n' {message} >&2")?;
+        writeln!(output, "{inner_indent}exit 1")?;
         writeln!(output, "{indent_str}fi")?;
         Ok(())
     }
 
+    fn emit_function_definition(
+        &self,
+        output: &mut String,
+        name: &str,
+        params: &[String],
+        body: &ShellIR,
+        indent_str: &str,
+        body_indent: usize,
+    ) -> Result<()> {
+        let inner_indent = "    ".repeat(body_indent + 1);
+        writeln!(output, "{indent_str}{}() {{", escape_variable_name(name))?;
+
+        for (position, param) in params.iter().enumerate() {
+            let param_name = escape_variable_name(param);
+            writeln!(
+                output,
+                "{inner_indent}{}",
+                self.dialect.parameter(&param_name, position + 1)
+            )?;
+        }
+
+        let mut assigned = Vec::new();
+        collect_assigned_variables(body, &mut assigned);
+        self.write_locals(output, &assigned, &inner_indent)?;
+
+        // A function body must contain at least one command
+        if params.is_empty() && is_empty_body(body) {
+            writeln!(output, "{inner_indent}:")?;
+        }
+
+        let in_helper = self.in_helper.replace(true);
+        let result = self.emit_ir(output, body, body_indent);
+        self.in_helper.set(in_helper);
+        result?;
+        writeln!(output, "{indent_str}}}")?;
+        writeln!(output)?;
+        Ok(())
+    }
+
+    /// Scope the variables a function assigns to it, in shells that can
+    fn write_locals(&self, output: &mut String, names: &[String], indent_str: &str) -> Result<()> {
+        if self.dialect.has_local() && !names.is_empty() {
+            let names: Vec<String> = names.iter().map(|n| escape_variable_name(n)).collect();
+            writeln!(output, "{indent_str}local {}", names.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Whether immutable bindings must be plain assignments: a helper may
+    /// run again, and without `local` its readonly variables would outlive it
+    fn readonly_is_unsafe(&self) -> bool {
+        self.in_helper.get() && !self.dialect.has_local()
+    }
+
+    fn emit_return_statement(
+        &self,
+        output: &mut String,
+        value: Option<&ShellValue>,
+        convention: ReturnConvention,
+        indent: usize,
+    ) -> Result<()> {
+        let indent_str = "    ".repeat(indent + 1);
+        match (convention, value) {
+            (ReturnConvention::Stdout, Some(value)) => {
+                let value = self.emit_shell_value(value)?;
+                self.write_statement(output, &indent_str, &format!("printf '%s\\n' {value} >&3"))?;
+                writeln!(output, "{indent_str}return 0")?;
+            }
+            (ReturnConvention::ExitStatus, Some(ShellValue::Bool(value))) => {
+                let status = if *value { 0 } else { 1 };
+                writeln!(output, "{indent_str}return {status}")?;
+            }
+            (ReturnConvention::ExitStatus, Some(value)) => {
+                let test_expr = self.emit_test_expression(value)?;
+                self.write_statement(output, &indent_str, &format!("if {test_expr}; then"))?;
+                writeln!(output, "{indent_str}    return 0")?;
+                writeln!(output, "{indent_str}fi")?;
+                writeln!(output, "{indent_str}return 1")?;
+            }
+            _ => writeln!(output, "{indent_str}return")?,
+        }
+        Ok(())
+    }
+
     fn emit_exit_statement(
         &self,
         output: &mut String,
@@ -210,7 +843,7 @@ impl PosixEmitter {
         let indent_str = "    ".repeat(indent + 1);
         if let Some(msg) = message {
             let escaped_msg = escape_shell_string(msg);
-            writeln!(output, "{indent_str}echo {escaped_msg} >&2")?;
+            writeln!(output, "{indent_str}printf '%s\\n' {escaped_msg} >&2")?;
         }
         writeln!(output, "{indent_str}exit {code}")?;
         Ok(())
@@ -239,9 +872,187 @@ impl PosixEmitter {
                 let cmd_str = self.emit_command(cmd)?;
                 Ok(format!("\"$({cmd_str})\""))
             }
+            ShellValue::FunctionCall {
+                name,
+                args,
+                convention,
+            } => {
+                let capture = self.emit_function_call_capture(name, args, *convention)?;
+                Ok(format!("\"{capture}\""))
+            }
+            ShellValue::Arithmetic { .. } => {
+                Ok(format!("\"$({})\"", self.emit_arithmetic_operand(value)?))
+            }
+            ShellValue::Comparison { .. }
+            | ShellValue::Logical { .. }
+            | ShellValue::Not(_)
+            | ShellValue::IsSome(_)
+            | ShellValue::CommandStatus(_) => {
+                Ok(format!("\"{}\"", self.emit_status_capture(value)?))
+            }
+            ShellValue::UnwrapOr { option, default } => {
+                Ok(format!("\"{}\"", self.emit_unwrap_or(option, default)?))
+            }
+            ShellValue::ChangeCase { value, case } => {
+                Ok(format!("\"{}\"", self.emit_change_case(value, *case)?))
+            }
+            ShellValue::List(elements) => self.emit_list_argument(elements),
+            ShellValue::ListElements(name) => self.emit_list_elements(name),
+            ShellValue::ListLength(name) => Ok(format!(
+                "\"{}\"",
+                self.dialect.list_length(&escape_variable_name(name))
+            )),
+            ShellValue::ListItem { list, index } => {
+                Ok(format!("\"{}\"", self.emit_list_item(list, index)?))
+            }
+            ShellValue::EnvVar(name) => Ok(format!("\"${{{name}:-}}\"")),
+            ShellValue::Arg(position) => Ok(format!("\"{}\"", positional_parameter(*position))),
+            ShellValue::Args => Ok("\"$@\"".to_string()),
+        }
+    }
+
+    fn emit_words(&self, values: &[ShellValue]) -> Result<Vec<String>> {
+        values
+            .iter()
+            .map(|value| self.emit_shell_value(value))
+            .collect()
+    }
+
+    /// Render a list as one word of quoted elements
+    fn emit_list_argument(&self, elements: &[ShellValue]) -> Result<String> {
+        if let [ShellValue::ListElements(name)] = elements {
+            return Ok(self.dialect.list_argument(&escape_variable_name(name)));
+        }
+        let words = self.emit_words(elements)?;
+        if !elements.iter().all(ShellValue::is_constant) {
+            self.runtime_calls.borrow_mut().push("rash_list");
+            return Ok(format!("\"$(rash_list {})\"", words.join(" ")));
+        }
+        // Constant elements are already quoted; quote the list once more as a whole
+        let mut serialized = String::from('"');
+        for c in words.join(" ").chars() {
+            if matches!(c, '"' | '$' | '`' | '\\') {
+                serialized.push('\\');
+            }
+            serialized.push(c);
+        }
+        serialized.push('"');
+        Ok(serialized)
+    }
+
+    /// Render the elements of a list variable as separate words
+    fn emit_list_elements(&self, name: &str) -> Result<String> {
+        let (unpack, words) = self.dialect.list_elements(&escape_variable_name(name));
+        if let Some(unpack) = unpack {
+            let mut expanded = self.expanded_list.borrow_mut();
+            if let Some((other, _)) = expanded.as_ref().filter(|(other, _)| other != name) {
+                return Err(Error::Emission(format!(
+                    "Lists '{other}' and '{name}' cannot both be expanded in one command; \
+                     combine them into one list first"
+                )));
+            }
+            *expanded = Some((name.to_string(), unpack));
+        }
+        Ok(words)
+    }
+
+    /// Render an element of a list variable (without quotes)
+    fn emit_list_item(&self, list: &str, index: &ShellValue) -> Result<String> {
+        let index = self.emit_arithmetic_operand(index)?;
+        let temporary = format!("_rash_item_{}", self.loop_id());
+        let (lookup, word) =
+            self.dialect
+                .list_item(&escape_variable_name(list), &index, &temporary);
+        if let Some(lookup) = lookup {
+            self.prelude.borrow_mut().push(lookup);
+        }
+        Ok(word)
+    }
+
+    /// Convert the letters of a value (without quotes), natively where the dialect can
+    fn emit_change_case(&self, value: &ShellValue, case: LetterCase) -> Result<String> {
+        if let ShellValue::Variable(name) = value {
+            if let Some(expansion) = self.dialect.change_case(&escape_variable_name(name), case) {
+                return Ok(expansion);
+            }
+        }
+        let (from, to) = match case {
+            LetterCase::Lower => ("'[:upper:]'", "'[:lower:]'"),
+            LetterCase::Upper => ("'[:lower:]'", "'[:upper:]'"),
+        };
+        Ok(format!(
+            "$(printf '%s' {} | tr {from} {to})",
+            self.emit_shell_value(value)?
+        ))
+    }
+
+    /// Capture the status of a condition as the word `true` or `false` (without quotes)
+    fn emit_status_capture(&self, test: &ShellValue) -> Result<String> {
+        let test_expr = self.emit_test_expression(test)?;
+        Ok(format!(
+            "$(if {test_expr}; then printf true; else printf false; fi)"
+        ))
+    }
+
+    fn emit_logical_operand(&self, operand: &ShellValue, parent: LogicalOp) -> Result<String> {
+        let test_expr = self.emit_test_expression(operand)?;
+        match operand {
+            ShellValue::Logical { op, .. } if *op != parent => Ok(format!("{{ {test_expr}; }}")),
+            _ => Ok(test_expr),
+        }
+    }
+
+    /// Render a value inside `$((...))`. Sums, differences and products are
+    /// masked to 32 bits so that the shell wraps exactly like `u32` does.
+    fn emit_arithmetic_operand(&self, value: &ShellValue) -> Result<String> {
+        match value {
+            ShellValue::Arithmetic { op, left, right } => {
+                let left = self.emit_arithmetic_operand(left)?;
+                let right = self.emit_arithmetic_operand(right)?;
+                let symbol = op.symbol();
+                Ok(match op {
+                    ArithmeticOp::Div => format!("({left} {symbol} {right})"),
+                    _ => format!("(({left} {symbol} {right}) & {U32_MASK})"),
+                })
+            }
+            ShellValue::String(s) if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) => {
+                Ok(s.clone())
+            }
+            ShellValue::Variable(name) => Ok(format!("${{{}}}", escape_variable_name(name))),
+            ShellValue::CommandSubst(cmd) => Ok(format!("$({})", self.emit_command(cmd)?)),
+            ShellValue::FunctionCall {
+                name,
+                args,
+                convention,
+            } => self.emit_function_call_capture(name, args, *convention),
+            ShellValue::ListLength(name) => {
+                Ok(self.dialect.list_length(&escape_variable_name(name)))
+            }
+            ShellValue::ListItem { list, index } => self.emit_list_item(list, index),
+            other => Err(Error::Emission(format!(
+                "Cannot use {other:?} as an arithmetic operand"
+            ))),
         }
     }
 
+    /// Capture the result of a shell function call as a word (without quotes)
+    fn emit_function_call_capture(
+        &self,
+        name: &str,
+        args: &[ShellValue],
+        convention: ReturnConvention,
+    ) -> Result<String> {
+        Ok(match convention {
+            ReturnConvention::ExitStatus => {
+                let call = self.emit_invocation(name, args)?;
+                format!("$(if {call}; then printf true; else printf false; fi)")
+            }
+            ReturnConvention::Stdout | ReturnConvention::Void => {
+                format!("$({})", self.emit_result_invocation(name, args)?)
+            }
+        })
+    }
+
     fn emit_bool_value(&self, value: bool) -> String {
         if value { "true" } else { "false" }.to_string()
     }
@@ -269,6 +1080,42 @@ impl PosixEmitter {
                 let cmd_str = self.emit_command(cmd)?;
                 result.push_str(&format!("$({cmd_str})"));
             }
+            ShellValue::FunctionCall {
+                name,
+                args,
+                convention,
+            } => {
+                result.push_str(&self.emit_function_call_capture(name, args, *convention)?);
+            }
+            ShellValue::Arithmetic { .. } => {
+                result.push_str(&format!("$({})", self.emit_arithmetic_operand(part)?));
+            }
+            ShellValue::Comparison { .. }
+            | ShellValue::Logical { .. }
+            | ShellValue::Not(_)
+            | ShellValue::IsSome(_)
+            | ShellValue::CommandStatus(_) => {
+                result.push_str(&self.emit_status_capture(part)?);
+            }
+            ShellValue::UnwrapOr { option, default } => {
+                result.push_str(&self.emit_unwrap_or(option, default)?);
+            }
+            ShellValue::ChangeCase { value, case } => {
+                result.push_str(&self.emit_change_case(value, *case)?);
+            }
+            ShellValue::ListLength(name) => {
+                result.push_str(&self.dialect.list_length(&escape_variable_name(name)));
+            }
+            ShellValue::ListItem { list, index } => {
+                result.push_str(&self.emit_list_item(list, index)?);
+            }
+            ShellValue::EnvVar(name) => result.push_str(&format!("${{{name}:-}}")),
+            ShellValue::Arg(position) => result.push_str(&positional_parameter(*position)),
+            ShellValue::List(_) | ShellValue::ListElements(_) | ShellValue::Args => {
+                return Err(Error::Emission(
+                    "A list cannot be part of a string".to_string(),
+                ))
+            }
             ShellValue::Concat(_) => {
                 // Nested concatenation - flatten it
                 let nested = self.emit_shell_value(part)?;
@@ -278,6 +1125,57 @@ impl PosixEmitter {
         Ok(())
     }
 
+    /// The value an option holds, or `default` (without quotes)
+    fn emit_unwrap_or(&self, option: &ShellValue, default: &ShellValue) -> Result<String> {
+        match option {
+            ShellValue::Variable(name) => {
+                let mut word = String::new();
+                self.append_double_quoted(&mut word, default)?;
+                Ok(format!("${{{}-{word}}}", escape_variable_name(name)))
+            }
+            // An empty environment variable counts as unset
+            ShellValue::EnvVar(name) => {
+                let mut word = String::new();
+                self.append_double_quoted(&mut word, default)?;
+                Ok(format!("${{{name}:-{word}}}"))
+            }
+            // A call returning no value prints nothing
+            ShellValue::FunctionCall { name, args, .. } => {
+                let invocation = self.emit_result_invocation(name, args)?;
+                let default = self.emit_shell_value(default)?;
+                Ok(format!("$({invocation} || printf '%s' {default})"))
+            }
+            other => Err(Error::Emission(format!(
+                "Cannot unwrap {other:?} as an option"
+            ))),
+        }
+    }
+
+    /// Append a value to a word inside double quotes, escaping literal text
+    fn append_double_quoted(&self, result: &mut String, value: &ShellValue) -> Result<()> {
+        match value {
+            // Bash, unlike POSIX, reads quotes inside `"${name-word}"`
+            ShellValue::String(s) if s.contains('\'') => {
+                result.push_str(&format!("$(printf '%s' {})", escape_shell_string(s)));
+            }
+            ShellValue::String(s) => {
+                for c in s.chars() {
+                    if matches!(c, '"' | '$' | '`' | '\\') {
+                        result.push('\\');
+                    }
+                    result.push(c);
+                }
+            }
+            ShellValue::Concat(parts) => {
+                for part in parts {
+                    self.append_double_quoted(result, part)?;
+                }
+            }
+            other => self.append_concat_part(result, other)?,
+        }
+        Ok(())
+    }
+
     fn append_flattened_content(&self, result: &mut String, nested: &str) {
         // Remove quotes from nested value and add content
         if nested.starts_with('"') && nested.ends_with('"') {
@@ -288,9 +1186,13 @@ impl PosixEmitter {
     }
 
     fn emit_command(&self, cmd: &Command) -> Result<String> {
-        let mut result = escape_command_name(&cmd.program);
+        self.emit_invocation(&cmd.program, &cmd.args)
+    }
+
+    fn emit_invocation(&self, program: &str, args: &[ShellValue]) -> Result<String> {
+        let mut result = escape_command_name(program);
 
-        for arg in &cmd.args {
+        for arg in args {
             result.push(' ');
             result.push_str(&self.emit_shell_value(arg)?);
         }
@@ -298,14 +1200,84 @@ impl PosixEmitter {
         Ok(result)
     }
 
+    /// A call whose value is captured. Functions of the script write their
+    /// value to fd 3, so that what they print reaches the script's stdout
+    fn emit_result_invocation(&self, name: &str, args: &[ShellValue]) -> Result<String> {
+        let invocation = self.emit_invocation(name, args)?;
+        if bashrs_runtime::helper(name).is_some() {
+            return Ok(invocation);
+        }
+        self.returns_on_fd.set(true);
+        Ok(format!("{invocation} 3>&1 >&4"))
+    }
+
+    /// A call whose value is unused
+    fn emit_discarded_invocation(&self, name: &str, args: &[ShellValue]) -> Result<String> {
+        let invocation = self.emit_invocation(name, args)?;
+        Ok(if bashrs_runtime::helper(name).is_some() {
+            format!("{invocation} >/dev/null")
+        } else {
+            format!("{invocation} 3>/dev/null")
+        })
+    }
+
     pub fn emit_test_expression(&self, test: &ShellValue) -> Result<String> {
         match test {
             ShellValue::Bool(true) => Ok("true".to_string()),
             ShellValue::Bool(false) => Ok("false".to_string()),
             ShellValue::Variable(name) => {
                 // Test if variable is non-empty
-                Ok(format!("test -n \"${}\"", escape_variable_name(name)))
+                Ok(self
+                    .dialect
+                    .test_non_empty(&format!("\"${}\"", escape_variable_name(name))))
+            }
+            ShellValue::FunctionCall {
+                name,
+                args,
+                convention: ReturnConvention::ExitStatus,
+            } => self.emit_invocation(name, args),
+            ShellValue::CommandStatus(cmd) => self.emit_command(cmd),
+            ShellValue::IsSome(option) => match option.as_ref() {
+                ShellValue::Variable(name) => Ok(self
+                    .dialect
+                    .test_non_empty(&format!("\"${{{}+x}}\"", escape_variable_name(name)))),
+                ShellValue::EnvVar(name) => {
+                    Ok(self.dialect.test_non_empty(&format!("\"${{{name}:-}}\"")))
+                }
+                ShellValue::Arg(position) => Ok(self
+                    .dialect
+                    .test_non_empty(&format!("\"${{{position}+x}}\""))),
+                // The value itself is not wanted
+                ShellValue::FunctionCall { name, args, .. } => {
+                    self.emit_discarded_invocation(name, args)
+                }
+                other => Err(Error::Emission(format!(
+                    "Cannot test {other:?} as an option"
+                ))),
+            },
+            ShellValue::Comparison { op, left, right } => Ok(self.dialect.test(&format!(
+                "{} {} {}",
+                self.emit_shell_value(left)?,
+                op.test_operator(),
+                self.emit_shell_value(right)?
+            ))),
+            ShellValue::Logical { op, left, right } => {
+                let separator = match op {
+                    LogicalOp::And => "&&",
+                    LogicalOp::Or => "||",
+                };
+                // `&&` and `||` share one precedence level in the shell, so mixed
+                // operands are grouped to keep Rust's precedence
+                let left = self.emit_logical_operand(left, *op)?;
+                let right = self.emit_logical_operand(right, *op)?;
+                Ok(format!("{left} {separator} {right}"))
             }
+            ShellValue::Not(operand) => match operand.as_ref() {
+                ShellValue::Logical { .. } | ShellValue::Not(_) => {
+                    Ok(format!("! {{ {}; }}", self.emit_test_expression(operand)?))
+                }
+                _ => Ok(format!("! {}", self.emit_test_expression(operand)?)),
+            },
             ShellValue::String(s) => {
                 if s == "true" || s == "0" {
                     Ok("true".to_string())
@@ -316,9 +1288,143 @@ impl PosixEmitter {
             other => {
                 // For complex expressions, evaluate them and test the result
                 let value = self.emit_shell_value(other)?;
-                Ok(format!("test -n {value}"))
+                Ok(self.dialect.test_non_empty(&value))
+            }
+        }
+    }
+}
+
+impl Emitter for PosixEmitter {
+    fn emit(&self, ir: &ShellIR) -> Result<String> {
+        PosixEmitter::emit(self, ir)
+    }
+
+    fn emit_with_source_map(&self, ir: &ShellIR) -> Result<(String, SourceMap)> {
+        PosixEmitter::emit_with_source_map(self, ir)
+    }
+}
+
+/// Expansion of positional parameter `position` (without quotes); from 10
+/// on the braces are required
+fn positional_parameter(position: u32) -> String {
+    if position < 10 {
+        format!("${position}")
+    } else {
+        format!("${{{position}}}")
+    }
+}
+
+/// `source_map` with every script position moved `offset` bytes later
+fn shift_formatted_positions(source_map: &SourceMap, offset: usize) -> SourceMap {
+    let mut shifted = SourceMap::new();
+    for (formatted, original) in source_map.reverse_mappings() {
+        shifted.add_mapping(original, CharPos(formatted.0 + offset));
+    }
+    shifted
+}
+
+/// The condition under which a match arm is taken, or `None` when every
+/// value reaching it takes it
+fn arm_test(scrutinee: &ShellValue, arm: &CaseArm) -> Option<ShellValue> {
+    // A wildcard among the alternatives matches everything
+    let pattern_test = arm
+        .patterns
+        .iter()
+        .map(|pattern| match pattern {
+            CasePattern::Literal(text) => Some(ShellValue::Comparison {
+                op: ComparisonOp::StrEq,
+                left: Box::new(scrutinee.clone()),
+                right: Box::new(ShellValue::String(text.clone())),
+            }),
+            CasePattern::Wildcard => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .and_then(|tests| {
+            tests.into_iter().reduce(|left, right| ShellValue::Logical {
+                op: LogicalOp::Or,
+                left: Box::new(left),
+                right: Box::new(right),
+            })
+        });
+    match (pattern_test, arm.guard.clone()) {
+        (Some(pattern_test), Some(guard)) => Some(ShellValue::Logical {
+            op: LogicalOp::And,
+            left: Box::new(pattern_test),
+            right: Box::new(guard),
+        }),
+        (pattern_test, guard) => pattern_test.or(guard),
+    }
+}
+
+/// Variables a function body assigns other than through readonly bindings,
+/// in order of first assignment
+fn collect_assigned_variables(ir: &ShellIR, names: &mut Vec<String>) {
+    let add = |name: &str, names: &mut Vec<String>| {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    };
+    match ir {
+        ShellIR::Assign { name, .. } | ShellIR::LetOption { name, .. } => add(name, names),
+        ShellIR::Try {
+            binding: Some(name),
+            mutable: true,
+            ..
+        } => add(name, names),
+        ShellIR::For { var, body, .. } => {
+            add(var, names);
+            collect_assigned_variables(body, names);
+        }
+        ShellIR::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            collect_assigned_variables(then_branch, names);
+            if let Some(else_branch) = else_branch {
+                collect_assigned_variables(else_branch, names);
+            }
+        }
+        ShellIR::While { body, .. } | ShellIR::Spanned { inner: body, .. } => {
+            collect_assigned_variables(body, names)
+        }
+        ShellIR::Case { arms, .. } => {
+            for arm in arms {
+                if let Some(binding) = &arm.binding {
+                    add(binding, names);
+                }
+                collect_assigned_variables(&arm.body, names);
             }
         }
+        ShellIR::Sequence(items) => items
+            .iter()
+            .for_each(|item| collect_assigned_variables(item, names)),
+        _ => {}
+    }
+}
+
+/// Whether a value is the outcome of a condition, bound as `true` or `false`
+fn is_status(value: &ShellValue) -> bool {
+    matches!(
+        value,
+        ShellValue::Comparison { .. }
+            | ShellValue::Logical { .. }
+            | ShellValue::Not(_)
+            | ShellValue::IsSome(_)
+            | ShellValue::CommandStatus(_)
+            | ShellValue::FunctionCall {
+                convention: ReturnConvention::ExitStatus,
+                ..
+            }
+    )
+}
+
+fn is_empty_body(ir: &ShellIR) -> bool {
+    match ir {
+        ShellIR::Noop => true,
+        ShellIR::Sequence(items) => items.iter().all(is_empty_body),
+        ShellIR::Spanned { inner, .. } => is_empty_body(inner),
+        _ => false,
     }
 }
 
@@ -1,8 +1,43 @@
 use super::*;
-use crate::ir::{Command, EffectSet, ShellIR, ShellValue};
-use crate::models::Config;
+use crate::ir::{
+    ArithmeticOp, CaseArm, CasePattern, Command, ComparisonOp, EffectSet, ForIter, LetterCase,
+    LogicalOp, ReturnConvention, ShellIR, ShellValue,
+};
+use crate::models::{Config, ShellDialect};
 use proptest::prelude::*;
 use rstest::*;
+use rstest_reuse::{self, *};
+use std::process::Output;
+
+/// Every target together with the shells that run its scripts
+#[template]
+#[rstest]
+#[case(ShellDialect::Posix, "sh")]
+#[case(ShellDialect::Posix, "dash")]
+#[case(ShellDialect::Bash, "bash")]
+fn shells(#[case] target: ShellDialect, #[case] shell: &str) {}
+
+fn transpile_for(source: &str, target: ShellDialect) -> String {
+    let config = Config {
+        target,
+        ..Config::default()
+    };
+    crate::transpile(source, config).unwrap()
+}
+
+/// Run `script` under `shell` with the given arguments and environment
+/// variables, or `None` when the shell is not installed
+fn run_script(shell: &str, script: &str, args: &[&str], env: &[(&str, &str)]) -> Option<Output> {
+    let output = std::process::Command::new(shell)
+        .args(["-c", script, "rash"])
+        .args(args)
+        .envs(env.iter().copied())
+        .output();
+    if output.is_err() {
+        eprintln!("{shell} not installed; skipping");
+    }
+    output.ok()
+}
 
 #[test]
 fn test_simple_let_emission() {
@@ -107,10 +142,32 @@ fn test_exit_statement_emission() {
     };
 
     let result = emitter.emit(&ir).unwrap();
-    assert!(result.contains("echo 'Error occurred' >&2"));
+    assert!(result.contains("printf '%s\\n' 'Error occurred' >&2"));
     assert!(result.contains("exit 1"));
 }
 
+#[apply(shells)]
+fn test_exit_message_is_printed_verbatim_in_shell(
+    #[case] target: ShellDialect,
+    #[case] shell: &str,
+) {
+    let config = Config {
+        target,
+        ..Config::default()
+    };
+    let ir = ShellIR::Exit {
+        code: 2,
+        message: Some("-n a\\c b".to_string()),
+    };
+    let script = super::emit(&ir, &config).unwrap();
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "-n a\\c b\n");
+    assert_eq!(output.status.code(), Some(2));
+}
+
 #[test]
 fn test_shell_value_emission() {
     let config = Config::default();
@@ -190,9 +247,10 @@ fn test_header_and_footer_structure() {
     assert!(result.contains("IFS=' \t\n'"));
     assert!(result.contains("export LC_ALL=C"));
 
-    // Check runtime functions
-    assert!(result.contains("rash_require()"));
-    assert!(result.contains("rash_download_verified()"));
+    // A script that calls no runtime functions carries none
+    assert!(!result.contains("# Rash runtime functions"));
+    assert!(!result.contains("rash_require()"));
+    assert!(!result.contains("rash_download_verified()"));
 
     // Check footer
     assert!(result.contains("main() {"));
@@ -205,17 +263,43 @@ fn test_runtime_functions_included() {
     let config = Config::default();
     let emitter = PosixEmitter::new(config);
 
-    let ir = ShellIR::Noop;
+    let ir = ShellIR::Exec {
+        cmd: Command {
+            program: "rash_require_command".to_string(),
+            args: vec![ShellValue::String("curl".to_string())],
+        },
+        effects: EffectSet::pure(),
+    };
     let result = emitter.emit(&ir).unwrap();
 
-    // Verify essential runtime functions are present
-    assert!(result.contains("rash_require() {"));
-    assert!(result.contains("rash_download_verified() {"));
+    // The called function is spliced in along with the ones it calls
+    assert!(result.contains("# Rash runtime functions"));
+    assert!(result.contains("rash_require_command() {"));
+    assert!(result.contains("rash_has_command() {"));
+    assert_eq!(result.matches("rash_has_command() {").count(), 1);
+    assert!(result.find("rash_has_command() {") < result.find("main() {"));
+
+    // Unused functions stay out
+    assert!(!result.contains("rash_require() {"));
+    assert!(!result.contains("rash_download_verified() {"));
+    assert!(!result.contains("rash_list() {"));
+}
+
+#[test]
+fn test_runtime_functions_include_emitted_list_helper() {
+    let config = Config::default();
+    let emitter = PosixEmitter::new(config);
+
+    let ir = ShellIR::Let {
+        name: "items".to_string(),
+        value: ShellValue::List(vec![ShellValue::Variable("x".to_string())]),
+        effects: EffectSet::pure(),
+    };
+    let result = emitter.emit(&ir).unwrap();
 
-    // Verify they contain expected functionality
-    assert!(result.contains("curl -fsSL"));
-    assert!(result.contains("sha256sum"));
-    assert!(result.contains("wget"));
+    assert!(result.contains("\"$(rash_list \"$x\")\""));
+    assert!(result.contains("rash_list() {"));
+    assert!(result.find("rash_list() {") < result.find("main() {"));
 }
 
 #[test]
@@ -617,7 +701,7 @@ fn test_complex_nested_emission() {
     assert!(result.contains("mkdir \"$prefix\""));
     assert!(result.contains("echo \"Installing to ${prefix}\""));
     assert!(result.contains("else"));
-    assert!(result.contains("echo 'Installation cancelled' >&2"));
+    assert!(result.contains("printf '%s\\n' 'Installation cancelled' >&2"));
     assert!(result.contains("exit 1"));
     assert!(result.contains("fi"));
 }
@@ -648,9 +732,14 @@ fn test_different_shell_dialects() {
     let result = emit(&ir, &config).unwrap();
     assert!(result.contains("#!/bin/sh"));
 
-    // Test Bash (should still emit POSIX for now)
+    // Test Bash
     config.target = crate::models::ShellDialect::Bash;
     let result = emit(&ir, &config).unwrap();
+    assert!(result.contains("#!/bin/bash"));
+
+    // Dash and ash run the POSIX output
+    config.target = crate::models::ShellDialect::Dash;
+    let result = emit(&ir, &config).unwrap();
     assert!(result.contains("#!/bin/sh"));
 }
 
@@ -697,3 +786,1213 @@ fn test_indentation_consistency() {
         );
     }
 }
+
+#[test]
+fn test_for_range_emission() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::For {
+        var: "i".to_string(),
+        iter: ForIter::Range {
+            start: ShellValue::String("0".to_string()),
+            end: ShellValue::Variable("n".to_string()),
+            inclusive: false,
+        },
+        body: Box::new(ShellIR::Exec {
+            cmd: Command {
+                program: "echo".to_string(),
+                args: vec![ShellValue::Variable("i".to_string())],
+            },
+            effects: EffectSet::pure(),
+        }),
+        max_iterations: 50,
+    };
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("_rash_i_0=0"));
+    assert!(result.contains("_rash_end_0=\"$n\""));
+    assert!(result.contains("if [ \"$((_rash_end_0 - _rash_i_0))\" -gt 50 ]; then"));
+    assert!(result.contains("while [ \"$_rash_i_0\" -lt \"$_rash_end_0\" ]; do"));
+    assert!(result.contains("i=\"$_rash_i_0\""));
+    assert!(result.contains("_rash_i_0=$((_rash_i_0 + 1))"));
+    assert!(result.contains("done"));
+}
+
+#[test]
+fn test_for_constant_range_has_no_runtime_guard() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::For {
+        var: "i".to_string(),
+        iter: ForIter::Range {
+            start: ShellValue::String("1".to_string()),
+            end: ShellValue::String("3".to_string()),
+            inclusive: true,
+        },
+        body: Box::new(ShellIR::Noop),
+        max_iterations: 3,
+    };
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("while [ \"$_rash_i_0\" -le \"$_rash_end_0\" ]; do"));
+    assert!(!result.contains("FATAL: loop exceeds"));
+}
+
+#[test]
+fn test_for_words_emission() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::For {
+        var: "pkg".to_string(),
+        iter: ForIter::Words(vec![
+            ShellValue::String("curl".to_string()),
+            ShellValue::String("build essential".to_string()),
+        ]),
+        body: Box::new(ShellIR::Noop),
+        max_iterations: 2,
+    };
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("for pkg in curl 'build essential'; do"));
+}
+
+#[test]
+fn test_while_emission_with_guard() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::While {
+        test: ShellValue::Bool(true),
+        body: Box::new(ShellIR::Sequence(vec![ShellIR::Continue, ShellIR::Break])),
+        max_iterations: 5,
+    };
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("_rash_guard_0=0"));
+    assert!(result.contains("while true; do"));
+    assert!(result.contains("_rash_guard_0=$((_rash_guard_0 + 1))"));
+    assert!(result.contains("if [ \"$_rash_guard_0\" -gt 5 ]; then"));
+    assert!(result.contains("FATAL: loop exceeds 5 iterations"));
+    assert!(result.contains("        continue\n"));
+    assert!(result.contains("        break\n"));
+}
+
+#[test]
+fn test_function_definitions_hoisted_before_main() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::Sequence(vec![
+        ShellIR::Function {
+            name: "greet".to_string(),
+            params: vec!["name".to_string(), "greeting".to_string()],
+            body: Box::new(ShellIR::Exec {
+                cmd: Command {
+                    program: "echo".to_string(),
+                    args: vec![ShellValue::Variable("name".to_string())],
+                },
+                effects: EffectSet::pure(),
+            }),
+        },
+        ShellIR::Function {
+            name: "nothing".to_string(),
+            params: vec![],
+            body: Box::new(ShellIR::Sequence(vec![])),
+        },
+        ShellIR::Exec {
+            cmd: Command {
+                program: "greet".to_string(),
+                args: vec![ShellValue::String("world".to_string())],
+            },
+            effects: EffectSet::pure(),
+        },
+    ]);
+
+    let result = emitter.emit(&ir).unwrap();
+    let definition = result.find("greet() {\n").unwrap();
+    let main = result.find("main() {\n").unwrap();
+    assert!(definition < main);
+    assert!(
+        result.contains("greet() {\n    name=\"$1\"\n    greeting=\"$2\"\n    echo \"$name\"\n}")
+    );
+    assert!(result.contains("nothing() {\n    :\n}"));
+    assert!(result[main..].contains("    greet world\n"));
+}
+
+#[test]
+fn test_function_return_emission() {
+    let emitter = PosixEmitter::new(Config::default());
+
+    let ir = ShellIR::Sequence(vec![
+        ShellIR::Function {
+            name: "version".to_string(),
+            params: vec![],
+            body: Box::new(ShellIR::Return {
+                value: Some(ShellValue::String("1.2.3".to_string())),
+                convention: ReturnConvention::Stdout,
+            }),
+        },
+        ShellIR::Function {
+            name: "ready".to_string(),
+            params: vec![],
+            body: Box::new(ShellIR::Return {
+                value: Some(ShellValue::Bool(false)),
+                convention: ReturnConvention::ExitStatus,
+            }),
+        },
+        ShellIR::Let {
+            name: "v".to_string(),
+            value: ShellValue::FunctionCall {
+                name: "version".to_string(),
+                args: vec![ShellValue::Variable("x".to_string())],
+                convention: ReturnConvention::Stdout,
+            },
+            effects: EffectSet::pure(),
+        },
+        ShellIR::If {
+            test: ShellValue::FunctionCall {
+                name: "ready".to_string(),
+                args: vec![],
+                convention: ReturnConvention::ExitStatus,
+            },
+            then_branch: Box::new(ShellIR::Noop),
+            else_branch: None,
+        },
+    ]);
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("version() {\n    printf '%s\\n' 1.2.3 >&3\n    return 0\n}"));
+    assert!(result.contains("ready() {\n    return 1\n}"));
+    assert!(result.contains("readonly v=\"$(version \"$x\" 3>&1 >&4)\""));
+    assert!(result.contains("if ready; then"));
+}
+
+#[test]
+fn test_arithmetic_emission_wraps_like_u32() {
+    let emitter = PosixEmitter::new(Config::default());
+    let value = ShellValue::Arithmetic {
+        op: ArithmeticOp::Div,
+        left: Box::new(ShellValue::Arithmetic {
+            op: ArithmeticOp::Add,
+            left: Box::new(ShellValue::Variable("x".to_string())),
+            right: Box::new(ShellValue::String("1".to_string())),
+        }),
+        right: Box::new(ShellValue::String("2".to_string())),
+    };
+
+    assert_eq!(
+        emitter.emit_shell_value(&value).unwrap(),
+        "\"$((((${x} + 1) & 4294967295) / 2))\""
+    );
+}
+
+#[test]
+fn test_comparison_and_logical_test_emission() {
+    let emitter = PosixEmitter::new(Config::default());
+    let compare = |op, left: &str, right: &str| ShellValue::Comparison {
+        op,
+        left: Box::new(ShellValue::Variable(left.to_string())),
+        right: Box::new(ShellValue::String(right.to_string())),
+    };
+    let test = ShellValue::Logical {
+        op: LogicalOp::And,
+        left: Box::new(ShellValue::Not(Box::new(compare(
+            ComparisonOp::StrEq,
+            "name",
+            "root",
+        )))),
+        right: Box::new(ShellValue::Logical {
+            op: LogicalOp::Or,
+            left: Box::new(compare(ComparisonOp::NumEq, "n", "1")),
+            right: Box::new(compare(ComparisonOp::Gt, "n", "9")),
+        }),
+    };
+
+    assert_eq!(
+        emitter.emit_test_expression(&test).unwrap(),
+        "! [ \"$name\" = root ] && { [ \"$n\" -eq 1 ] || [ \"$n\" -gt 9 ]; }"
+    );
+    assert_eq!(
+        emitter
+            .emit_shell_value(&compare(ComparisonOp::Le, "n", "3"))
+            .unwrap(),
+        "\"$(if [ \"$n\" -le 3 ]; then printf true; else printf false; fi)\""
+    );
+}
+
+#[test]
+fn test_case_emission_with_guards() {
+    let emitter = PosixEmitter::new(Config::default());
+    let echo = |text: &str| {
+        Box::new(ShellIR::Exec {
+            cmd: Command {
+                program: "echo".to_string(),
+                args: vec![ShellValue::String(text.to_string())],
+            },
+            effects: EffectSet::pure(),
+        })
+    };
+    let ir = ShellIR::Case {
+        scrutinee: ShellValue::Variable("os".to_string()),
+        arms: vec![
+            CaseArm {
+                patterns: vec![CasePattern::Literal("linux".to_string())],
+                binding: None,
+                guard: Some(ShellValue::Variable("fast".to_string())),
+                body: echo("fast"),
+            },
+            CaseArm {
+                patterns: vec![CasePattern::Literal("macos".to_string())],
+                binding: None,
+                guard: None,
+                body: echo("mac"),
+            },
+            CaseArm {
+                patterns: vec![CasePattern::Wildcard],
+                binding: None,
+                guard: None,
+                body: Box::new(ShellIR::Sequence(vec![])),
+            },
+        ],
+    };
+
+    let result = emitter.emit(&ir).unwrap();
+    let expected = r#"    if [ "$os" = linux ] && test -n "$fast"; then
+        echo fast
+    elif [ "$os" = macos ]; then
+        echo mac
+    fi
+"#;
+    assert!(result.contains(expected), "{result}");
+}
+
+#[test]
+fn test_guarded_case_grows_linearly_with_its_arms() {
+    let emitter = PosixEmitter::new(Config::default());
+    let guarded_match = |count: usize| {
+        let mut arms: Vec<CaseArm> = (0..count)
+            .map(|index| CaseArm {
+                patterns: vec![CasePattern::Literal(format!("v{}", index % 2))],
+                binding: None,
+                guard: Some(ShellValue::Variable(format!("ok{index}"))),
+                body: Box::new(ShellIR::Exec {
+                    cmd: Command {
+                        program: "echo".to_string(),
+                        args: vec![ShellValue::String(format!("arm{index}"))],
+                    },
+                    effects: EffectSet::pure(),
+                }),
+            })
+            .collect();
+        arms.push(CaseArm {
+            patterns: vec![CasePattern::Wildcard],
+            binding: None,
+            guard: None,
+            body: Box::new(ShellIR::Exec {
+                cmd: Command {
+                    program: "echo".to_string(),
+                    args: vec![ShellValue::String("other".to_string())],
+                },
+                effects: EffectSet::pure(),
+            }),
+        });
+        emitter
+            .emit(&ShellIR::Case {
+                scrutinee: ShellValue::Variable("x".to_string()),
+                arms,
+            })
+            .unwrap()
+    };
+
+    let small = guarded_match(8);
+    let large = guarded_match(16);
+    // Each arm adds its test line and its body line
+    assert_eq!(large.lines().count() - small.lines().count(), 16, "{large}");
+    for index in 0..16 {
+        assert_eq!(large.matches(&format!("echo arm{index}\n")).count(), 1);
+    }
+    assert_eq!(large.matches("echo other\n").count(), 1, "{large}");
+}
+
+#[test]
+fn test_source_map_records_spanned_statements() {
+    let emitter = PosixEmitter::new(Config::default());
+    let span = crate::ast::Span {
+        start: 16,
+        end: 26,
+        line: 2,
+        column: 5,
+    };
+    let ir = ShellIR::Sequence(vec![ShellIR::Spanned {
+        span,
+        inner: Box::new(ShellIR::Let {
+            name: "x".to_string(),
+            value: ShellValue::String("42".to_string()),
+            effects: EffectSet::pure(),
+        }),
+    }]);
+
+    let (script, source_map) = emitter.emit_with_source_map(&ir).unwrap();
+    let mappings = source_map.reverse_mappings();
+
+    assert_eq!(mappings.len(), 1);
+    let (script_pos, rust_pos) = mappings[0];
+    assert_eq!(rust_pos.offset(), 16);
+    assert!(script[script_pos.offset()..].starts_with("    readonly x=42"));
+}
+
+fn bash_config() -> Config {
+    Config {
+        target: ShellDialect::Bash,
+        ..Config::default()
+    }
+}
+
+#[test]
+fn test_emit_selects_emitter_for_target() {
+    let ir = ShellIR::Noop;
+
+    let posix = emit(&ir, &Config::default()).unwrap();
+    assert!(posix.starts_with("#!/bin/sh\n"));
+    assert!(posix.contains("set -euf\n"));
+
+    let bash = emit(&ir, &bash_config()).unwrap();
+    assert!(bash.starts_with("#!/bin/bash\n"));
+    assert!(bash.contains("set -euf -o pipefail\n"));
+}
+
+#[test]
+fn test_bash_emitter_uses_double_bracket_tests_and_locals() {
+    let emitter = BashEmitter::new(bash_config());
+    let ir = ShellIR::Sequence(vec![
+        ShellIR::Function {
+            name: "check".to_string(),
+            params: vec!["n".to_string()],
+            body: Box::new(ShellIR::If {
+                test: ShellValue::Comparison {
+                    op: ComparisonOp::Gt,
+                    left: Box::new(ShellValue::Variable("n".to_string())),
+                    right: Box::new(ShellValue::String("10".to_string())),
+                },
+                then_branch: Box::new(ShellIR::Noop),
+                else_branch: None,
+            }),
+        },
+        ShellIR::Let {
+            name: "name".to_string(),
+            value: ShellValue::String("rash".to_string()),
+            effects: EffectSet::pure(),
+        },
+        ShellIR::Let {
+            name: "os".to_string(),
+            value: ShellValue::CommandSubst(Command {
+                program: "uname".to_string(),
+                args: vec![],
+            }),
+            effects: EffectSet::pure(),
+        },
+    ]);
+
+    let result = emitter.emit(&ir).unwrap();
+    assert!(result.contains("    local n=\"$1\"\n"), "{result}");
+    assert!(result.contains("if [[ \"$n\" -gt 10 ]]; then"), "{result}");
+    assert!(result.contains("    local -r name=rash\n"), "{result}");
+    // Splitting the declaration keeps a failing command fatal under `set -e`
+    assert!(
+        result.contains("    local os\n    os=\"$(uname)\"\n    readonly os\n"),
+        "{result}"
+    );
+}
+
+#[rstest]
+#[case(
+    LetterCase::Lower,
+    "\"${name,,}\"",
+    "\"$(printf '%s' \"$name\" | tr '[:upper:]' '[:lower:]')\""
+)]
+#[case(
+    LetterCase::Upper,
+    "\"${name^^}\"",
+    "\"$(printf '%s' \"$name\" | tr '[:lower:]' '[:upper:]')\""
+)]
+fn test_change_case_per_dialect(#[case] case: LetterCase, #[case] bash: &str, #[case] posix: &str) {
+    let ir = ShellIR::Let {
+        name: "converted".to_string(),
+        value: ShellValue::ChangeCase {
+            value: Box::new(ShellValue::Variable("name".to_string())),
+            case,
+        },
+        effects: EffectSet::pure(),
+    };
+
+    let bash_script = emit(&ir, &bash_config()).unwrap();
+    assert!(
+        bash_script.contains(&format!("converted={bash}\n")),
+        "{bash_script}"
+    );
+
+    let posix_script = emit(&ir, &Config::default()).unwrap();
+    assert!(
+        posix_script.contains(&format!("readonly converted={posix}\n")),
+        "{posix_script}"
+    );
+}
+
+#[test]
+#[ignore = "needs shellcheck installed; run with `cargo test -- --ignored`"]
+fn test_bash_output_passes_shellcheck() {
+    let source = r#"
+        fn greet(name: &str) -> String {
+            name.to_uppercase()
+        }
+
+        fn main() {
+            let who = greet("world");
+            let n = 2 + 3;
+            if n > 4 && who == "WORLD" {
+                echo(who);
+            }
+            for i in 0..n {
+                echo(i);
+            }
+        }
+
+        fn echo(msg: &str) {}
+    "#;
+    let config = Config {
+        validation_level: Some(crate::validation::ValidationLevel::Paranoid),
+        ..bash_config()
+    };
+    let script = crate::transpile(source, config.clone()).unwrap();
+
+    // Paranoid validation already ran shellcheck, if installed; insist on it here
+    let pipeline = crate::validation::pipeline::ValidationPipeline::new(&config);
+    let mut child = pipeline
+        .shellcheck_command()
+        .spawn()
+        .expect("shellcheck must be installed for this test");
+    {
+        use std::io::Write;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(script.as_bytes()).unwrap();
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}\n{script}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+const LIST_PROGRAM: &str = r#"
+    fn show(items: Vec<&str>) {
+        for item in items {
+            echo(item);
+        }
+    }
+
+    fn main() {
+        let pkgs = vec!["curl", "it's", "$HOME"];
+        printf("[%s]", pkgs);
+        echo(pkgs.len());
+        show(pkgs);
+        let last = 2;
+        echo(pkgs[last]);
+        echo(pkgs[3]);
+    }
+
+    fn echo(msg: &str) {}
+    fn printf(format: &str, args: &str) {}
+"#;
+
+#[test]
+fn test_posix_lists_use_positional_parameters() {
+    let script = crate::transpile(LIST_PROGRAM, Config::default()).unwrap();
+
+    assert!(
+        script.contains("readonly pkgs=\"curl 'it'\\\"'\\\"'s' '\\$HOME'\"\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("eval \"set -- $pkgs\"; printf '[%s]' \"$@\"\n"),
+        "{script}"
+    );
+    assert!(script.contains("show \"$pkgs\"\n"), "{script}");
+    assert!(
+        script.contains("eval \"set -- $pkgs\"; eval \"_rash_item_1=\\\"\\${4?index out of bounds}\\\"\"; echo \"$_rash_item_1\"\n"),
+        "{script}"
+    );
+}
+
+#[test]
+fn test_bash_lists_use_arrays() {
+    let script = crate::transpile(LIST_PROGRAM, bash_config()).unwrap();
+
+    assert!(
+        script.contains("local -r pkgs=(curl 'it'\"'\"'s' '$HOME')\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("printf '[%s]' \"${pkgs[@]}\"\n"),
+        "{script}"
+    );
+    assert!(script.contains("echo \"${#pkgs[@]}\"\n"), "{script}");
+    assert!(script.contains("show \"${pkgs[*]@Q}\"\n"), "{script}");
+    assert!(
+        script.contains("eval \"local -a items=($items)\"\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("echo \"${pkgs[${last}]?index out of bounds}\"\n"),
+        "{script}"
+    );
+}
+
+#[apply(shells)]
+fn test_lists_run_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(LIST_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "[curl][it's][$HOME]3\ncurl\nit's\n$HOME\n$HOME\n"
+    );
+    // The out-of-bounds index aborts the script
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("index out of bounds"));
+}
+
+#[test]
+fn test_only_called_stdlib_helpers_are_defined() {
+    let script = crate::transpile(
+        include_str!("../../../examples/installer.rs"),
+        Config::default(),
+    )
+    .unwrap();
+
+    for helper in ["rash_log_info", "rash_mkdir_p", "rash_dir_exists"] {
+        assert_eq!(
+            script.matches(&format!("{helper}() {{")).count(),
+            1,
+            "{script}"
+        );
+    }
+    assert!(!script.contains("rash_chmod"), "{script}");
+}
+
+#[apply(shells)]
+fn test_stdlib_runs_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let source = r#"
+        use bashrs_std::{env, fs, log};
+
+        fn main() {
+            let dir = env::var_or("RASH_TEST_DIR", "/nonexistent");
+            fs::mkdir_p(dir);
+            if fs::dir_exists(dir) {
+                log::info(dir);
+            }
+        }
+    "#;
+    let script = transpile_for(source, target);
+    let root = tempfile::tempdir().unwrap();
+    let dir = root.path().join("a b/c");
+
+    let env = [("RASH_TEST_DIR", dir.to_str().unwrap())];
+    let Some(output) = run_script(shell, &script, &[], &env) else {
+        return;
+    };
+    assert!(output.status.success(), "{output:?}");
+    assert!(dir.is_dir());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("INFO: {}\n", dir.display())
+    );
+}
+
+const ARGS_PROGRAM: &str = r#"
+    use bashrs_std::env;
+
+    fn bin_dir(prefix: &str) -> String {
+        let name = env::var_or("RASH_TEST_BIN", "bin");
+        format!("{prefix}/{name}")
+    }
+
+    fn main() {
+        let mut prefix = env::var_or("RASH_TEST_PREFIX", "/usr/local");
+        let mut take = false;
+        for arg in env::args() {
+            if take {
+                prefix = arg;
+                take = false;
+            } else {
+                if arg == "--prefix" {
+                    take = true;
+                }
+            }
+        }
+        match env::var("RASH_TEST_UNSET") {
+            Some(value) => println!("set: {value}"),
+            None => println!("unset"),
+        }
+        let last = env::arg(3).unwrap_or("none");
+        let bin = bin_dir(&prefix);
+        println!("{bin}");
+        println!("{last} of {}", env::args().len());
+    }
+"#;
+
+#[test]
+fn test_env_intrinsics_are_quoted_expansions() {
+    let script = crate::transpile(ARGS_PROGRAM, Config::default()).unwrap();
+
+    assert!(script.contains("\"${RASH_TEST_BIN:-bin}\""), "{script}");
+    assert!(
+        script.contains("test -n \"${RASH_TEST_UNSET:-}\""),
+        "{script}"
+    );
+    assert!(
+        script.contains("readonly _rash_args=\"$(rash_list \"$@\")\""),
+        "{script}"
+    );
+    assert!(script.contains("test -n \"${3+x}\""), "{script}");
+    assert!(script.contains("_rash_arg_3=\"$3\""), "{script}");
+}
+
+#[apply(shells)]
+fn test_env_intrinsics_run_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(ARGS_PROGRAM, target);
+
+    let args = ["-v", "--prefix", "/opt/my tools"];
+    let Some(output) = run_script(shell, &script, &args, &[("RASH_TEST_BIN", "")]) else {
+        return;
+    };
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "unset\n/opt/my tools/bin\n/opt/my tools of 3\n"
+    );
+
+    let env = [("RASH_TEST_UNSET", "$HOME"), ("RASH_TEST_PREFIX", "/srv")];
+    let output = run_script(shell, &script, &[], &env).unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "set: $HOME\n/srv/bin\nnone of 0\n"
+    );
+}
+
+const PROCESS_PROGRAM: &str = r#"
+    use bashrs_std::process;
+
+    fn main() {
+        let text = process::capture("printf", ["%s|%s\\n\\n\\n", "a b", "it's"]);
+        println!("[{text}]");
+        if process::status("grep", ["-q", "b", "/dev/null"]) {
+            println!("found");
+        } else {
+            println!("not found");
+        }
+        process::status("false", []); // Does not stop the script
+        let ok = process::status("echo", ["hello"]);
+        println!("ok={ok}");
+        let failed = process::capture("sh", ["-c", "echo partial; exit 3"]);
+        println!("unreachable {failed}");
+    }
+"#;
+
+#[test]
+fn test_capture_and_status_lower_to_commands() {
+    let script = crate::transpile(PROCESS_PROGRAM, Config::default()).unwrap();
+
+    assert!(script.contains("text=\"$(printf "), "{script}");
+    assert!(
+        script.contains("|| return $?\n    readonly text\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("if grep '-q' b /dev/null; then"),
+        "{script}"
+    );
+    assert!(
+        script.contains("    if echo hello; then ok=true; else ok=false; fi\n    readonly ok\n"),
+        "{script}"
+    );
+}
+
+#[apply(shells)]
+fn test_capture_strips_trailing_newlines_and_stops_on_failure(
+    #[case] target: ShellDialect,
+    #[case] shell: &str,
+) {
+    let script = transpile_for(PROCESS_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "[a b|it's]\nnot found\nhello\nok=true\n"
+    );
+}
+
+const PRINT_PROGRAM: &str = r#"
+    fn main() {
+        let name = "it's $HOME";
+        println!("Hello, {}! 100% \\n {{done}}", name);
+        print!("-n");
+        println!();
+        eprintln!("{} warnings", 2 + 1);
+    }
+"#;
+
+#[test]
+fn test_print_macros_emit_printf() {
+    let script = crate::transpile(PRINT_PROGRAM, Config::default()).unwrap();
+
+    assert!(
+        script.contains("printf 'Hello, %s! 100%% \\\\n {done}\\n' \"$name\"\n"),
+        "{script}"
+    );
+    assert!(script.contains("printf -- '-n'\n"), "{script}");
+    assert!(script.contains(" >&2\n"), "{script}");
+    assert!(!script.contains("echo \"$name\""), "{script}");
+}
+
+#[apply(shells)]
+fn test_print_macros_run_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(PRINT_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Hello, it's $HOME! 100% \\n {done}\n-n\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "3 warnings\n");
+}
+
+#[test]
+fn test_constants_are_readonly_globals_before_main() {
+    let source = r#"
+        const VERSION: &str = "1.2.3";
+        static URL: &str = "https://example.com/tool";
+
+        fn show() {
+            println!("{URL}/{VERSION}");
+        }
+
+        fn main() {
+            show();
+        }
+    "#;
+    for target in [ShellDialect::Posix, ShellDialect::Bash] {
+        let config = Config {
+            target,
+            ..Config::default()
+        };
+        let script = crate::transpile(source, config).unwrap();
+
+        let globals = "readonly VERSION=1.2.3\nreadonly URL=https://example.com/tool\n";
+        let position = script.find(globals).expect(&script);
+        assert!(position < script.find("show() {").unwrap(), "{script}");
+    }
+}
+
+const RESULT_PROGRAM: &str = r#"
+    fn check(x: &str) -> Result<String, String> {
+        if x == "bad" {
+            return Err("bad input".to_string());
+        }
+        return Ok(String::from(x));
+    }
+
+    fn step() -> Result<(), String> {
+        let v = check("good")?;
+        println!("got {v}");
+        check("bad")?;
+        println!("unreachable");
+        return Ok(());
+    }
+
+    fn main() -> Result<(), String> {
+        step()?;
+        return Ok(());
+    }
+"#;
+
+#[test]
+fn test_try_emits_return_on_failure() {
+    let script = crate::transpile(RESULT_PROGRAM, Config::default()).unwrap();
+
+    assert!(
+        script.contains("v=\"$(check good 3>&1 >&4)\" || return $?\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("check bad 3>/dev/null || return $?\n"),
+        "{script}"
+    );
+    assert!(script.contains("printf 'bad input\\n' >&2\n"), "{script}");
+}
+
+#[apply(shells)]
+fn test_err_exits_non_zero_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(RESULT_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "got good\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "bad input\n");
+}
+
+const TAIL_PROGRAM: &str = r#"
+    fn is_big(x: u32) -> bool {
+        if x > 3 { true } else { false }
+    }
+
+    fn is_even(x: u32) -> bool {
+        match x {
+            0 => true,
+            2 => true,
+            _ => false,
+        }
+    }
+
+    fn size(x: u32) -> String {
+        if x > 3 { "big".to_string() } else if x > 1 { "mid".to_string() } else { "small".to_string() }
+    }
+
+    fn name(x: u32) -> String {
+        match x {
+            1 => "one",
+            _ => {
+                let many = "many";
+                many
+            }
+        }
+    }
+
+    fn main() {
+        if is_big(1) { println!("1 is big"); } else { println!("1 is small"); }
+        if is_big(5) { println!("5 is big"); }
+        if is_even(2) { println!("2 is even"); }
+        if is_even(3) { println!("3 is even"); }
+        let a = size(5);
+        let b = size(2);
+        let c = size(0);
+        let d = name(1);
+        let e = name(7);
+        println!("{a} {b} {c} {d} {e}");
+    }
+"#;
+
+#[apply(shells)]
+fn test_tail_if_and_match_return_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(TAIL_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "1 is small\n5 is big\n2 is even\nbig mid small one many\n"
+    );
+    assert!(output.status.success());
+}
+
+const STATUS_PROGRAM: &str = r#"
+    fn check(x: &str) -> bool {
+        println!("checking {x}");
+        x == "a"
+    }
+
+    fn main() {
+        let a = check("a");
+        let mut b = check("b");
+        let both = a && b;
+        b = !b && check("c");
+        println!("[{a}] [{b}] [{both}]");
+    }
+"#;
+
+#[test]
+fn test_bool_calls_are_bound_by_status() {
+    let script = crate::transpile(STATUS_PROGRAM, Config::default()).unwrap();
+
+    assert!(
+        script.contains("    if check a; then a=true; else a=false; fi\n    readonly a\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("    if check b; then b=true; else b=false; fi\n"),
+        "{script}"
+    );
+    assert!(!script.contains("echo true"), "{script}");
+    assert!(!script.contains("exec 4>&1"), "{script}");
+}
+
+#[apply(shells)]
+fn test_bool_calls_keep_their_output_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(STATUS_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "checking a\nchecking b\nchecking c\n[true] [false] [false]\n"
+    );
+    assert!(output.status.success());
+}
+
+const CALL_PROGRAM: &str = r#"
+    use bashrs_std::process;
+
+    fn read(path: &str) -> String {
+        let text = process::capture("cat", [path]);
+        text
+    }
+
+    fn first(path: &str) -> String {
+        read(path)
+    }
+
+    fn main() {
+        let a = first("/dev/null");
+        println!("a=[{a}]");
+        let b = first("/nonexistent");
+        println!("unreachable {b}");
+    }
+"#;
+
+#[apply(shells)]
+fn test_failing_calls_stop_the_script_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(CALL_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a=[]\n");
+    assert!(!output.status.success(), "{output:?}");
+}
+
+const OUTPUT_PROGRAM: &str = r#"
+    fn version() -> String {
+        println!("looking up the version");
+        "1.2.3"
+    }
+
+    fn ready(x: &str) -> bool {
+        println!("checking {x}");
+        x == "yes"
+    }
+
+    fn main() {
+        version();
+        ready("no");
+        let v = version();
+        println!("v={v}");
+    }
+"#;
+
+#[test]
+fn test_results_are_returned_on_their_own_descriptor() {
+    let script = crate::transpile(OUTPUT_PROGRAM, Config::default()).unwrap();
+
+    assert!(script.contains("export LC_ALL=C\nexec 4>&1\n"), "{script}");
+    assert!(
+        script.contains("    printf '%s\\n' 1.2.3 >&3\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("    version 3>/dev/null || return $?\n"),
+        "{script}"
+    );
+    assert!(script.contains("    if ready no; then\n"), "{script}");
+    assert!(
+        script.contains("v=\"$(version 3>&1 >&4)\" || return $?\n"),
+        "{script}"
+    );
+}
+
+#[apply(shells)]
+fn test_function_output_stays_out_of_results_in_shell(
+    #[case] target: ShellDialect,
+    #[case] shell: &str,
+) {
+    let script = transpile_for(OUTPUT_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "looking up the version\nchecking no\nlooking up the version\nv=1.2.3\n"
+    );
+    assert!(output.status.success(), "{output:?}");
+}
+
+const OPTION_PROGRAM: &str = r#"
+    fn lookup(key: &str) -> Option<String> {
+        if key == "prefix" {
+            return Some("/opt/tool");
+        }
+        return None;
+    }
+
+    fn relay(key: &str) -> Option<String> {
+        return lookup(key);
+    }
+
+    fn main() {
+        if let Some(prefix) = lookup("prefix") {
+            println!("prefix {prefix}");
+        }
+        let mode = relay("mode");
+        match mode {
+            Some(m) => println!("mode {m}"),
+            None => println!("no mode"),
+        }
+        let dir = lookup("dir").unwrap_or("/usr/local");
+        let quote = mode.unwrap_or_else(|| "it's \"$HOME\"");
+        println!("{dir} {quote}");
+    }
+"#;
+
+#[test]
+fn test_options_are_set_or_unset_variables() {
+    let script = crate::transpile(OPTION_PROGRAM, Config::default()).unwrap();
+
+    assert!(
+        script.contains("prefix=\"$(lookup prefix 3>&1 >&4)\" || unset prefix\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("if test -n \"${prefix+x}\"; then\n"),
+        "{script}"
+    );
+    assert!(
+        script.contains("dir=\"$(lookup dir 3>&1 >&4 || printf '%s' /usr/local)\""),
+        "{script}"
+    );
+    assert!(script.contains("\"${mode-$(printf '%s' "), "{script}");
+}
+
+#[apply(shells)]
+fn test_options_run_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(OPTION_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "prefix /opt/tool\nno mode\n/usr/local it's \"$HOME\"\n"
+    );
+    assert!(output.status.success());
+}
+
+const MUTABLE_PROGRAM: &str = r#"
+    fn main() {
+        let mut total = 0;
+        for i in 1..4 {
+            let square = i * i;
+            total += square;
+        }
+        {
+            let mut total = 100;
+            total += 1;
+            println!("block {total}");
+        }
+        let label = "outer";
+        if total > 0 {
+            let label = "inner";
+            println!("{label} {total}");
+        }
+        let mut greeting = "hello";
+        greeting += " world";
+        println!("{label} {greeting}");
+    }
+"#;
+
+#[test]
+fn test_mutable_bindings_are_not_readonly() {
+    let script = crate::transpile(MUTABLE_PROGRAM, Config::default()).unwrap();
+
+    assert!(script.contains("    total=0\n"), "{script}");
+    assert!(script.contains("    total_1=100\n"), "{script}");
+    assert!(script.contains("readonly label=outer\n"), "{script}");
+    assert!(script.contains("readonly label_1=inner\n"), "{script}");
+    assert!(
+        script.contains("greeting=\"${greeting} world\"\n"),
+        "{script}"
+    );
+    assert!(!script.contains("readonly total"), "{script}");
+}
+
+#[apply(shells)]
+fn test_mutable_bindings_run_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(MUTABLE_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "block 101\ninner 14\nouter hello world\n"
+    );
+    assert!(output.status.success());
+}
+
+const HELPER_PROGRAM: &str = r#"
+    fn greet(name: &str) {
+        let message = format!("hi {name}");
+        let mut count = 0;
+        count += 1;
+        println!("{message} {count}");
+    }
+
+    fn main() {
+        let name = "a";
+        let path = "/opt";
+        greet(name);
+        greet("b");
+        println!("{name} {path}");
+    }
+"#;
+
+#[test]
+fn test_function_variables_are_local_in_bash() {
+    let config = Config {
+        target: ShellDialect::Bash,
+        ..Config::default()
+    };
+    let script = crate::transpile(HELPER_PROGRAM, config).unwrap();
+
+    assert!(script.contains("    local count\n"), "{script}");
+    assert!(script.contains("local -r path_1=/opt\n"), "{script}");
+
+    // Without `local`, a helper's bindings stay assignable so that it can run again
+    let script = crate::transpile(HELPER_PROGRAM, Config::default()).unwrap();
+    assert!(script.contains("    message=\"hi ${name}\"\n"), "{script}");
+    assert!(script.contains("readonly name_1=a\n"), "{script}");
+    assert!(!script.contains("local "), "{script}");
+}
+
+#[apply(shells)]
+fn test_helpers_run_more_than_once_in_shell(#[case] target: ShellDialect, #[case] shell: &str) {
+    let script = transpile_for(HELPER_PROGRAM, target);
+
+    let Some(output) = run_script(shell, &script, &[], &[]) else {
+        return;
+    };
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "hi a 1\nhi b 1\na /opt\n"
+    );
+    assert!(output.status.success());
+}
@@ -66,6 +66,20 @@ pub struct SourceMap {
 
     /// Token boundaries for precise error reporting
     token_boundaries: Vec<TokenBoundary>,
+
+    /// Shell variable holding each Rust binding
+    names: Vec<NameMapping>,
+}
+
+/// A Rust binding and the shell variable the transpiler gave it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameMapping {
+    /// Function the binding belongs to; empty for constants
+    pub function: String,
+    pub rust_name: String,
+    pub shell_name: String,
+    /// Where the binding is introduced, when known
+    pub original: Option<CharPos>,
 }
 
 #[derive(Debug, Clone)]
@@ -97,6 +111,7 @@ impl SourceMap {
             reverse: BPlusTree::new(),
             deltas: Vec::new(),
             token_boundaries: Vec::new(),
+            names: Vec::new(),
         }
     }
 
@@ -126,6 +141,16 @@ impl SourceMap {
         self.reverse.insert(formatted, original);
     }
 
+    /// Record the shell variables the transpiler gave Rust bindings
+    pub fn add_names(&mut self, names: impl IntoIterator<Item = NameMapping>) {
+        self.names.extend(names);
+    }
+
+    /// Shell variable holding each Rust binding
+    pub fn names(&self) -> &[NameMapping] {
+        &self.names
+    }
+
     /// Add a span delta for compressed storage
     pub fn add_span_delta(&mut self, delta: SpanDelta) {
         self.deltas.push(delta);
@@ -221,6 +246,20 @@ impl SourceMap {
         self.find_token_boundary(pos)
     }
 
+    /// Original position recorded for the nearest mapped formatted position at or before `pos`
+    pub fn original_position(&self, pos: CharPos) -> Option<CharPos> {
+        self.reverse.search(pos)
+    }
+
+    /// All (formatted, original) pairs, ordered by formatted position
+    pub fn reverse_mappings(&self) -> Vec<(CharPos, CharPos)> {
+        self.reverse
+            .map
+            .iter()
+            .map(|(formatted, original)| (*formatted, *original))
+            .collect()
+    }
+
     /// Get all mappings in a range
     pub fn mappings_in_range(&self, start: CharPos, end: CharPos) -> Vec<(CharPos, CharPos)> {
         self.forward.range_query(start, end)
@@ -408,6 +447,20 @@ mod tests {
         assert_eq!(mapped.exact, CharPos(20));
     }
 
+    #[test]
+    fn test_original_position_lookup() {
+        let mut map = SourceMap::new();
+        map.add_mapping(CharPos(10), CharPos(20));
+        map.add_mapping(CharPos(30), CharPos(35));
+
+        assert_eq!(map.original_position(CharPos(25)), Some(CharPos(10)));
+        assert_eq!(map.original_position(CharPos(5)), None);
+        assert_eq!(
+            map.reverse_mappings(),
+            vec![(CharPos(20), CharPos(10)), (CharPos(35), CharPos(30))]
+        );
+    }
+
     #[test]
     fn test_source_map_builder() {
         let mut builder = SourceMapBuilder::new();
@@ -0,0 +1,314 @@
+//! Effect budgets: the effects a `#[rash::effects(...)]` attribute allows a
+//! function, and the ones `--allow-effects` and `--deny-effects` allow the
+//! whole script
+
+use super::{stdlib, Effect, EffectSet, ForIter, IrConverter, ShellIR, ShellValue};
+use crate::ast::restricted::{EffectBudget, Span};
+use crate::models::{Diagnostic, Error, Result};
+
+/// Code with an effect its budget doesn't allow
+struct Violation {
+    effect: Effect,
+    /// The calls that lead to the code, then the code itself
+    trail: Vec<(Option<Span>, String)>,
+}
+
+impl IrConverter {
+    /// Effects of running `ir`, including those of the values it tests or
+    /// loops over
+    pub(super) fn statement_effects(&self, ir: &ShellIR) -> EffectSet {
+        match ir {
+            ShellIR::If {
+                test,
+                then_branch,
+                else_branch,
+            } => {
+                let effects = self
+                    .value_effects(test)
+                    .union(&self.statement_effects(then_branch));
+                match else_branch {
+                    Some(else_branch) => effects.union(&self.statement_effects(else_branch)),
+                    None => effects,
+                }
+            }
+            ShellIR::While { test, body, .. } => self
+                .value_effects(test)
+                .union(&self.statement_effects(body)),
+            ShellIR::For { iter, body, .. } => {
+                self.iter_effects(iter).union(&self.statement_effects(body))
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                arms.iter().fold(self.value_effects(scrutinee), |acc, arm| {
+                    acc.union(&self.statement_effects(&arm.body))
+                })
+            }
+            ShellIR::Return {
+                value: Some(value), ..
+            } => self.value_effects(value),
+            ShellIR::Sequence(items) => items.iter().fold(EffectSet::pure(), |acc, item| {
+                acc.union(&self.statement_effects(item))
+            }),
+            ShellIR::Spanned { inner, .. } => self.statement_effects(inner),
+            other => other.effects(),
+        }
+    }
+
+    fn iter_effects(&self, iter: &ForIter) -> EffectSet {
+        match iter {
+            ForIter::Range { start, end, .. } => {
+                self.value_effects(start).union(&self.value_effects(end))
+            }
+            ForIter::Words(words) => words.iter().fold(EffectSet::pure(), |acc, word| {
+                acc.union(&self.value_effects(word))
+            }),
+        }
+    }
+
+    /// Refuse a function whose body has effects its attribute doesn't allow
+    pub(super) fn check_effect_budget(
+        &self,
+        function: &str,
+        budget: &EffectBudget,
+        body: &[ShellIR],
+    ) -> Result<()> {
+        let permits = |effect: &Effect| budget.effects.contains(effect);
+        let Some(violation) = body
+            .iter()
+            .find_map(|stmt| self.find_violation(stmt, &permits, None))
+        else {
+            return Ok(());
+        };
+        let declared = if budget.effects.is_empty() {
+            "no effects".to_string()
+        } else {
+            let names: Vec<&str> = budget.effects.iter().map(Effect::name).collect();
+            format!("only `{}`", names.join(", "))
+        };
+        let effect = violation.effect.clone();
+        Err(self.report(
+            violation,
+            format!(
+                "`{function}` {}, but its declared effects don't allow it",
+                effect.description()
+            ),
+            Some((budget.span, format!("declares {declared}"))),
+            with_stub_hint(
+                &effect,
+                format!(
+                    "add `{}` to the attribute, or move the code that {} out of `{function}`",
+                    effect.name(),
+                    effect.description()
+                ),
+            ),
+        ))
+    }
+
+    /// Refuse a script with effects the command line doesn't allow
+    pub(super) fn check_script_effects(&self, body: &[ShellIR]) -> Result<()> {
+        let policy = &self.script_effects;
+        let permits = |effect: &Effect| policy.permits(effect);
+        let Some(violation) = body
+            .iter()
+            .find_map(|stmt| self.find_violation(stmt, &permits, None))
+        else {
+            return Ok(());
+        };
+        let effect = violation.effect.clone();
+        let flag = if policy.deny.contains(&effect) {
+            "is denied by `--deny-effects`"
+        } else {
+            "is not in `--allow-effects`"
+        };
+        Err(self.report(
+            violation,
+            format!("the script {}, which {flag}", effect.description()),
+            None,
+            with_stub_hint(
+                &effect,
+                format!(
+                    "allow `{}` on the command line, or remove the code that {}",
+                    effect.name(),
+                    effect.description()
+                ),
+            ),
+        ))
+    }
+
+    fn report(
+        &self,
+        violation: Violation,
+        message: String,
+        declaration: Option<(Span, String)>,
+        help: String,
+    ) -> Error {
+        let mut diagnostic = Diagnostic::new("E0402", message).with_help(help);
+        if let Some((span, label)) = declaration {
+            diagnostic = diagnostic.with_secondary(span, label);
+        }
+        let mut trail = violation.trail;
+        let culprit = trail.pop();
+        for (span, label) in trail {
+            if let Some(span) = span {
+                diagnostic = diagnostic.with_secondary(span, label);
+            }
+        }
+        if let Some((Some(span), label)) = culprit {
+            diagnostic = diagnostic.with_primary(span, label);
+        }
+        diagnostic.into()
+    }
+
+    /// The first statement of `ir` with an effect `permits` refuses
+    fn find_violation(
+        &self,
+        ir: &ShellIR,
+        permits: &dyn Fn(&Effect) -> bool,
+        span: Option<Span>,
+    ) -> Option<Violation> {
+        let tested = |effects: EffectSet, value: &ShellValue| {
+            let effect = first_refused(&effects, permits)?;
+            Some(self.value_violation(value, effect, span))
+        };
+        match ir {
+            ShellIR::If {
+                test,
+                then_branch,
+                else_branch,
+            } => tested(self.value_effects(test), test)
+                .or_else(|| self.find_violation(then_branch, permits, span))
+                .or_else(|| {
+                    else_branch
+                        .as_ref()
+                        .and_then(|branch| self.find_violation(branch, permits, span))
+                }),
+            ShellIR::While { test, body, .. } => tested(self.value_effects(test), test)
+                .or_else(|| self.find_violation(body, permits, span)),
+            ShellIR::For { iter, body, .. } => {
+                let words = match iter {
+                    ForIter::Range { start, end, .. } => vec![start, end],
+                    ForIter::Words(words) => words.iter().collect(),
+                };
+                words
+                    .into_iter()
+                    .find_map(|word| tested(self.value_effects(word), word))
+                    .or_else(|| self.find_violation(body, permits, span))
+            }
+            ShellIR::Case { scrutinee, arms } => tested(self.value_effects(scrutinee), scrutinee)
+                .or_else(|| {
+                    arms.iter()
+                        .find_map(|arm| self.find_violation(&arm.body, permits, span))
+                }),
+            ShellIR::Sequence(items) => items
+                .iter()
+                .find_map(|item| self.find_violation(item, permits, span)),
+            ShellIR::Spanned { span, inner } => self.find_violation(inner, permits, Some(*span)),
+            ShellIR::Exec { cmd, effects } => {
+                let effect = first_refused(effects, permits)?;
+                if self
+                    .program_effects(&cmd.program, &cmd.args)
+                    .contains(&effect)
+                {
+                    return Some(self.call_violation(&cmd.program, effect, span));
+                }
+                cmd.args
+                    .iter()
+                    .find(|arg| self.value_effects(arg).contains(&effect))
+                    .map(|arg| self.value_violation(arg, effect.clone(), span))
+            }
+            ShellIR::Let { value, .. }
+            | ShellIR::Assign { value, .. }
+            | ShellIR::LetOption {
+                value: Some(value), ..
+            }
+            | ShellIR::Try { call: value, .. }
+            | ShellIR::Return {
+                value: Some(value), ..
+            } => tested(self.value_effects(value), value),
+            ShellIR::Printf { args, .. } => args
+                .iter()
+                .find_map(|arg| tested(self.value_effects(arg), arg)),
+            _ => None,
+        }
+    }
+
+    /// The command or function in `value` with `effect`
+    fn value_violation(&self, value: &ShellValue, effect: Effect, span: Option<Span>) -> Violation {
+        let mut commands = Vec::new();
+        value.collect_commands(&mut commands);
+        let culprit = commands
+            .into_iter()
+            .find(|name| self.program_effects(name, &[]).contains(&effect));
+        match culprit {
+            Some(name) => self.call_violation(&name, effect, span),
+            None => Violation {
+                trail: vec![(span, format!("{} here", effect.description()))],
+                effect,
+            },
+        }
+    }
+
+    /// A call to a command, runtime helper or script function with `effect`,
+    /// followed into the function's body
+    fn call_violation(&self, name: &str, effect: Effect, span: Option<Span>) -> Violation {
+        if let Some(function) = stdlib::by_helper(name) {
+            return Violation {
+                trail: vec![(
+                    span,
+                    format!("`{}` {}", function.path, effect.description()),
+                )],
+                effect,
+            };
+        }
+        let inner = self.function_bodies.borrow().get(name).and_then(|body| {
+            let wanted = effect.clone();
+            self.find_violation(body, &|refused| *refused != wanted, None)
+        });
+        match inner {
+            Some(mut violation) => {
+                violation.trail.insert(
+                    0,
+                    (
+                        span,
+                        format!("calls `{name}`, which {}", effect.description()),
+                    ),
+                );
+                violation
+            }
+            None => Violation {
+                trail: vec![(span, format!("`{name}` {}", effect.description()))],
+                effect,
+            },
+        }
+    }
+
+    /// Effects of running a runtime helper, script function or external
+    /// command, without those of its arguments
+    fn program_effects(&self, name: &str, args: &[ShellValue]) -> EffectSet {
+        if let Some(function) = stdlib::by_helper(name) {
+            return function.effects();
+        }
+        match self.function_effects.borrow().get(name) {
+            Some(effects) => effects.clone(),
+            None => self.command_effects(name, args),
+        }
+    }
+}
+
+/// The first effect in `effects` that `permits` refuses, in a fixed order
+fn first_refused(effects: &EffectSet, permits: &dyn Fn(&Effect) -> bool) -> Option<Effect> {
+    Effect::DECLARABLE
+        .into_iter()
+        .find(|effect| effects.contains(effect) && !permits(effect))
+}
+
+/// Point out that a command the table doesn't know can have its effects
+/// declared on its stub
+fn with_stub_hint(effect: &Effect, help: String) -> String {
+    if *effect != Effect::ProcessExec {
+        return help;
+    }
+    format!(
+        "{help}; to state what an external command does, declare its stub with an attribute, \
+         e.g. `#[rash::effects(fs_read)] fn tool(arg: &str) {{}}`"
+    )
+}
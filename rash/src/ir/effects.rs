@@ -1,3 +1,4 @@
+use super::shell_ir::ShellValue;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -25,6 +26,51 @@ pub enum Effect {
     SystemModification,
 }
 
+impl Effect {
+    /// The effects a `#[rash::effects(...)]` attribute or an effect flag can name
+    pub const DECLARABLE: [Effect; 6] = [
+        Effect::EnvRead,
+        Effect::FileRead,
+        Effect::FileWrite,
+        Effect::NetworkAccess,
+        Effect::ProcessExec,
+        Effect::SystemModification,
+    ];
+
+    /// Name of the effect in `#[rash::effects(...)]` and the effect flags
+    pub fn name(&self) -> &'static str {
+        match self {
+            Effect::Pure => "pure",
+            Effect::EnvRead => "env",
+            Effect::FileRead => "fs_read",
+            Effect::FileWrite => "fs_write",
+            Effect::NetworkAccess => "network",
+            Effect::ProcessExec => "process",
+            Effect::SystemModification => "system",
+        }
+    }
+
+    /// The effect called `name`
+    pub fn from_name(name: &str) -> Option<Effect> {
+        std::iter::once(Effect::Pure)
+            .chain(Effect::DECLARABLE)
+            .find(|effect| effect.name() == name)
+    }
+
+    /// What code with this effect does, e.g. "writes files"
+    pub fn description(&self) -> &'static str {
+        match self {
+            Effect::Pure => "has no effects",
+            Effect::EnvRead => "reads environment variables or arguments",
+            Effect::FileRead => "reads files",
+            Effect::FileWrite => "writes files",
+            Effect::NetworkAccess => "accesses the network",
+            Effect::ProcessExec => "runs programs whose effects are unknown",
+            Effect::SystemModification => "changes the system's configuration",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EffectSet {
     effects: HashSet<Effect>,
@@ -117,40 +163,174 @@ impl From<Vec<Effect>> for EffectSet {
     }
 }
 
+/// Effects the whole script may have, set with `--allow-effects` and
+/// `--deny-effects`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EffectPolicy {
+    /// The only effects allowed, or `None` for any
+    pub allow: Option<Vec<Effect>>,
+    /// Effects refused even when `allow` lists them
+    pub deny: Vec<Effect>,
+}
+
+impl EffectPolicy {
+    /// Whether the policy allows every effect
+    pub fn is_unrestricted(&self) -> bool {
+        self.allow.is_none() && self.deny.is_empty()
+    }
+
+    /// Whether code with `effect` may run under this policy
+    pub fn permits(&self, effect: &Effect) -> bool {
+        *effect == Effect::Pure
+            || (self
+                .allow
+                .as_ref()
+                .map_or(true, |allow| allow.contains(effect))
+                && !self.deny.contains(effect))
+    }
+}
+
 /// Analyze the effects of a shell command based on its name
+///
+/// Commands the table doesn't know count as [`Effect::ProcessExec`], so a
+/// budget that leaves it out refuses them.
 pub fn analyze_command_effects(command: &str) -> EffectSet {
-    match command {
-        // Pure commands
-        "echo" | "printf" | "test" | "[" => EffectSet::pure(),
+    use Effect::*;
+
+    let effects: &[Effect] = match command {
+        // Pure commands: output only, or facts about the running system
+        "echo" | "printf" | "true" | "false" | "test" | "[" | ":" | "expr" | "basename"
+        | "dirname" | "seq" | "sleep" | "yes" | "pwd" | "date" | "uname" | "arch" | "id"
+        | "whoami" | "groups" | "logname" | "hostname" | "nproc" | "tty" => &[],
+
+        "printenv" => &[EnvRead],
 
         // File system reads
-        "cat" | "ls" | "find" | "grep" | "head" | "tail" | "wc" => {
-            EffectSet::single(Effect::FileRead)
+        "cat" | "tac" | "head" | "tail" | "wc" | "ls" | "find" | "stat" | "file" | "du" | "df"
+        | "readlink" | "realpath" | "grep" | "egrep" | "fgrep" | "sed" | "cut" | "sort"
+        | "uniq" | "tr" | "nl" | "fold" | "fmt" | "paste" | "join" | "comm" | "diff" | "cmp"
+        | "od" | "xxd" | "hexdump" | "strings" | "base64" | "md5sum" | "sha1sum" | "sha256sum"
+        | "sha512sum" | "b2sum" | "cksum" | "jq" | "which" | "apt-cache" | "dpkg-query" => {
+            &[FileRead]
         }
 
         // File system writes
-        "cp" | "mv" | "rm" | "mkdir" | "rmdir" | "touch" | "chmod" | "chown" => {
-            vec![Effect::FileWrite, Effect::SystemModification].into()
+        "rm" | "rmdir" | "mkdir" | "touch" | "chmod" | "chown" | "chgrp" | "ln" | "unlink"
+        | "truncate" | "mktemp" | "mkfifo" | "mknod" | "shred" | "sync" => &[FileWrite],
+        "cp" | "mv" | "install" | "tee" | "dd" | "split" | "csplit" | "patch" => {
+            &[FileRead, FileWrite]
         }
 
-        // Network commands
-        "curl" | "wget" | "ssh" | "scp" | "rsync" => {
-            vec![Effect::NetworkAccess, Effect::FileWrite].into()
-        }
+        // Archives
+        "tar" | "gzip" | "gunzip" | "zcat" | "bzip2" | "bunzip2" | "xz" | "unxz" | "zstd"
+        | "unzstd" | "zip" | "unzip" | "cpio" | "7z" => &[FileRead, FileWrite],
 
-        // Archive commands
-        "tar" | "gzip" | "gunzip" | "zip" | "unzip" => {
-            vec![Effect::FileRead, Effect::FileWrite].into()
-        }
+        // Network commands
+        "curl" => &[NetworkAccess],
+        "ping" | "dig" | "nslookup" | "host" | "nc" | "netcat" => &[NetworkAccess],
+        "wget" | "scp" | "sftp" | "ftp" | "rsync" => &[NetworkAccess, FileRead, FileWrite],
+        "ssh" | "git" => &[NetworkAccess, FileRead, FileWrite, ProcessExec],
+
+        // Package managers fetch, install and run maintainer scripts
+        "apt" | "apt-get" | "dpkg" | "yum" | "dnf" | "rpm" | "zypper" | "pacman" | "apk"
+        | "brew" | "snap" | "flatpak" | "pip" | "pip3" | "npm" | "yarn" | "gem" | "cargo"
+        | "go" => &[
+            NetworkAccess,
+            FileRead,
+            FileWrite,
+            ProcessExec,
+            SystemModification,
+        ],
 
         // System modification
-        "sudo" | "su" | "systemctl" | "service" => {
-            vec![Effect::SystemModification, Effect::ProcessExec].into()
-        }
+        "systemctl"
+        | "service"
+        | "useradd"
+        | "userdel"
+        | "usermod"
+        | "groupadd"
+        | "groupdel"
+        | "passwd"
+        | "chpasswd"
+        | "mount"
+        | "umount"
+        | "modprobe"
+        | "sysctl"
+        | "kill"
+        | "killall"
+        | "pkill"
+        | "crontab"
+        | "update-alternatives"
+        | "ldconfig"
+        | "hostnamectl"
+        | "timedatectl"
+        | "iptables"
+        | "ufw"
+        | "reboot"
+        | "shutdown" => &[SystemModification],
+
+        // Commands that run another command, with more privileges for some
+        "sudo" | "su" | "doas" => &[SystemModification, ProcessExec],
+        "env" => &[EnvRead, ProcessExec],
+
+        // Interpreters run arbitrary code
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "ash" | "busybox" | "eval" | "." | "source"
+        | "awk" | "gawk" | "mawk" | "perl" | "python" | "python3" | "ruby" | "node" | "make" => &[
+            EnvRead,
+            FileRead,
+            FileWrite,
+            NetworkAccess,
+            ProcessExec,
+            SystemModification,
+        ],
+
+        // Process execution: `exec`, `xargs`, `nohup` and anything unknown
+        _ => &[ProcessExec],
+    };
+    effects.to_vec().into()
+}
 
-        // Process execution
-        _ => vec![Effect::ProcessExec].into(),
+/// Analyze the effects of running `command` with `args`, where the options
+/// the compiler can see refine the effects of the command's name
+pub fn analyze_invocation_effects(command: &str, args: &[ShellValue]) -> EffectSet {
+    let options: Vec<String> = args
+        .iter()
+        .filter_map(ShellValue::as_constant_string)
+        .filter(|arg| arg.starts_with('-'))
+        .collect();
+    let has = |names: &[&str]| {
+        options.iter().any(|option| {
+            names
+                .iter()
+                .any(|name| option == name || option.starts_with(&format!("{name}=")))
+        })
+    };
+    // A short option, alone or in a cluster such as `-fsSLo`
+    let has_short = |letter: char| {
+        options
+            .iter()
+            .any(|option| !option.starts_with("--") && option[1..].contains(letter))
+    };
+
+    let mut effects = analyze_command_effects(command);
+    match command {
+        "command" if has_short('v') || has_short('V') => return EffectSet::pure(),
+        "sed" if has_short('i') || has(&["--in-place"]) => effects.add(Effect::FileWrite),
+        "sort" if has_short('o') || has(&["--output"]) => effects.add(Effect::FileWrite),
+        "curl" if has_short('o') || has_short('O') || has(&["--output", "--remote-name"]) => {
+            effects.add(Effect::FileWrite)
+        }
+        "find" => {
+            if has(&["-delete", "-fprint", "-fprint0", "-fprintf", "-fls"]) {
+                effects.add(Effect::FileWrite);
+            }
+            if has(&["-exec", "-execdir", "-ok", "-okdir"]) {
+                effects.add(Effect::ProcessExec);
+            }
+        }
+        _ => {}
     }
+    effects
 }
 
 #[cfg(test)]
@@ -182,4 +362,55 @@ mod tests {
         assert!(analyze_command_effects("curl").has_network_effects());
         assert!(analyze_command_effects("rm").has_system_effects());
     }
+
+    #[test]
+    fn test_command_effect_table() {
+        let effects = |command| analyze_command_effects(command).to_vec();
+        assert_eq!(effects("sha256sum"), [Effect::FileRead]);
+        assert_eq!(effects("mkdir"), [Effect::FileWrite]);
+        assert_eq!(effects("systemctl"), [Effect::SystemModification]);
+        assert!(analyze_command_effects("apt-get").has_network_effects());
+        assert!(analyze_command_effects("apt-get").contains(&Effect::SystemModification));
+        assert!(analyze_command_effects("sh").contains(&Effect::FileWrite));
+        assert_eq!(effects("my-tool"), [Effect::ProcessExec]);
+    }
+
+    #[test]
+    fn test_invocation_options_refine_effects() {
+        let effects = |command, args: &[&str]| {
+            let args: Vec<ShellValue> = args
+                .iter()
+                .map(|arg| ShellValue::String(arg.to_string()))
+                .collect();
+            analyze_invocation_effects(command, &args)
+        };
+        assert!(!effects("sed", &["s/a/b/", "f"]).contains(&Effect::FileWrite));
+        assert!(effects("sed", &["-i.bak", "s/a/b/", "f"]).contains(&Effect::FileWrite));
+        assert!(!effects("curl", &["-fsSL", "https://x"]).contains(&Effect::FileWrite));
+        assert!(effects("curl", &["-fsSLo", "out", "https://x"]).contains(&Effect::FileWrite));
+        assert!(effects("find", &[".", "-delete"]).contains(&Effect::FileWrite));
+        assert!(effects("command", &["-v", "git"]).is_pure());
+        assert!(effects("command", &["git"]).contains(&Effect::ProcessExec));
+    }
+
+    #[test]
+    fn test_effect_names_round_trip() {
+        for effect in Effect::DECLARABLE {
+            assert_eq!(Effect::from_name(effect.name()), Some(effect.clone()));
+        }
+        assert_eq!(Effect::from_name("pure"), Some(Effect::Pure));
+        assert_eq!(Effect::from_name("disk"), None);
+    }
+
+    #[test]
+    fn test_effect_policy() {
+        let policy = EffectPolicy {
+            allow: None,
+            deny: vec![Effect::FileWrite],
+        };
+        assert!(policy.permits(&Effect::NetworkAccess));
+        assert!(!policy.permits(&Effect::FileWrite));
+        assert!(policy.permits(&Effect::Pure));
+        assert!(EffectPolicy::default().is_unrestricted());
+    }
 }
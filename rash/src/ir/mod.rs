@@ -1,19 +1,40 @@
+mod budget;
 pub mod effects;
+mod scope;
 pub mod shell_ir;
+pub mod stdlib;
 
 #[cfg(test)]
 mod tests;
 
-pub use effects::{Effect, EffectSet};
-pub use shell_ir::{Command, ShellExpression, ShellIR, ShellValue};
+pub use effects::{Effect, EffectPolicy, EffectSet};
+pub use shell_ir::{
+    ArithmeticOp, CaseArm, CasePattern, Command, ComparisonOp, ForIter, LetterCase, LogicalOp,
+    ReturnConvention, ShellExpression, ShellIR, ShellValue,
+};
 
+use crate::ast::restricted::{BinaryOp, UnaryOp};
 use crate::ast::RestrictedAst;
+use crate::formatter::source_map::NameMapping;
 use crate::models::{Config, Error, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 /// Convert AST to Shell IR
 pub fn from_ast(ast: &RestrictedAst) -> Result<ShellIR> {
-    let converter = IrConverter::new();
-    converter.convert(ast)
+    from_ast_with_names(ast, &EffectPolicy::default()).map(|(ir, _)| ir)
+}
+
+/// Convert AST to Shell IR, also returning the shell variable each Rust
+/// binding was renamed to; the script may only have the effects `effects`
+/// allows
+pub fn from_ast_with_names(
+    ast: &RestrictedAst,
+    effects: &EffectPolicy,
+) -> Result<(ShellIR, Vec<NameMapping>)> {
+    let (ast, names) = scope::resolve(ast)?;
+    let converter = IrConverter::new(effects.clone());
+    Ok((converter.convert(&ast)?, names))
 }
 
 /// Optimize Shell IR based on configuration
@@ -34,15 +55,61 @@ pub fn optimize(ir: ShellIR, config: &Config) -> Result<ShellIR> {
 }
 
 struct IrConverter {
-    // Converter state
+    /// Number of loops enclosing the statement being converted
+    loop_depth: Cell<usize>,
+    /// Effects of the user-defined shell functions converted so far
+    function_effects: RefCell<HashMap<String, EffectSet>>,
+    /// Bodies of the user-defined shell functions converted so far, to
+    /// point at the code behind a call's effects
+    function_bodies: RefCell<HashMap<String, ShellIR>>,
+    /// Effects of the external commands whose stubs declare them
+    command_declarations: RefCell<HashMap<String, EffectSet>>,
+    /// Effects the whole script may have
+    script_effects: EffectPolicy,
+    /// Declared return type of every user-defined shell function
+    return_types: RefCell<HashMap<String, crate::ast::Type>>,
+    /// Types of the variables in scope, used to pick string or integer operators
+    var_types: RefCell<HashMap<String, ValueType>>,
+    /// Element types of the list variables in scope, and the types of the
+    /// values the `Option` variables in scope may hold
+    element_types: RefCell<HashMap<String, ValueType>>,
+    /// Types of the `const` and `static` globals, which no binding may reuse
+    constants: RefCell<HashMap<String, ValueType>>,
+    /// Convention of the function being converted; `None` inside the entry point
+    current_convention: Cell<Option<ReturnConvention>>,
+    /// Whether the function being converted returns a `Result`, so that `?`
+    /// and `Err` can fail it
+    current_fallible: Cell<bool>,
+    /// Whether the function being converted returns an `Option`
+    current_optional: Cell<bool>,
+    /// The script arguments the program reads, which main binds to variables
+    script_arguments: RefCell<ScriptArguments>,
 }
 
 impl IrConverter {
-    fn new() -> Self {
-        Self {}
+    fn new(script_effects: EffectPolicy) -> Self {
+        Self {
+            loop_depth: Cell::new(0),
+            function_effects: RefCell::new(HashMap::new()),
+            function_bodies: RefCell::new(HashMap::new()),
+            command_declarations: RefCell::new(HashMap::new()),
+            script_effects,
+            return_types: RefCell::new(HashMap::new()),
+            var_types: RefCell::new(HashMap::new()),
+            element_types: RefCell::new(HashMap::new()),
+            constants: RefCell::new(HashMap::new()),
+            current_convention: Cell::new(None),
+            current_fallible: Cell::new(false),
+            current_optional: Cell::new(false),
+            script_arguments: RefCell::new(ScriptArguments::default()),
+        }
     }
 
     fn convert(&self, ast: &RestrictedAst) -> Result<ShellIR> {
+        let mut ast = ast.clone();
+        *self.script_arguments.borrow_mut() = bind_script_arguments(&mut ast);
+        let ast = &ast;
+
         // Find the entry point function
         let entry_function = ast
             .functions
@@ -50,29 +117,330 @@ impl IrConverter {
             .find(|f| f.name == ast.entry_point)
             .ok_or_else(|| Error::IrGeneration("Entry point not found".to_string()))?;
 
-        // Convert the main function to IR
+        for function in &ast.functions {
+            if function.name != ast.entry_point && !function.is_external_stub() {
+                self.return_types
+                    .borrow_mut()
+                    .insert(function.name.clone(), function.return_type.clone());
+            }
+            // A stub has no body to check, so its attribute states the
+            // effects of the command it stands for
+            if let (true, Some(budget)) = (function.is_external_stub(), &function.effects) {
+                self.command_declarations
+                    .borrow_mut()
+                    .insert(function.name.clone(), budget.effects.clone().into());
+            }
+        }
+
+        // Globals come first so that every function can read them
         let mut statements = Vec::new();
+        for constant in &ast.constants {
+            let global = self
+                .convert_constant(constant)
+                .map_err(|e| e.at(constant.span))?;
+            statements.push(global);
+        }
+
+        // Convert helpers callees-first so every call site knows its callee's effects
+        let mut definitions = HashMap::new();
+        for function in definition_order(ast) {
+            if function.name == ast.entry_point || function.is_external_stub() {
+                continue;
+            }
+            let definition = self.convert_function(function)?;
+            definitions.insert(function.name.as_str(), definition);
+        }
+
+        // Emit the definitions main can reach in source order, followed by
+        // the main function body
+        let reachable = reachable_functions(ast);
+        for function in &ast.functions {
+            if !reachable.contains(function.name.as_str()) {
+                continue;
+            }
+            if let Some(definition) = definitions.remove(function.name.as_str()) {
+                statements.push(definition);
+            }
+        }
 
+        if let Some(ok_type) = result_ok_type(&entry_function.return_type) {
+            if !matches!(ok_type, crate::ast::Type::Void) {
+                return Err(Error::Unsupported(
+                    "Entry point returning a value in `Ok`; use `Result<(), E>`".to_string(),
+                ));
+            }
+        }
+        self.enter_function(entry_function);
+        self.current_fallible
+            .set(result_ok_type(&entry_function.return_type).is_some());
+        let mut body = self.script_argument_bindings();
         for stmt in &entry_function.body {
-            statements.push(self.convert_stmt(stmt)?);
+            body.push(self.convert_stmt(stmt)?);
+        }
+        self.current_fallible.set(false);
+
+        if let Some(budget) = &entry_function.effects {
+            self.check_effect_budget(&entry_function.name, budget, &body)?;
         }
+        self.check_script_effects(&body)?;
+        statements.extend(body);
 
         Ok(ShellIR::Sequence(statements))
     }
 
+    fn convert_constant(&self, constant: &crate::ast::Constant) -> Result<ShellIR> {
+        let name = &constant.name;
+        if !self.is_constant_expr(&constant.value) {
+            return Err(Error::IrGeneration(format!(
+                "Constant '{name}' must be a literal or an expression of other constants"
+            )));
+        }
+        let declared = ValueType::from(&constant.ty);
+        if declared == ValueType::List {
+            return Err(Error::Unsupported(format!(
+                "List constant '{name}'; bind the list with `let` inside a function"
+            )));
+        }
+        let actual = self.infer_type(&constant.value);
+        if declared != ValueType::Unknown && actual != ValueType::Unknown && declared != actual {
+            return Err(Error::IrGeneration(format!(
+                "Constant '{name}' is declared as {} but its value is {}",
+                declared.description(),
+                actual.description()
+            )));
+        }
+
+        let value = self.convert_expr_to_value(&constant.value)?;
+        self.constants.borrow_mut().insert(name.clone(), declared);
+        Ok(ShellIR::Global {
+            name: name.clone(),
+            value,
+        })
+    }
+
+    /// Whether an expression only combines literals and the globals defined so far
+    fn is_constant_expr(&self, expr: &crate::ast::Expr) -> bool {
+        use crate::ast::Expr;
+
+        match expr {
+            Expr::Literal(_) => true,
+            Expr::Variable(name) => self.constants.borrow().contains_key(name),
+            Expr::Binary { left, right, .. } => {
+                self.is_constant_expr(left) && self.is_constant_expr(right)
+            }
+            Expr::Unary { operand, .. } => self.is_constant_expr(operand),
+            _ => false,
+        }
+    }
+
+    /// Reject a binding that would reassign a readonly global
+    fn check_not_constant(&self, name: &str) -> Result<()> {
+        if self.constants.borrow().contains_key(name) {
+            return Err(Error::IrGeneration(format!(
+                "Cannot assign to constant '{name}'; constants are readonly globals"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start a fresh variable scope holding the function's parameters
+    fn enter_function(&self, function: &crate::ast::Function) {
+        let mut var_types = self.var_types.borrow_mut();
+        let mut element_types = self.element_types.borrow_mut();
+        var_types.clear();
+        element_types.clear();
+        for param in &function.params {
+            var_types.insert(param.name.clone(), ValueType::from(&param.param_type));
+            if let crate::ast::Type::Vec { inner_type } = &param.param_type {
+                element_types.insert(param.name.clone(), ValueType::from(&**inner_type));
+            }
+        }
+        // The variables main binds the script arguments to are visible everywhere
+        let arguments = self.script_arguments.borrow();
+        if arguments.all {
+            var_types.insert(ARGS_VARIABLE.to_string(), ValueType::List);
+            element_types.insert(ARGS_VARIABLE.to_string(), ValueType::Str);
+        }
+        for &position in &arguments.positions {
+            var_types.insert(arg_variable(position), ValueType::Option);
+            element_types.insert(arg_variable(position), ValueType::Str);
+        }
+    }
+
+    /// Bind the script arguments the program reads at the start of main,
+    /// before anything can reset the positional parameters
+    fn script_argument_bindings(&self) -> Vec<ShellIR> {
+        let arguments = self.script_arguments.borrow();
+        let mut bindings = Vec::new();
+        if arguments.all {
+            bindings.push(self.bind_value(
+                ARGS_VARIABLE,
+                ShellValue::List(vec![ShellValue::Args]),
+                false,
+            ));
+        }
+        for &position in &arguments.positions {
+            let name = arg_variable(position);
+            bindings.push(ShellIR::If {
+                test: ShellValue::IsSome(Box::new(ShellValue::Arg(position))),
+                then_branch: Box::new(self.bind_value(&name, ShellValue::Arg(position), false)),
+                else_branch: Some(Box::new(ShellIR::LetOption {
+                    name,
+                    value: None,
+                    effects: EffectSet::pure(),
+                })),
+            });
+        }
+        bindings
+    }
+
+    fn convert_function(&self, function: &crate::ast::Function) -> Result<ShellIR> {
+        if matches!(function.return_type, crate::ast::Type::Vec { .. }) {
+            return Err(Error::Unsupported(format!(
+                "Function '{}' returns a list; shell functions can only return one word",
+                function.name
+            )));
+        }
+
+        if matches!(
+            result_ok_type(&function.return_type),
+            Some(crate::ast::Type::Bool)
+        ) {
+            return Err(Error::Unsupported(format!(
+                "Function '{}' returns `Result<bool, _>`; the exit status already reports failure, so return `Result<(), E>` or `bool`",
+                function.name
+            )));
+        }
+
+        for param in &function.params {
+            self.check_not_constant(&param.name)?;
+            if matches!(param.param_type, crate::ast::Type::Option { .. }) {
+                return Err(Error::Unsupported(format!(
+                    "Parameter '{}' is an `Option`; unwrap it or match on it before the call",
+                    param.name
+                )));
+            }
+        }
+        self.enter_function(function);
+        self.current_convention
+            .set(Some(return_convention(&function.return_type)));
+        self.current_fallible
+            .set(result_ok_type(&function.return_type).is_some());
+        self.current_optional.set(matches!(
+            function.return_type,
+            crate::ast::Type::Option { .. }
+        ));
+        let body = self.convert_stmts(&function.body);
+        self.current_convention.set(None);
+        self.current_fallible.set(false);
+        self.current_optional.set(false);
+        let body = match body? {
+            ShellIR::Sequence(mut stmts) => {
+                let unpacks = function
+                    .params
+                    .iter()
+                    .filter(|p| matches!(p.param_type, crate::ast::Type::Vec { .. }))
+                    .map(|p| ShellIR::UnpackList {
+                        name: p.name.clone(),
+                    });
+                stmts.splice(0..0, unpacks);
+                ShellIR::Sequence(stmts)
+            }
+            body => body,
+        };
+
+        if let Some(budget) = &function.effects {
+            self.check_effect_budget(&function.name, budget, std::slice::from_ref(&body))?;
+        }
+        self.function_effects
+            .borrow_mut()
+            .insert(function.name.clone(), self.statement_effects(&body));
+        self.function_bodies
+            .borrow_mut()
+            .insert(function.name.clone(), body.clone());
+
+        Ok(ShellIR::Function {
+            name: function.name.clone(),
+            params: function.params.iter().map(|p| p.name.clone()).collect(),
+            body: Box::new(body),
+        })
+    }
+
     fn convert_stmt(&self, stmt: &crate::ast::Stmt) -> Result<ShellIR> {
         use crate::ast::Stmt;
 
         match stmt {
-            Stmt::Let { name, value } => {
-                let shell_value = self.convert_expr_to_value(value)?;
-                Ok(ShellIR::Let {
-                    name: name.clone(),
-                    value: shell_value,
-                    effects: EffectSet::pure(),
-                })
+            Stmt::Let {
+                name,
+                value: crate::ast::Expr::Try { expr },
+                mutable,
+            } => {
+                self.check_not_constant(name)?;
+                let ir = self.convert_try(expr, Some(name), self.rebindable(*mutable))?;
+                let value_type = self.infer_type(expr);
+                self.var_types.borrow_mut().insert(name.clone(), value_type);
+                Ok(ir)
+            }
+            Stmt::Let {
+                name,
+                value,
+                mutable,
+            } if is_capture(value) => {
+                self.check_not_constant(name)?;
+                let ir = self.convert_capture(value, Some(name), self.rebindable(*mutable))?;
+                self.var_types
+                    .borrow_mut()
+                    .insert(name.clone(), ValueType::Str);
+                Ok(ir)
+            }
+            Stmt::Let {
+                name,
+                value,
+                mutable,
+            } if self.infer_type(value) == ValueType::Option => {
+                self.check_not_constant(name)?;
+                let option = self.convert_option(value)?;
+                let inner_type = self.option_inner_type(value);
+                let ir = self.bind_option(name, option, self.rebindable(*mutable));
+                self.var_types
+                    .borrow_mut()
+                    .insert(name.clone(), ValueType::Option);
+                self.element_types
+                    .borrow_mut()
+                    .insert(name.clone(), inner_type);
+                Ok(ir)
+            }
+            Stmt::Let {
+                name,
+                value,
+                mutable,
+            } => {
+                self.check_not_constant(name)?;
+                let shell_value = match self.convert_list(value)? {
+                    Some(list) => list,
+                    None => self.convert_expr_to_value(value)?,
+                };
+                let value_type = self.infer_type(value);
+                self.var_types.borrow_mut().insert(name.clone(), value_type);
+                if value_type == ValueType::List {
+                    let element_type = self.element_type(value);
+                    self.element_types
+                        .borrow_mut()
+                        .insert(name.clone(), element_type);
+                }
+                Ok(self.bind_value(name, shell_value, self.rebindable(*mutable)))
             }
+            Stmt::Assign { name, value } => self.convert_assign(name, value),
             Stmt::Expr(expr) => self.convert_expr(expr),
+            Stmt::Return(value) if self.current_fallible.get() => {
+                self.convert_fallible_return(value.as_ref())
+            }
+            Stmt::Return(value) if self.current_optional.get() => {
+                self.convert_optional_return(value.as_ref())
+            }
+            Stmt::Return(value) if self.current_convention.get().is_some() => {
+                self.convert_function_return(value.as_ref())
+            }
             Stmt::Return(Some(expr)) => {
                 let value = self.convert_expr_to_value(expr)?;
                 Ok(ShellIR::Exit {
@@ -89,7 +457,7 @@ impl IrConverter {
                 then_block,
                 else_block,
             } => {
-                let test_expr = self.convert_expr_to_value(condition)?;
+                let test_expr = self.convert_condition(condition)?;
                 let then_ir = self.convert_stmts(then_block)?;
                 let else_ir = if let Some(else_stmts) = else_block {
                     Some(Box::new(self.convert_stmts(else_stmts)?))
@@ -103,148 +471,2357 @@ impl IrConverter {
                     else_branch: else_ir,
                 })
             }
-            // Placeholder for new AST nodes - TODO: implement properly
-            _ => Ok(ShellIR::Noop), // Match, For, While, Break, Continue
+            Stmt::For {
+                pattern,
+                iter,
+                body,
+                max_iterations,
+            } => self.convert_for_loop(pattern, iter, body, *max_iterations),
+            Stmt::While {
+                condition,
+                body,
+                max_iterations,
+            } => {
+                let max_iterations = max_iterations.ok_or_else(|| {
+                    Error::IrGeneration("While loops must have bounded iterations".to_string())
+                })?;
+                Ok(ShellIR::While {
+                    test: self.convert_condition(condition)?,
+                    body: Box::new(self.convert_loop_body(body)?),
+                    max_iterations,
+                })
+            }
+            Stmt::Break => {
+                self.require_enclosing_loop("break")?;
+                Ok(ShellIR::Break)
+            }
+            Stmt::Continue => {
+                self.require_enclosing_loop("continue")?;
+                Ok(ShellIR::Continue)
+            }
+            Stmt::Match { scrutinee, arms } => self.convert_match(scrutinee, arms),
+            Stmt::Block(stmts) => self.convert_stmts(stmts),
+            Stmt::Spanned { span, stmt } => {
+                let inner = self
+                    .convert_stmt(stmt)
+                    .and_then(|inner| {
+                        self.check_calls(&inner)?;
+                        Ok(inner)
+                    })
+                    .map_err(|e| e.at(*span))?;
+                Ok(ShellIR::Spanned {
+                    span: *span,
+                    inner: Box::new(inner),
+                })
+            }
         }
     }
 
-    fn convert_stmts(&self, stmts: &[crate::ast::Stmt]) -> Result<ShellIR> {
-        let mut ir_stmts = Vec::new();
-        for stmt in stmts {
-            ir_stmts.push(self.convert_stmt(stmt)?);
+    fn convert_match(
+        &self,
+        scrutinee: &crate::ast::Expr,
+        arms: &[crate::ast::restricted::MatchArm],
+    ) -> Result<ShellIR> {
+        let scrutinee_type = self.infer_type(scrutinee);
+        if scrutinee_type == ValueType::Option {
+            return self.convert_option_match(scrutinee, arms);
         }
-        Ok(ShellIR::Sequence(ir_stmts))
+        let scrutinee_value = self.convert_expr_to_value(scrutinee)?;
+
+        let mut case_arms = Vec::new();
+        for arm in arms {
+            let mut patterns = Vec::new();
+            let mut binding = None;
+            self.convert_match_pattern(&arm.pattern, scrutinee_type, &mut patterns, &mut binding)?;
+
+            if let Some(name) = &binding {
+                self.check_not_constant(name)?;
+                self.var_types
+                    .borrow_mut()
+                    .insert(name.clone(), scrutinee_type);
+            }
+
+            let guard = match &arm.guard {
+                Some(guard) => Some(self.convert_condition(guard)?),
+                None => None,
+            };
+
+            case_arms.push(CaseArm {
+                patterns,
+                binding,
+                guard,
+                body: Box::new(self.convert_stmts(&arm.body)?),
+            });
+        }
+
+        if !is_exhaustive(&case_arms, scrutinee_type) {
+            return Err(Error::IrGeneration(
+                "Non-exhaustive match: add a wildcard arm `_ => ...`".to_string(),
+            ));
+        }
+
+        Ok(ShellIR::Case {
+            scrutinee: scrutinee_value,
+            arms: case_arms,
+        })
     }
 
-    fn convert_expr(&self, expr: &crate::ast::Expr) -> Result<ShellIR> {
+    fn convert_match_pattern(
+        &self,
+        pattern: &crate::ast::restricted::Pattern,
+        scrutinee_type: ValueType,
+        patterns: &mut Vec<CasePattern>,
+        binding: &mut Option<String>,
+    ) -> Result<()> {
+        use crate::ast::restricted::{Literal, Pattern};
+
+        match pattern {
+            Pattern::Literal(literal) => {
+                let (text, literal_type) = match literal {
+                    Literal::Bool(b) => (b.to_string(), ValueType::Bool),
+                    Literal::U32(n) => (n.to_string(), ValueType::Int),
+                    Literal::Str(s) => (s.clone(), ValueType::Str),
+                };
+                if scrutinee_type != ValueType::Unknown && scrutinee_type != literal_type {
+                    return Err(Error::IrGeneration(format!(
+                        "Pattern {text:?} does not match a {} scrutinee",
+                        scrutinee_type.description()
+                    )));
+                }
+                patterns.push(CasePattern::Literal(text));
+            }
+            Pattern::Wildcard => patterns.push(CasePattern::Wildcard),
+            Pattern::Variable(name) => {
+                if binding.is_some() {
+                    return Err(Error::Unsupported(
+                        "Multiple bindings in one match arm".to_string(),
+                    ));
+                }
+                *binding = Some(name.clone());
+                patterns.push(CasePattern::Wildcard);
+            }
+            Pattern::Or(alternatives) => {
+                for alternative in alternatives {
+                    self.convert_match_pattern(alternative, scrutinee_type, patterns, binding)?;
+                }
+            }
+            Pattern::Tuple(_) | Pattern::Struct { .. } => {
+                return Err(Error::Unsupported(
+                    "Destructuring patterns in match arms".to_string(),
+                ))
+            }
+            Pattern::Some(_) | Pattern::None => {
+                return Err(Error::IrGeneration(format!(
+                    "Patterns `Some` and `None` do not match a {} scrutinee",
+                    scrutinee_type.description()
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Lower a match on an `Option` to a test of whether it holds a value,
+    /// binding the value first when the `Some` arm names it
+    fn convert_option_match(
+        &self,
+        scrutinee: &crate::ast::Expr,
+        arms: &[crate::ast::restricted::MatchArm],
+    ) -> Result<ShellIR> {
+        use crate::ast::restricted::Pattern;
+
+        let mut some_arm = None;
+        let mut none_arm = None;
+        for arm in arms {
+            if arm.guard.is_some() {
+                return Err(Error::Unsupported(
+                    "Match guards on an `Option`".to_string(),
+                ));
+            }
+            match &arm.pattern {
+                Pattern::Some(inner) => {
+                    let binding = match inner.as_ref() {
+                        Pattern::Variable(name) => Some(name.clone()),
+                        Pattern::Wildcard => None,
+                        _ => {
+                            return Err(Error::Unsupported(
+                                "Patterns other than a binding or `_` inside `Some`".to_string(),
+                            ))
+                        }
+                    };
+                    some_arm.get_or_insert((binding, &arm.body));
+                }
+                Pattern::None => {
+                    none_arm.get_or_insert(&arm.body);
+                }
+                Pattern::Wildcard => {
+                    some_arm.get_or_insert((None, &arm.body));
+                    none_arm.get_or_insert(&arm.body);
+                }
+                _ => {
+                    return Err(Error::Unsupported(
+                        "Patterns other than `Some(..)`, `None` and `_` on an `Option`".to_string(),
+                    ))
+                }
+            }
+        }
+        let (Some((binding, some_body)), Some(none_body)) = (some_arm, none_arm) else {
+            return Err(Error::IrGeneration(
+                "Non-exhaustive match: handle both `Some(..)` and `None`".to_string(),
+            ));
+        };
+
+        let inner_type = self.option_inner_type(scrutinee);
+        let option = self.convert_option(scrutinee)?;
+        let mut statements = Vec::new();
+        let test = match (binding, option) {
+            (Some(name), option) => {
+                self.check_not_constant(&name)?;
+                statements.push(self.bind_option(&name, option, self.rebindable(false)));
+                self.var_types.borrow_mut().insert(name.clone(), inner_type);
+                ShellValue::IsSome(Box::new(ShellValue::Variable(name)))
+            }
+            (None, OptionValue::Empty) => ShellValue::Bool(false),
+            (None, OptionValue::Some(_)) => ShellValue::Bool(true),
+            (None, OptionValue::Held(option)) => ShellValue::IsSome(Box::new(option)),
+        };
+        let else_branch = if none_body.is_empty() {
+            None
+        } else {
+            Some(Box::new(self.convert_stmts(none_body)?))
+        };
+        statements.push(ShellIR::If {
+            test,
+            then_branch: Box::new(self.convert_stmts(some_body)?),
+            else_branch,
+        });
+        Ok(ShellIR::Sequence(statements))
+    }
+
+    /// Lower an expression of `Option` type
+    fn convert_option(&self, expr: &crate::ast::Expr) -> Result<OptionValue> {
         use crate::ast::Expr;
 
         match expr {
-            Expr::FunctionCall { name, args } => {
-                // Convert function calls to shell commands
-                let mut cmd_args = Vec::new();
-                for arg in args {
-                    cmd_args.push(self.convert_expr_to_value(arg)?);
+            Expr::Variable(name) if name == "None" => Ok(OptionValue::Empty),
+            Expr::FunctionCall { name, args } if name == "Some" => match args.as_slice() {
+                [value] if self.infer_type(value) != ValueType::Option => {
+                    Ok(OptionValue::Some(self.convert_expr_to_value(value)?))
                 }
-
-                Ok(ShellIR::Exec {
-                    cmd: Command {
-                        program: name.clone(),
-                        args: cmd_args,
-                    },
-                    effects: self.analyze_command_effects(name),
-                })
+                _ => Err(Error::IrGeneration(
+                    "`Some` takes a single value, which cannot itself be an `Option`".to_string(),
+                )),
+            },
+            Expr::Variable(name) => Ok(OptionValue::Held(ShellValue::Variable(name.clone()))),
+            Expr::FunctionCall { name, args }
+                if stdlib::intrinsic(name) == Some(stdlib::Intrinsic::Var) =>
+            {
+                Ok(OptionValue::Held(ShellValue::EnvVar(
+                    self.env_var_name(name, args)?,
+                )))
             }
-            _ => {
-                // For other expressions, convert to values and wrap in a noop
-                let _value = self.convert_expr_to_value(expr)?;
-                Ok(ShellIR::Noop)
+            Expr::FunctionCall { name, args } if stdlib::intrinsic(name).is_some() => {
+                self.convert_intrinsic(name, args)?;
+                Err(unhandled_option(name, true))
+            }
+            Expr::FunctionCall { name, args } if self.return_types.borrow().contains_key(name) => {
+                Ok(OptionValue::Held(ShellValue::FunctionCall {
+                    name: name.clone(),
+                    args: self.convert_call_args(name, args)?,
+                    convention: ReturnConvention::Stdout,
+                }))
             }
+            _ => Err(Error::Unsupported(
+                "This kind of `Option` expression; bind it with `let` first".to_string(),
+            )),
         }
     }
 
-    #[allow(clippy::only_used_in_recursion)]
-    fn convert_expr_to_value(&self, expr: &crate::ast::Expr) -> Result<ShellValue> {
-        use crate::ast::{restricted::Literal, Expr};
+    /// Type of the value an `Option`-typed expression may hold
+    fn option_inner_type(&self, expr: &crate::ast::Expr) -> ValueType {
+        use crate::ast::Expr;
 
         match expr {
-            Expr::Literal(literal) => match literal {
-                Literal::Bool(b) => Ok(ShellValue::Bool(*b)),
-                Literal::U32(n) => Ok(ShellValue::String(n.to_string())),
-                Literal::Str(s) => Ok(ShellValue::String(s.clone())),
+            Expr::Variable(name) => self
+                .element_types
+                .borrow()
+                .get(name)
+                .copied()
+                .unwrap_or(ValueType::Unknown),
+            Expr::FunctionCall { name, args } if name == "Some" && args.len() == 1 => {
+                self.infer_type(&args[0])
+            }
+            Expr::FunctionCall { name, .. } if stdlib::intrinsic(name).is_some() => ValueType::Str,
+            Expr::FunctionCall { name, .. } => match self.return_types.borrow().get(name) {
+                Some(crate::ast::Type::Option { inner_type }) => ValueType::from(&**inner_type),
+                _ => ValueType::Unknown,
             },
-            Expr::Variable(name) => Ok(ShellValue::Variable(name.clone())),
-            Expr::Binary { op: _, left, right } => {
-                let left_val = self.convert_expr_to_value(left)?;
-                let right_val = self.convert_expr_to_value(right)?;
-                Ok(ShellValue::Concat(vec![left_val, right_val])) // Simplified
+            _ => ValueType::Unknown,
+        }
+    }
+
+    /// Bind `option` to variable `name`, leaving it unset when there is no
+    /// value. A `mutable` binding is assigned rather than made readonly.
+    fn bind_option(&self, name: &str, option: OptionValue, mutable: bool) -> ShellIR {
+        let unset = ShellIR::LetOption {
+            name: name.to_string(),
+            value: None,
+            effects: EffectSet::pure(),
+        };
+        match option {
+            OptionValue::Empty => unset,
+            OptionValue::Some(value) => self.bind_value(name, value, mutable),
+            OptionValue::Held(ShellValue::Variable(source)) if source == name => ShellIR::Noop,
+            OptionValue::Held(source @ (ShellValue::Variable(_) | ShellValue::EnvVar(_))) => {
+                ShellIR::If {
+                    test: ShellValue::IsSome(Box::new(source.clone())),
+                    then_branch: Box::new(self.bind_value(name, source, mutable)),
+                    else_branch: Some(Box::new(unset)),
+                }
             }
-            _ => Ok(ShellValue::String("unknown".to_string())), // Fallback
+            OptionValue::Held(call) => ShellIR::LetOption {
+                name: name.to_string(),
+                effects: self.value_effects(&call),
+                value: Some(call),
+            },
         }
     }
 
-    fn analyze_command_effects(&self, command: &str) -> EffectSet {
-        // Simple effect analysis based on command name
-        let mut effects = EffectSet::pure();
+    /// Bind variable `name` to `value`: readonly, unless `mutable` says the
+    /// binding may be assigned again
+    fn bind_value(&self, name: &str, value: ShellValue, mutable: bool) -> ShellIR {
+        let effects = self.value_effects(&value);
+        let name = name.to_string();
+        // A failing call stops the script, as a failing statement would
+        if let ShellValue::FunctionCall {
+            convention: ReturnConvention::Stdout,
+            ..
+        } = &value
+        {
+            return ShellIR::Try {
+                binding: Some(name),
+                mutable,
+                call: value,
+                effects,
+            };
+        }
+        if mutable {
+            ShellIR::Assign {
+                name,
+                value,
+                effects,
+            }
+        } else {
+            ShellIR::Let {
+                name,
+                value,
+                effects,
+            }
+        }
+    }
+
+    /// Whether a binding must stay assignable: it is `let mut`, or it sits in
+    /// a loop body and so is bound again on every iteration
+    fn rebindable(&self, mutable: bool) -> bool {
+        mutable || self.loop_depth.get() > 0
+    }
+
+    /// `name = value` on a `let mut` binding, which the scope pass has
+    /// already checked and renamed
+    fn convert_assign(&self, name: &str, value: &crate::ast::Expr) -> Result<ShellIR> {
+        use crate::ast::Expr;
 
-        match command {
-            "curl" | "wget" => {
-                effects.add(Effect::NetworkAccess);
+        self.check_not_constant(name)?;
+        let declared = self
+            .var_types
+            .borrow()
+            .get(name)
+            .copied()
+            .unwrap_or(ValueType::Unknown);
+        let assigned = match value {
+            Expr::Try { expr } => self.infer_type(expr),
+            _ => self.infer_type(value),
+        };
+        if declared != ValueType::Unknown && assigned != ValueType::Unknown && declared != assigned
+        {
+            return Err(Error::IrGeneration(format!(
+                "Variable '{name}' is declared as {} but is assigned {}",
+                declared.description(),
+                assigned.description()
+            )));
+        }
+        match value {
+            Expr::Try { expr } => self.convert_try(expr, Some(&name.to_string()), true),
+            _ if is_capture(value) => self.convert_capture(value, Some(&name.to_string()), true),
+            _ if assigned == ValueType::Option => {
+                let option = self.convert_option(value)?;
+                Ok(self.bind_option(name, option, true))
             }
-            "echo" | "printf" => {
-                effects.add(Effect::FileWrite);
+            _ => {
+                let shell_value = match self.convert_list(value)? {
+                    Some(list) => list,
+                    None => self.convert_expr_to_value(value)?,
+                };
+                Ok(self.bind_value(name, shell_value, true))
             }
-            _ => {}
         }
+    }
 
-        effects
+    /// Return from a function returning `Option`: `Some` prints its value,
+    /// and `None` returns status 1 without printing anything
+    fn convert_optional_return(&self, value: Option<&crate::ast::Expr>) -> Result<ShellIR> {
+        let none = ShellIR::Return {
+            value: Some(ShellValue::Bool(false)),
+            convention: ReturnConvention::ExitStatus,
+        };
+        let Some(value) = value else {
+            return Err(Error::IrGeneration(
+                "Functions returning `Option` must return `Some(...)` or `None`".to_string(),
+            ));
+        };
+        if self.infer_type(value) != ValueType::Option {
+            return Err(Error::IrGeneration(
+                "Functions returning `Option` must return `Some(...)` or `None`".to_string(),
+            ));
+        }
+        Ok(match self.convert_option(value)? {
+            OptionValue::Empty => none,
+            OptionValue::Some(value) => ShellIR::Return {
+                value: Some(value),
+                convention: ReturnConvention::Stdout,
+            },
+            OptionValue::Held(ShellValue::Variable(name)) => ShellIR::If {
+                test: ShellValue::IsSome(Box::new(ShellValue::Variable(name.clone()))),
+                then_branch: Box::new(ShellIR::Return {
+                    value: Some(ShellValue::Variable(name)),
+                    convention: ReturnConvention::Stdout,
+                }),
+                else_branch: Some(Box::new(none)),
+            },
+            // The call prints the value, if any, and its status says whether it did
+            OptionValue::Held(ShellValue::FunctionCall { name, args, .. }) => {
+                self.forward_call(name, args)
+            }
+            OptionValue::Held(other) => {
+                return Err(Error::IrGeneration(format!(
+                    "Cannot return {other:?} as an option"
+                )))
+            }
+        })
     }
-}
 
-fn constant_fold(ir: ShellIR) -> ShellIR {
-    // Simple constant folding pass
-    let mut transform_fn = |node| match node {
-        ShellIR::Let {
-            name,
-            value: ShellValue::Concat(parts),
-            effects,
-        } => {
-            if parts.iter().all(|p| matches!(p, ShellValue::String(_))) {
-                let folded = parts
-                    .iter()
-                    .filter_map(|p| match p {
-                        ShellValue::String(s) => Some(s.as_str()),
-                        _ => None,
-                    })
-                    .collect::<String>();
-                ShellIR::Let {
-                    name,
-                    value: ShellValue::String(folded),
-                    effects,
+    /// Lower `is_some`, `is_none` and the `unwrap_or` family on an `Option`
+    fn convert_option_method(
+        &self,
+        receiver: &crate::ast::Expr,
+        method: &str,
+        args: &[crate::ast::Expr],
+    ) -> Result<ShellValue> {
+        let inner_type = self.option_inner_type(receiver);
+        let option = self.convert_option(receiver)?;
+        let default = match (method, args) {
+            ("is_some" | "is_none", []) => {
+                let is_some = match option {
+                    OptionValue::Empty => ShellValue::Bool(false),
+                    OptionValue::Some(_) => ShellValue::Bool(true),
+                    OptionValue::Held(option) => ShellValue::IsSome(Box::new(option)),
+                };
+                return Ok(match (method, is_some) {
+                    ("is_some", is_some) => is_some,
+                    (_, ShellValue::Bool(b)) => ShellValue::Bool(!b),
+                    (_, is_some) => ShellValue::Not(Box::new(is_some)),
+                });
+            }
+            ("unwrap_or" | "unwrap_or_else", [default]) => {
+                let default_type = self.infer_type(default);
+                if inner_type != ValueType::Unknown
+                    && default_type != ValueType::Unknown
+                    && inner_type != default_type
+                {
+                    return Err(Error::IrGeneration(format!(
+                        "The default of '{method}' must be a {}, not a {}",
+                        inner_type.description(),
+                        default_type.description()
+                    )));
                 }
-            } else {
-                ShellIR::Let {
+                self.convert_expr_to_value(default)?
+            }
+            ("unwrap_or_default", []) => ShellValue::String(match inner_type {
+                ValueType::Int => "0".to_string(),
+                _ => String::new(),
+            }),
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "Method '{method}' on an `Option`; use `unwrap_or`, `if let` or `match`"
+                )))
+            }
+        };
+        Ok(match option {
+            OptionValue::Empty => default,
+            OptionValue::Some(value) => value,
+            OptionValue::Held(option) => ShellValue::UnwrapOr {
+                option: Box::new(option),
+                default: Box::new(default),
+            },
+        })
+    }
+
+    fn convert_function_return(&self, value: Option<&crate::ast::Expr>) -> Result<ShellIR> {
+        let convention = self
+            .current_convention
+            .get()
+            .unwrap_or(ReturnConvention::Void);
+
+        let value = match (convention, value) {
+            (ReturnConvention::Void, None) => None,
+            (ReturnConvention::Void, Some(_)) => {
+                return Err(Error::IrGeneration(
+                    "Cannot return a value from a function without a return type".to_string(),
+                ))
+            }
+            (_, None) => {
+                return Err(Error::IrGeneration(
+                    "Functions with a return type must return a value".to_string(),
+                ))
+            }
+            (ReturnConvention::ExitStatus, Some(expr)) => Some(self.convert_condition(expr)?),
+            (ReturnConvention::Stdout, Some(expr)) => match self.convert_expr_to_value(expr)? {
+                ShellValue::FunctionCall {
                     name,
-                    value: ShellValue::Concat(parts),
-                    effects,
+                    args,
+                    convention: ReturnConvention::Stdout,
+                } if self.return_types.borrow().contains_key(&name) => {
+                    return Ok(self.forward_call(name, args));
                 }
-            }
-        }
-        _ => node,
-    };
-    transform_ir(ir, &mut transform_fn)
-}
+                value => Some(value),
+            },
+        };
 
-fn eliminate_dead_code(ir: ShellIR) -> ShellIR {
-    // Simple dead code elimination
-    ir // Placeholder - would implement actual DCE
-}
+        Ok(ShellIR::Return { value, convention })
+    }
 
-fn transform_ir<F>(ir: ShellIR, transform: &mut F) -> ShellIR
-where
-    F: FnMut(ShellIR) -> ShellIR,
-{
-    let transformed = match ir {
-        ShellIR::Sequence(stmts) => {
-            let new_stmts = stmts
-                .into_iter()
-                .map(|stmt| transform_ir(stmt, transform))
-                .collect();
-            ShellIR::Sequence(new_stmts)
-        }
-        ShellIR::If {
-            test,
-            then_branch,
-            else_branch,
-        } => {
-            let new_then = Box::new(transform_ir(*then_branch, transform));
-            let new_else = else_branch.map(|eb| Box::new(transform_ir(*eb, transform)));
+    /// Return the value of a call of another function returning one: the
+    /// call hands its value back as ours, and its failure is ours too
+    fn forward_call(&self, name: String, args: Vec<ShellValue>) -> ShellIR {
+        let call = ShellValue::FunctionCall {
+            name,
+            args,
+            convention: ReturnConvention::Void,
+        };
+        ShellIR::Sequence(vec![
+            ShellIR::Try {
+                binding: None,
+                mutable: false,
+                effects: self.value_effects(&call),
+                call,
+            },
+            ShellIR::Return {
+                value: Some(ShellValue::Bool(true)),
+                convention: ReturnConvention::ExitStatus,
+            },
+        ])
+    }
+
+    /// Refuse calls of user-defined functions, and `process::status` calls,
+    /// whose result a statement would take from inside another value. Only a binding can stop the script
+    /// when such a call fails, and only a condition can read a status
+    /// without capturing the function's output along with it.
+    fn check_calls(&self, ir: &ShellIR) -> Result<()> {
+        match ir {
+            ShellIR::Let { value, .. } | ShellIR::Assign { value, .. } => {
+                self.check_call_value(value, true)
+            }
+            ShellIR::LetOption {
+                value: Some(value), ..
+            }
+            | ShellIR::Try { call: value, .. } => self.check_call_value(value, true),
+            ShellIR::Global { value, .. } => self.check_call_value(value, false),
+            ShellIR::Exec { cmd, .. } => cmd
+                .args
+                .iter()
+                .try_for_each(|arg| self.check_call_value(arg, false)),
+            ShellIR::Printf { args, .. } => args
+                .iter()
+                .try_for_each(|arg| self.check_call_value(arg, false)),
             ShellIR::If {
                 test,
-                then_branch: new_then,
-                else_branch: new_else,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_call_value(test, true)?;
+                self.check_calls(then_branch)?;
+                else_branch
+                    .as_deref()
+                    .map_or(Ok(()), |branch| self.check_calls(branch))
+            }
+            ShellIR::For { iter, body, .. } => {
+                match iter {
+                    ForIter::Range { start, end, .. } => {
+                        self.check_call_value(start, false)?;
+                        self.check_call_value(end, false)?;
+                    }
+                    ForIter::Words(words) => words
+                        .iter()
+                        .try_for_each(|word| self.check_call_value(word, false))?,
+                }
+                self.check_calls(body)
             }
+            ShellIR::While { test, body, .. } => {
+                self.check_call_value(test, true)?;
+                self.check_calls(body)
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                self.check_call_value(scrutinee, false)?;
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.check_call_value(guard, true)?;
+                    }
+                    self.check_calls(&arm.body)?;
+                }
+                Ok(())
+            }
+            ShellIR::Return {
+                value: Some(value),
+                convention,
+            } => self.check_call_value(value, *convention == ReturnConvention::ExitStatus),
+            ShellIR::Sequence(items) => items.iter().try_for_each(|item| self.check_calls(item)),
+            ShellIR::Spanned { inner, .. } => self.check_calls(inner),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a value for calls of user-defined functions. `direct` says
+    /// whether the value is bound, returned or tested as it is, rather than
+    /// being part of another value.
+    fn check_call_value(&self, value: &ShellValue, direct: bool) -> Result<()> {
+        match value {
+            ShellValue::FunctionCall {
+                name,
+                args,
+                convention,
+            } => {
+                if !direct && self.return_types.borrow().contains_key(name) {
+                    return Err(Error::IrGeneration(match convention {
+                        ReturnConvention::ExitStatus => format!(
+                            "The result of '{name}' can only be bound with `let`, assigned or tested as a condition, so that its output is not taken for its result"
+                        ),
+                        _ => format!(
+                            "The result of '{name}' can only be bound with `let` or assigned, so that a failing call stops the script"
+                        ),
+                    }));
+                }
+                args.iter()
+                    .try_for_each(|arg| self.check_call_value(arg, false))
+            }
+            ShellValue::Logical { left, right, .. } => {
+                self.check_call_value(left, direct)?;
+                self.check_call_value(right, direct)
+            }
+            ShellValue::Not(operand) | ShellValue::IsSome(operand) => {
+                self.check_call_value(operand, direct)
+            }
+            // A failing call leaves the option empty
+            ShellValue::UnwrapOr { option, default } => {
+                self.check_call_value(option, true)?;
+                self.check_call_value(default, false)
+            }
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::Comparison { left, right, .. } => {
+                self.check_call_value(left, false)?;
+                self.check_call_value(right, false)
+            }
+            ShellValue::Concat(parts) | ShellValue::List(parts) => parts
+                .iter()
+                .try_for_each(|part| self.check_call_value(part, false)),
+            ShellValue::ChangeCase { value, .. } => self.check_call_value(value, false),
+            ShellValue::ListItem { index, .. } => self.check_call_value(index, false),
+            ShellValue::CommandStatus(cmd) if !direct => Err(Error::IrGeneration(format!(
+                "The status of '{}' can only be bound with `let`, assigned or tested as a condition, so that its output is not taken for its result",
+                cmd.program
+            ))),
+            ShellValue::CommandStatus(cmd) | ShellValue::CommandSubst(cmd) => cmd
+                .args
+                .iter()
+                .try_for_each(|arg| self.check_call_value(arg, false)),
+            ShellValue::String(_)
+            | ShellValue::Bool(_)
+            | ShellValue::Variable(_)
+            | ShellValue::ListElements(_)
+            | ShellValue::ListLength(_)
+            | ShellValue::EnvVar(_)
+            | ShellValue::Arg(_)
+            | ShellValue::Args => Ok(()),
         }
+    }
+
+    /// Return from a function returning `Result`: `Ok` succeeds with its value,
+    /// `Err` prints its message on stderr and fails with status 1, and a
+    /// call of another such function passes its outcome on
+    fn convert_fallible_return(&self, value: Option<&crate::ast::Expr>) -> Result<ShellIR> {
+        use crate::ast::Expr;
+
+        // The entry point ends the script rather than returning
+        let in_entry_point = self.current_convention.get().is_none();
+        let finish = |success: bool| {
+            if in_entry_point {
+                ShellIR::Exit {
+                    code: if success { 0 } else { 1 },
+                    message: None,
+                }
+            } else {
+                ShellIR::Return {
+                    value: Some(ShellValue::Bool(success)),
+                    convention: ReturnConvention::ExitStatus,
+                }
+            }
+        };
+
+        match value {
+            Some(Expr::FunctionCall { name, args }) if name == "Ok" => match args.as_slice() {
+                [] if self.current_convention.get() != Some(ReturnConvention::Stdout) => {
+                    Ok(finish(true))
+                }
+                [value] if self.current_convention.get() == Some(ReturnConvention::Stdout) => {
+                    self.convert_function_return(Some(value))
+                }
+                _ => Err(Error::IrGeneration(
+                    "`Ok` must hold a value of the function's success type".to_string(),
+                )),
+            },
+            Some(Expr::FunctionCall { name, args }) if name == "Err" => {
+                let mut statements = Vec::new();
+                match args.as_slice() {
+                    [] => {}
+                    [message] => {
+                        let line = Expr::Format(vec![
+                            message.clone(),
+                            Expr::Literal(crate::ast::restricted::Literal::Str("\n".to_string())),
+                        ]);
+                        statements.push(self.convert_print(&line, true)?);
+                    }
+                    _ => {
+                        return Err(Error::IrGeneration(
+                            "`Err` takes a single error value".to_string(),
+                        ))
+                    }
+                }
+                statements.push(finish(false));
+                Ok(ShellIR::Sequence(statements))
+            }
+            // Both the output and the status of the call become ours
+            Some(call @ Expr::FunctionCall { name, .. }) if self.result_type_of(name).is_some() => {
+                let forward = self.convert_try(call, None, false)?;
+                let forward = match forward {
+                    ShellIR::Try {
+                        binding,
+                        mutable,
+                        call: ShellValue::FunctionCall { name, args, .. },
+                        effects,
+                    } => ShellIR::Try {
+                        binding,
+                        mutable,
+                        // Not captured, so its stdout is the caller's value
+                        call: ShellValue::FunctionCall {
+                            name,
+                            args,
+                            convention: ReturnConvention::Void,
+                        },
+                        effects,
+                    },
+                    other => other,
+                };
+                Ok(ShellIR::Sequence(vec![forward, finish(true)]))
+            }
+            _ => Err(Error::IrGeneration(
+                "Functions returning `Result` must return `Ok(...)`, `Err(...)` or the result of such a function"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// `call?`: run a call of a function returning `Result`, returning its
+    /// failure status from the current function
+    fn convert_try(
+        &self,
+        call: &crate::ast::Expr,
+        binding: Option<&String>,
+        mutable: bool,
+    ) -> Result<ShellIR> {
+        use crate::ast::Expr;
+
+        if !self.current_fallible.get() {
+            return Err(Error::IrGeneration(
+                "The `?` operator can only be used in a function that returns `Result`".to_string(),
+            ));
+        }
+        let (name, args, ok_type) = match call {
+            Expr::FunctionCall { name, args } => match self.result_type_of(name) {
+                Some(ok_type) => (name, args, ok_type),
+                None => {
+                    return Err(Error::IrGeneration(format!(
+                        "The `?` operator needs a `Result`, but '{name}' does not return one"
+                    )))
+                }
+            },
+            _ => {
+                return Err(Error::Unsupported(
+                    "The `?` operator on anything but a function call".to_string(),
+                ))
+            }
+        };
+        if binding.is_some() && matches!(ok_type, crate::ast::Type::Void) {
+            return Err(Error::IrGeneration(format!(
+                "Function '{name}' returns `Result<(), _>`, which holds no value to bind"
+            )));
+        }
+
+        let call = ShellValue::FunctionCall {
+            name: name.clone(),
+            args: self.convert_call_args(name, args)?,
+            convention: return_convention(&ok_type),
+        };
+        Ok(ShellIR::Try {
+            binding: binding.cloned(),
+            mutable,
+            effects: self.value_effects(&call),
+            call,
+        })
+    }
+
+    /// Success type of a user-defined function returning `Result`
+    fn result_type_of(&self, name: &str) -> Option<crate::ast::Type> {
+        result_ok_type(self.return_types.borrow().get(name)?).cloned()
+    }
+
+    fn require_enclosing_loop(&self, keyword: &str) -> Result<()> {
+        if self.loop_depth.get() == 0 {
+            return Err(Error::IrGeneration(format!(
+                "'{keyword}' outside of a loop"
+            )));
+        }
+        Ok(())
+    }
+
+    fn convert_loop_body(&self, body: &[crate::ast::Stmt]) -> Result<ShellIR> {
+        self.loop_depth.set(self.loop_depth.get() + 1);
+        let result = self.convert_stmts(body);
+        self.loop_depth.set(self.loop_depth.get() - 1);
+        result
+    }
+
+    fn convert_for_loop(
+        &self,
+        pattern: &crate::ast::restricted::Pattern,
+        iter: &crate::ast::Expr,
+        body: &[crate::ast::Stmt],
+        max_iterations: Option<u32>,
+    ) -> Result<ShellIR> {
+        use crate::ast::{restricted::Pattern, Expr};
+
+        let max_iterations = max_iterations.ok_or_else(|| {
+            Error::IrGeneration("For loops must have bounded iterations".to_string())
+        })?;
+
+        let var = match pattern {
+            Pattern::Variable(name) => {
+                self.check_not_constant(name)?;
+                name.clone()
+            }
+            Pattern::Wildcard => "_rash_unused".to_string(),
+            _ => {
+                return Err(Error::Unsupported(
+                    "Destructuring patterns in for loops".to_string(),
+                ))
+            }
+        };
+
+        // `for x in xs.iter()` iterates the list itself
+        let iter = match iter {
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } if args.is_empty() && (method == "iter" || method == "into_iter") => receiver,
+            iter => iter,
+        };
+
+        let var_type = match iter {
+            Expr::Range { .. } => ValueType::Int,
+            _ => self.element_type(iter),
+        };
+        self.var_types.borrow_mut().insert(var.clone(), var_type);
+
+        let iter = match iter {
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+            } => ForIter::Range {
+                start: self.convert_expr_to_value(start)?,
+                end: self.convert_expr_to_value(end)?,
+                inclusive: *inclusive,
+            },
+            Expr::Array(elements) => {
+                let mut words = Vec::new();
+                for element in elements {
+                    words.push(self.convert_expr_to_value(element)?);
+                }
+                ForIter::Words(words)
+            }
+            Expr::Variable(name) if self.infer_type(iter) == ValueType::List => {
+                ForIter::Words(vec![ShellValue::ListElements(name.clone())])
+            }
+            _ => {
+                return Err(Error::Unsupported(
+                    "For loops can only iterate over ranges and lists".to_string(),
+                ))
+            }
+        };
+
+        // Reject loops whose static trip count already exceeds the bound
+        if let Some(count) = static_trip_count(&iter) {
+            if count > u64::from(max_iterations) {
+                return Err(Error::IrGeneration(format!(
+                    "For loop runs {count} iterations, exceeding its bound of {max_iterations}"
+                )));
+            }
+        }
+
+        Ok(ShellIR::For {
+            var,
+            iter,
+            body: Box::new(self.convert_loop_body(body)?),
+            max_iterations,
+        })
+    }
+
+    fn convert_stmts(&self, stmts: &[crate::ast::Stmt]) -> Result<ShellIR> {
+        let mut ir_stmts = Vec::new();
+        for stmt in stmts {
+            ir_stmts.push(self.convert_stmt(stmt)?);
+        }
+        Ok(ShellIR::Sequence(ir_stmts))
+    }
+
+    fn convert_expr(&self, expr: &crate::ast::Expr) -> Result<ShellIR> {
+        use crate::ast::Expr;
+
+        match expr {
+            Expr::FunctionCall { name, args } if stdlib::intrinsic(name).is_some() => {
+                match stdlib::intrinsic(name) {
+                    Some(stdlib::Intrinsic::Var) => {
+                        self.env_var_name(name, args)?;
+                        Err(Error::IrGeneration(format!(
+                            "The `Option` returned by '{name}' is unused; bind it with `let` or match on it"
+                        )))
+                    }
+                    Some(
+                        stdlib::Intrinsic::Capture
+                        | stdlib::Intrinsic::SanitizePath
+                        | stdlib::Intrinsic::SanitizeWord,
+                    ) => self.convert_capture(expr, None, false),
+                    // The status is unused, so a failure must not stop the script
+                    Some(stdlib::Intrinsic::Status) => Ok(ShellIR::If {
+                        test: self.convert_intrinsic(name, args)?,
+                        then_branch: Box::new(ShellIR::Noop),
+                        else_branch: None,
+                    }),
+                    _ => {
+                        self.convert_intrinsic(name, args)?;
+                        Ok(ShellIR::Noop)
+                    }
+                }
+            }
+            Expr::FunctionCall { name, args } if name.contains("::") => {
+                let function = self.stdlib_function(name, args)?;
+                Ok(unused_call(
+                    function.helper.to_string(),
+                    self.convert_call_args(name, args)?,
+                    Some(function.convention),
+                    function.effects(),
+                ))
+            }
+            Expr::FunctionCall { name, .. } if name == "Ok" || name == "Err" => {
+                Err(misplaced_result_constructor(name))
+            }
+            Expr::FunctionCall { name, .. } if self.infer_type(expr) == ValueType::Option => {
+                Err(Error::IrGeneration(format!(
+                    "The `Option` returned by '{name}' is unused; bind it with `let` or match on it"
+                )))
+            }
+            Expr::FunctionCall { name, args } => {
+                // Convert function calls to shell commands
+                let cmd_args = self.convert_call_args(name, args)?;
+
+                // Calls to user-defined functions carry the effects of their body
+                let effects = match self.function_effects.borrow().get(name) {
+                    Some(effects) => effects.clone(),
+                    None => self.command_effects(name, &cmd_args),
+                };
+
+                let convention = self.return_types.borrow().get(name).map(return_convention);
+                Ok(unused_call(name.clone(), cmd_args, convention, effects))
+            }
+            Expr::Print { message, stderr } => self.convert_print(message, *stderr),
+            Expr::Try { expr } => self.convert_try(expr, None, false),
+            _ => {
+                // Surface any error in the expression before refusing it
+                self.convert_expr_to_value(expr)?;
+                Err(Error::IrGeneration(
+                    "The value of this expression is unused, so the statement does nothing; \
+                     bind it with `let`, return it or remove it"
+                        .to_string(),
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::only_used_in_recursion)]
+    fn convert_expr_to_value(&self, expr: &crate::ast::Expr) -> Result<ShellValue> {
+        use crate::ast::{restricted::Literal, Expr};
+
+        match expr {
+            Expr::Literal(literal) => match literal {
+                Literal::Bool(b) => Ok(ShellValue::Bool(*b)),
+                Literal::U32(n) => Ok(ShellValue::String(n.to_string())),
+                Literal::Str(s) => Ok(ShellValue::String(s.clone())),
+            },
+            Expr::Variable(name) if self.infer_type(expr) == ValueType::List => {
+                Err(Error::IrGeneration(format!(
+                    "List '{name}' cannot be used as a single value; index it, iterate over it or pass it to a command"
+                )))
+            }
+            Expr::FunctionCall { name, args } if name == "String::from" => match args.as_slice() {
+                [value] => self.convert_string_conversion(value, name),
+                _ => Err(Error::IrGeneration(format!(
+                    "Function '{name}' takes 1 argument(s) but {} were given",
+                    args.len()
+                ))),
+            },
+            Expr::FunctionCall { name, args } if stdlib::intrinsic(name).is_some() => {
+                self.convert_intrinsic(name, args)
+            }
+            Expr::Variable(name) | Expr::FunctionCall { name, .. }
+                if self.infer_type(expr) == ValueType::Option =>
+            {
+                Err(unhandled_option(name, matches!(expr, Expr::FunctionCall { .. })))
+            }
+            Expr::Variable(name) => Ok(ShellValue::Variable(name.clone())),
+            Expr::FunctionCall { name, .. } if name == "Ok" || name == "Err" => {
+                Err(misplaced_result_constructor(name))
+            }
+            Expr::FunctionCall { name, .. } if self.result_type_of(name).is_some() => {
+                Err(Error::IrGeneration(format!(
+                    "The `Result` of '{name}' must be handled with `?`"
+                )))
+            }
+            Expr::Try { .. } => Err(Error::Unsupported(
+                "The `?` operator inside an expression; bind the call with `let x = f()?;` first"
+                    .to_string(),
+            )),
+            Expr::FunctionCall { name, args } if self.return_types.borrow().contains_key(name) => {
+                let convention = return_convention(&self.return_types.borrow()[name]);
+                Ok(ShellValue::FunctionCall {
+                    name: name.clone(),
+                    args: self.convert_call_args(name, args)?,
+                    convention,
+                })
+            }
+            Expr::FunctionCall { name, args } if name.contains("::") => {
+                let function = self.stdlib_function(name, args)?;
+                if function.convention == ReturnConvention::Void {
+                    return Err(Error::IrGeneration(format!(
+                        "Function '{name}' returns nothing and cannot be used as a value"
+                    )));
+                }
+                Ok(ShellValue::FunctionCall {
+                    name: function.helper.to_string(),
+                    args: self.convert_call_args(name, args)?,
+                    convention: function.convention,
+                })
+            }
+            Expr::Format(parts) => Ok(ShellValue::Concat(
+                parts
+                    .iter()
+                    .map(|part| self.convert_expr_to_value(part))
+                    .collect::<Result<_>>()?,
+            )),
+            Expr::Print { .. } => Err(Error::IrGeneration(
+                "Printing produces no value; print as a statement of its own".to_string(),
+            )),
+            Expr::Array(_) => Err(Error::IrGeneration(
+                "A list cannot be used as a single value; bind it with `let` or pass it to a command"
+                    .to_string(),
+            )),
+            Expr::Index { object, index } => self.convert_index(object, index),
+            Expr::Binary { op, left, right } => self.convert_binary(op, left, right),
+            Expr::Unary { op, operand } => match op {
+                UnaryOp::Not => {
+                    self.expect_type(operand, ValueType::Bool, "!")?;
+                    Ok(ShellValue::Not(Box::new(self.convert_condition(operand)?)))
+                }
+                UnaryOp::Neg => Err(Error::Unsupported(
+                    "Negation of unsigned integers".to_string(),
+                )),
+            },
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } if self.infer_type(receiver) == ValueType::Option => {
+                self.convert_option_method(receiver, method, args)
+            }
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => match letter_case(method) {
+                Some(case) if args.is_empty() => self.convert_change_case(receiver, method, case),
+                _ if args.is_empty() && (method == "len" || method == "is_empty") => {
+                    self.convert_list_length(receiver, method)
+                }
+                _ if args.is_empty() && is_string_conversion(method) => {
+                    self.convert_string_conversion(receiver, &format!(".{method}()"))
+                }
+                // Copying a string or integer is a no-op; lists are refused above
+                _ if args.is_empty() && method == "clone" => self.convert_expr_to_value(receiver),
+                _ => Err(unsupported_method(method, self.infer_type(receiver))),
+            },
+            Expr::FunctionCall { name, .. } => Err(Error::IrGeneration(format!(
+                "Function '{name}' returns nothing and cannot be used as a value"
+            ))),
+            Expr::Block(_) => Err(Error::Unsupported(
+                "A block used as a value; bind its result with `let` inside it".to_string(),
+            )),
+            Expr::Range { .. } => Err(Error::Unsupported(
+                "A range used as a value; ranges can only be iterated with `for`".to_string(),
+            )),
+        }
+    }
+
+    /// Convert `.to_string()`, `.to_owned()` or `String::from`, which leave a
+    /// string or integer as it is: every shell value is already a string
+    fn convert_string_conversion(
+        &self,
+        value: &crate::ast::Expr,
+        conversion: &str,
+    ) -> Result<ShellValue> {
+        match self.infer_type(value) {
+            ValueType::Str | ValueType::Int | ValueType::Unknown => {
+                self.convert_expr_to_value(value)
+            }
+            other => Err(Error::IrGeneration(format!(
+                "'{conversion}' needs a string or an integer, not a {}",
+                other.description()
+            ))),
+        }
+    }
+
+    /// Convert a list-valued expression: an array literal, `vec![...]` or a list
+    /// variable. Returns `None` for anything else.
+    fn convert_list(&self, expr: &crate::ast::Expr) -> Result<Option<ShellValue>> {
+        use crate::ast::Expr;
+
+        match expr {
+            Expr::Array(elements) => {
+                let mut words = Vec::new();
+                for element in elements {
+                    if matches!(element, Expr::Array(_))
+                        || self.infer_type(element) == ValueType::List
+                    {
+                        return Err(Error::Unsupported("Nested lists".to_string()));
+                    }
+                    words.push(self.convert_expr_to_value(element)?);
+                }
+                Ok(Some(ShellValue::List(words)))
+            }
+            Expr::Variable(name) if self.infer_type(expr) == ValueType::List => Ok(Some(
+                ShellValue::List(vec![ShellValue::ListElements(name.clone())]),
+            )),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } if args.is_empty() && (method == "clone" || method == "to_vec") => {
+                self.convert_list(receiver)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Type of the elements of a list-valued expression
+    fn element_type(&self, list: &crate::ast::Expr) -> ValueType {
+        use crate::ast::Expr;
+
+        match list {
+            Expr::Array(elements) => elements
+                .first()
+                .map_or(ValueType::Unknown, |element| self.infer_type(element)),
+            Expr::Variable(name) => self
+                .element_types
+                .borrow()
+                .get(name)
+                .copied()
+                .unwrap_or(ValueType::Unknown),
+            Expr::MethodCall { receiver, .. } => self.element_type(receiver),
+            _ => ValueType::Unknown,
+        }
+    }
+
+    /// Convert call arguments. External commands receive the elements of a list
+    /// as separate arguments; shell functions receive it as one word.
+    fn convert_call_args(&self, name: &str, args: &[crate::ast::Expr]) -> Result<Vec<ShellValue>> {
+        let is_shell_function = self.return_types.borrow().contains_key(name);
+        let mut values = Vec::new();
+        for arg in args {
+            match self.convert_list(arg)? {
+                Some(ShellValue::List(elements)) if !is_shell_function => values.extend(elements),
+                Some(list) => values.push(list),
+                None => values.push(self.convert_expr_to_value(arg)?),
+            }
+        }
+        Ok(values)
+    }
+
+    /// Lower a printing macro to printf: literal text goes into the format
+    /// string and every other value is passed as a `%s` argument
+    fn convert_print(&self, message: &crate::ast::Expr, stderr: bool) -> Result<ShellIR> {
+        use crate::ast::Expr;
+
+        let parts = match message {
+            Expr::Format(parts) => parts.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        let mut format = String::new();
+        let mut args = Vec::new();
+        let mut effects = EffectSet::pure();
+        for part in parts {
+            let value = self.convert_expr_to_value(part)?;
+            effects = effects.union(&self.value_effects(&value));
+            match value.as_constant_string() {
+                Some(text) => format.push_str(&escape_printf_format(&text)),
+                None => {
+                    format.push_str("%s");
+                    args.push(value);
+                }
+            }
+        }
+        Ok(ShellIR::Printf {
+            format,
+            args,
+            stderr,
+            effects,
+        })
+    }
+
+    /// Resolve a call to a `bashrs_std` function, checking its arity
+    fn stdlib_function(
+        &self,
+        name: &str,
+        args: &[crate::ast::Expr],
+    ) -> Result<&'static stdlib::StdlibFunction> {
+        let function = stdlib::lookup(name).ok_or_else(|| {
+            Error::IrGeneration(format!(
+                "Unknown function '{name}'; paths can only name `bashrs_std` functions"
+            ))
+        })?;
+        if args.len() != function.arity {
+            return Err(Error::IrGeneration(format!(
+                "Function '{}' takes {} argument(s) but {} were given",
+                function.path,
+                function.arity,
+                args.len()
+            )));
+        }
+        Ok(function)
+    }
+
+    /// Lower a call of an intrinsic used as a value. Main binds `env::args()`
+    /// and `env::arg(n)` to variables before conversion, so any call left
+    /// is malformed.
+    fn convert_intrinsic(&self, name: &str, args: &[crate::ast::Expr]) -> Result<ShellValue> {
+        use crate::ast::{restricted::Literal, Expr};
+
+        match stdlib::intrinsic(name) {
+            Some(stdlib::Intrinsic::Var) => {
+                self.env_var_name(name, args)?;
+                Err(unhandled_option(name, true))
+            }
+            Some(stdlib::Intrinsic::VarOr) => {
+                let [variable, default] = args else {
+                    return Err(Error::IrGeneration(format!(
+                        "Function '{name}' takes 2 argument(s) but {} were given",
+                        args.len()
+                    )));
+                };
+                let variable = self.env_var_name(name, std::slice::from_ref(variable))?;
+                let default_type = self.infer_type(default);
+                if !matches!(default_type, ValueType::Str | ValueType::Unknown) {
+                    return Err(Error::IrGeneration(format!(
+                        "The default of '{name}' must be a string, not a {}",
+                        default_type.description()
+                    )));
+                }
+                Ok(ShellValue::UnwrapOr {
+                    option: Box::new(ShellValue::EnvVar(variable)),
+                    default: Box::new(self.convert_expr_to_value(default)?),
+                })
+            }
+            Some(stdlib::Intrinsic::Args) => Err(Error::IrGeneration(format!(
+                "Function '{name}' takes 0 argument(s) but {} were given",
+                args.len()
+            ))),
+            Some(stdlib::Intrinsic::Arg) => match args {
+                [Expr::Literal(Literal::U32(0))] => Err(Error::IrGeneration(format!(
+                    "'{name}' counts arguments from 1; the script name is not an argument"
+                ))),
+                _ => Err(Error::IrGeneration(format!(
+                    "'{name}' takes an integer literal, such as `{name}(1)`"
+                ))),
+            },
+            Some(stdlib::Intrinsic::Capture) => {
+                self.convert_command(name, args)?;
+                Err(Error::IrGeneration(format!(
+                    "The output of '{name}' can only be bound with `let` or assigned, so that a failing command stops the script"
+                )))
+            }
+            Some(stdlib::Intrinsic::SanitizePath | stdlib::Intrinsic::SanitizeWord) => {
+                self.convert_capture_call(name, args)?;
+                Err(Error::IrGeneration(format!(
+                    "The result of '{name}' can only be bound with `let` or assigned, so that a failing check stops the script"
+                )))
+            }
+            Some(stdlib::Intrinsic::Status) => {
+                Ok(ShellValue::CommandStatus(self.convert_command(name, args)?))
+            }
+            None => Err(Error::IrGeneration(format!("Unknown intrinsic '{name}'"))),
+        }
+    }
+
+    /// Run `process::capture(program, args)` or a sanitizer, binding its
+    /// output to `binding`. A failing command or check returns its status
+    /// from the enclosing function, as a failing statement would stop the
+    /// script.
+    fn convert_capture(
+        &self,
+        call: &crate::ast::Expr,
+        binding: Option<&String>,
+        mutable: bool,
+    ) -> Result<ShellIR> {
+        let crate::ast::Expr::FunctionCall { name, args } = call else {
+            return Err(Error::IrGeneration(
+                "Expected a call of `process::capture`".to_string(),
+            ));
+        };
+        let call = self.convert_capture_call(name, args)?;
+        Ok(ShellIR::Try {
+            binding: binding.cloned(),
+            mutable,
+            effects: self.value_effects(&call),
+            call,
+        })
+    }
+
+    /// The command substitution a `process::capture` call runs, or the
+    /// helper call a sanitizer runs
+    fn convert_capture_call(&self, name: &str, args: &[crate::ast::Expr]) -> Result<ShellValue> {
+        let Some(helper) = stdlib::intrinsic(name).and_then(stdlib::Intrinsic::sanitizer_helper)
+        else {
+            return Ok(ShellValue::CommandSubst(self.convert_command(name, args)?));
+        };
+        let [value] = args else {
+            return Err(Error::IrGeneration(format!(
+                "Function '{name}' takes 1 argument(s) but {} were given",
+                args.len()
+            )));
+        };
+        Ok(ShellValue::FunctionCall {
+            name: helper.to_string(),
+            args: vec![self.convert_expr_to_value(value)?],
+            convention: ReturnConvention::Stdout,
+        })
+    }
+
+    /// The command a `process::capture` or `process::status` call runs: a
+    /// program named by a string literal and a list of arguments
+    fn convert_command(&self, function: &str, args: &[crate::ast::Expr]) -> Result<Command> {
+        use crate::ast::{restricted::Literal, Expr};
+
+        let [program, arguments] = args else {
+            return Err(Error::IrGeneration(format!(
+                "Function '{function}' takes 2 argument(s) but {} were given",
+                args.len()
+            )));
+        };
+        let program = match program {
+            Expr::Literal(Literal::Str(program)) if !program.is_empty() => program.clone(),
+            _ => {
+                return Err(Error::IrGeneration(format!(
+                    "'{function}' takes the program as a string literal, such as `{function}(\"uname\", [\"-m\"])`"
+                )))
+            }
+        };
+        let args = match self.convert_list(arguments)? {
+            Some(ShellValue::List(words)) => words,
+            _ => {
+                return Err(Error::IrGeneration(format!(
+                    "'{function}' takes the arguments as a list, such as `[\"-m\"]`"
+                )))
+            }
+        };
+        Ok(Command { program, args })
+    }
+
+    /// The environment variable an `env::var` or `env::var_or` call names,
+    /// which must be a string literal so that it can be expanded directly
+    fn env_var_name(&self, function: &str, args: &[crate::ast::Expr]) -> Result<String> {
+        use crate::ast::{restricted::Literal, Expr};
+
+        match args {
+            [Expr::Literal(Literal::Str(name))] if is_shell_identifier(name) => Ok(name.clone()),
+            [Expr::Literal(Literal::Str(name))] => Err(Error::IrGeneration(format!(
+                "'{name}' is not a valid environment variable name"
+            ))),
+            [_] => Err(Error::IrGeneration(format!(
+                "'{function}' takes a string literal naming the variable, such as `{function}(\"HOME\")`"
+            ))),
+            _ => Err(Error::IrGeneration(format!(
+                "Function '{function}' takes 1 argument(s) but {} were given",
+                args.len()
+            ))),
+        }
+    }
+
+    /// Lower `list[index]`, checking constant indices into literals at compile time
+    fn convert_index(
+        &self,
+        object: &crate::ast::Expr,
+        index: &crate::ast::Expr,
+    ) -> Result<ShellValue> {
+        use crate::ast::Expr;
+
+        if !matches!(self.infer_type(index), ValueType::Int | ValueType::Unknown) {
+            return Err(Error::IrGeneration(
+                "List indices must be integers".to_string(),
+            ));
+        }
+        let index = self.convert_expr_to_value(index)?;
+
+        match object {
+            Expr::Variable(name) if self.infer_type(object) == ValueType::List => {
+                Ok(ShellValue::ListItem {
+                    list: name.clone(),
+                    index: Box::new(index),
+                })
+            }
+            Expr::Array(elements) => {
+                let position = index
+                    .as_constant_string()
+                    .and_then(|text| text.parse::<usize>().ok())
+                    .ok_or_else(|| {
+                        Error::Unsupported(
+                            "Indexing a list literal with a computed index; bind the list with `let` first"
+                                .to_string(),
+                        )
+                    })?;
+                let element = elements.get(position).ok_or_else(|| {
+                    Error::IrGeneration(format!(
+                        "Index {position} is out of bounds for a list of length {}",
+                        elements.len()
+                    ))
+                })?;
+                self.convert_expr_to_value(element)
+            }
+            _ => Err(Error::Unsupported(
+                "Indexing anything but a list".to_string(),
+            )),
+        }
+    }
+
+    /// Lower `.len()` and `.is_empty()` on lists, folding them on literals
+    fn convert_list_length(&self, receiver: &crate::ast::Expr, method: &str) -> Result<ShellValue> {
+        use crate::ast::Expr;
+
+        let length = match receiver {
+            Expr::Array(elements) => ShellValue::String(elements.len().to_string()),
+            Expr::Variable(name) if self.infer_type(receiver) == ValueType::List => {
+                ShellValue::ListLength(name.clone())
+            }
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "Method '{method}' on anything but a list"
+                )))
+            }
+        };
+
+        if method == "len" {
+            return Ok(length);
+        }
+        Ok(match length {
+            ShellValue::String(count) => ShellValue::Bool(count == "0"),
+            length => ShellValue::Comparison {
+                op: ComparisonOp::NumEq,
+                left: Box::new(length),
+                right: Box::new(ShellValue::String("0".to_string())),
+            },
+        })
+    }
+
+    /// Lower `to_lowercase`/`to_uppercase`, folding them on literals
+    fn convert_change_case(
+        &self,
+        receiver: &crate::ast::Expr,
+        method: &str,
+        case: LetterCase,
+    ) -> Result<ShellValue> {
+        if !matches!(
+            self.infer_type(receiver),
+            ValueType::Str | ValueType::Unknown
+        ) {
+            return Err(Error::IrGeneration(format!(
+                "Method '{method}' requires a string receiver"
+            )));
+        }
+        Ok(match self.convert_expr_to_value(receiver)? {
+            ShellValue::String(text) => ShellValue::String(match case {
+                LetterCase::Lower => text.to_lowercase(),
+                LetterCase::Upper => text.to_uppercase(),
+            }),
+            value => ShellValue::ChangeCase {
+                value: Box::new(value),
+                case,
+            },
+        })
+    }
+
+    /// Convert an expression used where the shell expects a command status
+    fn convert_condition(&self, expr: &crate::ast::Expr) -> Result<ShellValue> {
+        use crate::ast::Expr;
+
+        // Boolean variables and list elements hold the words `true` and `false`
+        match expr {
+            Expr::Variable(_) | Expr::Index { .. } if self.infer_type(expr) == ValueType::Bool => {
+                Ok(ShellValue::Comparison {
+                    op: ComparisonOp::StrEq,
+                    left: Box::new(self.convert_expr_to_value(expr)?),
+                    right: Box::new(ShellValue::String("true".to_string())),
+                })
+            }
+            _ => self.convert_expr_to_value(expr),
+        }
+    }
+
+    fn convert_binary(
+        &self,
+        op: &BinaryOp,
+        left: &crate::ast::Expr,
+        right: &crate::ast::Expr,
+    ) -> Result<ShellValue> {
+        let left_type = self.infer_type(left);
+        let right_type = self.infer_type(right);
+        let has = |ty| left_type == ty || right_type == ty;
+
+        let arithmetic_op = match op {
+            BinaryOp::Add if has(ValueType::Str) && has(ValueType::Int) => {
+                return Err(Error::IrGeneration(
+                    "Operator '+' cannot add an integer to a string; use `format!(\"{s}{n}\")` to join them"
+                        .to_string(),
+                ));
+            }
+            // `String + &str` appends, so anything involving a string concatenates
+            BinaryOp::Add if has(ValueType::Bool) => {
+                return Err(Error::IrGeneration(
+                    "Operator '+' requires string or integer operands".to_string(),
+                ));
+            }
+            BinaryOp::Add if has(ValueType::Str) || !has(ValueType::Int) => {
+                let left_val = self.convert_expr_to_value(left)?;
+                let right_val = self.convert_expr_to_value(right)?;
+                return Ok(ShellValue::Concat(vec![left_val, right_val]));
+            }
+            BinaryOp::Add => ArithmeticOp::Add,
+            BinaryOp::Sub => ArithmeticOp::Sub,
+            BinaryOp::Mul => ArithmeticOp::Mul,
+            BinaryOp::Div => ArithmeticOp::Div,
+            BinaryOp::Eq | BinaryOp::Ne => {
+                if has(ValueType::Int) && (has(ValueType::Str) || has(ValueType::Bool)) {
+                    return Err(Error::IrGeneration(
+                        "Cannot compare an integer with a non-integer value".to_string(),
+                    ));
+                }
+                let op = match (op, has(ValueType::Int)) {
+                    (BinaryOp::Eq, true) => ComparisonOp::NumEq,
+                    (BinaryOp::Eq, false) => ComparisonOp::StrEq,
+                    (_, true) => ComparisonOp::NumNe,
+                    (_, false) => ComparisonOp::StrNe,
+                };
+                return self.convert_comparison(op, left, right);
+            }
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                if has(ValueType::Str) {
+                    return Err(Error::Unsupported(
+                        "Ordering comparisons between strings".to_string(),
+                    ));
+                }
+                let symbol = binary_op_symbol(op);
+                self.expect_operand_types(left_type, right_type, ValueType::Int, symbol)?;
+                let op = match op {
+                    BinaryOp::Lt => ComparisonOp::Lt,
+                    BinaryOp::Le => ComparisonOp::Le,
+                    BinaryOp::Gt => ComparisonOp::Gt,
+                    _ => ComparisonOp::Ge,
+                };
+                return self.convert_comparison(op, left, right);
+            }
+            BinaryOp::And | BinaryOp::Or => {
+                let symbol = binary_op_symbol(op);
+                self.expect_operand_types(left_type, right_type, ValueType::Bool, symbol)?;
+                let op = match op {
+                    BinaryOp::And => LogicalOp::And,
+                    _ => LogicalOp::Or,
+                };
+                return Ok(ShellValue::Logical {
+                    op,
+                    left: Box::new(self.convert_condition(left)?),
+                    right: Box::new(self.convert_condition(right)?),
+                });
+            }
+        };
+
+        self.expect_operand_types(
+            left_type,
+            right_type,
+            ValueType::Int,
+            arithmetic_op.symbol(),
+        )?;
+        let left_val = self.convert_expr_to_value(left)?;
+        let right_val = self.convert_expr_to_value(right)?;
+        fold_arithmetic(arithmetic_op, left_val, right_val)
+    }
+
+    fn convert_comparison(
+        &self,
+        op: ComparisonOp,
+        left: &crate::ast::Expr,
+        right: &crate::ast::Expr,
+    ) -> Result<ShellValue> {
+        Ok(ShellValue::Comparison {
+            op,
+            left: Box::new(self.convert_expr_to_value(left)?),
+            right: Box::new(self.convert_expr_to_value(right)?),
+        })
+    }
+
+    fn expect_type(
+        &self,
+        expr: &crate::ast::Expr,
+        expected: ValueType,
+        symbol: &str,
+    ) -> Result<()> {
+        let actual = self.infer_type(expr);
+        self.expect_operand_types(actual, expected, expected, symbol)
+    }
+
+    fn expect_operand_types(
+        &self,
+        left: ValueType,
+        right: ValueType,
+        expected: ValueType,
+        symbol: &str,
+    ) -> Result<()> {
+        let compatible = |ty| ty == expected || ty == ValueType::Unknown;
+        if compatible(left) && compatible(right) {
+            Ok(())
+        } else {
+            Err(Error::IrGeneration(format!(
+                "Operator '{symbol}' requires {} operands",
+                expected.description()
+            )))
+        }
+    }
+
+    /// Best-effort static type of an expression
+    fn infer_type(&self, expr: &crate::ast::Expr) -> ValueType {
+        use crate::ast::{restricted::Literal, Expr};
+
+        match expr {
+            Expr::Literal(Literal::Bool(_)) => ValueType::Bool,
+            Expr::Literal(Literal::U32(_)) => ValueType::Int,
+            Expr::Literal(Literal::Str(_)) => ValueType::Str,
+            Expr::Variable(name) if name == "None" => ValueType::Option,
+            Expr::FunctionCall { name, .. } if name == "Some" => ValueType::Option,
+            Expr::Variable(name) => self
+                .var_types
+                .borrow()
+                .get(name)
+                .or(self.constants.borrow().get(name))
+                .copied()
+                .unwrap_or(ValueType::Unknown),
+            Expr::FunctionCall { name, .. } if name == "String::from" => ValueType::Str,
+            Expr::FunctionCall { name, .. } if stdlib::intrinsic(name).is_some() => {
+                match stdlib::intrinsic(name) {
+                    Some(stdlib::Intrinsic::VarOr | stdlib::Intrinsic::Capture) => ValueType::Str,
+                    Some(stdlib::Intrinsic::Status) => ValueType::Bool,
+                    Some(stdlib::Intrinsic::Args) => ValueType::List,
+                    _ => ValueType::Option,
+                }
+            }
+            Expr::FunctionCall { name, .. } if name.contains("::") => {
+                match stdlib::lookup(name).map(|function| function.convention) {
+                    Some(ReturnConvention::Stdout) => ValueType::Str,
+                    Some(ReturnConvention::ExitStatus) => ValueType::Bool,
+                    _ => ValueType::Unknown,
+                }
+            }
+            Expr::FunctionCall { name, .. } => self
+                .return_types
+                .borrow()
+                .get(name)
+                .map_or(ValueType::Unknown, ValueType::from),
+            Expr::Try { expr } => match expr.as_ref() {
+                Expr::FunctionCall { name, .. } => self
+                    .result_type_of(name)
+                    .map_or(ValueType::Unknown, |ok_type| ValueType::from(&ok_type)),
+                _ => ValueType::Unknown,
+            },
+            Expr::Binary { op, left, right } => match op {
+                BinaryOp::Add => {
+                    let types = [self.infer_type(left), self.infer_type(right)];
+                    if types.contains(&ValueType::Str) {
+                        ValueType::Str
+                    } else if types.contains(&ValueType::Int) {
+                        ValueType::Int
+                    } else {
+                        ValueType::Unknown
+                    }
+                }
+                BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => ValueType::Int,
+                _ => ValueType::Bool,
+            },
+            Expr::Unary { op, .. } => match op {
+                UnaryOp::Not => ValueType::Bool,
+                UnaryOp::Neg => ValueType::Int,
+            },
+            Expr::MethodCall {
+                receiver, method, ..
+            } if self.infer_type(receiver) == ValueType::Option => match method.as_str() {
+                "is_some" | "is_none" => ValueType::Bool,
+                _ => self.option_inner_type(receiver),
+            },
+            Expr::MethodCall { method, .. } if letter_case(method).is_some() => ValueType::Str,
+            Expr::MethodCall { method, .. } if method == "len" => ValueType::Int,
+            Expr::MethodCall { method, .. } if is_string_conversion(method) => ValueType::Str,
+            Expr::MethodCall { method, .. } if method == "is_empty" => ValueType::Bool,
+            Expr::MethodCall {
+                receiver, method, ..
+            } if method == "clone" => self.infer_type(receiver),
+            Expr::MethodCall {
+                receiver, method, ..
+            } if method == "to_vec" => match self.infer_type(receiver) {
+                ValueType::List => ValueType::List,
+                _ => ValueType::Unknown,
+            },
+            Expr::Array(_) => ValueType::List,
+            Expr::Format(_) => ValueType::Str,
+            Expr::Index { object, .. } => self.element_type(object),
+            _ => ValueType::Unknown,
+        }
+    }
+
+    /// Effects of evaluating a value, i.e. of the functions it calls
+    fn value_effects(&self, value: &ShellValue) -> EffectSet {
+        match value {
+            ShellValue::FunctionCall { name, args, .. } => {
+                let callee = match stdlib::by_helper(name) {
+                    Some(function) => function.effects(),
+                    None => self
+                        .function_effects
+                        .borrow()
+                        .get(name)
+                        .cloned()
+                        .unwrap_or_default(),
+                };
+                args.iter()
+                    .fold(callee, |acc, arg| acc.union(&self.value_effects(arg)))
+            }
+            ShellValue::Concat(parts) => parts.iter().fold(EffectSet::pure(), |acc, part| {
+                acc.union(&self.value_effects(part))
+            }),
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::Comparison { left, right, .. }
+            | ShellValue::Logical { left, right, .. }
+            | ShellValue::UnwrapOr {
+                option: left,
+                default: right,
+            } => self.value_effects(left).union(&self.value_effects(right)),
+            ShellValue::Not(operand)
+            | ShellValue::ChangeCase { value: operand, .. }
+            | ShellValue::ListItem { index: operand, .. }
+            | ShellValue::IsSome(operand) => self.value_effects(operand),
+            ShellValue::List(elements) => {
+                elements.iter().fold(EffectSet::pure(), |acc, element| {
+                    acc.union(&self.value_effects(element))
+                })
+            }
+            ShellValue::EnvVar(_) | ShellValue::Arg(_) | ShellValue::Args => {
+                [Effect::EnvRead].to_vec().into()
+            }
+            ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => cmd
+                .args
+                .iter()
+                .fold(self.command_effects(&cmd.program, &cmd.args), |acc, arg| {
+                    acc.union(&self.value_effects(arg))
+                }),
+            _ => EffectSet::pure(),
+        }
+    }
+
+    /// Effects of running an external command, as its stub declares them
+    /// or as the command table knows them
+    fn command_effects(&self, program: &str, args: &[ShellValue]) -> EffectSet {
+        match self.command_declarations.borrow().get(program) {
+            Some(effects) => effects.clone(),
+            None => effects::analyze_invocation_effects(program, args),
+        }
+    }
+}
+
+/// Static type of a value, as far as the converter can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueType {
+    Str,
+    Int,
+    Bool,
+    List,
+    Option,
+    Unknown,
+}
+
+/// An expression of `Option` type, as the converter lowers it
+enum OptionValue {
+    /// `None`
+    Empty,
+    /// `Some(value)`
+    Some(ShellValue),
+    /// A variable that is set, or a call of a function returning `Option`
+    /// that succeeds, exactly when the option holds a value
+    Held(ShellValue),
+}
+
+impl ValueType {
+    fn description(&self) -> &'static str {
+        match self {
+            ValueType::Str => "string",
+            ValueType::Int => "integer",
+            ValueType::Bool => "boolean",
+            ValueType::List => "list",
+            ValueType::Option => "option",
+            ValueType::Unknown => "unknown",
+        }
+    }
+}
+
+impl From<&crate::ast::Type> for ValueType {
+    fn from(ty: &crate::ast::Type) -> Self {
+        use crate::ast::Type;
+
+        match ty {
+            Type::Bool => ValueType::Bool,
+            Type::U32 => ValueType::Int,
+            Type::Str => ValueType::Str,
+            Type::Vec { .. } => ValueType::List,
+            Type::Option { .. } => ValueType::Option,
+            _ => ValueType::Unknown,
+        }
+    }
+}
+
+fn binary_op_symbol(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "/",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Le => "<=",
+        BinaryOp::Gt => ">",
+        BinaryOp::Ge => ">=",
+        BinaryOp::And => "&&",
+        BinaryOp::Or => "||",
+    }
+}
+
+/// Build an arithmetic value, evaluating it at compile time when both operands
+/// are constants so that overflow is reported instead of silently wrapping
+fn fold_arithmetic(op: ArithmeticOp, left: ShellValue, right: ShellValue) -> Result<ShellValue> {
+    let constant = |value: &ShellValue| value.as_constant_string()?.parse::<u32>().ok();
+
+    if constant(&right) == Some(0) && op == ArithmeticOp::Div {
+        return Err(Error::IrGeneration("Division by zero".to_string()));
+    }
+
+    if let (Some(l), Some(r)) = (constant(&left), constant(&right)) {
+        return match op.checked_apply(l, r) {
+            Some(result) => Ok(ShellValue::String(result.to_string())),
+            None => Err(Error::IrGeneration(format!(
+                "Arithmetic overflow: {l} {} {r} does not fit in u32",
+                op.symbol()
+            ))),
+        };
+    }
+
+    Ok(ShellValue::Arithmetic {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    })
+}
+
+/// A call in statement position, whose value is unused: it must neither
+/// reach stdout nor, when it is `false`, stop the script
+fn unused_call(
+    name: String,
+    args: Vec<ShellValue>,
+    convention: Option<ReturnConvention>,
+    effects: EffectSet,
+) -> ShellIR {
+    match convention {
+        Some(ReturnConvention::Stdout) => ShellIR::Try {
+            binding: None,
+            mutable: false,
+            call: ShellValue::FunctionCall {
+                name,
+                args,
+                convention: ReturnConvention::Stdout,
+            },
+            effects,
+        },
+        Some(ReturnConvention::ExitStatus) => ShellIR::If {
+            test: ShellValue::FunctionCall {
+                name,
+                args,
+                convention: ReturnConvention::ExitStatus,
+            },
+            then_branch: Box::new(ShellIR::Noop),
+            else_branch: None,
+        },
+        _ => ShellIR::Exec {
+            cmd: Command {
+                program: name,
+                args,
+            },
+            effects,
+        },
+    }
+}
+
+fn return_convention(return_type: &crate::ast::Type) -> ReturnConvention {
+    use crate::ast::Type;
+
+    match return_type {
+        Type::Void => ReturnConvention::Void,
+        Type::Bool => ReturnConvention::ExitStatus,
+        // Failure is reported through the exit status, success like the `Ok` type
+        Type::Result { ok_type, .. } => return_convention(ok_type),
+        _ => ReturnConvention::Stdout,
+    }
+}
+
+/// The success type, for a `Result` type
+fn result_ok_type(ty: &crate::ast::Type) -> Option<&crate::ast::Type> {
+    match ty {
+        crate::ast::Type::Result { ok_type, .. } => Some(ok_type),
+        _ => None,
+    }
+}
+
+fn unhandled_option(name: &str, is_call: bool) -> Error {
+    let subject = match name {
+        "Some" | "None" => {
+            return Error::IrGeneration(format!(
+                "`{name}` can only be bound with `let`, matched on or returned"
+            ))
+        }
+        _ if is_call => format!("'{name}' returns an `Option`"),
+        _ => format!("'{name}' is an `Option`"),
+    };
+    Error::IrGeneration(format!(
+        "{subject}; get its value with `unwrap_or`, `if let` or `match`"
+    ))
+}
+
+fn misplaced_result_constructor(name: &str) -> Error {
+    Error::IrGeneration(format!(
+        "`{name}(...)` can only be returned from a function that returns `Result`"
+    ))
+}
+
+/// Literal text as part of a printf format string
+fn escape_printf_format(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '%' => escaped.push_str("%%"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The case a string method such as `to_lowercase` converts to
+/// A method the compiler has no shell lowering for
+fn unsupported_method(method: &str, receiver: ValueType) -> Error {
+    let hint = match receiver {
+        ValueType::List => "; iterate over the list with `for`, index it or pass it to a command",
+        ValueType::Str => "; pass the string to a command such as `tr` or `sed`",
+        _ => "",
+    };
+    let on = match receiver {
+        ValueType::Unknown => String::new(),
+        ValueType::Int => " on an integer".to_string(),
+        other => format!(" on a {}", other.description()),
+    };
+    Error::Unsupported(format!("Method '{method}'{on}{hint}"))
+}
+
+/// Whether `method` turns a string into an owned one, a no-op in the shell
+fn is_string_conversion(method: &str) -> bool {
+    matches!(method, "to_string" | "to_owned")
+}
+
+fn letter_case(method: &str) -> Option<LetterCase> {
+    match method {
+        "to_lowercase" => Some(LetterCase::Lower),
+        "to_uppercase" => Some(LetterCase::Upper),
+        _ => None,
+    }
+}
+
+/// Names of the functions the entry point calls, directly or through others
+/// Variable main binds `env::args()` to
+const ARGS_VARIABLE: &str = "_rash_args";
+
+/// Variable main binds `env::arg(position)` to
+fn arg_variable(position: u32) -> String {
+    format!("_rash_arg_{position}")
+}
+
+/// The script arguments a program reads
+#[derive(Debug, Default)]
+struct ScriptArguments {
+    /// Whether it calls `env::args()`
+    all: bool,
+    /// The positions it passes to `env::arg`
+    positions: BTreeSet<u32>,
+}
+
+/// Replace `env::args()` and `env::arg(n)` with the variables main binds
+/// them to, so that they work in any function and after `set --`
+fn bind_script_arguments(ast: &mut RestrictedAst) -> ScriptArguments {
+    use crate::ast::{restricted::Literal, Expr};
+
+    let mut arguments = ScriptArguments::default();
+    crate::ast::visitor::transform_exprs(ast, |expr| {
+        let Expr::FunctionCall { name, args } = expr else {
+            return;
+        };
+        match (stdlib::intrinsic(name), args.as_slice()) {
+            (Some(stdlib::Intrinsic::Args), []) => {
+                arguments.all = true;
+                *expr = Expr::Variable(ARGS_VARIABLE.to_string());
+            }
+            (Some(stdlib::Intrinsic::Arg), [Expr::Literal(Literal::U32(position))])
+                if *position > 0 =>
+            {
+                let position = *position;
+                arguments.positions.insert(position);
+                *expr = Expr::Variable(arg_variable(position));
+            }
+            _ => {}
+        }
+    });
+    arguments
+}
+
+/// Whether an expression calls `process::capture` or a sanitizer, whose
+/// output is bound and whose failure stops the script
+fn is_capture(expr: &crate::ast::Expr) -> bool {
+    matches!(
+        expr,
+        crate::ast::Expr::FunctionCall { name, .. }
+            if matches!(
+                stdlib::intrinsic(name),
+                Some(
+                    stdlib::Intrinsic::Capture
+                        | stdlib::Intrinsic::SanitizePath
+                        | stdlib::Intrinsic::SanitizeWord
+                )
+            )
+    )
+}
+
+/// Whether `name` can be expanded as a shell variable
+fn is_shell_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+fn reachable_functions(ast: &RestrictedAst) -> HashSet<&str> {
+    let mut reachable = HashSet::new();
+    let mut pending = vec![ast.entry_point.as_str()];
+    while let Some(name) = pending.pop() {
+        let Some(function) = ast.functions.iter().find(|f| f.name == name) else {
+            continue;
+        };
+        if !reachable.insert(function.name.as_str()) {
+            continue;
+        }
+        let mut calls = Vec::new();
+        function.collect_function_calls(&mut calls);
+        for call in calls {
+            if let Some(callee) = ast.functions.iter().find(|f| f.name == call) {
+                pending.push(callee.name.as_str());
+            }
+        }
+    }
+    reachable
+}
+
+/// Order functions so that every function comes after the functions it calls
+fn definition_order(ast: &RestrictedAst) -> Vec<&crate::ast::Function> {
+    fn visit<'a>(
+        function: &'a crate::ast::Function,
+        ast: &'a RestrictedAst,
+        visited: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a crate::ast::Function>,
+    ) {
+        if !visited.insert(function.name.as_str()) {
+            return;
+        }
+        let mut calls = Vec::new();
+        function.collect_function_calls(&mut calls);
+        for call in calls {
+            if let Some(callee) = ast.functions.iter().find(|f| f.name == call) {
+                visit(callee, ast, visited, order);
+            }
+        }
+        order.push(function);
+    }
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for function in &ast.functions {
+        visit(function, ast, &mut visited, &mut order);
+    }
+    order
+}
+
+/// Whether some arm is taken for every possible value of the scrutinee
+fn is_exhaustive(arms: &[CaseArm], scrutinee_type: ValueType) -> bool {
+    if arms.iter().any(CaseArm::is_catch_all) {
+        return true;
+    }
+
+    // Booleans only have two values, so `true` and `false` arms cover them
+    scrutinee_type == ValueType::Bool
+        && ["true", "false"].iter().all(|value| {
+            arms.iter().any(|arm| {
+                arm.guard.is_none()
+                    && arm
+                        .patterns
+                        .contains(&CasePattern::Literal((*value).to_string()))
+            })
+        })
+}
+
+/// Number of iterations a loop performs, when it is known at compile time
+fn static_trip_count(iter: &ForIter) -> Option<u64> {
+    match iter {
+        ForIter::Words(words)
+            if words
+                .iter()
+                .any(|w| matches!(w, ShellValue::ListElements(_))) =>
+        {
+            None
+        }
+        ForIter::Words(words) => Some(words.len() as u64),
+        ForIter::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let start: u64 = start.as_constant_string()?.parse().ok()?;
+            let end: u64 = end.as_constant_string()?.parse().ok()?;
+            let end = if *inclusive { end + 1 } else { end };
+            Some(end.saturating_sub(start))
+        }
+    }
+}
+
+fn constant_fold(ir: ShellIR) -> ShellIR {
+    // Values of the globals folded so far; globals precede every use and
+    // can't be rebound, so their uses can be replaced by their values
+    let mut constants = HashMap::new();
+    let mut transform_fn = |node| match node {
+        ShellIR::Global { name, value } => {
+            let value = fold_value(value, &constants, true);
+            if let Some(text) = value.as_constant_string() {
+                constants.insert(name.clone(), text);
+            }
+            ShellIR::Global { name, value }
+        }
+        ShellIR::Let {
+            name,
+            value,
+            effects,
+        } => ShellIR::Let {
+            name,
+            value: fold_value(value, &constants, false),
+            effects,
+        },
+        ShellIR::Assign {
+            name,
+            value,
+            effects,
+        } => ShellIR::Assign {
+            name,
+            value: fold_value(value, &constants, false),
+            effects,
+        },
+        _ => node,
+    };
+    transform_ir(ir, &mut transform_fn)
+}
+
+/// Evaluate string concatenation and `u32` arithmetic on known values.
+/// Globals are replaced by their values inside expressions, and at the top
+/// level when `inline_globals` is set.
+fn fold_value(
+    value: ShellValue,
+    constants: &HashMap<String, String>,
+    inline_globals: bool,
+) -> ShellValue {
+    match value {
+        ShellValue::Variable(name) if inline_globals && constants.contains_key(&name) => {
+            ShellValue::String(constants[&name].clone())
+        }
+        ShellValue::Concat(parts) => {
+            let parts: Vec<ShellValue> = parts
+                .into_iter()
+                .map(|part| fold_value(part, constants, true))
+                .collect();
+            if parts.iter().all(|p| matches!(p, ShellValue::String(_))) {
+                ShellValue::String(
+                    parts
+                        .iter()
+                        .filter_map(|p| match p {
+                            ShellValue::String(s) => Some(s.as_str()),
+                            _ => None,
+                        })
+                        .collect(),
+                )
+            } else {
+                ShellValue::Concat(parts)
+            }
+        }
+        ShellValue::Arithmetic { op, left, right } => {
+            let left = fold_value(*left, constants, true);
+            let right = fold_value(*right, constants, true);
+            let operand = |value: &ShellValue| match value {
+                ShellValue::String(s) => s.parse::<u32>().ok(),
+                _ => None,
+            };
+            // Overflow and division by zero are left to the script
+            let folded = match (operand(&left), operand(&right)) {
+                (Some(l), Some(r)) => op.checked_apply(l, r),
+                _ => None,
+            };
+            match folded {
+                Some(n) => ShellValue::String(n.to_string()),
+                None => ShellValue::Arithmetic {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+            }
+        }
+        other => other,
+    }
+}
+
+fn eliminate_dead_code(ir: ShellIR) -> ShellIR {
+    // Simple dead code elimination
+    ir // Placeholder - would implement actual DCE
+}
+
+fn transform_ir<F>(ir: ShellIR, transform: &mut F) -> ShellIR
+where
+    F: FnMut(ShellIR) -> ShellIR,
+{
+    let transformed = match ir {
+        ShellIR::Sequence(stmts) => {
+            let new_stmts = stmts
+                .into_iter()
+                .map(|stmt| transform_ir(stmt, transform))
+                .collect();
+            ShellIR::Sequence(new_stmts)
+        }
+        ShellIR::If {
+            test,
+            then_branch,
+            else_branch,
+        } => {
+            let new_then = Box::new(transform_ir(*then_branch, transform));
+            let new_else = else_branch.map(|eb| Box::new(transform_ir(*eb, transform)));
+            ShellIR::If {
+                test,
+                then_branch: new_then,
+                else_branch: new_else,
+            }
+        }
+        ShellIR::For {
+            var,
+            iter,
+            body,
+            max_iterations,
+        } => ShellIR::For {
+            var,
+            iter,
+            body: Box::new(transform_ir(*body, transform)),
+            max_iterations,
+        },
+        ShellIR::While {
+            test,
+            body,
+            max_iterations,
+        } => ShellIR::While {
+            test,
+            body: Box::new(transform_ir(*body, transform)),
+            max_iterations,
+        },
+        ShellIR::Function { name, params, body } => ShellIR::Function {
+            name,
+            params,
+            body: Box::new(transform_ir(*body, transform)),
+        },
+        ShellIR::Spanned { span, inner } => ShellIR::Spanned {
+            span,
+            inner: Box::new(transform_ir(*inner, transform)),
+        },
+        ShellIR::Case { scrutinee, arms } => ShellIR::Case {
+            scrutinee,
+            arms: arms
+                .into_iter()
+                .map(|arm| CaseArm {
+                    body: Box::new(transform_ir(*arm.body, transform)),
+                    ..arm
+                })
+                .collect(),
+        },
         other => other,
     };
 
@@ -0,0 +1,482 @@
+//! Renaming Rust bindings to shell variables
+//!
+//! Shell variables are not scoped to blocks, and in POSIX sh not even to
+//! functions, so a binding that reuses a name bound elsewhere in the script
+//! would overwrite the earlier variable (or fail outright if it is readonly).
+//! This pass gives every binding in the script its own shell name, renaming
+//! the uses it reaches, and keeps clear of variables the shell or the
+//! environment give a meaning, such as `PATH` and `IFS`. It also rejects
+//! assignments to anything but a `let mut` binding in scope.
+
+use crate::ast::restricted::{Literal, MatchArm, Pattern, Span};
+use crate::ast::{Expr, Function, RestrictedAst, Stmt};
+use crate::formatter::source_map::NameMapping;
+use crate::formatter::types::CharPos;
+use crate::ir::stdlib::{self, Intrinsic};
+use crate::models::{Diagnostic, Error, Result};
+use std::collections::{HashMap, HashSet};
+
+/// Variables with a meaning to the shell, the commands it runs or the
+/// generated script, which no binding may take, whatever its case
+const RESERVED_NAMES: &[&str] = &[
+    "PATH",
+    "IFS",
+    "HOME",
+    "PWD",
+    "OLDPWD",
+    "CDPATH",
+    "ENV",
+    "PS1",
+    "PS2",
+    "PS4",
+    "OPTIND",
+    "OPTARG",
+    "OPTERR",
+    "LINENO",
+    "PPID",
+    "SHELL",
+    "SHLVL",
+    "USER",
+    "LOGNAME",
+    "TERM",
+    "LANG",
+    "TMPDIR",
+    "TZ",
+    "MAIL",
+    "MAILPATH",
+    "HISTFILE",
+    "RANDOM",
+    "SECONDS",
+    "UID",
+    "EUID",
+    "GROUPS",
+    "HOSTNAME",
+    "FUNCNAME",
+    "REPLY",
+    "COLUMNS",
+    "LINES",
+    "POSIXLY_CORRECT",
+];
+
+/// Prefixes of reserved names: locale settings, Bash's own variables and the
+/// temporaries of the generated script
+const RESERVED_PREFIXES: &[&str] = &["LC_", "BASH", "_RASH"];
+
+/// The AST with every binding renamed apart, and the name each one got
+pub fn resolve(ast: &RestrictedAst) -> Result<(RestrictedAst, Vec<NameMapping>)> {
+    let mut resolved = ast.clone();
+    let mut resolver = Resolver::new(&resolved);
+    for constant in &mut resolved.constants {
+        resolver.resolve_expr(&mut constant.value)?;
+        let shell_name = resolver.shell_name(&constant.name);
+        resolver.record(&constant.name, &shell_name, Some(constant.span));
+        resolver.globals.insert(
+            constant.name.clone(),
+            Binding {
+                shell_name: shell_name.clone(),
+                kind: BindingKind::Constant,
+            },
+        );
+        constant.name = shell_name;
+    }
+    for function in &mut resolved.functions {
+        resolver.resolve_function(function)?;
+    }
+    Ok((resolved, resolver.names))
+}
+
+/// What a binding in scope allows
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    /// A `const` or `static` item
+    Constant,
+    Immutable,
+    /// `let mut`
+    Mutable,
+}
+
+/// A binding in scope and the shell variable holding it
+struct Binding {
+    shell_name: String,
+    kind: BindingKind,
+}
+
+struct Resolver {
+    /// The constants, visible in every function
+    globals: HashMap<String, Binding>,
+    /// Scopes of the function being resolved, innermost last
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Every name the script mentions, which fresh names must avoid
+    taken: HashSet<String>,
+    /// Shell names given out so far
+    bound: HashSet<String>,
+    /// Environment variables the script reads with `env::var`, which no
+    /// binding may shadow
+    environment: HashSet<String>,
+    /// Name of the function being resolved
+    function: String,
+    /// Span of the statement being resolved
+    span: Option<Span>,
+    names: Vec<NameMapping>,
+}
+
+impl Resolver {
+    fn new(ast: &RestrictedAst) -> Self {
+        let mut taken: HashSet<String> = ast.constants.iter().map(|c| c.name.clone()).collect();
+        let mut environment = HashSet::new();
+        for function in &ast.functions {
+            taken.extend(function.params.iter().map(|p| p.name.clone()));
+            for stmt in &function.body {
+                collect_names(stmt, &mut taken, &mut environment);
+            }
+        }
+        Self {
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            taken,
+            bound: HashSet::new(),
+            environment,
+            function: String::new(),
+            span: None,
+            names: Vec::new(),
+        }
+    }
+
+    fn resolve_function(&mut self, function: &mut Function) -> Result<()> {
+        self.function = function.name.clone();
+        self.span = None;
+        self.scopes.push(HashMap::new());
+        let result = (|| {
+            for param in &mut function.params {
+                param.name = self.bind(&param.name, BindingKind::Immutable)?;
+            }
+            self.resolve_block(&mut function.body)
+        })();
+        self.scopes.pop();
+        result
+    }
+
+    /// Resolve statements in a scope of their own
+    fn resolve_block(&mut self, stmts: &mut [Stmt]) -> Result<()> {
+        self.scopes.push(HashMap::new());
+        let result = stmts
+            .iter_mut()
+            .try_for_each(|stmt| self.resolve_stmt(stmt));
+        self.scopes.pop();
+        result
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) -> Result<()> {
+        match stmt {
+            Stmt::Let {
+                name,
+                value,
+                mutable,
+            } => {
+                // The value still sees any binding the new one shadows
+                self.resolve_expr(value)?;
+                let kind = if *mutable {
+                    BindingKind::Mutable
+                } else {
+                    BindingKind::Immutable
+                };
+                *name = self.bind(name, kind)?;
+            }
+            Stmt::Assign { name, value } => {
+                self.resolve_expr(value)?;
+                *name = self.assignable(name)?;
+            }
+            Stmt::Expr(expr) | Stmt::Return(Some(expr)) => self.resolve_expr(expr)?,
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+            Stmt::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.resolve_expr(condition)?;
+                self.resolve_block(then_block)?;
+                if let Some(else_block) = else_block {
+                    self.resolve_block(else_block)?;
+                }
+            }
+            Stmt::Match { scrutinee, arms } => {
+                self.resolve_expr(scrutinee)?;
+                for arm in arms {
+                    self.resolve_arm(arm)?;
+                }
+            }
+            Stmt::For {
+                pattern,
+                iter,
+                body,
+                ..
+            } => {
+                self.resolve_expr(iter)?;
+                self.scopes.push(HashMap::new());
+                let result = self
+                    .bind_pattern(pattern)
+                    .and_then(|()| self.resolve_block(body));
+                self.scopes.pop();
+                result?;
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.resolve_expr(condition)?;
+                self.resolve_block(body)?;
+            }
+            Stmt::Block(stmts) => self.resolve_block(stmts)?,
+            Stmt::Spanned { span, stmt } => {
+                let outer = self.span.replace(*span);
+                let result = self.resolve_stmt(stmt).map_err(|e| e.at(*span));
+                self.span = outer;
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_arm(&mut self, arm: &mut MatchArm) -> Result<()> {
+        self.scopes.push(HashMap::new());
+        let result = (|| {
+            self.bind_pattern(&mut arm.pattern)?;
+            if let Some(guard) = &mut arm.guard {
+                self.resolve_expr(guard)?;
+            }
+            self.resolve_block(&mut arm.body)
+        })();
+        self.scopes.pop();
+        result
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) -> Result<()> {
+        match expr {
+            Expr::Variable(name) => {
+                if let Some(binding) = self.lookup(name) {
+                    *name = binding.shell_name.clone();
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::FunctionCall { args, .. } | Expr::Array(args) | Expr::Format(args) => {
+                for arg in args {
+                    self.resolve_expr(arg)?;
+                }
+            }
+            Expr::MethodCall { receiver, args, .. } => {
+                self.resolve_expr(receiver)?;
+                for arg in args {
+                    self.resolve_expr(arg)?;
+                }
+            }
+            Expr::Binary { left, right, .. }
+            | Expr::Index {
+                object: left,
+                index: right,
+            }
+            | Expr::Range {
+                start: left,
+                end: right,
+                ..
+            } => {
+                self.resolve_expr(left)?;
+                self.resolve_expr(right)?;
+            }
+            Expr::Unary { operand, .. }
+            | Expr::Try { expr: operand }
+            | Expr::Print {
+                message: operand, ..
+            } => self.resolve_expr(operand)?,
+            Expr::Block(stmts) => self.resolve_block(stmts)?,
+        }
+        Ok(())
+    }
+
+    /// Bind the variables a pattern introduces in the innermost scope
+    fn bind_pattern(&mut self, pattern: &mut Pattern) -> Result<()> {
+        match pattern {
+            Pattern::Variable(name) => *name = self.bind(name, BindingKind::Immutable)?,
+            Pattern::Tuple(patterns) | Pattern::Or(patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern)?;
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for (_, pattern) in fields {
+                    self.bind_pattern(pattern)?;
+                }
+            }
+            Pattern::Some(inner) => self.bind_pattern(inner)?,
+            Pattern::Literal(_) | Pattern::Wildcard | Pattern::None => {}
+        }
+        Ok(())
+    }
+
+    /// Bind `name` in the innermost scope, returning its shell name
+    fn bind(&mut self, name: &str, kind: BindingKind) -> Result<String> {
+        if self.globals.contains_key(name) {
+            return Err(Error::IrGeneration(format!(
+                "Cannot assign to constant '{name}'; constants are readonly globals"
+            )));
+        }
+        let shell_name = self.shell_name(name);
+        self.record(name, &shell_name, self.span);
+        self.scopes.last_mut().expect("scope").insert(
+            name.to_string(),
+            Binding {
+                shell_name: shell_name.clone(),
+                kind,
+            },
+        );
+        Ok(shell_name)
+    }
+
+    /// A shell name for a new binding of `name`: the name itself, unless the
+    /// script already gave it out or it is reserved
+    fn shell_name(&mut self, name: &str) -> String {
+        let free =
+            |candidate: &str| !is_reserved(candidate) && !self.environment.contains(candidate);
+        let shell_name = if free(name) && !self.bound.contains(name) {
+            name.to_string()
+        } else {
+            // No suffix frees a name whose prefix is reserved
+            let base = if has_reserved_prefix(name) {
+                format!("_{name}")
+            } else {
+                name.to_string()
+            };
+            (1..)
+                .map(|n| format!("{base}_{n}"))
+                .find(|candidate| !self.taken.contains(candidate) && free(candidate))
+                .expect("unbounded")
+        };
+        self.taken.insert(shell_name.clone());
+        self.bound.insert(shell_name.clone());
+        shell_name
+    }
+
+    fn record(&mut self, name: &str, shell_name: &str, span: Option<Span>) {
+        self.names.push(NameMapping {
+            function: self.function.clone(),
+            rust_name: name.to_string(),
+            shell_name: shell_name.to_string(),
+            original: span.map(|span| CharPos(span.start)),
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    /// The shell name of the mutable variable an assignment writes to
+    fn assignable(&self, name: &str) -> Result<String> {
+        match self.lookup(name) {
+            Some(binding) if binding.kind == BindingKind::Mutable => Ok(binding.shell_name.clone()),
+            Some(binding) if binding.kind == BindingKind::Constant => Err(Diagnostic::new(
+                "E0133",
+                format!("cannot assign to constant `{name}`"),
+            )
+            .with_help("constants are readonly globals; bind a `let mut` variable instead")
+            .into()),
+            Some(_) => Err(Diagnostic::new(
+                "E0132",
+                format!("cannot assign twice to immutable variable `{name}`"),
+            )
+            .with_help(format!("make the binding mutable: `let mut {name}`"))
+            .into()),
+            None => Err(Diagnostic::new(
+                "E0133",
+                format!("cannot find variable `{name}` in this scope"),
+            )
+            .with_help(format!("declare it first: `let mut {name} = ...;`"))
+            .into()),
+        }
+    }
+}
+
+/// Whether a binding named `name` would change how the script or the
+/// commands it runs behave
+fn is_reserved(name: &str) -> bool {
+    RESERVED_NAMES.contains(&name.to_ascii_uppercase().as_str()) || has_reserved_prefix(name)
+}
+
+fn has_reserved_prefix(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    RESERVED_PREFIXES
+        .iter()
+        .any(|prefix| upper.starts_with(prefix))
+}
+
+/// Record every variable name a statement binds or mentions, and the
+/// environment variables it reads
+fn collect_names(stmt: &Stmt, names: &mut HashSet<String>, environment: &mut HashSet<String>) {
+    let mut stmt = stmt.clone();
+    crate::ast::visitor::transform_stmt_exprs(&mut stmt, &mut |expr| match expr {
+        Expr::Variable(name) => {
+            names.insert(name.clone());
+        }
+        Expr::FunctionCall { name, args }
+            if matches!(
+                stdlib::intrinsic(name),
+                Some(Intrinsic::Var | Intrinsic::VarOr)
+            ) =>
+        {
+            if let Some(Expr::Literal(Literal::Str(variable))) = args.first() {
+                environment.insert(variable.clone());
+            }
+        }
+        _ => {}
+    });
+    collect_bound_names(&stmt, names);
+}
+
+fn collect_bound_names(stmt: &Stmt, names: &mut HashSet<String>) {
+    match stmt {
+        Stmt::Let { name, .. } | Stmt::Assign { name, .. } => {
+            names.insert(name.clone());
+        }
+        Stmt::If {
+            then_block,
+            else_block,
+            ..
+        } => {
+            for stmt in then_block.iter().chain(else_block.iter().flatten()) {
+                collect_bound_names(stmt, names);
+            }
+        }
+        Stmt::Match { arms, .. } => {
+            for arm in arms {
+                collect_pattern_names(&arm.pattern, names);
+                arm.body.iter().for_each(|s| collect_bound_names(s, names));
+            }
+        }
+        Stmt::For { pattern, body, .. } => {
+            collect_pattern_names(pattern, names);
+            body.iter().for_each(|s| collect_bound_names(s, names));
+        }
+        Stmt::While { body, .. } | Stmt::Block(body) => {
+            body.iter().for_each(|s| collect_bound_names(s, names))
+        }
+        Stmt::Spanned { stmt, .. } => collect_bound_names(stmt, names),
+        Stmt::Expr(_) | Stmt::Return(_) | Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+fn collect_pattern_names(pattern: &Pattern, names: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Variable(name) => {
+            names.insert(name.clone());
+        }
+        Pattern::Tuple(patterns) | Pattern::Or(patterns) => patterns
+            .iter()
+            .for_each(|p| collect_pattern_names(p, names)),
+        Pattern::Struct { fields, .. } => fields
+            .iter()
+            .for_each(|(_, p)| collect_pattern_names(p, names)),
+        Pattern::Some(inner) => collect_pattern_names(inner, names),
+        Pattern::Literal(_) | Pattern::Wildcard | Pattern::None => {}
+    }
+}
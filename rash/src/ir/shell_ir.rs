@@ -10,9 +10,50 @@ pub enum ShellIR {
         effects: EffectSet,
     },
 
+    /// Mutable variable assignment: NAME=VALUE, for `let mut`, reassignments
+    /// and bindings that run more than once
+    Assign {
+        name: String,
+        value: ShellValue,
+        effects: EffectSet,
+    },
+
+    /// Bind an `Option` variable, which is unset when it holds no value.
+    /// `value` is a call of a function returning `Option`, whose stdout is
+    /// bound when it succeeds; `None` unsets the variable.
+    LetOption {
+        name: String,
+        value: Option<ShellValue>,
+        effects: EffectSet,
+    },
+
+    /// Readonly global defined before any function runs, from a `const` or `static`
+    Global { name: String, value: ShellValue },
+
     /// Command execution
     Exec { cmd: Command, effects: EffectSet },
 
+    /// Formatted output with printf, on stderr when `stderr` is set. `format`
+    /// is a printf format string with one `%s` for each argument.
+    Printf {
+        format: String,
+        args: Vec<ShellValue>,
+        stderr: bool,
+        effects: EffectSet,
+    },
+
+    /// Call a function returning `Result` (`?`), or run a captured command. A
+    /// failing call returns its status from the enclosing function; otherwise
+    /// `binding`, if any, is bound to the call's stdout. `call` is a
+    /// [`ShellValue::FunctionCall`] or a [`ShellValue::CommandSubst`].
+    Try {
+        binding: Option<String>,
+        /// Whether `binding` is assigned rather than bound readonly
+        mutable: bool,
+        call: ShellValue,
+        effects: EffectSet,
+    },
+
     /// Conditional execution
     If {
         test: ShellValue,
@@ -20,12 +61,62 @@ pub enum ShellIR {
         else_branch: Option<Box<ShellIR>>,
     },
 
+    /// Bounded loop over a range or a list of words
+    For {
+        var: String,
+        iter: ForIter,
+        body: Box<ShellIR>,
+        max_iterations: u32,
+    },
+
+    /// Bounded while loop
+    While {
+        test: ShellValue,
+        body: Box<ShellIR>,
+        max_iterations: u32,
+    },
+
+    /// Dispatch on a value: case ... in ... esac, or an if/elif chain when
+    /// some arm has a guard
+    Case {
+        scrutinee: ShellValue,
+        arms: Vec<CaseArm>,
+    },
+
+    /// Exit the innermost loop
+    Break,
+
+    /// Skip to the next iteration of the innermost loop
+    Continue,
+
+    /// Shell function definition; parameters are bound from positional arguments
+    Function {
+        name: String,
+        params: Vec<String>,
+        body: Box<ShellIR>,
+    },
+
+    /// Rebuild a list parameter, which callers pass as one word of quoted elements
+    UnpackList { name: String },
+
+    /// Return from the enclosing shell function using its calling convention
+    Return {
+        value: Option<ShellValue>,
+        convention: ReturnConvention,
+    },
+
     /// Exit with code
     Exit { code: u8, message: Option<String> },
 
     /// Sequence of operations
     Sequence(Vec<ShellIR>),
 
+    /// Node produced by the Rust statement at `span`
+    Spanned {
+        span: crate::ast::Span,
+        inner: Box<ShellIR>,
+    },
+
     /// No-op
     Noop,
 }
@@ -34,7 +125,12 @@ impl ShellIR {
     /// Get all effects from this IR node and its children
     pub fn effects(&self) -> EffectSet {
         match self {
-            ShellIR::Let { effects, .. } | ShellIR::Exec { effects, .. } => effects.clone(),
+            ShellIR::Let { effects, .. }
+            | ShellIR::Assign { effects, .. }
+            | ShellIR::LetOption { effects, .. }
+            | ShellIR::Exec { effects, .. }
+            | ShellIR::Printf { effects, .. }
+            | ShellIR::Try { effects, .. } => effects.clone(),
             ShellIR::If {
                 then_branch,
                 else_branch,
@@ -46,10 +142,24 @@ impl ShellIR {
                 }
                 combined
             }
+            ShellIR::For { body, .. } | ShellIR::While { body, .. } => body.effects(),
+            ShellIR::Spanned { inner, .. } => inner.effects(),
+            ShellIR::Case { arms, .. } => arms
+                .iter()
+                .fold(EffectSet::pure(), |acc, arm| acc.union(&arm.body.effects())),
             ShellIR::Sequence(items) => items
                 .iter()
                 .fold(EffectSet::pure(), |acc, item| acc.union(&item.effects())),
-            ShellIR::Exit { .. } | ShellIR::Noop => EffectSet::pure(),
+            // Defining a function has no effects; calling it carries the body's effects.
+            // Globals hold constant values.
+            ShellIR::Function { .. }
+            | ShellIR::Global { .. }
+            | ShellIR::UnpackList { .. }
+            | ShellIR::Return { .. }
+            | ShellIR::Exit { .. }
+            | ShellIR::Break
+            | ShellIR::Continue
+            | ShellIR::Noop => EffectSet::pure(),
         }
     }
 
@@ -57,6 +167,135 @@ impl ShellIR {
     pub fn is_pure(&self) -> bool {
         self.effects().is_pure()
     }
+
+    /// Names of the commands and shell functions this node runs
+    pub fn collect_commands(&self, commands: &mut Vec<String>) {
+        match self {
+            ShellIR::Let { value, .. }
+            | ShellIR::Assign { value, .. }
+            | ShellIR::Global { value, .. }
+            | ShellIR::Try { call: value, .. }
+            | ShellIR::LetOption {
+                value: Some(value), ..
+            } => value.collect_commands(commands),
+            ShellIR::LetOption { value: None, .. } => {}
+            ShellIR::Exec { cmd, .. } => cmd.collect_commands(commands),
+            ShellIR::Printf { args, .. } => {
+                args.iter().for_each(|arg| arg.collect_commands(commands))
+            }
+            ShellIR::If {
+                test,
+                then_branch,
+                else_branch,
+            } => {
+                test.collect_commands(commands);
+                then_branch.collect_commands(commands);
+                if let Some(else_ir) = else_branch {
+                    else_ir.collect_commands(commands);
+                }
+            }
+            ShellIR::For { iter, body, .. } => {
+                match iter {
+                    ForIter::Range { start, end, .. } => {
+                        start.collect_commands(commands);
+                        end.collect_commands(commands);
+                    }
+                    ForIter::Words(words) => words
+                        .iter()
+                        .for_each(|word| word.collect_commands(commands)),
+                }
+                body.collect_commands(commands);
+            }
+            ShellIR::While { test, body, .. } => {
+                test.collect_commands(commands);
+                body.collect_commands(commands);
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                scrutinee.collect_commands(commands);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        guard.collect_commands(commands);
+                    }
+                    arm.body.collect_commands(commands);
+                }
+            }
+            ShellIR::Function { body, .. } => body.collect_commands(commands),
+            ShellIR::Return {
+                value: Some(value), ..
+            } => value.collect_commands(commands),
+            ShellIR::Sequence(items) => items
+                .iter()
+                .for_each(|item| item.collect_commands(commands)),
+            ShellIR::Spanned { inner, .. } => inner.collect_commands(commands),
+            ShellIR::UnpackList { .. }
+            | ShellIR::Return { value: None, .. }
+            | ShellIR::Exit { .. }
+            | ShellIR::Break
+            | ShellIR::Continue
+            | ShellIR::Noop => {}
+        }
+    }
+}
+
+/// How a shell function hands its result back to the caller
+///
+/// Shell functions cannot return values directly. Functions returning strings
+/// or numbers write the value to fd 3, which callers capture with
+/// `$(f 3>&1 >&4)` while fd 4 holds the script's stdout, so that whatever else
+/// the function prints still reaches stdout. Runtime library functions print
+/// their value on stdout itself. Functions returning `bool` report the result
+/// through their exit status, so callers can use the call directly as an `if`
+/// condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReturnConvention {
+    /// No value; `return` only ends the function
+    Void,
+    /// Value written to fd 3, or printed on stdout by runtime library functions
+    Stdout,
+    /// Value encoded as exit status: 0 for true, 1 for false
+    ExitStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ForIter {
+    /// Integer range: start..end, or start..=end when inclusive
+    Range {
+        start: ShellValue,
+        end: ShellValue,
+        inclusive: bool,
+    },
+
+    /// Fixed list of words, each quoted separately
+    Words(Vec<ShellValue>),
+}
+
+/// One arm of a `case` statement
+///
+/// An arm whose guard fails does not end the dispatch: the remaining arms are
+/// still tried, as with Rust match guards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseArm {
+    /// Alternatives; the arm is taken when any of them matches
+    pub patterns: Vec<CasePattern>,
+    /// Variable bound to the scrutinee before the guard and body run
+    pub binding: Option<String>,
+    pub guard: Option<ShellValue>,
+    pub body: Box<ShellIR>,
+}
+
+impl CaseArm {
+    /// Whether this arm matches every value
+    pub fn is_catch_all(&self) -> bool {
+        self.guard.is_none() && self.patterns.contains(&CasePattern::Wildcard)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CasePattern {
+    /// Matches exactly this string
+    Literal(String),
+    /// Matches anything (`*`)
+    Wildcard,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +321,13 @@ impl Command {
         self.args.extend(args);
         self
     }
+
+    fn collect_commands(&self, commands: &mut Vec<String>) {
+        commands.push(self.program.clone());
+        self.args
+            .iter()
+            .for_each(|arg| arg.collect_commands(commands));
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -100,6 +346,158 @@ pub enum ShellValue {
 
     /// Command substitution
     CommandSubst(Command),
+
+    /// Whether a command exits with status 0
+    CommandStatus(Command),
+
+    /// Result of calling a user-defined shell function
+    FunctionCall {
+        name: String,
+        args: Vec<ShellValue>,
+        convention: ReturnConvention,
+    },
+
+    /// Integer arithmetic with u32 wrapping semantics, emitted as $((...))
+    Arithmetic {
+        op: ArithmeticOp,
+        left: Box<ShellValue>,
+        right: Box<ShellValue>,
+    },
+
+    /// String or integer comparison, emitted as [ ... ]
+    Comparison {
+        op: ComparisonOp,
+        left: Box<ShellValue>,
+        right: Box<ShellValue>,
+    },
+
+    /// Short-circuiting combination of two conditions
+    Logical {
+        op: LogicalOp,
+        left: Box<ShellValue>,
+        right: Box<ShellValue>,
+    },
+
+    /// Negated condition
+    Not(Box<ShellValue>),
+
+    /// String with its letters converted to one case
+    ChangeCase {
+        value: Box<ShellValue>,
+        case: LetterCase,
+    },
+
+    /// List of words. A `Let` binds it as a list and command arguments take its
+    /// elements as separate words; anywhere else it is one word of quoted elements.
+    List(Vec<ShellValue>),
+
+    /// Every element of a list variable, as separate words
+    ListElements(String),
+
+    /// Number of elements in a list variable
+    ListLength(String),
+
+    /// Element of a list variable; out-of-range indices abort the script
+    ListItem {
+        list: String,
+        index: Box<ShellValue>,
+    },
+
+    /// Whether an `Option` holds a value. The option is a variable, set when
+    /// it does, or a call of a function returning `Option`, which succeeds when it does.
+    IsSome(Box<ShellValue>),
+
+    /// The value an `Option` (as in [`ShellValue::IsSome`]) holds, or `default`
+    UnwrapOr {
+        option: Box<ShellValue>,
+        default: Box<ShellValue>,
+    },
+
+    /// Environment variable, empty when unset
+    EnvVar(String),
+
+    /// Positional parameter of the script, counting from 1, empty when absent
+    Arg(u32),
+
+    /// Every positional parameter of the script, as separate words
+    Args,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LetterCase {
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArithmeticOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl ArithmeticOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ArithmeticOp::Add => "+",
+            ArithmeticOp::Sub => "-",
+            ArithmeticOp::Mul => "*",
+            ArithmeticOp::Div => "/",
+        }
+    }
+
+    /// Evaluate with u32 semantics, returning `None` on overflow or division by zero
+    pub fn checked_apply(&self, left: u32, right: u32) -> Option<u32> {
+        match self {
+            ArithmeticOp::Add => left.checked_add(right),
+            ArithmeticOp::Sub => left.checked_sub(right),
+            ArithmeticOp::Mul => left.checked_mul(right),
+            ArithmeticOp::Div => left.checked_div(right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComparisonOp {
+    /// String equality (`=`)
+    StrEq,
+    /// String inequality (`!=`)
+    StrNe,
+    /// Integer equality (`-eq`)
+    NumEq,
+    /// Integer inequality (`-ne`)
+    NumNe,
+    /// Integer less-than (`-lt`)
+    Lt,
+    /// Integer less-or-equal (`-le`)
+    Le,
+    /// Integer greater-than (`-gt`)
+    Gt,
+    /// Integer greater-or-equal (`-ge`)
+    Ge,
+}
+
+impl ComparisonOp {
+    /// The operator as spelled by the `test` utility
+    pub fn test_operator(&self) -> &'static str {
+        match self {
+            ComparisonOp::StrEq => "=",
+            ComparisonOp::StrNe => "!=",
+            ComparisonOp::NumEq => "-eq",
+            ComparisonOp::NumNe => "-ne",
+            ComparisonOp::Lt => "-lt",
+            ComparisonOp::Le => "-le",
+            ComparisonOp::Gt => "-gt",
+            ComparisonOp::Ge => "-ge",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogicalOp {
+    And,
+    Or,
 }
 
 impl ShellValue {
@@ -107,8 +505,26 @@ impl ShellValue {
     pub fn is_constant(&self) -> bool {
         match self {
             ShellValue::String(_) | ShellValue::Bool(_) => true,
-            ShellValue::Variable(_) | ShellValue::CommandSubst(_) => false,
+            ShellValue::Variable(_)
+            | ShellValue::CommandSubst(_)
+            | ShellValue::CommandStatus(_)
+            | ShellValue::FunctionCall { .. }
+            | ShellValue::ListElements(_)
+            | ShellValue::ListLength(_)
+            | ShellValue::ListItem { .. }
+            | ShellValue::IsSome(_)
+            | ShellValue::UnwrapOr { .. }
+            | ShellValue::EnvVar(_)
+            | ShellValue::Arg(_)
+            | ShellValue::Args => false,
+            ShellValue::List(elements) => elements.iter().all(|e| e.is_constant()),
             ShellValue::Concat(parts) => parts.iter().all(|p| p.is_constant()),
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::Comparison { left, right, .. }
+            | ShellValue::Logical { left, right, .. } => left.is_constant() && right.is_constant(),
+            ShellValue::Not(operand) | ShellValue::ChangeCase { value: operand, .. } => {
+                operand.is_constant()
+            }
         }
     }
 
@@ -139,6 +555,44 @@ impl ShellValue {
             _ => None,
         }
     }
+
+    /// Names of the commands and shell functions evaluating this value runs
+    pub fn collect_commands(&self, commands: &mut Vec<String>) {
+        match self {
+            ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => {
+                cmd.collect_commands(commands)
+            }
+            ShellValue::FunctionCall { name, args, .. } => {
+                commands.push(name.clone());
+                args.iter().for_each(|arg| arg.collect_commands(commands));
+            }
+            ShellValue::Concat(values) | ShellValue::List(values) => values
+                .iter()
+                .for_each(|value| value.collect_commands(commands)),
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::Comparison { left, right, .. }
+            | ShellValue::Logical { left, right, .. }
+            | ShellValue::UnwrapOr {
+                option: left,
+                default: right,
+            } => {
+                left.collect_commands(commands);
+                right.collect_commands(commands);
+            }
+            ShellValue::Not(operand)
+            | ShellValue::ChangeCase { value: operand, .. }
+            | ShellValue::ListItem { index: operand, .. }
+            | ShellValue::IsSome(operand) => operand.collect_commands(commands),
+            ShellValue::String(_)
+            | ShellValue::Bool(_)
+            | ShellValue::Variable(_)
+            | ShellValue::ListElements(_)
+            | ShellValue::ListLength(_)
+            | ShellValue::EnvVar(_)
+            | ShellValue::Arg(_)
+            | ShellValue::Args => {}
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,147 @@
+//! Functions of the typed `bashrs-std` crate, as seen by the converter
+//!
+//! Programs call them through their module (`fs::mkdir_p(dir)`); each call
+//! becomes a call to the runtime helper that implements it in the script.
+
+use super::ReturnConvention::{self, ExitStatus, Void};
+use super::{Effect, EffectSet};
+
+/// A `bashrs-std` function and the runtime helper behind it
+#[derive(Debug)]
+pub struct StdlibFunction {
+    /// Path below the crate root, such as `fs::mkdir_p`
+    pub path: &'static str,
+    /// Runtime helper called in its place
+    pub helper: &'static str,
+    pub arity: usize,
+    pub convention: ReturnConvention,
+    effects: &'static [Effect],
+}
+
+impl StdlibFunction {
+    pub fn effects(&self) -> EffectSet {
+        self.effects.to_vec().into()
+    }
+}
+
+const fn function(
+    path: &'static str,
+    helper: &'static str,
+    arity: usize,
+    convention: ReturnConvention,
+    effects: &'static [Effect],
+) -> StdlibFunction {
+    StdlibFunction {
+        path,
+        helper,
+        arity,
+        convention,
+        effects,
+    }
+}
+
+static FUNCTIONS: &[StdlibFunction] = &[
+    function("fs::mkdir_p", "rash_mkdir_p", 1, Void, &[Effect::FileWrite]),
+    function(
+        "fs::file_exists",
+        "rash_file_exists",
+        1,
+        ExitStatus,
+        &[Effect::FileRead],
+    ),
+    function(
+        "fs::dir_exists",
+        "rash_dir_exists",
+        1,
+        ExitStatus,
+        &[Effect::FileRead],
+    ),
+    function("fs::chmod", "rash_chmod", 2, Void, &[Effect::FileWrite]),
+    function(
+        "net::download_verified",
+        "rash_download_verified",
+        3,
+        Void,
+        &[Effect::NetworkAccess, Effect::FileWrite],
+    ),
+    function("log::info", "rash_log_info", 1, Void, &[]),
+    function("log::warn", "rash_log_warn", 1, Void, &[]),
+    function("log::error", "rash_log_error", 1, Void, &[]),
+    function("log::debug", "rash_log_debug", 1, Void, &[Effect::EnvRead]),
+    function(
+        "sys::has_command",
+        "rash_has_command",
+        1,
+        ExitStatus,
+        &[Effect::EnvRead],
+    ),
+    function(
+        "sys::require_command",
+        "rash_require_command",
+        1,
+        Void,
+        &[Effect::EnvRead],
+    ),
+];
+
+/// A `bashrs-std` function the converter lowers to a parameter expansion
+/// rather than a runtime helper call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    /// `env::var(name)`: the variable as an `Option<String>`
+    Var,
+    /// `env::var_or(name, default)`: the variable, or `default` if unset or empty
+    VarOr,
+    /// `env::args()`: the script's arguments as a list
+    Args,
+    /// `env::arg(n)`: the `n`th argument, counting from 1, as an `Option<String>`
+    Arg,
+    /// `process::capture(program, args)`: the command's stdout, without
+    /// trailing newlines
+    Capture,
+    /// `process::status(program, args)`: whether the command succeeds
+    Status,
+    /// `sanitize::path(value)`: the value, once checked to be safe to remove
+    /// recursively
+    SanitizePath,
+    /// `sanitize::word(value)`: the value, once checked to be safe as a
+    /// command name or in evaluated code
+    SanitizeWord,
+}
+
+impl Intrinsic {
+    /// The runtime helper that checks a sanitizer's argument
+    pub fn sanitizer_helper(self) -> Option<&'static str> {
+        match self {
+            Intrinsic::SanitizePath => Some("rash_sanitize_path"),
+            Intrinsic::SanitizeWord => Some("rash_sanitize_word"),
+            _ => None,
+        }
+    }
+}
+
+/// Look up an intrinsic such as `env::var` or `bashrs_std::process::capture`
+pub fn intrinsic(name: &str) -> Option<Intrinsic> {
+    match name.strip_prefix("bashrs_std::").unwrap_or(name) {
+        "env::var" => Some(Intrinsic::Var),
+        "env::var_or" => Some(Intrinsic::VarOr),
+        "env::args" => Some(Intrinsic::Args),
+        "env::arg" => Some(Intrinsic::Arg),
+        "process::capture" => Some(Intrinsic::Capture),
+        "process::status" => Some(Intrinsic::Status),
+        "sanitize::path" => Some(Intrinsic::SanitizePath),
+        "sanitize::word" => Some(Intrinsic::SanitizeWord),
+        _ => None,
+    }
+}
+
+/// Look up a call such as `fs::mkdir_p` or `bashrs_std::fs::mkdir_p`
+pub fn lookup(name: &str) -> Option<&'static StdlibFunction> {
+    let path = name.strip_prefix("bashrs_std::").unwrap_or(name);
+    FUNCTIONS.iter().find(|function| function.path == path)
+}
+
+/// The function implemented by runtime helper `helper`, if any
+pub fn by_helper(helper: &str) -> Option<&'static StdlibFunction> {
+    FUNCTIONS.iter().find(|function| function.helper == helper)
+}
@@ -14,9 +14,12 @@ fn test_simple_ast_to_ir_conversion() {
             body: vec![Stmt::Let {
                 name: "x".to_string(),
                 value: Expr::Literal(Literal::U32(42)),
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -47,8 +50,10 @@ fn test_function_call_to_command() {
                 name: "echo".to_string(),
                 args: vec![Expr::Literal(Literal::Str("hello".to_string()))],
             })],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -214,14 +219,18 @@ fn test_if_statement_conversion() {
                 then_block: vec![Stmt::Let {
                     name: "result".to_string(),
                     value: Expr::Literal(Literal::Str("true_branch".to_string())),
+                    mutable: false,
                 }],
                 else_block: Some(vec![Stmt::Let {
                     name: "result".to_string(),
                     value: Expr::Literal(Literal::Str("false_branch".to_string())),
+                    mutable: false,
                 }]),
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -256,8 +265,10 @@ fn test_return_statement_conversion() {
             body: vec![Stmt::Return(Some(Expr::Literal(Literal::Str(
                 "success".to_string(),
             ))))],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -291,9 +302,12 @@ fn test_binary_expression_conversion() {
                     left: Box::new(Expr::Literal(Literal::Str("hello".to_string()))),
                     right: Box::new(Expr::Literal(Literal::Str(" world".to_string()))),
                 },
+                mutable: false,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -398,6 +412,7 @@ fn test_error_handling_in_conversion() {
     let empty_ast = RestrictedAst {
         functions: vec![],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     assert!(from_ast(&empty_ast).is_err());
@@ -417,13 +432,16 @@ fn test_complex_nested_structures() {
                     then_block: vec![Stmt::Let {
                         name: "nested".to_string(),
                         value: Expr::Literal(Literal::Str("deep".to_string())),
+                        mutable: false,
                     }],
                     else_block: None,
                 }],
                 else_block: None,
             }],
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
 
     let ir = from_ast(&ast).unwrap();
@@ -431,3 +449,1671 @@ fn test_complex_nested_structures() {
     // Should handle nested structures without panicking
     assert!(ir.effects().is_pure()); // Only let statements, should be pure
 }
+
+#[test]
+fn test_for_range_to_ir() {
+    use crate::ast::restricted::Pattern;
+
+    let ast = RestrictedAst {
+        functions: vec![Function {
+            name: "main".to_string(),
+            params: vec![],
+            return_type: Type::Void,
+            body: vec![Stmt::For {
+                pattern: Pattern::Variable("i".to_string()),
+                iter: Expr::Range {
+                    start: Box::new(Expr::Literal(Literal::U32(0))),
+                    end: Box::new(Expr::Variable("n".to_string())),
+                    inclusive: false,
+                },
+                body: vec![Stmt::Expr(Expr::FunctionCall {
+                    name: "echo".to_string(),
+                    args: vec![Expr::Variable("i".to_string())],
+                })],
+                max_iterations: Some(100),
+            }],
+            effects: None,
+        }],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    let ir = from_ast(&ast).unwrap();
+    match ir {
+        ShellIR::Sequence(stmts) => match &stmts[0] {
+            ShellIR::For {
+                var,
+                iter: ForIter::Range { inclusive, .. },
+                max_iterations,
+                ..
+            } => {
+                assert_eq!(var, "i");
+                assert!(!inclusive);
+                assert_eq!(*max_iterations, 100);
+            }
+            other => panic!("Expected range loop, got {other:?}"),
+        },
+        _ => panic!("Expected Sequence"),
+    }
+}
+
+#[test]
+fn test_for_loop_exceeding_bound_is_rejected() {
+    use crate::ast::restricted::Pattern;
+
+    let ast = RestrictedAst {
+        functions: vec![Function {
+            name: "main".to_string(),
+            params: vec![],
+            return_type: Type::Void,
+            body: vec![Stmt::For {
+                pattern: Pattern::Variable("pkg".to_string()),
+                iter: Expr::Array(vec![
+                    Expr::Literal(Literal::Str("curl".to_string())),
+                    Expr::Literal(Literal::Str("git".to_string())),
+                ]),
+                body: vec![],
+                max_iterations: Some(1),
+            }],
+            effects: None,
+        }],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    assert!(from_ast(&ast).is_err());
+}
+
+#[test]
+fn test_while_loop_to_ir() {
+    let ast = RestrictedAst {
+        functions: vec![Function {
+            name: "main".to_string(),
+            params: vec![],
+            return_type: Type::Void,
+            body: vec![Stmt::While {
+                condition: Expr::Literal(Literal::Bool(true)),
+                body: vec![Stmt::Continue, Stmt::Break],
+                max_iterations: Some(3),
+            }],
+            effects: None,
+        }],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    let ir = from_ast(&ast).unwrap();
+    match ir {
+        ShellIR::Sequence(stmts) => match &stmts[0] {
+            ShellIR::While {
+                body,
+                max_iterations,
+                ..
+            } => {
+                assert_eq!(*max_iterations, 3);
+                match body.as_ref() {
+                    ShellIR::Sequence(items) => {
+                        assert!(matches!(items[0], ShellIR::Continue));
+                        assert!(matches!(items[1], ShellIR::Break));
+                    }
+                    other => panic!("Expected loop body, got {other:?}"),
+                }
+            }
+            other => panic!("Expected while loop, got {other:?}"),
+        },
+        _ => panic!("Expected Sequence"),
+    }
+}
+
+#[test]
+fn test_break_outside_loop_is_rejected() {
+    let ast = RestrictedAst {
+        functions: vec![Function {
+            name: "main".to_string(),
+            params: vec![],
+            return_type: Type::Void,
+            body: vec![Stmt::Break],
+            effects: None,
+        }],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    let err = from_ast(&ast).unwrap_err();
+    assert!(err.to_string().contains("'break' outside of a loop"));
+}
+
+#[test]
+fn test_user_functions_become_shell_functions() {
+    use crate::ast::restricted::Parameter;
+
+    let ast = RestrictedAst {
+        functions: vec![
+            Function {
+                name: "main".to_string(),
+                params: vec![],
+                return_type: Type::Void,
+                body: vec![Stmt::Expr(Expr::FunctionCall {
+                    name: "install".to_string(),
+                    args: vec![Expr::Literal(Literal::Str("/opt".to_string()))],
+                })],
+                effects: None,
+            },
+            Function {
+                name: "install".to_string(),
+                params: vec![Parameter {
+                    name: "prefix".to_string(),
+                    param_type: Type::Str,
+                }],
+                return_type: Type::Void,
+                body: vec![Stmt::Expr(Expr::FunctionCall {
+                    name: "curl".to_string(),
+                    args: vec![Expr::Variable("prefix".to_string())],
+                })],
+                effects: None,
+            },
+            // Empty-bodied functions declare external commands
+            Function {
+                name: "curl".to_string(),
+                params: vec![Parameter {
+                    name: "url".to_string(),
+                    param_type: Type::Str,
+                }],
+                return_type: Type::Void,
+                body: vec![],
+                effects: None,
+            },
+        ],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    let ir = from_ast(&ast).unwrap();
+    let ShellIR::Sequence(stmts) = ir else {
+        panic!("Expected Sequence");
+    };
+    assert_eq!(stmts.len(), 2);
+
+    match &stmts[0] {
+        ShellIR::Function { name, params, .. } => {
+            assert_eq!(name, "install");
+            assert_eq!(params, &vec!["prefix".to_string()]);
+        }
+        other => panic!("Expected function definition, got {other:?}"),
+    }
+
+    // The call site inherits the effects of the function body
+    match &stmts[1] {
+        ShellIR::Exec { cmd, effects } => {
+            assert_eq!(cmd.program, "install");
+            assert!(effects.has_network_effects());
+        }
+        other => panic!("Expected call, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_function_return_conventions() {
+    let ast = RestrictedAst {
+        functions: vec![
+            Function {
+                name: "main".to_string(),
+                params: vec![],
+                return_type: Type::Void,
+                body: vec![
+                    Stmt::Let {
+                        name: "v".to_string(),
+                        value: Expr::FunctionCall {
+                            name: "version".to_string(),
+                            args: vec![],
+                        },
+                        mutable: false,
+                    },
+                    Stmt::If {
+                        condition: Expr::FunctionCall {
+                            name: "ready".to_string(),
+                            args: vec![],
+                        },
+                        then_block: vec![],
+                        else_block: None,
+                    },
+                ],
+                effects: None,
+            },
+            Function {
+                name: "version".to_string(),
+                params: vec![],
+                return_type: Type::Str,
+                body: vec![Stmt::Return(Some(Expr::Literal(Literal::Str(
+                    "1.2.3".to_string(),
+                ))))],
+                effects: None,
+            },
+            Function {
+                name: "ready".to_string(),
+                params: vec![],
+                return_type: Type::Bool,
+                body: vec![Stmt::Return(Some(Expr::Literal(Literal::Bool(true))))],
+                effects: None,
+            },
+        ],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    let ShellIR::Sequence(stmts) = from_ast(&ast).unwrap() else {
+        panic!("Expected Sequence");
+    };
+
+    match &stmts[0] {
+        ShellIR::Function { body, .. } => match body.as_ref() {
+            ShellIR::Sequence(items) => assert!(matches!(
+                items[0],
+                ShellIR::Return {
+                    value: Some(_),
+                    convention: ReturnConvention::Stdout
+                }
+            )),
+            other => panic!("Expected function body, got {other:?}"),
+        },
+        other => panic!("Expected function definition, got {other:?}"),
+    }
+
+    // Binding the result stops the script when the call fails
+    assert!(matches!(
+        &stmts[2],
+        ShellIR::Try {
+            binding: Some(_),
+            call: ShellValue::FunctionCall {
+                convention: ReturnConvention::Stdout,
+                ..
+            },
+            ..
+        }
+    ));
+    assert!(matches!(
+        &stmts[3],
+        ShellIR::If {
+            test: ShellValue::FunctionCall {
+                convention: ReturnConvention::ExitStatus,
+                ..
+            },
+            ..
+        }
+    ));
+}
+
+fn collect_returns<'a>(ir: &'a ShellIR, returns: &mut Vec<&'a ShellIR>) {
+    match ir {
+        ShellIR::Return { .. } => returns.push(ir),
+        ShellIR::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            collect_returns(then_branch, returns);
+            if let Some(else_branch) = else_branch {
+                collect_returns(else_branch, returns);
+            }
+        }
+        ShellIR::Case { arms, .. } => arms
+            .iter()
+            .for_each(|arm| collect_returns(&arm.body, returns)),
+        ShellIR::Sequence(items) => items.iter().for_each(|item| collect_returns(item, returns)),
+        ShellIR::Spanned { inner, .. } => collect_returns(inner, returns),
+        _ => {}
+    }
+}
+
+#[rstest]
+#[case(
+    "fn f(x: u32) -> bool { if x > 3 { true } else { false } } fn main() { if f(1) {} }",
+    ReturnConvention::ExitStatus,
+    2
+)]
+#[case(
+    r#"fn f(x: u32) -> String { if x > 3 { "big" } else if x > 1 { "mid" } else { "small" } } fn main() { let s = f(1); }"#,
+    ReturnConvention::Stdout,
+    3
+)]
+#[case(
+    r#"fn f(x: u32) -> String { match x { 1 => "one", _ => { let s = "many"; s } } } fn main() { let s = f(1); }"#,
+    ReturnConvention::Stdout,
+    2
+)]
+fn test_tail_branches_return_their_values(
+    #[case] source: &str,
+    #[case] expected: ReturnConvention,
+    #[case] count: usize,
+) {
+    let ast = crate::services::parse(source).unwrap();
+    let ShellIR::Sequence(stmts) = from_ast(&ast).unwrap() else {
+        panic!("Expected Sequence");
+    };
+    let Some(ShellIR::Function { body, .. }) = stmts.first() else {
+        panic!("Expected function definition, got {stmts:?}");
+    };
+
+    let mut returns = Vec::new();
+    collect_returns(body, &mut returns);
+    assert_eq!(returns.len(), count, "{body:?}");
+    for ret in returns {
+        assert!(
+            matches!(ret, ShellIR::Return { value: Some(_), convention } if *convention == expected),
+            "{ret:?}"
+        );
+    }
+}
+
+#[rstest]
+#[case("fn main() { 1; }")]
+#[case(r#"fn main() { let s = "a"; s; }"#)]
+#[case("fn main() { let n = 1; n + 1; }")]
+fn test_unused_expression_statements_are_rejected(#[case] source: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains("is unused"), "{err}");
+}
+
+#[rstest]
+#[case(r#"fn get() -> String { "x" } fn main() { println!("v={}", get()); }"#)]
+#[case("fn num() -> u32 { 2 } fn main() { let n = num() + 1; }")]
+#[case(r#"fn get() -> String { "x" } fn main() { if get() == "x" {} }"#)]
+#[case(r#"fn get() -> String { "x" } fn show(s: &str) {} fn main() { show(get()); }"#)]
+#[case("fn get() -> String { \"x\" } fn main() { match get() { _ => {} } }")]
+#[case(r#"fn ok() -> bool { true } fn main() { println!("{}", ok()); }"#)]
+#[case(r#"use bashrs_std::process; fn main() { println!("{}", process::status("true", [])); }"#)]
+fn test_nested_function_results_are_rejected(#[case] source: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(
+        err.to_string().contains("can only be bound with `let`"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_returning_a_call_forwards_its_value() {
+    let source = r#"
+        fn inner() -> String { "x" }
+        fn outer() -> String { inner() }
+        fn main() { let v = outer(); }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let ShellIR::Sequence(stmts) = from_ast(&ast).unwrap() else {
+        panic!("Expected Sequence");
+    };
+    let outer = stmts
+        .iter()
+        .find(|stmt| matches!(stmt, ShellIR::Function { name, .. } if name == "outer"))
+        .unwrap();
+
+    let mut returns = Vec::new();
+    let ShellIR::Function { body, .. } = outer else {
+        unreachable!()
+    };
+    collect_returns(body, &mut returns);
+    assert!(
+        matches!(
+            returns.as_slice(),
+            [ShellIR::Return {
+                value: Some(ShellValue::Bool(true)),
+                convention: ReturnConvention::ExitStatus,
+            }]
+        ),
+        "{body:?}"
+    );
+}
+
+#[test]
+fn test_void_function_cannot_return_value() {
+    let ast = RestrictedAst {
+        functions: vec![
+            Function {
+                name: "main".to_string(),
+                params: vec![],
+                return_type: Type::Void,
+                body: vec![],
+                effects: None,
+            },
+            Function {
+                name: "helper".to_string(),
+                params: vec![],
+                return_type: Type::Void,
+                body: vec![Stmt::Return(Some(Expr::Literal(Literal::U32(1))))],
+                effects: None,
+            },
+        ],
+        entry_point: "main".to_string(),
+        constants: vec![],
+    };
+
+    assert!(from_ast(&ast).is_err());
+}
+
+fn main_body_ir(source: &str) -> Result<Vec<ShellIR>> {
+    let ast = crate::services::parse(source)?;
+    match from_ast(&ast)? {
+        ShellIR::Sequence(stmts) => Ok(stmts
+            .into_iter()
+            .map(|stmt| match stmt {
+                ShellIR::Spanned { inner, .. } => *inner,
+                other => other,
+            })
+            .collect()),
+        other => panic!("Expected sequence, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_integer_addition_becomes_arithmetic() {
+    let stmts = main_body_ir("fn main() { let x = 1; let y = x + 2; }").unwrap();
+
+    assert!(matches!(
+        &stmts[1],
+        ShellIR::Let {
+            value: ShellValue::Arithmetic {
+                op: ArithmeticOp::Add,
+                ..
+            },
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_constant_arithmetic_is_folded() {
+    let stmts = main_body_ir("fn main() { let x = 6 * 7; }").unwrap();
+
+    assert!(matches!(
+        &stmts[0],
+        ShellIR::Let { value: ShellValue::String(s), .. } if s == "42"
+    ));
+}
+
+#[rstest]
+#[case("fn main() { let x = 4294967295 + 1; }")]
+#[case("fn main() { let x = 1 - 2; }")]
+#[case("fn main() { let x = 65536 * 65536; }")]
+#[case("fn main() { let x = 1; let y = x / 0; }")]
+fn test_constant_overflow_is_rejected(#[case] source: &str) {
+    let error = main_body_ir(source).unwrap_err();
+    assert!(matches!(error.unlocated(), Error::IrGeneration(_)));
+}
+
+#[test]
+fn test_comparison_operators_follow_operand_types() {
+    let stmts = main_body_ir(
+        r#"fn main() { let n = 3; let s = "a"; let a = n == 3; let b = s != "b"; let c = n <= 4; }"#,
+    )
+    .unwrap();
+
+    let ops: Vec<_> = stmts[2..]
+        .iter()
+        .map(|stmt| match stmt {
+            ShellIR::Let {
+                value: ShellValue::Comparison { op, .. },
+                ..
+            } => *op,
+            other => panic!("Expected comparison, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        vec![ComparisonOp::NumEq, ComparisonOp::StrNe, ComparisonOp::Le]
+    );
+}
+
+#[test]
+fn test_logical_operators_and_boolean_variables() {
+    let stmts =
+        main_body_ir("fn main() { let ok = true; if !ok || 1 > 2 { let x = 1; } }").unwrap();
+
+    match &stmts[1] {
+        ShellIR::If {
+            test:
+                ShellValue::Logical {
+                    op: LogicalOp::Or,
+                    left,
+                    ..
+                },
+            ..
+        } => match left.as_ref() {
+            ShellValue::Not(operand) => assert!(matches!(
+                operand.as_ref(),
+                ShellValue::Comparison {
+                    op: ComparisonOp::StrEq,
+                    ..
+                }
+            )),
+            other => panic!("Expected negation, got {other:?}"),
+        },
+        other => panic!("Expected logical test, got {other:?}"),
+    }
+}
+
+#[rstest]
+#[case(r#"fn main() { let s = "a"; let b = s < "b"; }"#)]
+#[case(r#"fn main() { let s = "a"; let n = s - 1; }"#)]
+#[case("fn main() { let n = 1; let b = n && true; }")]
+#[case(r#"fn main() { let s = "a"; let t = true + s; }"#)]
+fn test_ill_typed_operators_are_rejected(#[case] source: &str) {
+    assert!(main_body_ir(source).is_err());
+}
+
+#[rstest]
+#[case(r#"fn main() { let s = "v"; let t = s + 1; }"#)]
+#[case(r#"fn main() { let n = 2; let t = "v" + n; }"#)]
+fn test_adding_integers_to_strings_is_rejected(#[case] source: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Operator '+' cannot add an integer to a string"),
+        "{err}"
+    );
+    assert!(err.to_string().contains("format!"), "{err}");
+}
+
+#[test]
+fn test_match_lowers_to_case() {
+    let stmts = main_body_ir(
+        r#"fn main() { let os = "linux"; match os { "linux" | "freebsd" => {} v if v == "x" => {} _ => {} } }"#,
+    )
+    .unwrap();
+
+    match &stmts[1] {
+        ShellIR::Case { arms, .. } => {
+            assert_eq!(
+                arms[0].patterns,
+                vec![
+                    CasePattern::Literal("linux".to_string()),
+                    CasePattern::Literal("freebsd".to_string())
+                ]
+            );
+            assert_eq!(arms[1].binding.as_deref(), Some("v"));
+            assert!(arms[1].guard.is_some());
+            assert!(arms[2].is_catch_all());
+        }
+        other => panic!("Expected case statement, got {other:?}"),
+    }
+}
+
+#[rstest]
+#[case(r#"fn main() { let os = "linux"; match os { "linux" => {} } }"#, false)]
+#[case(r#"fn main() { let n = 1; match n { x if x > 1 => {} } }"#, false)]
+#[case("fn main() { let b = true; match b { true => {} false => {} } }", true)]
+#[case("fn main() { let n = 1; match n { 1 => {} _ => {} } }", true)]
+fn test_match_exhaustiveness(#[case] source: &str, #[case] exhaustive: bool) {
+    assert_eq!(main_body_ir(source).is_ok(), exhaustive);
+}
+
+#[test]
+fn test_match_pattern_type_mismatch_is_rejected() {
+    assert!(main_body_ir(r#"fn main() { let n = 1; match n { "one" => {} _ => {} } }"#).is_err());
+}
+
+#[test]
+fn test_case_conversion_methods() {
+    let stmts =
+        main_body_ir(r#"fn main() { let a = "MiXed".to_lowercase(); let b = a.to_uppercase(); }"#)
+            .unwrap();
+
+    // Literals are converted at compile time
+    assert!(matches!(
+        &stmts[0],
+        ShellIR::Let { value: ShellValue::String(s), .. } if s == "mixed"
+    ));
+    assert!(matches!(
+        &stmts[1],
+        ShellIR::Let {
+            value: ShellValue::ChangeCase {
+                case: LetterCase::Upper,
+                ..
+            },
+            ..
+        }
+    ));
+
+    let err = main_body_ir("fn main() { let n = 1; let s = n.to_lowercase(); }").unwrap_err();
+    assert!(err.to_string().contains("requires a string receiver"));
+}
+
+#[test]
+fn test_list_binding_and_command_arguments() {
+    let stmts = main_body_ir(
+        r#"use bashrs_std::process;
+           fn main() { let pkgs = vec!["install", "curl"]; process::status("apt-get", pkgs); }"#,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        &stmts[0],
+        ShellIR::Let { value: ShellValue::List(words), .. } if words.len() == 2
+    ));
+    // Commands receive each element as its own argument
+    match &stmts[1] {
+        ShellIR::If {
+            test: ShellValue::CommandStatus(cmd),
+            ..
+        } => {
+            assert_eq!(cmd.program, "apt-get");
+            assert!(matches!(&cmd.args[..], [ShellValue::ListElements(name)] if name == "pkgs"));
+        }
+        other => panic!("Expected command, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_list_length_and_indexing() {
+    let stmts = main_body_ir(
+        r#"fn main() { let xs = ["a", "b"]; let n = xs.len(); let i = 1; let x = xs[i]; let y = ["a", "b"].len(); }"#,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        &stmts[1],
+        ShellIR::Let { value: ShellValue::ListLength(name), .. } if name == "xs"
+    ));
+    assert!(matches!(
+        &stmts[3],
+        ShellIR::Let { value: ShellValue::ListItem { list, .. }, .. } if list == "xs"
+    ));
+    assert!(matches!(
+        &stmts[4],
+        ShellIR::Let { value: ShellValue::String(n), .. } if n == "2"
+    ));
+}
+
+#[test]
+fn test_for_loop_over_list_variable() {
+    let stmts =
+        main_body_ir(r#"fn main() { let xs = ["a", "b"]; for x in xs.iter() { echo(x); } }"#)
+            .unwrap();
+
+    match &stmts[1] {
+        ShellIR::For {
+            iter: ForIter::Words(words),
+            ..
+        } => assert!(matches!(&words[..], [ShellValue::ListElements(name)] if name == "xs")),
+        other => panic!("Expected for loop, got {other:?}"),
+    }
+}
+
+#[rstest]
+#[case(r#"fn main() { let x = ["a"][3]; }"#, "out of bounds")]
+#[case(r#"fn main() { let xs = ["a"]; let s = "p" + xs; }"#, "single value")]
+#[case(
+    r#"fn f() -> Vec<String> { return 1; } fn main() {}"#,
+    "returns a list"
+)]
+#[case(
+    r#"fn main() { let xs = ["a"]; let x = xs["0"]; }"#,
+    "must be integers"
+)]
+fn test_list_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[rstest]
+#[case(
+    r#"fn main() { let v = ["a", "b"]; let s = v.join(","); }"#,
+    "Method 'join' on a list"
+)]
+#[case(
+    r#"fn main() { let v = ["a"]; let it = v.iter(); }"#,
+    "Method 'iter' on a list"
+)]
+#[case(
+    r#"fn main() { let s = "a b"; let t = s.trim(); }"#,
+    "Method 'trim' on a string"
+)]
+#[case(
+    r#"fn main() { let n = 1; let m = n.pow(2); }"#,
+    "Method 'pow' on an integer"
+)]
+fn test_unsupported_methods_are_rejected(#[case] source: &str, #[case] message: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    let diagnostic = from_ast(&ast).unwrap_err().to_diagnostic().unwrap();
+
+    assert_eq!(diagnostic.code, "E0300");
+    assert!(diagnostic.message.contains(message), "{diagnostic:?}");
+    // Located at the statement that calls the method
+    assert!(diagnostic.primary.is_some(), "{diagnostic:?}");
+}
+
+#[test]
+fn test_clone_copies_scalars() {
+    let body = main_body_ir(r#"fn main() { let s = "a"; let t = s.clone(); }"#).unwrap();
+
+    assert!(matches!(
+        &body[1],
+        ShellIR::Let { value: ShellValue::Variable(name), .. } if name == "s"
+    ));
+}
+
+#[test]
+fn test_list_parameters_are_unpacked() {
+    let ast = crate::services::parse(
+        "fn f(xs: &[u32]) { for x in xs { echo(x); } } fn main() { f([1, 2]); } fn echo(x: u32) {}",
+    )
+    .unwrap();
+    let ShellIR::Sequence(items) = from_ast(&ast).unwrap() else {
+        panic!("Expected sequence");
+    };
+
+    match &items[0] {
+        ShellIR::Function { body, .. } => match body.as_ref() {
+            ShellIR::Sequence(stmts) => {
+                assert!(matches!(&stmts[0], ShellIR::UnpackList { name } if name == "xs"))
+            }
+            other => panic!("Expected sequence, got {other:?}"),
+        },
+        other => panic!("Expected function, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_stdlib_calls_become_runtime_helpers() {
+    let stmts = main_body_ir(
+        r#"
+        use bashrs_std::fs;
+        fn main() {
+            fs::mkdir_p("/opt/tool");
+            let ready = fs::dir_exists("/opt/tool");
+        }
+    "#,
+    )
+    .unwrap();
+
+    match &stmts[0] {
+        ShellIR::Exec { cmd, effects } => {
+            assert_eq!(cmd.program, "rash_mkdir_p");
+            assert!(effects.contains(&Effect::FileWrite));
+        }
+        other => panic!("Expected helper call, got {other:?}"),
+    }
+    match &stmts[1] {
+        ShellIR::Let {
+            value: ShellValue::FunctionCall {
+                name, convention, ..
+            },
+            effects,
+            ..
+        } => {
+            assert_eq!(name, "rash_dir_exists");
+            assert_eq!(*convention, ReturnConvention::ExitStatus);
+            assert!(effects.contains(&Effect::FileRead));
+        }
+        other => panic!("Expected captured helper call, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_env_intrinsics_expand_variables_and_arguments() {
+    let stmts = main_body_ir(
+        r#"
+        use bashrs_std::env;
+        fn main() {
+            let prefix = env::var_or("PREFIX", "/usr/local");
+            let home = env::var("HOME");
+            let first = env::arg(1);
+            for arg in env::args() {}
+        }
+    "#,
+    )
+    .unwrap();
+
+    // Main binds the arguments first, while "$@" is still the script's
+    match &stmts[0] {
+        ShellIR::Let {
+            name,
+            value: ShellValue::List(words),
+            effects,
+        } => {
+            assert_eq!(name, "_rash_args");
+            assert!(matches!(words.as_slice(), [ShellValue::Args]));
+            assert!(effects.contains(&Effect::EnvRead));
+        }
+        other => panic!("Expected argument list, got {other:?}"),
+    }
+    match &stmts[1] {
+        ShellIR::If {
+            test: ShellValue::IsSome(option),
+            then_branch,
+            ..
+        } => {
+            assert!(matches!(option.as_ref(), ShellValue::Arg(1)));
+            assert!(matches!(
+                then_branch.as_ref(),
+                ShellIR::Let { name, value: ShellValue::Arg(1), .. } if name == "_rash_arg_1"
+            ));
+        }
+        other => panic!("Expected argument binding, got {other:?}"),
+    }
+    match &stmts[2] {
+        ShellIR::Let {
+            value: ShellValue::UnwrapOr { option, .. },
+            effects,
+            ..
+        } => {
+            assert!(matches!(option.as_ref(), ShellValue::EnvVar(name) if name == "PREFIX"));
+            assert!(effects.contains(&Effect::EnvRead));
+        }
+        other => panic!("Expected defaulted variable, got {other:?}"),
+    }
+    assert!(matches!(
+        &stmts[3],
+        ShellIR::If { test: ShellValue::IsSome(option), .. }
+            if matches!(option.as_ref(), ShellValue::EnvVar(name) if name == "HOME")
+    ));
+    assert!(matches!(
+        &stmts[5],
+        ShellIR::For { iter: ForIter::Words(words), .. }
+            if matches!(words.as_slice(), [ShellValue::ListElements(name)] if name == "_rash_args")
+    ));
+}
+
+#[test]
+fn test_process_intrinsics_capture_output_and_test_status() {
+    let stmts = main_body_ir(
+        r#"
+        use bashrs_std::process;
+        fn main() {
+            let arch = process::capture("curl", ["-fsS", "https://example.com/arch"]);
+            if process::status("grep", ["-q", arch, "/etc/arches"]) {}
+            process::status("false", []);
+        }
+    "#,
+    )
+    .unwrap();
+
+    // A failing command stops main like a failing `?` call
+    match &stmts[0] {
+        ShellIR::Try {
+            binding: Some(name),
+            call: ShellValue::CommandSubst(cmd),
+            effects,
+            ..
+        } => {
+            assert_eq!(name, "arch");
+            assert_eq!(cmd.program, "curl");
+            assert_eq!(cmd.args.len(), 2);
+            assert!(effects.contains(&Effect::NetworkAccess));
+        }
+        other => panic!("Expected captured command, got {other:?}"),
+    }
+    assert!(matches!(
+        &stmts[1],
+        ShellIR::If { test: ShellValue::CommandStatus(cmd), .. } if cmd.program == "grep"
+    ));
+    // An unused status must not stop the script when the command fails
+    assert!(matches!(
+        &stmts[2],
+        ShellIR::If { test: ShellValue::CommandStatus(cmd), then_branch, else_branch: None }
+            if cmd.program == "false" && matches!(then_branch.as_ref(), ShellIR::Noop)
+    ));
+}
+
+#[rstest]
+#[case(
+    r#"fn main() { println!("{}", process::capture("uname", [])); }"#,
+    "can only be bound with `let`"
+)]
+#[case(
+    r#"fn main() { let p = "uname"; let x = process::capture(p, []); }"#,
+    "program as a string literal"
+)]
+#[case(
+    r#"fn main() { let x = process::capture("uname", "-m"); }"#,
+    "arguments as a list"
+)]
+#[case(
+    r#"fn main() { let x = process::status("true"); }"#,
+    "takes 2 argument(s) but 1"
+)]
+fn test_process_intrinsic_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[rstest]
+#[case(r#"fn main() { let x = env::var(name); }"#, "takes a string literal")]
+#[case(
+    r#"fn main() { let x = env::var("A-B"); }"#,
+    "not a valid environment variable"
+)]
+#[case(r#"fn main() { env::var("HOME"); }"#, "is unused")]
+#[case(
+    r#"fn main() { let x = env::var("HOME") + "/bin"; }"#,
+    "returns an `Option`"
+)]
+#[case(r#"fn main() { let x = env::var_or("HOME", 1); }"#, "must be a string")]
+#[case(r#"fn main() { let x = env::arg(0); }"#, "counts arguments from 1")]
+#[case(
+    r#"fn main() { let n = 1; let x = env::arg(n); }"#,
+    "takes an integer literal"
+)]
+fn test_env_intrinsic_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_sanitize_intrinsics_check_through_runtime_helpers() {
+    let stmts = main_body_ir(
+        r#"
+        use bashrs_std::{env, sanitize};
+        fn main() {
+            let dir = sanitize::path(env::var_or("PREFIX", "/usr/local"));
+            let name = sanitize::word(env::arg(1).unwrap_or("app"));
+        }
+    "#,
+    )
+    .unwrap();
+
+    // An unsafe value stops main like a failing `?` call
+    let helpers = stmts.iter().filter_map(|stmt| match stmt {
+        ShellIR::Try {
+            call:
+                ShellValue::FunctionCall {
+                    name,
+                    args,
+                    convention,
+                },
+            ..
+        } => {
+            assert_eq!(args.len(), 1);
+            assert_eq!(*convention, ReturnConvention::Stdout);
+            Some(name.as_str())
+        }
+        _ => None,
+    });
+    assert_eq!(
+        helpers.collect::<Vec<_>>(),
+        ["rash_sanitize_path", "rash_sanitize_word"]
+    );
+}
+
+#[rstest]
+#[case(
+    r#"fn main() { echo(sanitize::path("a")); } fn echo(a: &str) {}"#,
+    "can only be bound with `let` or assigned"
+)]
+#[case(
+    r#"fn main() { let x = sanitize::word("a", "b"); }"#,
+    "takes 1 argument(s) but 2"
+)]
+fn test_sanitize_intrinsic_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[rstest]
+#[case(r#"fn main() { fs::mkdir_p("a", "b"); }"#, "takes 1 argument(s) but 2")]
+#[case(r#"fn main() { net::fetch("a"); }"#, "Unknown function 'net::fetch'")]
+#[case(r#"fn main() { let x = log::info("a"); }"#, "returns nothing")]
+fn test_stdlib_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+fn effect_budget_error(source: &str, effects: &EffectPolicy) -> crate::models::Diagnostic {
+    let ast = crate::services::parse(source).unwrap();
+    from_ast_with_names(&ast, effects)
+        .unwrap_err()
+        .to_diagnostic()
+        .unwrap()
+}
+
+#[test]
+fn test_effect_budget_follows_calls() {
+    let source = r#"
+        #[rash::effects(fs_read, network)]
+        fn preflight() {
+            if process::status("curl", ["-fsS", "https://example.com"]) {
+                record();
+            }
+        }
+        fn record() {
+            tee("-a", "/var/log/preflight.log");
+        }
+        fn main() { preflight(); }
+        fn tee(a: &str, b: &str) {}
+    "#;
+    let diagnostic = effect_budget_error(source, &EffectPolicy::default());
+
+    assert_eq!(diagnostic.code, "E0402");
+    assert_eq!(
+        diagnostic.message,
+        "`preflight` writes files, but its declared effects don't allow it"
+    );
+    let primary = diagnostic.primary.unwrap();
+    assert_eq!(primary.span.line, 9);
+    assert_eq!(primary.message, "`tee` writes files");
+    let labels: Vec<_> = diagnostic
+        .secondary
+        .iter()
+        .map(|label| (label.span.line, label.message.as_str()))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            (2, "declares only `fs_read, network`"),
+            (5, "calls `record`, which writes files"),
+        ]
+    );
+}
+
+#[test]
+fn test_effect_budget_counts_tested_values() {
+    // The command in the condition runs even though the branch is empty
+    let source = r#"
+        #[rash::effects(fs_read)]
+        fn main() {
+            if process::status("curl", ["-fsS", "https://example.com"]) {}
+        }
+    "#;
+    let diagnostic = effect_budget_error(source, &EffectPolicy::default());
+    assert_eq!(
+        diagnostic.primary.unwrap().message,
+        "`curl` accesses the network"
+    );
+}
+
+#[rstest]
+#[case(
+    r#"#[rash::effects(fs_read)] fn main() { let v = process::capture("cat", ["/etc/os-release"]); }"#
+)]
+#[case(r#"#[rash::effects(pure)] fn main() { let x = 1 + 2; println!("{x}"); }"#)]
+#[case(
+    r#"#[rash::effects(fs_read)] fn main() { tool("--check"); }
+       #[rash::effects(fs_read)] fn tool(a: &str) {}"#
+)]
+#[case(
+    r#"#[rash::effects(fs_read)] fn main() { if fs::dir_exists("/opt") { check(); } }
+       fn check() { sha256sum("-c", "SUMS"); }
+       fn sha256sum(a: &str, b: &str) {}"#
+)]
+fn test_effect_budget_accepts_declared_effects(#[case] source: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    assert!(from_ast(&ast).is_ok(), "{source}");
+}
+
+#[test]
+fn test_unknown_commands_need_declared_effects() {
+    let source = r#"#[rash::effects(fs_read)] fn main() { tool("--check"); } fn tool(a: &str) {}"#;
+    let diagnostic = effect_budget_error(source, &EffectPolicy::default());
+    assert_eq!(
+        diagnostic.primary.unwrap().message,
+        "`tool` runs programs whose effects are unknown"
+    );
+    assert!(diagnostic.help.unwrap().contains("declare its stub"));
+}
+
+#[test]
+fn test_script_effect_policy() {
+    let source = r#"
+        fn main() {
+            fs::mkdir_p("/opt/app");
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let allow = |effects: &[Effect]| EffectPolicy {
+        allow: Some(effects.to_vec()),
+        deny: vec![],
+    };
+
+    assert!(from_ast_with_names(&ast, &allow(&[Effect::FileWrite])).is_ok());
+    let diagnostic = effect_budget_error(source, &allow(&[Effect::FileRead]));
+    assert_eq!(
+        diagnostic.message,
+        "the script writes files, which is not in `--allow-effects`"
+    );
+    assert_eq!(
+        diagnostic.primary.unwrap().message,
+        "`fs::mkdir_p` writes files"
+    );
+
+    let deny = EffectPolicy {
+        allow: None,
+        deny: vec![Effect::FileWrite],
+    };
+    assert_eq!(
+        effect_budget_error(source, &deny).message,
+        "the script writes files, which is denied by `--deny-effects`"
+    );
+}
+
+#[test]
+fn test_print_lowers_to_printf() {
+    let stmts = main_body_ir(
+        r#"
+        fn main() {
+            let name = "x";
+            println!("100% \\ {name}");
+            eprint!("{}", true);
+            let s = format!("{name}-{}", 2);
+        }
+    "#,
+    )
+    .unwrap();
+
+    match &stmts[1] {
+        ShellIR::Printf {
+            format,
+            args,
+            stderr,
+            ..
+        } => {
+            assert_eq!(format, "100%% \\\\ %s\\n");
+            assert!(matches!(args.as_slice(), [ShellValue::Variable(v)] if v == "name"));
+            assert!(!stderr);
+        }
+        other => panic!("Expected printf, got {other:?}"),
+    }
+    // Constant values are folded into the format string
+    assert!(matches!(
+        &stmts[2],
+        ShellIR::Printf { format, args, stderr: true, .. } if format == "true" && args.is_empty()
+    ));
+    assert!(matches!(
+        &stmts[3],
+        ShellIR::Let { value: ShellValue::Concat(parts), .. } if parts.len() == 3
+    ));
+}
+
+#[test]
+fn test_constants_become_folded_globals() {
+    let ast = crate::services::parse(
+        r#"
+        const BASE: u32 = 40;
+        const PORT: u32 = BASE * 200 + 80;
+        const VERSION: &str = "1.2.3";
+        fn main() { let next = PORT + 1; }
+    "#,
+    )
+    .unwrap();
+    let ir = optimize(from_ast(&ast).unwrap(), &crate::models::Config::default()).unwrap();
+    let ShellIR::Sequence(items) = ir else {
+        panic!("Expected sequence");
+    };
+
+    let globals: Vec<(&str, Option<String>)> = items
+        .iter()
+        .filter_map(|item| match item {
+            ShellIR::Global { name, value } => Some((name.as_str(), value.as_constant_string())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        globals,
+        [
+            ("BASE", Some("40".to_string())),
+            ("PORT", Some("8080".to_string())),
+            ("VERSION", Some("1.2.3".to_string())),
+        ]
+    );
+    // Uses of constants in arithmetic fold as well
+    assert!(items.iter().any(|item| matches!(
+        item,
+        ShellIR::Spanned { inner, .. }
+            if matches!(inner.as_ref(), ShellIR::Let { value: ShellValue::String(s), .. } if s == "8081")
+    )));
+}
+
+#[rstest]
+#[case(
+    "const A: u32 = 1; fn main() { let A = 2; }",
+    "Cannot assign to constant 'A'"
+)]
+#[case(
+    "const A: u32 = 1; fn main() { for A in 0..3 {} }",
+    "Cannot assign to constant 'A'"
+)]
+#[case(
+    "const A: u32 = 1; fn f(A: u32) { g(A); } fn g(x: u32) {} fn main() { f(2); }",
+    "Cannot assign to constant 'A'"
+)]
+#[case(
+    "const A: u32 = f(); fn f() -> u32 { return 1; } fn main() {}",
+    "must be a literal"
+)]
+#[case(
+    "const B: u32 = A; const A: u32 = 1; fn main() {}",
+    "must be a literal"
+)]
+#[case(r#"const A: u32 = "x"; fn main() {}"#, "declared as integer")]
+fn test_constant_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    let err = from_ast(&ast).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_only_reachable_functions_are_emitted() {
+    let source = r#"
+        fn used() { helper(); }
+        fn helper() { let x = 1; }
+        fn unused() { helper(); }
+        fn main() { used(); }
+    "#;
+    let ast = crate::services::parser::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let ShellIR::Sequence(items) = &ir else {
+        panic!("Expected a sequence, got {ir:?}");
+    };
+    let defined: Vec<&str> = items
+        .iter()
+        .filter_map(|item| match item {
+            ShellIR::Function { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(defined, ["used", "helper"]);
+}
+
+#[test]
+fn test_try_propagates_failure_status() {
+    let source = r#"
+        fn check(x: &str) -> Result<String, String> {
+            if x == "" {
+                return Err("empty");
+            }
+            return Ok(x);
+        }
+        fn run() -> Result<(), String> {
+            let v = check("a")?;
+            check(v)?;
+            return Ok(());
+        }
+        fn main() -> Result<(), String> {
+            run()?;
+            return Ok(());
+        }
+    "#;
+    let ast = crate::services::parser::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let ShellIR::Sequence(items) = &ir else {
+        panic!("Expected a sequence, got {ir:?}");
+    };
+    let run = items
+        .iter()
+        .find_map(|item| match item {
+            ShellIR::Function { name, body, .. } if name == "run" => Some(body),
+            _ => None,
+        })
+        .unwrap();
+    let ShellIR::Sequence(body) = run.as_ref() else {
+        panic!("Expected a sequence, got {run:?}");
+    };
+    let tries: Vec<_> = body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ShellIR::Spanned { inner, .. } => match inner.as_ref() {
+                ShellIR::Try { binding, call, .. } => Some((binding.clone(), call)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tries.len(), 2, "{body:?}");
+    assert_eq!(tries[0].0.as_deref(), Some("v"));
+    assert!(matches!(
+        tries[0].1,
+        ShellValue::FunctionCall { name, convention: ReturnConvention::Stdout, .. } if name == "check"
+    ));
+    assert_eq!(tries[1].0, None);
+}
+
+#[rstest]
+#[case(
+    "fn f() -> Result<(), String> { return Ok(()); } fn main() { f()?; }",
+    "can only be used in a function that returns `Result`"
+)]
+#[case(
+    "fn f() -> u32 { return 1; } fn main() -> Result<(), String> { let x = f()?; return Ok(()); }",
+    "does not return one"
+)]
+#[case(
+    "fn f() -> Result<u32, String> { return Ok(1); } fn main() -> Result<(), String> { let x = f(); return Ok(()); }",
+    "must be handled with `?`"
+)]
+#[case(
+    "fn f() -> Result<(), String> { return Ok(()); } fn main() -> Result<(), String> { let x = f()?; return Ok(()); }",
+    "holds no value to bind"
+)]
+#[case(
+    "fn main() { let x = Ok(1); }",
+    "can only be returned from a function that returns `Result`"
+)]
+#[case(
+    "fn f() -> Result<bool, String> { return Ok(true); } fn main() -> Result<(), String> { f()?; return Ok(()); }",
+    "`Result<bool, _>`"
+)]
+fn test_result_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    let err = from_ast(&ast).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_string_conversions_keep_the_value() {
+    let body = main_body_ir(
+        r#"fn main() { let n = 3; let s = n.to_string(); let t = String::from("x"); let u = t.to_owned(); }"#,
+    )
+    .unwrap();
+
+    let values: Vec<_> = body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            ShellIR::Let { value, .. } => Some(value.clone()),
+            _ => None,
+        })
+        .collect();
+    assert!(matches!(&values[1], ShellValue::Variable(name) if name == "n"));
+    assert!(matches!(&values[2], ShellValue::String(text) if text == "x"));
+    assert!(matches!(&values[3], ShellValue::Variable(name) if name == "t"));
+}
+
+#[rstest]
+#[case(r#"fn main() { let v = ["a"]; let s = v.to_string(); }"#, "not a list")]
+#[case(r#"fn main() { let s = String::from(true); }"#, "not a boolean")]
+#[case(r#"fn main() { let r = 0..3; }"#, "range used as a value")]
+#[case(
+    r#"fn tool(arg: &str) {} fn main() { let x = tool("a"); }"#,
+    "returns nothing and cannot be used as a value"
+)]
+fn test_unsupported_values_are_rejected(#[case] source: &str, #[case] message: &str) {
+    let err = main_body_ir(source).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_option_binding_leaves_variable_unset() {
+    let source = r#"
+        fn lookup(key: &str) -> Option<String> {
+            if key == "prefix" {
+                return Some("/opt");
+            }
+            return None;
+        }
+        fn main() {
+            let prefix = lookup("prefix");
+            let dir = prefix.unwrap_or("/usr/local");
+            let missing: Option<String> = None;
+        }
+    "#;
+    let ast = crate::services::parser::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let mut lets = Vec::new();
+    collect_lets(&ir, &mut lets);
+    assert!(lets.iter().any(|ir| matches!(
+        ir,
+        ShellIR::LetOption { name, value: Some(ShellValue::FunctionCall { name: callee, .. }), .. }
+            if name == "prefix" && callee == "lookup"
+    )));
+    assert!(lets.iter().any(|ir| matches!(
+        ir,
+        ShellIR::Let { name, value: ShellValue::UnwrapOr { option, .. }, .. }
+            if name == "dir" && matches!(option.as_ref(), ShellValue::Variable(v) if v == "prefix")
+    )));
+    assert!(lets.iter().any(|ir| matches!(
+        ir,
+        ShellIR::LetOption { name, value: None, .. } if name == "missing"
+    )));
+}
+
+fn collect_lets<'a>(ir: &'a ShellIR, lets: &mut Vec<&'a ShellIR>) {
+    match ir {
+        ShellIR::Let { .. } | ShellIR::Assign { .. } | ShellIR::LetOption { .. } => lets.push(ir),
+        ShellIR::If {
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            collect_lets(then_branch, lets);
+            if let Some(else_branch) = else_branch {
+                collect_lets(else_branch, lets);
+            }
+        }
+        ShellIR::For { body, .. } | ShellIR::While { body, .. } => collect_lets(body, lets),
+        ShellIR::Sequence(items) => items.iter().for_each(|item| collect_lets(item, lets)),
+        ShellIR::Spanned { inner, .. } => collect_lets(inner, lets),
+        ShellIR::Function { body, .. } => collect_lets(body, lets),
+        _ => {}
+    }
+}
+
+#[rstest]
+#[case(
+    "fn f() -> Option<String> { return None; } fn main() { let x = f(); println!(\"{x}\"); }",
+    "'x' is an `Option`"
+)]
+#[case(
+    "fn f(x: Option<String>) { let y = 1; } fn main() {}",
+    "Parameter 'x' is an `Option`"
+)]
+#[case(
+    "fn f() -> Option<String> { return None; } fn main() { f(); }",
+    "is unused"
+)]
+#[case(
+    "fn f() -> Option<String> { return None; } fn main() { match f() { Some(v) => {} } }",
+    "handle both `Some(..)` and `None`"
+)]
+#[case(
+    "fn f() -> Option<u32> { return Some(1); } fn main() { let x = f().unwrap_or(\"a\"); }",
+    "must be a integer"
+)]
+#[case(
+    "fn f() -> Option<String> { return \"a\"; } fn main() {}",
+    "must return `Some(...)` or `None`"
+)]
+#[case(
+    "fn main() { let x = 1; match x { Some(v) => {} _ => {} } }",
+    "do not match"
+)]
+#[case(
+    "fn f() -> Option<String> { return None; } fn main() { let x = f().ok_or(\"unset\"); }",
+    "Method 'ok_or' on an `Option`"
+)]
+#[case(
+    "fn up(s: &str) -> String { return s; } fn main() { let o = env::var(\"HOME\"); let x = o.map(up); }",
+    "Method 'map' on an `Option`"
+)]
+#[case(
+    "fn up(s: &str) -> String { return s; } fn f() -> Option<String> { return None; } fn main() { if let Some(v) = f().map(up) {} }",
+    "Method 'map' on an `Option`"
+)]
+#[case(
+    "fn f() -> Option<String> { return None; } fn main() { let o = f(); o.unwrap(); }",
+    "Method 'unwrap' on an `Option`"
+)]
+fn test_option_misuse_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    let err = from_ast(&ast).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_mutable_bindings_are_assigned() {
+    let source = r#"
+        fn main() {
+            let mut total = 0;
+            for i in 0..3 {
+                let doubled = i * 2;
+                total += doubled;
+            }
+            let limit = 10;
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let mut lets = Vec::new();
+    collect_lets(&ir, &mut lets);
+    let bindings: Vec<(&str, bool)> = lets
+        .iter()
+        .filter_map(|ir| match ir {
+            ShellIR::Let { name, .. } => Some((name.as_str(), false)),
+            ShellIR::Assign { name, .. } => Some((name.as_str(), true)),
+            _ => None,
+        })
+        .collect();
+    // A binding in a loop body is bound again on every iteration
+    assert_eq!(
+        bindings,
+        [
+            ("total", true),
+            ("doubled", true),
+            ("total", true),
+            ("limit", false)
+        ]
+    );
+}
+
+#[test]
+fn test_shadowing_renames_inner_binding() {
+    let source = r#"
+        fn main() {
+            let name = "outer";
+            if true {
+                let name = "inner";
+                println!("{name}");
+            }
+            println!("{name}");
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let mut lets = Vec::new();
+    collect_lets(&ir, &mut lets);
+    let names: Vec<&str> = lets
+        .iter()
+        .filter_map(|ir| match ir {
+            ShellIR::Let { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["name", "name_1"]);
+}
+
+#[test]
+fn test_block_statement_scopes_its_bindings() {
+    let source = r#"
+        fn main() {
+            let x = 1;
+            {
+                let x = 2;
+                println!("{x}");
+            }
+            println!("{x}");
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let ir = from_ast(&ast).unwrap();
+
+    let mut lets = Vec::new();
+    collect_lets(&ir, &mut lets);
+    let names: Vec<&str> = lets
+        .iter()
+        .filter_map(|ir| match ir {
+            ShellIR::Let { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["x", "x_1"]);
+}
+
+#[rstest]
+#[case(
+    "fn main() { let x = 1; x = 2; }",
+    "cannot assign twice to immutable variable `x`"
+)]
+#[case(
+    "fn main() { let mut x = 1; if true { let x = 2; x += 1; } }",
+    "immutable variable `x`"
+)]
+#[case(
+    "fn main() { let mut x = 1; { let x = 2; x += 1; } }",
+    "immutable variable `x`"
+)]
+#[case("fn main() { { let mut y = 1; } y = 2; }", "cannot find variable `y`")]
+#[case("fn main() { y = 2; }", "cannot find variable `y`")]
+#[case(
+    "const LIMIT: u32 = 3; fn main() { LIMIT = 4; }",
+    "cannot assign to constant `LIMIT`"
+)]
+#[case(
+    "fn main() { let mut x = 1; x = \"a\"; }",
+    "declared as integer but is assigned string"
+)]
+fn test_invalid_assignment_is_rejected(#[case] source: &str, #[case] message: &str) {
+    let ast = crate::services::parse(source).unwrap();
+    let err = from_ast(&ast).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_bindings_get_unique_shell_names() {
+    let source = r#"
+        fn greet(name: &str) {
+            let path = name;
+            println!("{path}");
+        }
+        fn main() {
+            let name = "world";
+            let IFS = ",";
+            let lc_all = "C";
+            greet(name);
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let (ir, names) = from_ast_with_names(&ast, &EffectPolicy::default()).unwrap();
+
+    let renamed: Vec<(&str, &str, &str)> = names
+        .iter()
+        .map(|n| {
+            (
+                n.function.as_str(),
+                n.rust_name.as_str(),
+                n.shell_name.as_str(),
+            )
+        })
+        .collect();
+    // Reserved names are avoided whatever their case, and each binding in
+    // the script gets its own variable
+    assert_eq!(
+        renamed,
+        [
+            ("greet", "name", "name"),
+            ("greet", "path", "path_1"),
+            ("main", "name", "name_1"),
+            ("main", "IFS", "IFS_1"),
+            ("main", "lc_all", "_lc_all_1"),
+        ]
+    );
+    let mut lets = Vec::new();
+    collect_lets(&ir, &mut lets);
+    assert!(lets.iter().any(|ir| matches!(
+        ir,
+        ShellIR::Let { name, value: ShellValue::Variable(v), .. } if name == "path_1" && v == "name"
+    )));
+}
+
+#[test]
+fn test_bindings_avoid_environment_variables_read() {
+    let source = r#"
+        use bashrs_std::env;
+        fn main() {
+            let version = env::var_or("version", "1.0");
+        }
+    "#;
+    let ast = crate::services::parse(source).unwrap();
+    let (_, names) = from_ast_with_names(&ast, &EffectPolicy::default()).unwrap();
+
+    assert_eq!(names[0].shell_name, "version_1");
+}
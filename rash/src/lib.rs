@@ -22,20 +22,64 @@ pub use models::{Config, Error, Result};
 /// Transpile Rust source code to POSIX shell script
 pub fn transpile(input: &str, config: Config) -> Result<String> {
     let validation_pipeline = validation::pipeline::ValidationPipeline::new(&config);
+    let (ir, _) = lower(input, &config, &validation_pipeline)?;
+    let shell_code = emitter::emit(&ir, &config)?;
 
-    let ast = services::parser::parse(input)?;
-    ast::validate(&ast)?;
-    validation_pipeline.validate_ast(&ast)?;
+    validation_pipeline.validate_output(&shell_code)?;
 
-    let ir = ir::from_ast(&ast)?;
-    validation_pipeline.validate_ir(&ir)?;
+    Ok(shell_code)
+}
 
-    let optimized = ir::optimize(ir, &config)?;
-    let shell_code = emitter::emit(&optimized, &config)?;
+/// Transpile Rust source code, also returning a map from Rust statements to
+/// the shell lines generated for them
+pub fn transpile_with_source_map(
+    input: &str,
+    config: Config,
+) -> Result<(String, formatter::source_map::SourceMap)> {
+    let ast = services::parser::parse(input)?;
+    transpile_ast_with_source_map(&ast, config)
+}
+
+/// Transpile a parsed program, such as one loaded from several files by
+/// [`services::modules::load`], along with its source map
+pub fn transpile_ast_with_source_map(
+    ast: &ast::RestrictedAst,
+    config: Config,
+) -> Result<(String, formatter::source_map::SourceMap)> {
+    let validation_pipeline = validation::pipeline::ValidationPipeline::new(&config);
+    let (ir, names) = lower_ast(ast, &config, &validation_pipeline)?;
+    let (shell_code, mut source_map) = emitter::emit_with_source_map(&ir, &config)?;
+    source_map.add_names(names);
 
     validation_pipeline.validate_output(&shell_code)?;
 
-    Ok(shell_code)
+    Ok((shell_code, source_map))
+}
+
+/// Parse, validate and optimize the input down to shell IR, along with the
+/// shell variable each Rust binding was renamed to
+fn lower(
+    input: &str,
+    config: &Config,
+    validation_pipeline: &validation::pipeline::ValidationPipeline,
+) -> Result<(ir::ShellIR, Vec<formatter::source_map::NameMapping>)> {
+    let ast = services::parser::parse(input)?;
+    lower_ast(&ast, config, validation_pipeline)
+}
+
+fn lower_ast(
+    ast: &ast::RestrictedAst,
+    config: &Config,
+    validation_pipeline: &validation::pipeline::ValidationPipeline,
+) -> Result<(ir::ShellIR, Vec<formatter::source_map::NameMapping>)> {
+    ast::validate(ast)?;
+    validation_pipeline.validate_ast(ast)?;
+
+    let (ir, names) = ir::from_ast_with_names(ast, &config.effects)?;
+    validation_pipeline.validate_ir(&ir)?;
+    verifier::verify_script(&ir, config.verify)?;
+
+    Ok((ir::optimize(ir, config)?, names))
 }
 
 /// Check if the given Rust code is valid for transpilation
@@ -1,3 +1,4 @@
+use crate::ir::EffectPolicy;
 use crate::validation::ValidationLevel;
 use serde::{Deserialize, Serialize};
 
@@ -9,6 +10,9 @@ pub struct Config {
     pub optimize: bool,
     pub validation_level: Option<ValidationLevel>,
     pub strict_mode: bool,
+    /// Effects the whole script may have
+    #[serde(default)]
+    pub effects: EffectPolicy,
 }
 
 impl Default for Config {
@@ -20,6 +24,7 @@ impl Default for Config {
             optimize: true,
             validation_level: Some(ValidationLevel::Minimal),
             strict_mode: false,
+            effects: EffectPolicy::default(),
         }
     }
 }
@@ -39,3 +44,14 @@ pub enum VerificationLevel {
     Strict,
     Paranoid,
 }
+
+impl ShellDialect {
+    /// Shell name understood by `shellcheck --shell`
+    pub fn shellcheck_shell(&self) -> &'static str {
+        match self {
+            ShellDialect::Posix | ShellDialect::Ash => "sh",
+            ShellDialect::Bash => "bash",
+            ShellDialect::Dash => "dash",
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::ast::Span;
+use std::fmt;
+
+#[cfg(test)]
+#[path = "diagnostic_tests.rs"]
+mod tests;
+
+/// A compile error tied to locations in the Rust source, rendered rustc-style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Stable error code such as `E0110`
+    pub code: &'static str,
+    pub message: String,
+    /// The location the error is about
+    pub primary: Option<Label>,
+    /// Related locations that explain the error
+    pub secondary: Vec<Label>,
+    pub help: Option<String>,
+}
+
+/// A source location with an optional explanation shown under it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn with_primary(mut self, span: Span, label: impl Into<String>) -> Self {
+        self.primary = Some(Label {
+            span,
+            message: label.into(),
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, label: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: label.into(),
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Point at `span` unless a more precise location is already known
+    pub fn at(self, span: Span) -> Self {
+        if self.primary.is_some() {
+            self
+        } else {
+            self.with_primary(span, "")
+        }
+    }
+
+    /// The diagnostic with every label's span passed through `f`; labels
+    /// for which `f` returns `None` are dropped
+    pub fn map_spans(mut self, f: &impl Fn(Span) -> Option<Span>) -> Self {
+        self.primary = self.primary.and_then(|label| {
+            Some(Label {
+                span: f(label.span)?,
+                ..label
+            })
+        });
+        self.secondary = self
+            .secondary
+            .into_iter()
+            .filter_map(|label| {
+                Some(Label {
+                    span: f(label.span)?,
+                    ..label
+                })
+            })
+            .collect();
+        self
+    }
+
+    /// Render for a terminal, with caret-underlined snippets of `source`
+    #[cfg(feature = "pretty-errors")]
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let mut labels: Vec<(&Label, char)> = Vec::new();
+        if let Some(primary) = &self.primary {
+            labels.push((primary, '^'));
+        }
+        labels.extend(self.secondary.iter().map(|label| (label, '-')));
+        labels.sort_by_key(|(label, _)| label.span.start);
+
+        let width = labels
+            .iter()
+            .map(|(label, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = " ".repeat(width);
+
+        let mut out = format!("error[{}]: {}\n", self.code, self.message);
+        match &self.primary {
+            Some(primary) => out.push_str(&format!(
+                "{gutter}--> {file}:{}:{}\n",
+                primary.span.line, primary.span.column
+            )),
+            None => out.push_str(&format!("{gutter}--> {file}\n")),
+        }
+
+        if !labels.is_empty() {
+            out.push_str(&format!("{gutter} |\n"));
+        }
+        let mut previous_line = None;
+        for (label, marker) in labels {
+            let line = label.span.line;
+            if previous_line.is_some_and(|previous| line > previous + 1) {
+                out.push_str("...\n");
+            }
+            if previous_line != Some(line) {
+                let text = source.lines().nth(line - 1).unwrap_or_default();
+                out.push_str(&format!("{line:>width$} | {text}\n"));
+                previous_line = Some(line);
+            }
+            let underline = marker.to_string().repeat(underline_len(source, label.span));
+            let padding = " ".repeat(label.span.column.saturating_sub(1));
+            let text = format!("{padding}{underline} {}", label.message);
+            out.push_str(&format!("{gutter} | {}\n", text.trim_end()));
+        }
+
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{gutter} |\n{gutter} = help: {help}\n"));
+        }
+        out
+    }
+
+    /// Render as a single line when the `pretty-errors` feature is disabled
+    #[cfg(not(feature = "pretty-errors"))]
+    pub fn render(&self, file: &str, _source: &str) -> String {
+        let location = match &self.primary {
+            Some(primary) => format!("{file}:{}:{}", primary.span.line, primary.span.column),
+            None => file.to_string(),
+        };
+        let mut out = format!("{location}: error[{}]: {}\n", self.code, self.message);
+        if let Some(help) = &self.help {
+            out.push_str(&format!("{location}: help: {help}\n"));
+        }
+        out
+    }
+
+    /// Machine-readable form for `--message-format=json`
+    pub fn to_json(&self, file: &str) -> serde_json::Value {
+        let span = |label: &Label, is_primary: bool| {
+            serde_json::json!({
+                "file": file,
+                "byte_start": label.span.start,
+                "byte_end": label.span.end,
+                "line": label.span.line,
+                "column": label.span.column,
+                "label": label.message,
+                "is_primary": is_primary,
+            })
+        };
+        let spans: Vec<_> = self
+            .primary
+            .iter()
+            .map(|label| span(label, true))
+            .chain(self.secondary.iter().map(|label| span(label, false)))
+            .collect();
+
+        serde_json::json!({
+            "level": "error",
+            "code": self.code,
+            "message": self.message,
+            "spans": spans,
+            "help": self.help,
+        })
+    }
+}
+
+/// Number of columns to underline: the span's extent on its first line, at least one
+#[cfg(feature = "pretty-errors")]
+fn underline_len(source: &str, span: Span) -> usize {
+    let start = span.start.min(source.len());
+    let end = span.end.clamp(start, source.len());
+    source
+        .get(start..end)
+        .and_then(|text| text.lines().next())
+        .map_or(0, |first| first.chars().count())
+        .max(1)
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error[{}]: {}", self.code, self.message)?;
+        if let Some(primary) = &self.primary {
+            write!(
+                f,
+                " (at line {}, column {})",
+                primary.span.line, primary.span.column
+            )?;
+        }
+        Ok(())
+    }
+}
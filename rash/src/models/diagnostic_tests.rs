@@ -0,0 +1,88 @@
+use super::*;
+
+fn span_of(source: &str, needle: &str) -> Span {
+    let start = source.find(needle).unwrap();
+    let before = &source[..start];
+    Span {
+        start,
+        end: start + needle.len(),
+        line: before.matches('\n').count() + 1,
+        column: start - before.rfind('\n').map_or(0, |i| i + 1) + 1,
+    }
+}
+
+#[test]
+#[cfg(feature = "pretty-errors")]
+fn test_render_underlines_primary_and_secondary_spans() {
+    let source = "fn main() {\n    let f = |x| x;\n    f(1);\n}\n";
+    let diagnostic = Diagnostic::new("E0110", "closures are not supported")
+        .with_primary(span_of(source, "|x| x"), "closure")
+        .with_secondary(span_of(source, "f(1)"), "called here")
+        .with_help("closures aren't supported; use a named fn");
+
+    let rendered = diagnostic.render("main.rs", source);
+    let expected = "\
+error[E0110]: closures are not supported
+ --> main.rs:2:13
+  |
+2 |     let f = |x| x;
+  |             ^^^^^ closure
+3 |     f(1);
+  |     ---- called here
+  |
+  = help: closures aren't supported; use a named fn
+";
+    assert_eq!(rendered, expected);
+}
+
+#[test]
+#[cfg(feature = "pretty-errors")]
+fn test_render_without_span_omits_snippet() {
+    let diagnostic = Diagnostic::new("E0103", "no entry point function found");
+
+    let rendered = diagnostic.render("main.rs", "");
+    assert_eq!(
+        rendered,
+        "error[E0103]: no entry point function found\n --> main.rs\n"
+    );
+}
+
+#[test]
+fn test_json_lists_primary_span_first() {
+    let source = "fn main() { x[0]; }";
+    let diagnostic = Diagnostic::new("E0110", "indexing is not supported")
+        .with_secondary(span_of(source, "main"), "in this function")
+        .with_primary(span_of(source, "x[0]"), "index expression");
+
+    let json = diagnostic.to_json("main.rs");
+    assert_eq!(json["code"], "E0110");
+    assert_eq!(json["level"], "error");
+    assert_eq!(json["spans"][0]["is_primary"], true);
+    assert_eq!(json["spans"][0]["column"], 13);
+    assert_eq!(json["spans"][0]["label"], "index expression");
+    assert_eq!(json["spans"][1]["is_primary"], false);
+    assert!(json["help"].is_null());
+}
+
+#[test]
+fn test_at_keeps_existing_primary_span() {
+    let inner = Span {
+        start: 4,
+        end: 6,
+        line: 1,
+        column: 5,
+    };
+    let outer = Span {
+        start: 0,
+        end: 10,
+        line: 1,
+        column: 1,
+    };
+
+    let diagnostic =
+        Diagnostic::new("E0112", "unsupported binary operator").with_primary(inner, "");
+    assert_eq!(diagnostic.at(outer).primary.unwrap().span, inner);
+
+    let diagnostic = Diagnostic::new("E0112", "unsupported binary operator").at(outer);
+    assert_eq!(diagnostic.primary.unwrap().span, outer);
+}
@@ -42,4 +42,95 @@ pub enum Error {
 
     #[error("Unsupported feature: {0}")]
     Unsupported(String),
+
+    #[error("{0}")]
+    Diagnostic(Box<crate::models::Diagnostic>),
+
+    #[error("{inner} (at line {}, column {})", span.line, span.column)]
+    Located {
+        span: crate::ast::Span,
+        inner: Box<Error>,
+    },
+
+    /// Error in one of the files of a multi-file program, with spans
+    /// relative to that file
+    #[error("{inner} (in {})", path.display())]
+    InFile {
+        path: std::path::PathBuf,
+        inner: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attach a source location, keeping the innermost one when already located
+    pub fn at(self, span: crate::ast::Span) -> Self {
+        match self {
+            Error::Located { .. } => self,
+            Error::Diagnostic(diagnostic) => Error::Diagnostic(Box::new(diagnostic.at(span))),
+            other => Error::Located {
+                span,
+                inner: Box::new(other),
+            },
+        }
+    }
+
+    /// Source location of the error, if known
+    pub fn span(&self) -> Option<crate::ast::Span> {
+        match self {
+            Error::Located { span, .. } => Some(*span),
+            Error::InFile { inner, .. } => inner.span(),
+            Error::Diagnostic(diagnostic) => diagnostic.primary.as_ref().map(|label| label.span),
+            _ => None,
+        }
+    }
+
+    /// The error without its source location
+    pub fn unlocated(&self) -> &Error {
+        match self {
+            Error::Located { inner, .. } | Error::InFile { inner, .. } => inner.unlocated(),
+            other => other,
+        }
+    }
+
+    /// The error with every source location passed through `f`; locations
+    /// for which `f` returns `None` are dropped
+    pub fn map_spans(self, f: &impl Fn(crate::ast::Span) -> Option<crate::ast::Span>) -> Self {
+        match self {
+            Error::Located { span, inner } => {
+                let inner = inner.map_spans(f);
+                match f(span) {
+                    Some(span) => Error::Located {
+                        span,
+                        inner: Box::new(inner),
+                    },
+                    None => inner,
+                }
+            }
+            Error::Diagnostic(diagnostic) => Error::Diagnostic(Box::new(diagnostic.map_spans(f))),
+            other => other,
+        }
+    }
+
+    /// The error as a diagnostic, for errors that point into the Rust source
+    pub fn to_diagnostic(&self) -> Option<crate::models::Diagnostic> {
+        use crate::models::Diagnostic;
+
+        let diagnostic = match self {
+            Error::Diagnostic(diagnostic) => (**diagnostic).clone(),
+            Error::Located { span, inner } => return Some(inner.to_diagnostic()?.at(*span)),
+            Error::InFile { inner, .. } => return inner.to_diagnostic(),
+            Error::Parse(e) => Diagnostic::new("E0001", e.to_string()),
+            Error::Validation(message) => Diagnostic::new("E0100", message.clone()),
+            Error::IrGeneration(message) => Diagnostic::new("E0200", message.clone()),
+            Error::Unsupported(message) => Diagnostic::new("E0300", message.clone()),
+            _ => return None,
+        };
+        Some(diagnostic)
+    }
+}
+
+impl From<crate::models::Diagnostic> for Error {
+    fn from(diagnostic: crate::models::Diagnostic) -> Self {
+        Error::Diagnostic(Box::new(diagnostic))
+    }
 }
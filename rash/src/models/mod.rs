@@ -1,5 +1,7 @@
 pub mod config;
+pub mod diagnostic;
 pub mod error;
 
 pub use config::{Config, ShellDialect, VerificationLevel};
+pub use diagnostic::{Diagnostic, Label};
 pub use error::{Error, Result};
@@ -0,0 +1,848 @@
+//! Multi-file programs: `mod` declarations are followed to their files and
+//! `use` paths resolved, flattening the crate into one AST whose functions
+//! all live in the shell script's single namespace.
+
+use crate::ast::restricted::{Constant, Expr, Function, RestrictedAst, Span, Stmt};
+use crate::ast::visitor::transform_stmt_exprs;
+use crate::models::{Diagnostic, Error, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the project file written by `bashrs init`
+pub const MANIFEST: &str = "Rash.toml";
+
+/// Items of one source file or inline `mod` block, before paths are resolved
+#[derive(Debug, Default)]
+pub struct Module {
+    pub functions: Vec<Function>,
+    /// Span of each function's signature, in the order of `functions`
+    pub signatures: Vec<Span>,
+    /// Whether each function is `pub`, in the order of `functions`
+    pub public: Vec<bool>,
+    pub constants: Vec<Constant>,
+    pub imports: Vec<Import>,
+    pub submodules: Vec<Submodule>,
+    /// Function marked as the entry point, with the span of its signature
+    pub entry_point: Option<(String, Span)>,
+}
+
+/// One name brought into scope by a `use` item
+#[derive(Debug)]
+pub struct Import {
+    /// Path of the imported item, e.g. `["crate", "net", "download"]`
+    pub path: Vec<String>,
+    /// Name the item is visible under, or `None` for a glob import
+    pub name: Option<String>,
+    pub span: Span,
+}
+
+/// A `mod name;` declaration, or `mod name { ... }` with its items
+#[derive(Debug)]
+pub struct Submodule {
+    pub name: String,
+    pub span: Span,
+    pub items: Option<Module>,
+}
+
+impl Module {
+    /// Move every span `base` bytes further, into the file's place among
+    /// the project's sources
+    fn offset_by(&mut self, base: usize) {
+        for function in &mut self.functions {
+            offset_stmts(&mut function.body, base);
+            if let Some(budget) = &mut function.effects {
+                budget.span = budget.span.offset_by(base);
+            }
+        }
+        for span in &mut self.signatures {
+            *span = span.offset_by(base);
+        }
+        for constant in &mut self.constants {
+            constant.span = constant.span.offset_by(base);
+        }
+        for import in &mut self.imports {
+            import.span = import.span.offset_by(base);
+        }
+        for submodule in &mut self.submodules {
+            submodule.span = submodule.span.offset_by(base);
+            if let Some(items) = &mut submodule.items {
+                items.offset_by(base);
+            }
+        }
+        if let Some((_, span)) = &mut self.entry_point {
+            *span = span.offset_by(base);
+        }
+    }
+}
+
+fn offset_stmts(stmts: &mut [Stmt], base: usize) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Spanned { span, stmt } => {
+                *span = span.offset_by(base);
+                offset_stmts(std::slice::from_mut(stmt.as_mut()), base);
+            }
+            Stmt::If {
+                then_block,
+                else_block,
+                ..
+            } => {
+                offset_stmts(then_block, base);
+                if let Some(else_block) = else_block {
+                    offset_stmts(else_block, base);
+                }
+            }
+            Stmt::For { body, .. } | Stmt::While { body, .. } | Stmt::Block(body) => {
+                offset_stmts(body, base)
+            }
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    offset_stmts(&mut arm.body, base);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A source file of a project
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+    /// Where the file starts in the offsets shared by all of the project's spans
+    pub offset: usize,
+}
+
+/// The files of a project, laid out one after another so that the byte
+/// offset of a span identifies the file it points into
+#[derive(Debug, Default)]
+pub struct SourceFiles {
+    files: Vec<SourceFile>,
+}
+
+impl SourceFiles {
+    fn add(&mut self, path: PathBuf, source: String) -> usize {
+        let offset = self
+            .files
+            .last()
+            .map_or(0, |last| last.offset + last.source.len() + 1);
+        self.files.push(SourceFile {
+            path,
+            source,
+            offset,
+        });
+        offset
+    }
+
+    /// The file containing `offset`
+    pub fn file_at(&self, offset: usize) -> Option<&SourceFile> {
+        self.files.iter().rev().find(|file| file.offset <= offset)
+    }
+
+    /// Tie a located error to the file it points into, with its spans made
+    /// relative to that file
+    pub fn attribute(&self, error: Error) -> Error {
+        if matches!(error, Error::InFile { .. }) {
+            return error;
+        }
+        let Some(file) = error.span().and_then(|span| self.file_at(span.start)) else {
+            return error;
+        };
+        let end = file.offset + file.source.len();
+        let inner = self.cite_other_files(error, file).map_spans(&|span| {
+            (file.offset..=end)
+                .contains(&span.start)
+                .then(|| span.relative_to(file.offset))
+        });
+        Error::InFile {
+            path: file.path.clone(),
+            inner: Box::new(inner),
+        }
+    }
+
+    /// Name the labels that point into files other than `file` in the help,
+    /// since only `file` is shown under the error
+    fn cite_other_files(&self, error: Error, file: &SourceFile) -> Error {
+        match error {
+            Error::Located { span, inner } => Error::Located {
+                span,
+                inner: Box::new(self.cite_other_files(*inner, file)),
+            },
+            Error::Diagnostic(mut diagnostic) => {
+                let mut notes = Vec::new();
+                diagnostic
+                    .secondary
+                    .retain(|label| match self.file_at(label.span.start) {
+                        Some(other) if other.offset != file.offset => {
+                            notes.push(format!(
+                                "{} at {}:{}",
+                                label.message,
+                                other.path.display(),
+                                label.span
+                            ));
+                            false
+                        }
+                        _ => true,
+                    });
+                if !notes.is_empty() {
+                    notes.extend(diagnostic.help.take());
+                    diagnostic.help = Some(notes.join("; "));
+                }
+                Error::Diagnostic(diagnostic)
+            }
+            other => other,
+        }
+    }
+}
+
+/// The entry file named by a path given to `bashrs build`: a project
+/// directory or its `Rash.toml` select the manifest's entry, any other
+/// path is the entry file itself
+pub fn project_entry(path: &Path) -> Result<PathBuf> {
+    let manifest = if path.is_dir() {
+        path.join(MANIFEST)
+    } else if path.file_name().is_some_and(|name| name == MANIFEST) {
+        path.to_path_buf()
+    } else {
+        return Ok(path.to_path_buf());
+    };
+
+    #[derive(serde::Deserialize)]
+    struct Manifest {
+        package: Package,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Package {
+        #[serde(default = "default_entry")]
+        entry: PathBuf,
+    }
+
+    fn default_entry() -> PathBuf {
+        PathBuf::from("src/main.rs")
+    }
+
+    let text = fs::read_to_string(&manifest).map_err(Error::Io)?;
+    let parsed: Manifest = toml::from_str(&text).map_err(|e| {
+        Error::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {e}", manifest.display()),
+        ))
+    })?;
+    let root = manifest.parent().unwrap_or(Path::new(""));
+    Ok(root.join(parsed.package.entry))
+}
+
+/// Load the program whose entry is `entry`, reading the file of each
+/// `mod name;` from `name.rs` or `name/mod.rs` beside its parent's, as rustc
+/// does. Every file read is recorded in `files` so that errors can be
+/// reported against it.
+pub fn load(entry: &Path, files: &mut SourceFiles) -> Result<RestrictedAst> {
+    let root = read_module(entry, files)?;
+    let dir = entry.parent().unwrap_or(Path::new("")).to_path_buf();
+    link(root, &dir, &mut |dir, submodule| {
+        let flat = dir.join(format!("{}.rs", submodule.name));
+        let nested = dir.join(&submodule.name).join("mod.rs");
+        match (flat.is_file(), nested.is_file()) {
+            (true, false) => read_module(&flat, files),
+            (false, true) => read_module(&nested, files),
+            (true, true) => Err(Diagnostic::new(
+                "E0128",
+                format!(
+                    "file for module `{}` found at both locations",
+                    submodule.name
+                ),
+            )
+            .with_primary(submodule.span, "ambiguous module file")
+            .with_help(format!(
+                "delete or rename either `{}` or `{}`",
+                flat.display(),
+                nested.display()
+            ))
+            .into()),
+            (false, false) => Err(Diagnostic::new(
+                "E0128",
+                format!("file not found for module `{}`", submodule.name),
+            )
+            .with_primary(submodule.span, "no file for this module")
+            .with_help(format!(
+                "create `{}` or `{}`",
+                flat.display(),
+                nested.display()
+            ))
+            .into()),
+        }
+    })
+}
+
+fn read_module(path: &Path, files: &mut SourceFiles) -> Result<Module> {
+    let source = fs::read_to_string(path).map_err(Error::Io)?;
+    let base = files.add(path.to_path_buf(), source);
+    let source = &files.files.last().expect("file was just added").source;
+    let mut module = super::parser::parse_module(source)
+        .map_err(|e| e.map_spans(&|span| Some(span.offset_by(base))))?;
+    module.offset_by(base);
+    Ok(module)
+}
+
+/// Resolve the paths of a parsed crate and flatten it into one AST.
+///
+/// `load` reads the module declared by a `mod name;` in directory `dir`.
+/// Functions of the root module are all kept; functions of other modules
+/// only when something calls them. Kept functions share the shell's one
+/// namespace, so functions of other modules are named after their module,
+/// e.g. `net__get`.
+pub fn link(
+    root: Module,
+    dir: &Path,
+    load: &mut dyn FnMut(&Path, &Submodule) -> Result<Module>,
+) -> Result<RestrictedAst> {
+    let entry_point = root.entry_point.as_ref().map(|(name, _)| name.clone());
+    let mut linker = Linker::default();
+    linker.add_module(root, Vec::new(), None, None, dir, load)?;
+
+    let entry_point = entry_point.ok_or_else(|| {
+        Diagnostic::new("E0103", "No #[bashrs::main] function found")
+            .with_help("add a `fn main() { ... }` function")
+    })?;
+
+    linker.check_imports()?;
+    linker.resolve_constants()?;
+
+    // Everything in the root module is compiled, and pulls in what it calls
+    let mut pending: Vec<usize> = linker.scopes[0].functions.values().copied().collect();
+    let mut kept = vec![false; linker.functions.len()];
+    while let Some(index) = pending.pop() {
+        if std::mem::replace(&mut kept[index], true) {
+            continue;
+        }
+        pending.extend(linker.resolve_calls(index)?);
+    }
+
+    let mut functions: Vec<(usize, Function, Span)> = linker
+        .functions
+        .into_iter()
+        .zip(kept)
+        .filter_map(|(function, kept)| kept.then_some(function))
+        .collect();
+    check_collisions(
+        &linker.scopes,
+        functions
+            .iter()
+            .map(|(scope, f, span)| (*scope, f.name.as_str(), *span)),
+        "Function",
+    )?;
+    check_collisions(
+        &linker.scopes,
+        linker
+            .constants
+            .iter()
+            .map(|(scope, c)| (*scope, c.name.as_str(), c.span)),
+        "Constant",
+    )?;
+
+    Ok(RestrictedAst {
+        functions: functions
+            .drain(..)
+            .map(|(_, function, _)| function)
+            .collect(),
+        entry_point,
+        constants: linker.constants.into_iter().map(|(_, c)| c).collect(),
+    })
+}
+
+/// Two items of different modules that would share a shell name
+fn check_collisions<'a>(
+    scopes: &[Scope],
+    items: impl Iterator<Item = (usize, &'a str, Span)>,
+    kind: &str,
+) -> Result<()> {
+    let mut seen: HashMap<&str, (usize, Span)> = HashMap::new();
+    for (scope, name, span) in items {
+        match seen.get(name) {
+            Some(&(first, first_span)) if first != scope => {
+                return Err(Diagnostic::new(
+                    "E0130",
+                    format!(
+                        "{kind} `{name}` is defined in both `{}` and `{}`",
+                        scopes[first].display(),
+                        scopes[scope].display()
+                    ),
+                )
+                .with_primary(span, "second definition")
+                .with_secondary(first_span, "first definition")
+                .with_help("shell functions and globals share one namespace; rename one of them")
+                .into());
+            }
+            _ => {
+                seen.entry(name).or_insert((scope, span));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What a path can name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    Module(usize),
+    Function(usize),
+    Constant(usize),
+}
+
+/// The items declared in, and imported into, one module
+#[derive(Debug, Default)]
+struct Scope {
+    path: Vec<String>,
+    parent: Option<usize>,
+    /// Span of the `mod` declaration, or `None` for the crate root
+    span: Option<Span>,
+    modules: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+    /// Names of the functions declared `pub`
+    public: HashSet<String>,
+    constants: HashMap<String, usize>,
+    imports: Vec<Import>,
+}
+
+impl Scope {
+    fn display(&self) -> String {
+        if self.path.is_empty() {
+            "crate".to_string()
+        } else {
+            self.path.join("::")
+        }
+    }
+}
+
+/// Imports may name other imports; deeper chains are treated as cycles
+const MAX_IMPORT_DEPTH: usize = 16;
+
+#[derive(Default)]
+struct Linker {
+    scopes: Vec<Scope>,
+    functions: Vec<(usize, Function, Span)>,
+    constants: Vec<(usize, Constant)>,
+}
+
+impl Linker {
+    fn add_module(
+        &mut self,
+        module: Module,
+        path: Vec<String>,
+        parent: Option<usize>,
+        span: Option<Span>,
+        dir: &Path,
+        load: &mut dyn FnMut(&Path, &Submodule) -> Result<Module>,
+    ) -> Result<usize> {
+        let index = self.scopes.len();
+        self.scopes.push(Scope {
+            path,
+            parent,
+            span,
+            imports: module.imports,
+            ..Scope::default()
+        });
+
+        let functions = module.functions.into_iter().zip(module.signatures);
+        for ((mut function, span), public) in functions.zip(module.public) {
+            let scope = &mut self.scopes[index];
+            if let Some(&first) = scope.functions.get(&function.name) {
+                return Err(Diagnostic::new(
+                    "E0119",
+                    format!("Function '{}' is defined more than once", function.name),
+                )
+                .with_primary(span, "redefined here")
+                .with_secondary(self.functions[first].2, "first defined here")
+                .with_help("rename or remove one of the definitions")
+                .into());
+            }
+            scope
+                .functions
+                .insert(function.name.clone(), self.functions.len());
+            if public {
+                scope.public.insert(function.name.clone());
+            }
+            // Functions of different modules may share a name, so those
+            // outside the crate root are named after their module; calls are
+            // rewritten to whatever name their function ends up with. A stub
+            // keeps the name of the command it stands for.
+            if !scope.path.is_empty() && !function.is_external_stub() {
+                function.name = format!("{}__{}", scope.path.join("__"), function.name);
+            }
+            self.functions.push((index, function, span));
+        }
+
+        for constant in module.constants {
+            let scope = &mut self.scopes[index];
+            if let Some(&first) = scope.constants.get(&constant.name) {
+                return Err(Diagnostic::new(
+                    "E0119",
+                    format!("Constant '{}' is defined more than once", constant.name),
+                )
+                .with_primary(constant.span, "redefined here")
+                .with_secondary(self.constants[first].1.span, "first defined here")
+                .with_help("rename or remove one of the definitions")
+                .into());
+            }
+            scope
+                .constants
+                .insert(constant.name.clone(), self.constants.len());
+            self.constants.push((index, constant));
+        }
+
+        let mut declared: HashMap<String, Span> = HashMap::new();
+        for submodule in module.submodules {
+            if let Some(&first) = declared.get(&submodule.name) {
+                return Err(Diagnostic::new(
+                    "E0119",
+                    format!("Module '{}' is defined more than once", submodule.name),
+                )
+                .with_primary(submodule.span, "redefined here")
+                .with_secondary(first, "first defined here")
+                .with_help("rename or remove one of the definitions")
+                .into());
+            }
+            declared.insert(submodule.name.clone(), submodule.span);
+            let items = match submodule.items {
+                Some(items) => items,
+                None => load(dir, &submodule)?,
+            };
+            let mut path = self.scopes[index].path.clone();
+            path.push(submodule.name.clone());
+            let child = self.add_module(
+                items,
+                path,
+                Some(index),
+                Some(submodule.span),
+                &dir.join(&submodule.name),
+                load,
+            )?;
+            self.scopes[index].modules.insert(submodule.name, child);
+        }
+
+        Ok(index)
+    }
+
+    /// An item declared directly in a module
+    fn member(&self, scope: usize, name: &str) -> Option<Item> {
+        let scope = &self.scopes[scope];
+        if let Some(&index) = scope.modules.get(name) {
+            Some(Item::Module(index))
+        } else if let Some(&index) = scope.functions.get(name) {
+            Some(Item::Function(index))
+        } else {
+            scope
+                .constants
+                .get(name)
+                .map(|&index| Item::Constant(index))
+        }
+    }
+
+    /// Where an item is declared, when it has a declaration
+    fn declaration(&self, item: Item) -> Option<Span> {
+        match item {
+            Item::Module(index) => self.scopes[index].span,
+            Item::Function(index) => Some(self.functions[index].2),
+            Item::Constant(index) => Some(self.constants[index].1.span),
+        }
+    }
+
+    /// An item visible by name inside a module, declared or imported.
+    /// Declared items and names imported by name shadow glob imports. A name
+    /// imported by name twice, or both imported by name and declared, is an
+    /// error, as is one that glob imports give different items.
+    fn lookup(&self, scope: usize, name: &str, depth: usize) -> Result<Option<Item>> {
+        let member = self.member(scope, name);
+        if depth > MAX_IMPORT_DEPTH {
+            return Ok(member);
+        }
+        let imports = &self.scopes[scope].imports;
+        let mut named = imports
+            .iter()
+            .filter(|import| import.name.as_deref() == Some(name));
+        if let Some(import) = named.next() {
+            if let Some(again) = named.next() {
+                return Err(Diagnostic::new(
+                    "E0135",
+                    format!("the name `{name}` is imported more than once"),
+                )
+                .with_primary(again.span, "imported again here")
+                .with_secondary(import.span, "first imported here")
+                .with_help(format!(
+                    "remove one of the imports, or rename one with `as`, e.g. `use {} as other_{name};`",
+                    again.path.join("::")
+                ))
+                .into());
+            }
+            if let Some(item) = member {
+                let mut diagnostic = Diagnostic::new(
+                    "E0135",
+                    format!("the name `{name}` is both declared and imported"),
+                )
+                .with_primary(import.span, "imported here");
+                if let Some(span) = self.declaration(item) {
+                    diagnostic = diagnostic.with_secondary(span, "declared here");
+                }
+                return Err(diagnostic
+                    .with_help("rename the declared item, or rename the import with `as`")
+                    .into());
+            }
+            return self.resolve(scope, &import.path, depth + 1);
+        }
+        if member.is_some() {
+            return Ok(member);
+        }
+
+        let mut found: Option<(Item, &Import)> = None;
+        for import in imports.iter().filter(|import| import.name.is_none()) {
+            let Some(Item::Module(module)) = self.resolve(scope, &import.path, depth + 1)? else {
+                continue;
+            };
+            let Some(item) = self.member(module, name) else {
+                continue;
+            };
+            match found {
+                Some((first, _)) if first == item => {}
+                Some((_, first)) => {
+                    return Err(Diagnostic::new("E0136", format!("`{name}` is ambiguous"))
+                        .with_secondary(first.span, format!("`{name}` could come from this import"))
+                        .with_secondary(import.span, "or from this one")
+                        .with_help(format!(
+                            "import `{name}` by name, or call it by its path, e.g. `{}::{name}`",
+                            import.path.join("::")
+                        ))
+                        .into())
+                }
+                None => found = Some((item, import)),
+            }
+        }
+        Ok(found.map(|(item, _)| item))
+    }
+
+    /// The item a path names from inside `scope`
+    fn resolve(&self, scope: usize, path: &[String], depth: usize) -> Result<Option<Item>> {
+        let Some((first, rest)) = path.split_first() else {
+            return Ok(None);
+        };
+        let item = match first.as_str() {
+            "crate" => Some(Item::Module(0)),
+            "self" => Some(Item::Module(scope)),
+            "super" => self.scopes[scope].parent.map(Item::Module),
+            name => self.lookup(scope, name, depth)?,
+        };
+        let Some(item) = item else {
+            return Ok(None);
+        };
+        Ok(rest.iter().try_fold(item, |item, segment| {
+            let Item::Module(module) = item else {
+                return None;
+            };
+            match segment.as_str() {
+                "super" => self.scopes[module].parent.map(Item::Module),
+                name => self.member(module, name),
+            }
+        }))
+    }
+
+    /// Whether a path starts at a module of this crate, rather than at
+    /// `bashrs_std` or an external name
+    fn is_crate_path(&self, scope: usize, path: &[String]) -> Result<bool> {
+        Ok(match path.first().map(String::as_str) {
+            Some("crate" | "self" | "super") => true,
+            Some(name) => self.lookup(scope, name, 0)?.is_some(),
+            None => false,
+        })
+    }
+
+    fn check_imports(&self) -> Result<()> {
+        for (index, scope) in self.scopes.iter().enumerate() {
+            for import in &scope.imports {
+                let path = import.path.join("::");
+                // Names imported twice, or also declared, are errors even
+                // when nothing uses them
+                if let Some(name) = &import.name {
+                    self.lookup(index, name, 0)?;
+                }
+                let item = self
+                    .resolve(index, &import.path, 0)
+                    .map_err(|e| e.at(import.span))?;
+                match item {
+                    None => {
+                        return Err(Diagnostic::new(
+                            "E0129",
+                            format!("unresolved import `{path}`"),
+                        )
+                        .with_primary(import.span, "no such item")
+                        .with_help(
+                            "Rash code can import the modules of `bashrs_std` and the items of its own crate",
+                        )
+                        .into())
+                    }
+                    Some(Item::Module(_)) => {}
+                    Some(_) if import.name.is_none() => {
+                        return Err(Diagnostic::new(
+                            "E0129",
+                            format!("`{path}` is not a module"),
+                        )
+                        .with_primary(import.span, "glob imports need a module")
+                        .with_help("import the items by name instead")
+                        .into())
+                    }
+                    Some(Item::Constant(_)) if import.path.last() != import.name.as_ref() => {
+                        return Err(Diagnostic::new(
+                            "E0129",
+                            format!("constant `{path}` can't be renamed"),
+                        )
+                        .with_primary(import.span, "renamed here")
+                        .with_help(
+                            "constants become globals named after their definition; import it without `as`",
+                        )
+                        .into())
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrite paths to constants in constant values to the constants' names
+    fn resolve_constants(&mut self) -> Result<()> {
+        for index in 0..self.constants.len() {
+            let (scope, constant) = &self.constants[index];
+            let (scope, span) = (*scope, constant.span);
+            let mut value = constant.value.clone();
+            let mut called = Vec::new();
+            let mut error = None;
+            rewrite_expr(&mut value, &mut |expr| {
+                if error.is_none() {
+                    error = self.resolve_expr(scope, expr, &mut called).err();
+                }
+            });
+            if let Some(error) = error {
+                return Err(error.at(span));
+            }
+            self.constants[index].1.value = value;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the calls in a function to the names of the functions they
+    /// reach, returning the functions called
+    fn resolve_calls(&mut self, index: usize) -> Result<Vec<usize>> {
+        let scope = self.functions[index].0;
+        let mut body = std::mem::take(&mut self.functions[index].1.body);
+        let mut called = Vec::new();
+        let mut error = None;
+        for stmt in &mut body {
+            let span = match stmt {
+                Stmt::Spanned { span, .. } => Some(*span),
+                _ => None,
+            };
+            transform_stmt_exprs(stmt, &mut |expr| {
+                if error.is_none() {
+                    error = self
+                        .resolve_expr(scope, expr, &mut called)
+                        .err()
+                        .map(|e| match span {
+                            Some(span) => e.at(span),
+                            None => e,
+                        });
+                }
+            });
+        }
+        self.functions[index].1.body = body;
+        match error {
+            Some(error) => Err(error),
+            None => Ok(called),
+        }
+    }
+
+    fn resolve_expr(&self, scope: usize, expr: &mut Expr, called: &mut Vec<usize>) -> Result<()> {
+        match expr {
+            Expr::FunctionCall { name, .. } => {
+                let path: Vec<String> = name.split("::").map(str::to_string).collect();
+                if path.len() == 1 {
+                    match self.lookup(scope, name, 0)? {
+                        Some(Item::Function(index)) => {
+                            called.push(index);
+                            name.clone_from(&self.functions[index].1.name);
+                        }
+                        Some(_) => {}
+                        None => self.check_not_hidden(scope, name)?,
+                    }
+                } else if self.is_crate_path(scope, &path)? {
+                    match self.resolve(scope, &path, 0)? {
+                        Some(Item::Function(index)) => {
+                            called.push(index);
+                            name.clone_from(&self.functions[index].1.name);
+                        }
+                        _ => {
+                            return Err(Diagnostic::new(
+                                "E0129",
+                                format!("cannot find function `{name}`"),
+                            )
+                            .with_help("check the module path and the function's name")
+                            .into())
+                        }
+                    }
+                }
+            }
+            Expr::Variable(name) if name.contains("::") => {
+                let path: Vec<String> = name.split("::").map(str::to_string).collect();
+                if self.is_crate_path(scope, &path)? {
+                    match self.resolve(scope, &path, 0)? {
+                        Some(Item::Constant(index)) => {
+                            name.clone_from(&self.constants[index].1.name);
+                        }
+                        _ => {
+                            return Err(Diagnostic::new(
+                                "E0129",
+                                format!("cannot find constant `{name}`"),
+                            )
+                            .with_help("check the module path and the constant's name")
+                            .into())
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Calls to names that aren't in scope run external commands, unless
+    /// another module exports a function by that name, which is most likely
+    /// what was meant
+    fn check_not_hidden(&self, scope: usize, name: &str) -> Result<()> {
+        let Some(owner) = self
+            .scopes
+            .iter()
+            .enumerate()
+            .find(|(index, owner)| *index != scope && owner.public.contains(name))
+            .map(|(_, owner)| owner)
+        else {
+            return Ok(());
+        };
+        let mut path = vec!["crate".to_string()];
+        path.extend(owner.path.iter().cloned());
+        path.push(name.to_string());
+        Err(
+            Diagnostic::new("E0129", format!("function `{name}` is not in scope"))
+                .with_help(format!("import it with `use {};`", path.join("::")))
+                .into(),
+        )
+    }
+}
+
+/// Call `f` on an expression and each expression inside it
+fn rewrite_expr(expr: &mut Expr, f: &mut impl FnMut(&mut Expr)) {
+    let mut stmt = Stmt::Expr(std::mem::replace(expr, Expr::Variable(String::new())));
+    transform_stmt_exprs(&mut stmt, f);
+    if let Stmt::Expr(rewritten) = stmt {
+        *expr = rewritten;
+    }
+}
@@ -1,20 +1,52 @@
 use crate::ast::restricted::{
-    BinaryOp, Expr, Function, Literal, Parameter, RestrictedAst, Stmt, Type, UnaryOp,
+    BinaryOp, Constant, EffectBudget, Expr, Function, Literal, MatchArm, Parameter, Pattern,
+    RestrictedAst, Span, Stmt, Type, UnaryOp,
 };
-use crate::models::{Error, Result};
+use crate::ir::Effect;
+use crate::models::{Diagnostic, Error, Result};
+use crate::services::modules::{Import, Module, Submodule};
+use std::path::Path;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{
-    BinOp, Block, Expr as SynExpr, File, FnArg, Item, ItemFn, Lit, Pat, ReturnType,
-    Stmt as SynStmt, Type as SynType, UnOp,
+    BinOp, Block, Expr as SynExpr, ExprForLoop, ExprIf, ExprLoop, ExprMatch, ExprWhile, File,
+    FnArg, GenericArgument, Item, ItemFn, Lit, Pat, PathArguments, RangeLimits, ReturnType,
+    Stmt as SynStmt, Token, Type as SynType, UnOp,
 };
 
+/// Iteration bound assigned to loops whose trip count is not known statically
+pub const DEFAULT_MAX_ITERATIONS: u32 = 10_000;
+
 /// Parse Rust source code into a RestrictedAst
+///
+/// Inline `mod` blocks are resolved like the modules of a project; modules
+/// kept in their own files need [`super::modules::load`].
 pub fn parse(input: &str) -> Result<RestrictedAst> {
-    let file: File = syn::parse_str(input)?;
+    let module = parse_module(input)?;
+    super::modules::link(module, Path::new(""), &mut |_, submodule| {
+        Err(Diagnostic::new(
+            "E0128",
+            format!("module `{}` is declared without a body", submodule.name),
+        )
+        .with_primary(submodule.span, "its file can't be found from a string")
+        .with_help("build the project from its entry file so that `mod` files are loaded")
+        .into())
+    })
+}
 
-    let mut functions = Vec::new();
-    let mut entry_point = None;
+/// Parse one source file into its items, leaving module paths unresolved
+pub fn parse_module(input: &str) -> Result<Module> {
+    let file: File = syn::parse_str(input).map_err(|e| {
+        let span = span_of(&e.span());
+        Error::Parse(e).at(span)
+    })?;
+    convert_items(file.items)
+}
 
-    for item in file.items {
+fn convert_items(items: Vec<Item>) -> Result<Module> {
+    let mut module = Module::default();
+
+    for item in items {
         match item {
             Item::Fn(item_fn) => {
                 // Check if this is the main function marked with #[bashrs::main]
@@ -26,38 +58,205 @@ pub fn parse(input: &str) -> Result<RestrictedAst> {
                         && path.segments[1].ident == "main"
                 }) || item_fn.sig.ident == "main";
 
-                let function = convert_function(item_fn)?;
+                let span = span_of(&item_fn.sig);
+                let public = !matches!(item_fn.vis, syn::Visibility::Inherited);
+                let function = convert_function(item_fn).map_err(|e| e.at(span))?;
 
                 if is_main {
-                    if entry_point.is_some() {
-                        return Err(Error::Validation(
-                            "Multiple #[bashrs::main] functions found".to_string(),
-                        ));
+                    if let Some((_, first)) = module.entry_point {
+                        return Err(Diagnostic::new(
+                            "E0102",
+                            "Multiple #[bashrs::main] functions found",
+                        )
+                        .with_primary(span, "second entry point")
+                        .with_secondary(first, "first entry point defined here")
+                        .with_help("keep a single `fn main` or `#[rash::main]` function")
+                        .into());
                     }
-                    entry_point = Some(function.name.clone());
+                    module.entry_point = Some((function.name.clone(), span));
                 }
 
-                functions.push(function);
+                module.functions.push(function);
+                module.signatures.push(span);
+                module.public.push(public);
+            }
+            Item::Use(item_use) => {
+                if matches!(&item_use.tree, syn::UseTree::Path(root) if root.ident == "bashrs_std")
+                {
+                    check_use(&item_use)?;
+                } else {
+                    convert_use_tree(&item_use.tree, &mut Vec::new(), &mut module.imports)?;
+                }
+            }
+            Item::Mod(item_mod) => {
+                let items = match item_mod.content {
+                    Some((_, items)) => Some(convert_items(items)?),
+                    None => None,
+                };
+                module.submodules.push(Submodule {
+                    name: item_mod.ident.to_string(),
+                    span: span_of(&item_mod.ident),
+                    items,
+                });
             }
-            _ => {
-                return Err(Error::Validation(
-                    "Only functions are allowed in Rash code".to_string(),
-                ));
+            Item::Const(item_const) => {
+                let span = span_of(&item_const);
+                module.constants.push(
+                    convert_constant(&item_const.ident, &item_const.ty, &item_const.expr, span)
+                        .map_err(|e| e.at(span))?,
+                );
+            }
+            Item::Static(item_static) => {
+                let span = span_of(&item_static);
+                if let syn::StaticMutability::Mut(token) = &item_static.mutability {
+                    return Err(
+                        Diagnostic::new("E0127", "mutable statics are not supported")
+                            .with_primary(span_of(token), "globals are readonly in the script")
+                            .with_help(
+                                "remove `mut`, or keep the changing value in a local variable",
+                            )
+                            .into(),
+                    );
+                }
+                module.constants.push(
+                    convert_constant(&item_static.ident, &item_static.ty, &item_static.expr, span)
+                        .map_err(|e| e.at(span))?,
+                );
+            }
+            item => {
+                return Err(
+                    Diagnostic::new("E0101", "Only functions are allowed in Rash code")
+                        .with_primary(span_of(&item), "not a function")
+                        .with_help("move this logic into a function")
+                        .into(),
+                );
             }
         }
     }
 
-    let entry_point = entry_point
-        .ok_or_else(|| Error::Validation("No #[bashrs::main] function found".to_string()))?;
+    Ok(module)
+}
 
-    Ok(RestrictedAst {
-        functions,
-        entry_point,
+/// Flatten a `use` tree of the crate's own items into one import per name
+fn convert_use_tree(
+    tree: &syn::UseTree,
+    prefix: &mut Vec<String>,
+    imports: &mut Vec<Import>,
+) -> Result<()> {
+    match tree {
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            convert_use_tree(&path.tree, prefix, imports)?;
+            prefix.pop();
+        }
+        syn::UseTree::Name(name) if name.ident == "self" => {
+            let Some(module) = prefix.last() else {
+                return Err(unsupported_use(tree));
+            };
+            imports.push(Import {
+                name: Some(module.clone()),
+                path: prefix.clone(),
+                span: span_of(tree),
+            });
+        }
+        syn::UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            imports.push(Import {
+                name: Some(name.ident.to_string()),
+                path,
+                span: span_of(tree),
+            });
+        }
+        syn::UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            path.push(rename.ident.to_string());
+            imports.push(Import {
+                name: Some(rename.rename.to_string()),
+                path,
+                span: span_of(tree),
+            });
+        }
+        syn::UseTree::Glob(_) => imports.push(Import {
+            name: None,
+            path: prefix.clone(),
+            span: span_of(tree),
+        }),
+        syn::UseTree::Group(group) => {
+            for item in &group.items {
+                convert_use_tree(item, prefix, imports)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn convert_constant(
+    ident: &syn::Ident,
+    ty: &SynType,
+    value: &SynExpr,
+    span: Span,
+) -> Result<Constant> {
+    Ok(Constant {
+        name: ident.to_string(),
+        ty: convert_type(ty)?,
+        value: convert_expr(value)?,
+        span,
     })
 }
 
+/// Accept imports of `bashrs_std` modules, which need no translation since
+/// calls name their module (`fs::mkdir_p`)
+fn check_use(item_use: &syn::ItemUse) -> Result<()> {
+    let syn::UseTree::Path(root) = &item_use.tree else {
+        return Err(unsupported_use(&item_use.tree));
+    };
+    if root.ident != "bashrs_std" {
+        return Err(unsupported_use(&item_use.tree));
+    }
+    let modules: Vec<&syn::UseTree> = match &*root.tree {
+        syn::UseTree::Group(group) => group.items.iter().collect(),
+        tree => vec![tree],
+    };
+    match modules
+        .into_iter()
+        .find(|tree| !matches!(tree, syn::UseTree::Name(_)))
+    {
+        Some(tree) => Err(Diagnostic::new(
+            "E0125",
+            "Only modules of `bashrs_std` can be imported",
+        )
+        .with_primary(span_of(tree), "imports more than a module")
+        .with_help("import the module and call through it, as in `use bashrs_std::fs;` and `fs::mkdir_p(dir)`")
+        .into()),
+        None => Ok(()),
+    }
+}
+
+fn unsupported_use(tree: &syn::UseTree) -> Error {
+    Diagnostic::new("E0125", "Only `bashrs_std` can be imported in Rash code")
+        .with_primary(span_of(tree), "unsupported import")
+        .with_help("Rash programs can use the modules of `bashrs_std`, such as `use bashrs_std::{fs, log};`")
+        .into()
+}
+
+/// Location of a syntax node in the parsed source
+fn span_of(node: &impl Spanned) -> Span {
+    let span = node.span();
+    let bytes = span.byte_range();
+    let start = span.start();
+    Span {
+        start: bytes.start,
+        end: bytes.end,
+        line: start.line,
+        // proc-macro2 columns are 0-based
+        column: start.column + 1,
+    }
+}
+
 fn convert_function(item_fn: ItemFn) -> Result<Function> {
     let name = item_fn.sig.ident.to_string();
+    let effects = convert_effects_attribute(&item_fn.attrs)?;
 
     // Convert parameters
     let mut params = Vec::new();
@@ -72,34 +271,112 @@ fn convert_function(item_fn: ItemFn) -> Result<Function> {
                         param_type,
                     });
                 } else {
-                    return Err(Error::Validation(
-                        "Complex parameter patterns not supported".to_string(),
-                    ));
+                    return Err(Diagnostic::new("E0104", "unsupported parameter pattern")
+                        .with_primary(span_of(&pat_type.pat), "expected a plain name")
+                        .with_help("bind the parameter to a name and destructure it in the body")
+                        .into());
                 }
             }
-            FnArg::Receiver(_) => {
-                return Err(Error::Validation("Self parameters not allowed".to_string()));
+            FnArg::Receiver(receiver) => {
+                return Err(
+                    Diagnostic::new("E0105", "`self` parameters are not allowed")
+                        .with_primary(span_of(&receiver), "methods aren't supported")
+                        .with_help("use a free function that takes the value as a parameter")
+                        .into(),
+                );
             }
         }
     }
 
     // Convert return type
     let return_type = match &item_fn.sig.output {
-        ReturnType::Default => Type::Str, // Default to unit type represented as string
+        ReturnType::Default => Type::Void,
         ReturnType::Type(_, ty) => convert_type(ty)?,
     };
 
     // Convert function body
-    let body = convert_block(&item_fn.block)?;
+    let mut body = convert_block(&item_fn.block)?;
+
+    // A trailing expression without a semicolon is the function's return value
+    if !matches!(return_type, Type::Void) {
+        if let Some(SynStmt::Expr(_, None)) = item_fn.block.stmts.last() {
+            if let Some(tail) = body.pop() {
+                body.push(return_tail(tail));
+            }
+        }
+    }
 
     Ok(Function {
         name,
         params,
         return_type,
         body,
+        effects,
     })
 }
 
+/// The effects named by the function's `#[rash::effects(...)]` attribute
+fn convert_effects_attribute(attrs: &[syn::Attribute]) -> Result<Option<EffectBudget>> {
+    let mut budget: Option<EffectBudget> = None;
+    for attr in attrs {
+        let path = attr.path();
+        if !(path.segments.len() == 2
+            && path.segments[0].ident == "rash"
+            && path.segments[1].ident == "effects")
+        {
+            continue;
+        }
+        let span = span_of(attr);
+        if let Some(first) = &budget {
+            return Err(
+                Diagnostic::new("E0134", "effects are declared more than once")
+                    .with_primary(span, "second declaration")
+                    .with_secondary(first.span, "first declared here")
+                    .with_help("list every effect in one `#[rash::effects(...)]` attribute")
+                    .into(),
+            );
+        }
+        let names = match &attr.meta {
+            // `#[rash::effects]` alone allows no effects, like `#[rash::effects()]`
+            syn::Meta::Path(_) => Punctuated::new(),
+            syn::Meta::List(list) => list
+                .parse_args_with(Punctuated::<syn::Ident, Token![,]>::parse_terminated)
+                .map_err(|e| {
+                    Diagnostic::new("E0134", "expected a list of effect names")
+                        .with_primary(span_of(&e.span()), "not an effect name")
+                        .with_help(effect_names_help())
+                })?,
+            syn::Meta::NameValue(_) => {
+                return Err(Diagnostic::new("E0134", "expected a list of effect names")
+                    .with_primary(span, "write the effects in parentheses")
+                    .with_help(effect_names_help())
+                    .into())
+            }
+        };
+        let mut effects = Vec::new();
+        for name in names {
+            let effect = Effect::from_name(&name.to_string()).ok_or_else(|| {
+                Diagnostic::new("E0134", format!("unknown effect `{name}`"))
+                    .with_primary(span_of(&name), "not an effect")
+                    .with_help(effect_names_help())
+            })?;
+            if effect != Effect::Pure && !effects.contains(&effect) {
+                effects.push(effect);
+            }
+        }
+        budget = Some(EffectBudget { effects, span });
+    }
+    Ok(budget)
+}
+
+fn effect_names_help() -> String {
+    let names: Vec<String> = Effect::DECLARABLE
+        .iter()
+        .map(|effect| format!("`{}`", effect.name()))
+        .collect();
+    format!("the effects are {}, or `pure` for none", names.join(", "))
+}
+
 fn convert_type(ty: &SynType) -> Result<Type> {
     match ty {
         SynType::Path(type_path) => {
@@ -115,25 +392,65 @@ fn convert_type(ty: &SynType) -> Result<Type> {
                 "bool" => Ok(Type::Bool),
                 "u32" | "i32" => Ok(Type::U32), // Treat i32 as u32 for now
                 "str" | "String" => Ok(Type::Str),
-                path if path.starts_with("Result") => {
-                    // Simple Result type parsing - assumes Result<T, E>
-                    Ok(Type::Result {
-                        ok_type: Box::new(Type::Str), // Simplified
-                        err_type: Box::new(Type::Str),
-                    })
+                "Vec" => {
+                    let last = type_path.path.segments.last();
+                    match last.map(|segment| &segment.arguments) {
+                        Some(PathArguments::AngleBracketed(generics)) => {
+                            match generics.args.first() {
+                                Some(GenericArgument::Type(elem)) if generics.args.len() == 1 => {
+                                    list_type(elem)
+                                }
+                                _ => Err(unsupported_type(ty, "expected `Vec<T>`")),
+                            }
+                        }
+                        _ => Err(unsupported_type(ty, "expected `Vec<T>`")),
+                    }
                 }
-                path if path.starts_with("Option") => {
-                    // Simple Option type parsing - assumes Option<T>
-                    Ok(Type::Option {
-                        inner_type: Box::new(Type::Str), // Simplified
-                    })
+                "Result" => {
+                    let last = type_path.path.segments.last();
+                    match last.map(|segment| &segment.arguments) {
+                        Some(PathArguments::AngleBracketed(generics))
+                            if generics.args.len() == 2 =>
+                        {
+                            let mut types = generics.args.iter().map(|arg| match arg {
+                                GenericArgument::Type(part) => result_part_type(part),
+                                _ => Err(unsupported_type(ty, "expected `Result<T, E>`")),
+                            });
+                            let ok_type = types.next().expect("two arguments")?;
+                            let err_type = types.next().expect("two arguments")?;
+                            Ok(Type::Result {
+                                ok_type: Box::new(ok_type),
+                                err_type: Box::new(err_type),
+                            })
+                        }
+                        _ => Err(unsupported_type(ty, "expected `Result<T, E>`")),
+                    }
+                }
+                "Option" => {
+                    let last = type_path.path.segments.last();
+                    match last.map(|segment| &segment.arguments) {
+                        Some(PathArguments::AngleBracketed(generics)) => {
+                            match generics.args.first() {
+                                Some(GenericArgument::Type(inner)) if generics.args.len() == 1 => {
+                                    option_type(inner)
+                                }
+                                _ => Err(unsupported_type(ty, "expected `Option<T>`")),
+                            }
+                        }
+                        _ => Err(unsupported_type(ty, "expected `Option<T>`")),
+                    }
                 }
-                _ => Err(Error::Validation(format!("Unsupported type: {path_str}"))),
+                _ => Err(unsupported_type(
+                    ty,
+                    format!("unsupported type `{path_str}`"),
+                )),
             }
         }
         SynType::Reference(type_ref) => {
-            // Handle &str and other reference types
-            if let SynType::Path(path) = &*type_ref.elem {
+            // Handle &str, slices and other reference types
+            if let SynType::Slice(slice) = &*type_ref.elem {
+                list_type(&slice.elem)
+            } else if let SynType::Path(path) = &*type_ref.elem {
                 let path_str = path
                     .path
                     .segments
@@ -144,25 +461,78 @@ fn convert_type(ty: &SynType) -> Result<Type> {
 
                 match path_str.as_str() {
                     "str" => Ok(Type::Str),
-                    _ => Err(Error::Validation(format!(
-                        "Unsupported reference type: &{path_str}"
-                    ))),
+                    _ => Err(unsupported_type(
+                        ty,
+                        format!("unsupported reference type `&{path_str}`"),
+                    )),
                 }
             } else {
-                Err(Error::Validation(
-                    "Complex reference types not supported".to_string(),
-                ))
+                Err(unsupported_type(ty, "unsupported reference type"))
             }
         }
-        _ => Err(Error::Validation("Complex types not supported".to_string())),
+        SynType::Array(array) => list_type(&array.elem),
+        _ => Err(unsupported_type(ty, "unsupported type")),
+    }
+}
+
+/// A list whose elements have type `elem`, which must fit in a single shell word
+fn list_type(elem: &SynType) -> Result<Type> {
+    let inner_type = convert_type(elem)?;
+    if !matches!(inner_type, Type::Bool | Type::U32 | Type::Str) {
+        return Err(Diagnostic::new(
+            "E0106",
+            "lists can only hold strings, integers and booleans",
+        )
+        .with_primary(span_of(elem), "element type not supported in shell")
+        .with_help("use `Vec<&str>`, `Vec<String>` or `Vec<u32>`")
+        .into());
     }
+    Ok(Type::Vec {
+        inner_type: Box::new(inner_type),
+    })
+}
+
+/// An optional value of type `inner`, which is a variable that may be unset
+fn option_type(inner: &SynType) -> Result<Type> {
+    let inner_type = convert_type(inner)?;
+    if !matches!(inner_type, Type::U32 | Type::Str) {
+        return Err(
+            Diagnostic::new("E0106", "options can only hold strings and integers")
+                .with_primary(span_of(inner), "type not supported in an `Option`")
+                .with_help("use `Option<String>`, `Option<&str>` or `Option<u32>`")
+                .into(),
+        );
+    }
+    Ok(Type::Option {
+        inner_type: Box::new(inner_type),
+    })
+}
+
+/// The success or error type of a `Result`, where `()` means no value
+fn result_part_type(ty: &SynType) -> Result<Type> {
+    match ty {
+        SynType::Tuple(tuple) if tuple.elems.is_empty() => Ok(Type::Void),
+        _ => convert_type(ty),
+    }
+}
+
+fn unsupported_type(ty: &SynType, message: impl Into<String>) -> Error {
+    Diagnostic::new("E0106", message)
+        .with_primary(span_of(ty), "type not supported in shell")
+        .with_help("use `bool`, `u32`, `&str`, `String`, `Vec`, `Option` or `Result`")
+        .into()
 }
 
 fn convert_block(block: &Block) -> Result<Vec<Stmt>> {
     let mut statements = Vec::new();
 
     for stmt in &block.stmts {
-        statements.push(convert_stmt(stmt)?);
+        let span = span_of(stmt);
+        let converted = convert_stmt(stmt).map_err(|e| e.at(span))?;
+        statements.push(Stmt::Spanned {
+            span,
+            stmt: Box::new(converted),
+        });
     }
 
     Ok(statements)
@@ -171,49 +541,369 @@ fn convert_block(block: &Block) -> Result<Vec<Stmt>> {
 fn convert_stmt(stmt: &SynStmt) -> Result<Stmt> {
     match stmt {
         SynStmt::Local(local) => {
-            if let Pat::Ident(pat_ident) = &local.pat {
+            // `let name: T = value` checks that T is expressible, then drops it
+            let pat = match &local.pat {
+                Pat::Type(pat_type) => {
+                    convert_type(&pat_type.ty)?;
+                    &*pat_type.pat
+                }
+                pat => pat,
+            };
+            if let Pat::Ident(pat_ident) = pat {
                 let name = pat_ident.ident.to_string();
+                let mutable = pat_ident.mutability.is_some();
                 if let Some(init) = &local.init {
                     let value = convert_expr(&init.expr)?;
-                    Ok(Stmt::Let { name, value })
+                    Ok(Stmt::Let {
+                        name,
+                        value,
+                        mutable,
+                    })
                 } else {
-                    Err(Error::Validation(
-                        "Let bindings must have initializers".to_string(),
-                    ))
+                    Err(
+                        Diagnostic::new("E0108", "`let` bindings must have an initializer")
+                            .with_primary(span_of(local), "binding has no value")
+                            .with_help("give the binding a value: `let name = value;`")
+                            .into(),
+                    )
                 }
             } else {
-                Err(Error::Validation(
-                    "Complex patterns not supported".to_string(),
-                ))
+                Err(Diagnostic::new("E0109", "unsupported `let` pattern")
+                    .with_primary(span_of(pat), "expected a plain name")
+                    .with_help("bind each value with its own `let`")
+                    .into())
             }
         }
-        SynStmt::Expr(expr, _) => {
-            // Check if this is an if expression used as a statement
-            if let SynExpr::If(expr_if) = expr {
-                let condition = convert_expr(&expr_if.cond)?;
-                let then_block = convert_block(&expr_if.then_branch)?;
-                let else_block = if let Some((_, else_expr)) = &expr_if.else_branch {
-                    match &**else_expr {
-                        SynExpr::Block(block) => Some(convert_block(&block.block)?),
-                        SynExpr::If(_) => {
-                            // Handle else-if by converting to nested if statement
-                            Some(vec![Stmt::Expr(convert_expr(else_expr)?)])
-                        }
-                        _ => None,
-                    }
-                } else {
-                    None
-                };
-                Ok(Stmt::If {
-                    condition,
-                    then_block,
-                    else_block,
+        SynStmt::Expr(expr, _) => convert_expr_stmt(expr),
+        SynStmt::Item(item) => Err(Diagnostic::new("E0107", "nested items are not supported")
+            .with_primary(span_of(item), "defined inside a function")
+            .with_help("move the item to the top level of the file")
+            .into()),
+        SynStmt::Macro(stmt_macro) => match convert_macro(&stmt_macro.mac)? {
+            Some(expr) => Ok(Stmt::Expr(expr)),
+            None => Err(unsupported_expr_diagnostic(
+                span_of(stmt_macro),
+                "macros are not supported",
+                "macro invocation",
+                "call a function instead",
+            )),
+        },
+    }
+}
+
+fn convert_expr_stmt(expr: &SynExpr) -> Result<Stmt> {
+    match expr {
+        // Control flow expressions used as statements
+        SynExpr::If(expr_if) => convert_if_stmt(expr_if),
+        SynExpr::ForLoop(for_loop) => convert_for_loop(for_loop),
+        SynExpr::While(expr_while) => convert_while_loop(expr_while),
+        SynExpr::Loop(expr_loop) => convert_loop(expr_loop),
+        SynExpr::Match(expr_match) => convert_match_stmt(expr_match),
+        SynExpr::Block(expr_block) if expr_block.label.is_none() => {
+            Ok(Stmt::Block(convert_block(&expr_block.block)?))
+        }
+        SynExpr::Return(ret_expr) => match &ret_expr.expr {
+            Some(value) => Ok(Stmt::Return(Some(convert_expr(value)?))),
+            None => Ok(Stmt::Return(None)),
+        },
+        SynExpr::Break(expr_break) => {
+            if let Some(label) = &expr_break.label {
+                return Err(loop_label_error(label));
+            }
+            if let Some(value) = &expr_break.expr {
+                return Err(
+                    Diagnostic::new("E0118", "`break` with a value is not supported")
+                        .with_primary(span_of(value), "loops don't produce values in shell")
+                        .with_help("assign the value to a variable before breaking")
+                        .into(),
+                );
+            }
+            Ok(Stmt::Break)
+        }
+        SynExpr::Continue(expr_continue) => {
+            if let Some(label) = &expr_continue.label {
+                return Err(loop_label_error(label));
+            }
+            Ok(Stmt::Continue)
+        }
+        SynExpr::Assign(assign) => Ok(Stmt::Assign {
+            name: assignment_target(&assign.left)?,
+            value: convert_expr(&assign.right)?,
+        }),
+        SynExpr::Binary(expr_binary) if compound_assignment_op(&expr_binary.op).is_some() => {
+            // `x += y` is `x = x + y`
+            let name = assignment_target(&expr_binary.left)?;
+            let op = compound_assignment_op(&expr_binary.op).expect("checked above");
+            Ok(Stmt::Assign {
+                value: Expr::Binary {
+                    op,
+                    left: Box::new(Expr::Variable(name.clone())),
+                    right: Box::new(convert_expr(&expr_binary.right)?),
+                },
+                name,
+            })
+        }
+        _ => Ok(Stmt::Expr(convert_expr(expr)?)),
+    }
+}
+
+/// The variable an assignment writes to
+fn assignment_target(left: &SynExpr) -> Result<String> {
+    match left {
+        SynExpr::Path(expr_path) if expr_path.path.get_ident().is_some() => {
+            Ok(expr_path.path.segments[0].ident.to_string())
+        }
+        _ => Err(
+            Diagnostic::new("E0131", "only variables can be assigned to")
+                .with_primary(span_of(left), "not a variable")
+                .with_help("assign to a variable declared with `let mut`")
+                .into(),
+        ),
+    }
+}
+
+/// The operator a compound assignment such as `+=` applies
+fn compound_assignment_op(op: &BinOp) -> Option<BinaryOp> {
+    match op {
+        BinOp::AddAssign(_) => Some(BinaryOp::Add),
+        BinOp::SubAssign(_) => Some(BinaryOp::Sub),
+        BinOp::MulAssign(_) => Some(BinaryOp::Mul),
+        BinOp::DivAssign(_) => Some(BinaryOp::Div),
+        _ => None,
+    }
+}
+
+/// Return the value of a function's tail statement: the expression itself, or
+/// the last expression of each branch of a tail `if`, `match` or block
+fn return_tail(stmt: Stmt) -> Stmt {
+    let tails = |mut stmts: Vec<Stmt>| {
+        if let Some(last) = stmts.pop() {
+            stmts.push(return_tail(last));
+        }
+        stmts
+    };
+    match stmt {
+        Stmt::Expr(expr) => Stmt::Return(Some(expr)),
+        Stmt::If {
+            condition,
+            then_block,
+            else_block,
+        } => Stmt::If {
+            condition,
+            then_block: tails(then_block),
+            else_block: else_block.map(tails),
+        },
+        Stmt::Match { scrutinee, arms } => Stmt::Match {
+            scrutinee,
+            arms: arms
+                .into_iter()
+                .map(|arm| MatchArm {
+                    body: tails(arm.body),
+                    ..arm
                 })
-            } else {
-                Ok(Stmt::Expr(convert_expr(expr)?))
+                .collect(),
+        },
+        Stmt::Block(stmts) => Stmt::Block(tails(stmts)),
+        Stmt::Spanned { span, stmt } => Stmt::Spanned {
+            span,
+            stmt: Box::new(return_tail(*stmt)),
+        },
+        other => other,
+    }
+}
+
+fn convert_if_stmt(expr_if: &ExprIf) -> Result<Stmt> {
+    let then_block = convert_block(&expr_if.then_branch)?;
+    let else_block = if let Some((_, else_expr)) = &expr_if.else_branch {
+        match &**else_expr {
+            SynExpr::Block(block) => Some(convert_block(&block.block)?),
+            SynExpr::If(else_if) => {
+                // `else if` is an `else` holding only the nested `if`
+                let span = span_of(else_if);
+                let nested = convert_if_stmt(else_if).map_err(|e| e.at(span))?;
+                Some(vec![Stmt::Spanned {
+                    span,
+                    stmt: Box::new(nested),
+                }])
             }
+            _ => None,
         }
-        _ => Err(Error::Validation("Unsupported statement type".to_string())),
+    } else {
+        None
+    };
+
+    // `if let pattern = value` is a match with a fallback arm
+    if let SynExpr::Let(expr_let) = &*expr_if.cond {
+        let arms = vec![
+            MatchArm {
+                pattern: convert_pattern(&expr_let.pat)?,
+                guard: None,
+                body: then_block,
+            },
+            MatchArm {
+                pattern: Pattern::Wildcard,
+                guard: None,
+                body: else_block.unwrap_or_default(),
+            },
+        ];
+        return Ok(Stmt::Match {
+            scrutinee: convert_expr(&expr_let.expr)?,
+            arms,
+        });
+    }
+
+    Ok(Stmt::If {
+        condition: convert_expr(&expr_if.cond)?,
+        then_block,
+        else_block,
+    })
+}
+
+fn convert_match_stmt(expr_match: &ExprMatch) -> Result<Stmt> {
+    let scrutinee = convert_expr(&expr_match.expr)?;
+    let mut arms = Vec::new();
+
+    for arm in &expr_match.arms {
+        let guard = match &arm.guard {
+            Some((_, guard)) => Some(convert_expr(guard)?),
+            None => None,
+        };
+        // `pat => expr,` is shorthand for a block holding a single statement
+        let body = match &*arm.body {
+            SynExpr::Block(block) => convert_block(&block.block)?,
+            body => {
+                let span = span_of(body);
+                let stmt = convert_expr_stmt(body).map_err(|e| e.at(span))?;
+                vec![Stmt::Spanned {
+                    span,
+                    stmt: Box::new(stmt),
+                }]
+            }
+        };
+        arms.push(MatchArm {
+            pattern: convert_pattern(&arm.pat)?,
+            guard,
+            body,
+        });
+    }
+
+    Ok(Stmt::Match { scrutinee, arms })
+}
+
+fn convert_pattern(pat: &Pat) -> Result<Pattern> {
+    match pat {
+        Pat::Lit(expr_lit) => Ok(Pattern::Literal(convert_literal(&expr_lit.lit)?)),
+        Pat::Ident(pat_ident) if pat_ident.ident == "None" => Ok(Pattern::None),
+        Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => {
+            Ok(Pattern::Variable(pat_ident.ident.to_string()))
+        }
+        Pat::Wild(_) => Ok(Pattern::Wildcard),
+        Pat::Or(pat_or) => {
+            let mut cases = Vec::new();
+            for case in &pat_or.cases {
+                cases.push(convert_pattern(case)?);
+            }
+            Ok(Pattern::Or(cases))
+        }
+        Pat::Paren(pat_paren) => convert_pattern(&pat_paren.pat),
+        Pat::Path(pat_path) if pat_path.path.is_ident("None") => Ok(Pattern::None),
+        Pat::TupleStruct(pat_struct)
+            if pat_struct.path.is_ident("Some") && pat_struct.elems.len() == 1 =>
+        {
+            Ok(Pattern::Some(Box::new(convert_pattern(
+                &pat_struct.elems[0],
+            )?)))
+        }
+        _ => Err(Diagnostic::new("E0115", "unsupported match pattern")
+            .with_primary(span_of(pat), "pattern not supported")
+            .with_help(
+                "match on literals, bindings, `_`, `Some(x)`, `None` or alternatives joined by `|`",
+            )
+            .into()),
+    }
+}
+
+fn loop_label_error(label: &syn::Lifetime) -> Error {
+    Diagnostic::new("E0113", "loop labels are not supported")
+        .with_primary(span_of(label), "label")
+        .with_help("restructure the loop, or use a flag variable to exit early")
+        .into()
+}
+
+fn convert_for_loop(for_loop: &ExprForLoop) -> Result<Stmt> {
+    if let Some(label) = &for_loop.label {
+        return Err(loop_label_error(&label.name));
+    }
+
+    let pattern = match &*for_loop.pat {
+        Pat::Ident(pat_ident) => Pattern::Variable(pat_ident.ident.to_string()),
+        Pat::Wild(_) => Pattern::Wildcard,
+        pat => {
+            return Err(Diagnostic::new("E0115", "unsupported `for` loop pattern")
+                .with_primary(span_of(pat), "expected a plain name or `_`")
+                .with_help("bind the loop variable to a name")
+                .into())
+        }
+    };
+
+    let iter = convert_expr(&for_loop.expr)?;
+    let body = convert_block(&for_loop.body)?;
+
+    // Loops with a statically known trip count are bounded by exactly that count
+    let max_iterations = Some(static_iteration_count(&iter).unwrap_or(DEFAULT_MAX_ITERATIONS));
+
+    Ok(Stmt::For {
+        pattern,
+        iter,
+        body,
+        max_iterations,
+    })
+}
+
+fn convert_while_loop(expr_while: &ExprWhile) -> Result<Stmt> {
+    if let Some(label) = &expr_while.label {
+        return Err(loop_label_error(&label.name));
+    }
+
+    Ok(Stmt::While {
+        condition: convert_expr(&expr_while.cond)?,
+        body: convert_block(&expr_while.body)?,
+        max_iterations: Some(DEFAULT_MAX_ITERATIONS),
+    })
+}
+
+fn convert_loop(expr_loop: &ExprLoop) -> Result<Stmt> {
+    if let Some(label) = &expr_loop.label {
+        return Err(loop_label_error(&label.name));
+    }
+
+    // `loop { .. }` is a while loop whose condition is always true
+    Ok(Stmt::While {
+        condition: Expr::Literal(Literal::Bool(true)),
+        body: convert_block(&expr_loop.body)?,
+        max_iterations: Some(DEFAULT_MAX_ITERATIONS),
+    })
+}
+
+fn static_iteration_count(iter: &Expr) -> Option<u32> {
+    match iter {
+        Expr::Range {
+            start,
+            end,
+            inclusive,
+        } => match (&**start, &**end) {
+            (Expr::Literal(Literal::U32(start)), Expr::Literal(Literal::U32(end))) => {
+                let count = end.saturating_sub(*start);
+                Some(if *inclusive && end >= start {
+                    count.saturating_add(1)
+                } else {
+                    count
+                })
+            }
+            _ => None,
+        },
+        Expr::Array(elements) => u32::try_from(elements.len()).ok(),
+        _ => None,
     }
 }
 
@@ -233,6 +923,9 @@ fn convert_expr(expr: &SynExpr) -> Result<Expr> {
                 .join("::");
             Ok(Expr::Variable(name))
         }
+        SynExpr::Try(expr_try) => Ok(Expr::Try {
+            expr: Box::new(convert_expr(&expr_try.expr)?),
+        }),
         SynExpr::Call(expr_call) => {
             if let SynExpr::Path(path) = &*expr_call.func {
                 let name = path
@@ -245,14 +938,23 @@ fn convert_expr(expr: &SynExpr) -> Result<Expr> {
 
                 let mut args = Vec::new();
                 for arg in &expr_call.args {
+                    // `Ok(())` and `Err(())` carry no value
+                    if matches!(arg, SynExpr::Tuple(unit) if unit.elems.is_empty())
+                        && (name == "Ok" || name == "Err")
+                    {
+                        continue;
+                    }
                     args.push(convert_expr(arg)?);
                 }
 
                 Ok(Expr::FunctionCall { name, args })
             } else {
-                Err(Error::Validation(
-                    "Complex function calls not supported".to_string(),
-                ))
+                Err(
+                    Diagnostic::new("E0116", "only named functions can be called")
+                        .with_primary(span_of(&expr_call.func), "not a function name")
+                        .with_help("call a named function directly")
+                        .into(),
+                )
             }
         }
         SynExpr::Binary(expr_binary) => {
@@ -271,7 +973,16 @@ fn convert_expr(expr: &SynExpr) -> Result<Expr> {
             let method = method_call.method.to_string();
             let mut args = Vec::new();
             for arg in &method_call.args {
-                args.push(convert_expr(arg)?);
+                match arg {
+                    // `unwrap_or_else(|| value)` computes the fallback lazily,
+                    // as the shell's `${name-word}` does anyway
+                    SynExpr::Closure(closure)
+                        if method == "unwrap_or_else" && closure.inputs.is_empty() =>
+                    {
+                        args.push(convert_closure_body(&closure.body)?)
+                    }
+                    arg => args.push(convert_expr(arg)?),
+                }
             }
             Ok(Expr::MethodCall {
                 receiver,
@@ -292,28 +1003,324 @@ fn convert_expr(expr: &SynExpr) -> Result<Expr> {
             // Handle parenthesized expressions by unwrapping them
             convert_expr(&expr_paren.expr)
         }
+        SynExpr::Range(expr_range) => {
+            let (Some(start), Some(end)) = (&expr_range.start, &expr_range.end) else {
+                return Err(
+                    Diagnostic::new("E0114", "ranges must have a start and an end")
+                        .with_primary(span_of(expr_range), "open-ended range")
+                        .with_help("write both bounds, e.g. `0..10`")
+                        .into(),
+                );
+            };
+            Ok(Expr::Range {
+                start: Box::new(convert_expr(start)?),
+                end: Box::new(convert_expr(end)?),
+                inclusive: matches!(expr_range.limits, RangeLimits::Closed(_)),
+            })
+        }
+        SynExpr::Array(expr_array) => {
+            let mut elements = Vec::new();
+            for element in &expr_array.elems {
+                elements.push(convert_expr(element)?);
+            }
+            Ok(Expr::Array(elements))
+        }
+        SynExpr::Index(expr_index) => Ok(Expr::Index {
+            object: Box::new(convert_expr(&expr_index.expr)?),
+            index: Box::new(convert_expr(&expr_index.index)?),
+        }),
+        SynExpr::Macro(expr_macro) => match convert_macro(&expr_macro.mac)? {
+            Some(converted) => Ok(converted),
+            None => Err(unsupported_expr(expr)),
+        },
+        SynExpr::Reference(expr_ref) => {
+            // `&[...]` and `&value` are transparent in shell
+            convert_expr(&expr_ref.expr)
+        }
         SynExpr::If(_) => {
             // For now, reject if expressions in expression position
             // They should be used as statements instead
-            Err(Error::Validation(
-                "If expressions not supported in expression position".to_string(),
-            ))
+            Err(
+                Diagnostic::new("E0117", "`if` is not supported in expression position")
+                    .with_primary(span_of(expr), "used as a value")
+                    .with_help("use `if` as a statement and assign the value in each branch")
+                    .into(),
+            )
+        }
+        _ => Err(unsupported_expr(expr)),
+    }
+}
+
+/// The value of a closure body: an expression or a block holding only one
+fn convert_closure_body(body: &SynExpr) -> Result<Expr> {
+    match body {
+        SynExpr::Block(block) if block.block.stmts.len() == 1 => match &block.block.stmts[0] {
+            SynStmt::Expr(expr, None) => convert_expr(expr),
+            _ => Err(unsupported_expr(body)),
+        },
+        body => convert_expr(body),
+    }
+}
+
+/// Convert the macros Rash understands: `vec!`, `format!` and the printing
+/// macros. Returns `None` for any other macro.
+fn convert_macro(mac: &syn::Macro) -> Result<Option<Expr>> {
+    let Some(name) = mac.path.get_ident() else {
+        return Ok(None);
+    };
+    let (stderr, newline) = match name.to_string().as_str() {
+        "vec" => {
+            // `vec![a, b]` builds the same list as `[a, b]`
+            let mut elements = Vec::new();
+            for element in &macro_args(mac)? {
+                elements.push(convert_expr(element)?);
+            }
+            return Ok(Some(Expr::Array(elements)));
+        }
+        "format" => return convert_format(mac, false).map(Some),
+        "print" => (false, false),
+        "println" => (false, true),
+        "eprint" => (true, false),
+        "eprintln" => (true, true),
+        _ => return Ok(None),
+    };
+    let message = if mac.tokens.is_empty() {
+        // `println!()` prints just the newline
+        Expr::Format(vec![Expr::Literal(Literal::Str("\n".to_string()))])
+    } else {
+        convert_format(mac, newline)?
+    };
+    Ok(Some(Expr::Print {
+        message: Box::new(message),
+        stderr,
+    }))
+}
+
+fn macro_args(mac: &syn::Macro) -> Result<Punctuated<SynExpr, Token![,]>> {
+    mac.parse_body_with(Punctuated::<SynExpr, Token![,]>::parse_terminated)
+        .map_err(|e| {
+            let span = span_of(&e.span());
+            Error::Parse(e).at(span)
+        })
+}
+
+/// Split a format string into literal text and the values of its `{}`,
+/// `{0}` and `{name}` placeholders
+fn convert_format(mac: &syn::Macro, newline: bool) -> Result<Expr> {
+    let args = macro_args(mac)?;
+    let mut args = args.iter();
+    let template = match args.next() {
+        Some(SynExpr::Lit(syn::ExprLit {
+            lit: Lit::Str(template),
+            ..
+        })) => template,
+        other => {
+            let span = other.map_or_else(|| span_of(mac), span_of);
+            return Err(format_error(span, "expected a format string literal"));
+        }
+    };
+
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    for arg in args {
+        match arg {
+            SynExpr::Assign(assign) => match &*assign.left {
+                SynExpr::Path(path) if path.path.get_ident().is_some() => {
+                    let name = path.path.segments[0].ident.to_string();
+                    named.push((name, convert_expr(&assign.right)?, span_of(arg)));
+                }
+                _ => return Err(format_error(span_of(arg), "expected `name = value`")),
+            },
+            _ if !named.is_empty() => {
+                return Err(format_error(
+                    span_of(arg),
+                    "positional arguments must come before named ones",
+                ))
+            }
+            _ => positional.push((convert_expr(arg)?, span_of(arg))),
         }
-        _ => Err(Error::Validation("Unsupported expression type".to_string())),
     }
+
+    let span = span_of(template);
+    let mut used_positional = vec![false; positional.len()];
+    let mut used_named = vec![false; named.len()];
+    let mut next_positional = 0;
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let template = template.value();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(format_error(span, "unclosed `{` in format string")),
+                    }
+                }
+                let (argument, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                if !spec.is_empty() {
+                    return Err(Diagnostic::new(
+                        "E0126",
+                        format!("format spec `:{spec}` is not supported"),
+                    )
+                    .with_primary(span, "in this format string")
+                    .with_help("use `{}` or `{name}`; values are printed as they are")
+                    .into());
+                }
+                let argument = argument.trim();
+                let value = if argument.is_empty() || argument.parse::<usize>().is_ok() {
+                    let index = argument.parse().unwrap_or(next_positional);
+                    if argument.is_empty() {
+                        next_positional += 1;
+                    }
+                    let (value, _) = positional.get(index).ok_or_else(|| {
+                        format_error(span, &format!("no argument for placeholder {index}"))
+                    })?;
+                    used_positional[index] = true;
+                    value.clone()
+                } else if let Some(index) = named.iter().position(|(name, ..)| name == argument) {
+                    used_named[index] = true;
+                    named[index].1.clone()
+                } else if syn::parse_str::<syn::Ident>(argument).is_ok() {
+                    // `{name}` captures a variable in scope
+                    Expr::Variable(argument.to_string())
+                } else {
+                    return Err(format_error(
+                        span,
+                        &format!("invalid placeholder `{{{placeholder}}}`"),
+                    ));
+                };
+                if !text.is_empty() {
+                    parts.push(Expr::Literal(Literal::Str(std::mem::take(&mut text))));
+                }
+                parts.push(value);
+            }
+            '}' => return Err(format_error(span, "unmatched `}` in format string")),
+            c => text.push(c),
+        }
+    }
+    if newline {
+        text.push('\n');
+    }
+    if !text.is_empty() {
+        parts.push(Expr::Literal(Literal::Str(text)));
+    }
+
+    let unused = positional
+        .iter()
+        .zip(&used_positional)
+        .map(|((_, span), used)| (span, used))
+        .chain(
+            named
+                .iter()
+                .zip(&used_named)
+                .map(|((_, _, span), used)| (span, used)),
+        )
+        .find(|(_, used)| !**used);
+    if let Some((span, _)) = unused {
+        return Err(format_error(*span, "argument never used"));
+    }
+    Ok(Expr::Format(parts))
+}
+
+fn format_error(span: Span, message: &str) -> Error {
+    Diagnostic::new("E0126", message.to_string())
+        .with_primary(span, "invalid format arguments")
+        .with_help("write a string literal with `{}` or `{name}` placeholders, followed by one value per `{}`")
+        .into()
+}
+
+/// Explain why an expression has no shell equivalent
+fn unsupported_expr(expr: &SynExpr) -> Error {
+    let (message, label, help) = match expr {
+        SynExpr::Closure(_) => (
+            "closures are not supported",
+            "closure",
+            "closures aren't supported; use a named fn",
+        ),
+        SynExpr::Macro(_) => (
+            "macros are not supported",
+            "macro invocation",
+            "call a function instead",
+        ),
+        SynExpr::Struct(_) => (
+            "struct literals are not supported",
+            "struct literal",
+            "pass the fields as separate values",
+        ),
+        SynExpr::Tuple(_) => (
+            "tuples are not supported",
+            "tuple",
+            "use separate variables for each element",
+        ),
+        SynExpr::Field(_) => (
+            "field access is not supported",
+            "field access",
+            "pass the field as a separate value",
+        ),
+        SynExpr::Cast(_) => (
+            "`as` casts are not supported",
+            "cast",
+            "values are untyped strings in shell; drop the cast",
+        ),
+        SynExpr::Block(_) | SynExpr::Unsafe(_) => (
+            "block expressions are not supported",
+            "block used as a value",
+            "move the statements before the expression",
+        ),
+        SynExpr::Async(_) | SynExpr::Await(_) => (
+            "async code is not supported",
+            "async expression",
+            "shell commands run sequentially; call the function directly",
+        ),
+        SynExpr::Assign(_) => (
+            "assignment is not supported in expression position",
+            "assignment used as a value",
+            "assign in a statement of its own",
+        ),
+        _ => (
+            "unsupported expression",
+            "expression not supported in shell",
+            "rewrite this using literals, variables, operators and function calls",
+        ),
+    };
+    unsupported_expr_diagnostic(span_of(expr), message, label, help)
+}
+
+fn unsupported_expr_diagnostic(span: Span, message: &str, label: &str, help: &str) -> Error {
+    Diagnostic::new("E0110", message)
+        .with_primary(span, label)
+        .with_help(help)
+        .into()
 }
 
 fn convert_literal(lit: &Lit) -> Result<Literal> {
     match lit {
         Lit::Bool(lit_bool) => Ok(Literal::Bool(lit_bool.value)),
         Lit::Int(lit_int) => {
-            let value: u32 = lit_int
-                .base10_parse()
-                .map_err(|_| Error::Validation("Invalid integer literal".to_string()))?;
+            let value: u32 = lit_int.base10_parse().map_err(|_| {
+                Diagnostic::new("E0111", "invalid integer literal")
+                    .with_primary(span_of(lit_int), "does not fit in a u32")
+                    .with_help("integers must be between 0 and 4294967295")
+            })?;
             Ok(Literal::U32(value))
         }
         Lit::Str(lit_str) => Ok(Literal::Str(lit_str.value())),
-        _ => Err(Error::Validation("Unsupported literal type".to_string())),
+        _ => Err(Diagnostic::new("E0111", "unsupported literal")
+            .with_primary(span_of(lit), "literal not supported")
+            .with_help("use a string, integer or boolean literal")
+            .into()),
     }
 }
 
@@ -331,7 +1338,10 @@ fn convert_binary_op(op: &BinOp) -> Result<BinaryOp> {
         BinOp::Ge(_) => Ok(BinaryOp::Ge),
         BinOp::And(_) => Ok(BinaryOp::And),
         BinOp::Or(_) => Ok(BinaryOp::Or),
-        _ => Err(Error::Validation("Unsupported binary operator".to_string())),
+        _ => Err(Diagnostic::new("E0112", "unsupported binary operator")
+            .with_primary(span_of(op), "operator not supported")
+            .with_help("supported operators are + - * / == != < <= > >= && ||")
+            .into()),
     }
 }
 
@@ -339,6 +1349,9 @@ fn convert_unary_op(op: &UnOp) -> Result<UnaryOp> {
     match op {
         UnOp::Not(_) => Ok(UnaryOp::Not),
         UnOp::Neg(_) => Ok(UnaryOp::Neg),
-        _ => Err(Error::Validation("Unsupported unary operator".to_string())),
+        _ => Err(Diagnostic::new("E0112", "unsupported unary operator")
+            .with_primary(span_of(op), "operator not supported")
+            .with_help("supported unary operators are `!` and `-`")
+            .into()),
     }
 }
@@ -1,8 +1,45 @@
 use super::*;
-use crate::ast::restricted::{BinaryOp, Literal};
+use crate::ast::restricted::{BinaryOp, Literal, Pattern};
+use crate::ast::{Expr, Stmt, Type};
 use proptest::prelude::*;
 use rstest::*;
 
+/// Parse and drop source locations, so that tests can match on statement shapes
+fn parse(input: &str) -> crate::models::Result<crate::ast::RestrictedAst> {
+    let mut ast = super::parse(input)?;
+    for function in &mut ast.functions {
+        strip_spans(&mut function.body);
+    }
+    Ok(ast)
+}
+
+fn strip_spans(stmts: &mut [Stmt]) {
+    for stmt in stmts {
+        *stmt = stmt.unspanned().clone();
+        match stmt {
+            Stmt::If {
+                then_block,
+                else_block,
+                ..
+            } => {
+                strip_spans(then_block);
+                if let Some(else_stmts) = else_block {
+                    strip_spans(else_stmts);
+                }
+            }
+            Stmt::For { body, .. } | Stmt::While { body, .. } | Stmt::Block(body) => {
+                strip_spans(body)
+            }
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    strip_spans(&mut arm.body);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 #[test]
 fn test_simple_function_parsing() {
     let source = r#"
@@ -186,10 +223,10 @@ fn test_return_statement_parsing() {
     let main_func = &ast.functions[0];
 
     match &main_func.body[0] {
-        crate::ast::Stmt::Expr(crate::ast::Expr::Literal(Literal::Str(s))) => {
+        crate::ast::Stmt::Return(Some(crate::ast::Expr::Literal(Literal::Str(s)))) => {
             assert_eq!(s, "success");
         }
-        _ => panic!("Expected return expression"),
+        _ => panic!("Expected return statement"),
     }
 }
 
@@ -391,6 +428,54 @@ fn test_type_conversion_edge_cases() {
     ));
 }
 
+#[rstest]
+#[case("Vec<&str>")]
+#[case("Vec<String>")]
+#[case("&[u32]")]
+#[case("[bool; 2]")]
+fn test_list_type_parsing(#[case] ty: &str) {
+    let source = format!("fn install(pkgs: {ty}) {{}} fn main() {{}}");
+    let ast = parse(&source).unwrap();
+
+    assert!(matches!(
+        ast.functions[0].params[0].param_type,
+        crate::ast::Type::Vec { .. }
+    ));
+}
+
+#[test]
+fn test_nested_list_type_rejected() {
+    let err = parse("fn f(x: Vec<Vec<u32>>) {} fn main() {}").unwrap_err();
+    assert!(err.to_string().contains("lists can only hold"));
+}
+
+#[test]
+fn test_vec_macro_and_indexing_parsing() {
+    let source = r#"
+        fn main() {
+            let pkgs: Vec<&str> = vec!["curl", "git"];
+            let first = pkgs[0];
+        }
+    "#;
+    let ast = parse(source).unwrap();
+    let body = &ast.functions[0].body;
+
+    match &body[0] {
+        Stmt::Let {
+            value: Expr::Array(elements),
+            ..
+        } => assert_eq!(elements.len(), 2),
+        other => panic!("Expected list binding, got {other:?}"),
+    }
+    assert!(matches!(
+        &body[1],
+        Stmt::Let {
+            value: Expr::Index { .. },
+            ..
+        }
+    ));
+}
+
 // Property-based tests
 proptest! {
     #[test]
@@ -574,3 +659,808 @@ fn test_parser_maintains_source_information() {
         _ => panic!("Expected third let statement"),
     }
 }
+
+#[test]
+fn test_for_loop_over_range_parsing() {
+    let source = r#"
+        fn main() {
+            for i in 0..5 {
+                echo(i);
+            }
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    match &ast.functions[0].body[0] {
+        Stmt::For {
+            pattern,
+            iter,
+            body,
+            max_iterations,
+        } => {
+            assert!(matches!(pattern, Pattern::Variable(n) if n == "i"));
+            assert!(matches!(
+                iter,
+                Expr::Range {
+                    inclusive: false,
+                    ..
+                }
+            ));
+            assert_eq!(body.len(), 1);
+            assert_eq!(*max_iterations, Some(5));
+        }
+        other => panic!("Expected for loop, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_for_loop_bounds() {
+    let source = r#"
+        fn main() {
+            for _ in 1..=3 {}
+            for pkg in ["curl", "git"] {}
+            for i in 0..n {}
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    let bounds: Vec<_> = ast.functions[0]
+        .body
+        .iter()
+        .map(|stmt| match stmt {
+            Stmt::For { max_iterations, .. } => *max_iterations,
+            other => panic!("Expected for loop, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        bounds,
+        vec![Some(3), Some(2), Some(parser::DEFAULT_MAX_ITERATIONS)]
+    );
+}
+
+#[test]
+fn test_for_loop_rejects_open_range() {
+    let source = r#"
+        fn main() {
+            for i in 0.. {}
+        }
+    "#;
+
+    assert!(parse(source).is_err());
+}
+
+#[test]
+fn test_while_loop_and_loop_control_parsing() {
+    let source = r#"
+        fn main() {
+            while ready {
+                continue;
+            }
+            loop {
+                break;
+            }
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    let body = &ast.functions[0].body;
+
+    match &body[0] {
+        Stmt::While {
+            body,
+            max_iterations,
+            ..
+        } => {
+            assert!(matches!(body[0], Stmt::Continue));
+            assert_eq!(*max_iterations, Some(parser::DEFAULT_MAX_ITERATIONS));
+        }
+        other => panic!("Expected while loop, got {other:?}"),
+    }
+
+    match &body[1] {
+        Stmt::While {
+            condition, body, ..
+        } => {
+            assert!(matches!(condition, Expr::Literal(Literal::Bool(true))));
+            assert!(matches!(body[0], Stmt::Break));
+        }
+        other => panic!("Expected loop, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_block_statement_parsing() {
+    let source = r#"
+        fn main() {
+            let x = 1;
+            {
+                let x = 2;
+            }
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    let body = &ast.functions[0].body;
+
+    assert_eq!(body.len(), 2);
+    match &body[1] {
+        Stmt::Block(stmts) => assert!(matches!(
+            &stmts[..],
+            [Stmt::Let { name, value: Expr::Literal(Literal::U32(2)), .. }] if name == "x"
+        )),
+        other => panic!("Expected block, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_labeled_loops_rejected() {
+    let source = r#"
+        fn main() {
+            'outer: loop {
+                break 'outer;
+            }
+        }
+    "#;
+
+    assert!(parse(source).is_err());
+}
+
+#[test]
+fn test_return_statements_and_tail_expressions() {
+    let source = r#"
+        fn main() {
+            let v = version();
+        }
+
+        fn version() -> &'static str {
+            "1.2.3"
+        }
+
+        fn ready() -> bool {
+            return true;
+        }
+
+        fn nothing() {
+            return;
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    let find = |name: &str| ast.functions.iter().find(|f| f.name == name).unwrap();
+
+    assert!(matches!(
+        find("version").body.as_slice(),
+        [Stmt::Return(Some(Expr::Literal(Literal::Str(v))))] if v == "1.2.3"
+    ));
+    assert!(matches!(
+        find("ready").body.as_slice(),
+        [Stmt::Return(Some(Expr::Literal(Literal::Bool(true))))]
+    ));
+    assert!(matches!(
+        find("nothing").body.as_slice(),
+        [Stmt::Return(None)]
+    ));
+    assert!(matches!(
+        find("nothing").return_type,
+        crate::ast::Type::Void
+    ));
+}
+
+#[test]
+fn test_match_parsing() {
+    let source = r#"
+        fn main() {
+            let os = "linux";
+            match os {
+                "linux" | "freebsd" if true => install(),
+                name => {
+                    echo(name);
+                }
+            }
+        }
+    "#;
+
+    let ast = parse(source).unwrap();
+    match &ast.functions[0].body[1] {
+        Stmt::Match { arms, .. } => {
+            assert_eq!(arms.len(), 2);
+            assert!(matches!(&arms[0].pattern, Pattern::Or(cases) if cases.len() == 2));
+            assert!(arms[0].guard.is_some());
+            assert!(matches!(arms[0].body.as_slice(), [Stmt::Expr(_)]));
+            assert!(matches!(&arms[1].pattern, Pattern::Variable(n) if n == "name"));
+        }
+        other => panic!("Expected match statement, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_statements_carry_source_spans() {
+    let source = "fn main() {\n    let x = 1;\n    if x == 1 {\n        echo(x);\n    }\n}\n\nfn echo(v: u32) {}\n";
+
+    let ast = super::parse(source).unwrap();
+    let body = &ast.functions[0].body;
+
+    match &body[0] {
+        Stmt::Spanned { span, stmt } => {
+            assert_eq!((span.line, span.column), (2, 5));
+            assert_eq!(&source[span.start..span.end], "let x = 1;");
+            assert!(matches!(stmt.as_ref(), Stmt::Let { .. }));
+        }
+        other => panic!("Expected spanned statement, got {other:?}"),
+    }
+
+    match body[1].unspanned() {
+        Stmt::If { then_block, .. } => match &then_block[0] {
+            Stmt::Spanned { span, .. } => assert_eq!((span.line, span.column), (4, 9)),
+            other => panic!("Expected spanned statement, got {other:?}"),
+        },
+        other => panic!("Expected if statement, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_errors_carry_locations() {
+    let source = "fn main() {\n    let x = 1;\n    let (a, b) = (1, 2);\n}\n";
+
+    let error = super::parse(source).unwrap_err();
+    let span = error.span().expect("error should be located");
+    assert_eq!(span.line, 3);
+    assert!(matches!(error, crate::models::Error::Diagnostic(_)));
+    assert!(error.to_string().contains("line 3"));
+}
+
+fn diagnostic_for(source: &str) -> crate::models::Diagnostic {
+    super::parse(source)
+        .unwrap_err()
+        .to_diagnostic()
+        .expect("parse errors should be diagnostics")
+}
+
+#[test]
+fn test_closure_diagnostic_points_at_closure() {
+    let source = "fn main() {\n    let f = |x| x + 1;\n}\n";
+
+    let diagnostic = diagnostic_for(source);
+    assert_eq!(diagnostic.code, "E0110");
+    assert_eq!(
+        diagnostic.help.as_deref(),
+        Some("closures aren't supported; use a named fn")
+    );
+    let primary = diagnostic.primary.unwrap();
+    assert_eq!((primary.span.line, primary.span.column), (2, 13));
+    assert_eq!(&source[primary.span.start..primary.span.end], "|x| x + 1");
+}
+
+#[test]
+fn test_duplicate_entry_point_labels_both_functions() {
+    let source = "fn main() {}\n\n#[rash::main]\nfn start() {}\n";
+
+    let diagnostic = diagnostic_for(source);
+    assert_eq!(diagnostic.code, "E0102");
+    assert_eq!(diagnostic.primary.unwrap().span.line, 4);
+    assert_eq!(diagnostic.secondary.len(), 1);
+    assert_eq!(diagnostic.secondary[0].span.line, 1);
+}
+
+#[rstest]
+#[case("struct S;\nfn main() {}", "E0101")]
+#[case("fn helper() {}", "E0103")]
+#[case("fn main() {}\nfn half(x: f64) {}", "E0106")]
+#[case("fn main() { let x; }", "E0108")]
+#[case("fn main() { 'outer: loop { break 'outer; } }", "E0113")]
+#[case("fn main() { for i in 0.. {} }", "E0114")]
+#[case("fn main() { let x = 1 % 2; }", "E0112")]
+#[case("fn main() { let x = 99999999999; }", "E0111")]
+fn test_parser_error_codes(#[case] source: &str, #[case] code: &str) {
+    assert_eq!(diagnostic_for(source).code, code);
+}
+
+#[test]
+fn test_effects_attribute_parsing() {
+    let source = "#[rash::effects(fs_read, network, fs_read)]\nfn check() {}\n#[rash::effects(pure)]\nfn main() {}\nfn helper() {}\n";
+    let ast = parse(source).unwrap();
+
+    let budget = ast.functions[0].effects.as_ref().unwrap();
+    assert_eq!(
+        budget.effects,
+        [
+            crate::ir::Effect::FileRead,
+            crate::ir::Effect::NetworkAccess
+        ]
+    );
+    assert_eq!(
+        &source[budget.span.start..budget.span.end],
+        "#[rash::effects(fs_read, network, fs_read)]"
+    );
+    assert!(ast.functions[1]
+        .effects
+        .as_ref()
+        .unwrap()
+        .effects
+        .is_empty());
+    assert!(ast.functions[2].effects.is_none());
+}
+
+#[rstest]
+#[case("#[rash::effects(disk)]\nfn main() {}", "unknown effect `disk`")]
+#[case("#[rash::effects(\"fs_read\")]\nfn main() {}", "expected a list")]
+#[case("#[rash::effects = \"fs_read\"]\nfn main() {}", "expected a list")]
+#[case(
+    "#[rash::effects(env)]\n#[rash::effects(network)]\nfn main() {}",
+    "declared more than once"
+)]
+fn test_effects_attribute_errors(#[case] source: &str, #[case] message: &str) {
+    let diagnostic = diagnostic_for(source);
+    assert_eq!(diagnostic.code, "E0134");
+    assert!(
+        diagnostic.message.contains(message),
+        "{}",
+        diagnostic.message
+    );
+}
+
+#[rstest]
+#[case("use bashrs_std::fs;")]
+#[case("use bashrs_std::{fs, log};")]
+fn test_stdlib_module_imports_accepted(#[case] import: &str) {
+    let ast = parse(&format!("{import} fn main() {{ fs::mkdir_p(\"/opt\"); }}")).unwrap();
+    assert_eq!(ast.functions.len(), 1);
+}
+
+#[rstest]
+#[case("use std::fs;", "unresolved import `std::fs`")]
+#[case("use bashrs_std::fs::mkdir_p;", "Only modules of `bashrs_std`")]
+#[case("use bashrs_std::{fs, log::*};", "Only modules of `bashrs_std`")]
+fn test_other_imports_rejected(#[case] import: &str, #[case] message: &str) {
+    let err = parse(&format!("{import} fn main() {{}}")).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_print_macro_parsing() {
+    let source = r#"
+        fn main() {
+            let n = 1;
+            eprintln!("{{n}} = {}, {n}, {label}", n + 1, label = "x");
+        }
+    "#;
+    let ast = parse(source).unwrap();
+
+    match &ast.functions[0].body[1] {
+        Stmt::Expr(Expr::Print { message, stderr }) => {
+            assert!(*stderr);
+            let Expr::Format(parts) = message.as_ref() else {
+                panic!("Expected format parts, got {message:?}");
+            };
+            assert!(matches!(&parts[0], Expr::Literal(Literal::Str(s)) if s == "{n} = "));
+            assert!(matches!(&parts[1], Expr::Binary { .. }));
+            assert!(matches!(&parts[3], Expr::Variable(name) if name == "n"));
+            assert!(matches!(&parts[5], Expr::Literal(Literal::Str(s)) if s == "x"));
+            assert!(matches!(&parts[6], Expr::Literal(Literal::Str(s)) if s == "\n"));
+        }
+        other => panic!("Expected print, got {other:?}"),
+    }
+}
+
+#[rstest]
+#[case(r#"println!("{:>5}", 1)"#, "format spec `:>5`")]
+#[case(r#"println!("{} {}", 1)"#, "no argument for placeholder 1")]
+#[case(r#"println!("x", 1)"#, "argument never used")]
+#[case(r#"let s = "x"; println!(s)"#, "format string literal")]
+#[case(r#"println!("}")"#, "unmatched `}`")]
+fn test_invalid_format_rejected(#[case] body: &str, #[case] message: &str) {
+    let err = parse(&format!("fn main() {{ {body}; }}")).unwrap_err();
+    assert!(err.to_string().contains(message), "{err}");
+}
+
+#[test]
+fn test_const_and_static_items_parsing() {
+    let source = r#"
+        const VERSION: &str = "1.2.3";
+        static RETRIES: u32 = 3;
+        fn main() {}
+    "#;
+    let ast = parse(source).unwrap();
+
+    let names: Vec<&str> = ast.constants.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["VERSION", "RETRIES"]);
+    assert!(matches!(ast.constants[0].ty, Type::Str));
+    assert!(matches!(
+        &ast.constants[1].value,
+        Expr::Literal(Literal::U32(3))
+    ));
+}
+
+#[test]
+fn test_mutable_static_rejected() {
+    let err = parse("static mut COUNT: u32 = 0; fn main() {}").unwrap_err();
+    assert!(err.to_string().contains("mutable statics"), "{err}");
+}
+
+#[test]
+fn test_inline_module_paths_resolve_to_their_items() {
+    let source = r#"
+        mod net {
+            pub fn fetch(url: &str) { super::log(url); }
+            pub fn unused() {}
+        }
+        mod util {
+            pub const PREFIX: &str = "/opt";
+        }
+        use net::fetch as get;
+        fn log(msg: &str) {}
+        fn main() {
+            get(util::PREFIX);
+            crate::net::fetch("x");
+        }
+    "#;
+    let ast = parse(source).unwrap();
+
+    let names: Vec<&str> = ast.functions.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["log", "main", "net__fetch"]);
+    match &ast.functions[1].body[0] {
+        Stmt::Expr(Expr::FunctionCall { name, args }) => {
+            assert_eq!(name, "net__fetch");
+            assert!(matches!(&args[0], Expr::Variable(v) if v == "PREFIX"));
+        }
+        other => panic!("Expected a call, got {other:?}"),
+    }
+    assert!(matches!(
+        &ast.functions[2].body[0],
+        Stmt::Expr(Expr::FunctionCall { name, .. }) if name == "log"
+    ));
+}
+
+#[rstest]
+#[case("mod net; fn main() {}", "E0128")]
+#[case("use util::missing; mod util {} fn main() {}", "E0129")]
+#[case("mod util { pub fn f() {} } fn main() { f(); }", "E0129")]
+#[case("mod util { pub fn f() {} } fn main() { util::g(); }", "E0129")]
+#[case(
+    "mod util { pub fn shout() {} } mod other { pub fn shout() {} } \
+     use util::shout; use other::shout; fn main() { shout(); }",
+    "E0135"
+)]
+#[case(
+    "mod util { pub fn shout() {} } use util::shout; fn shout() {} fn main() {}",
+    "E0135"
+)]
+#[case(
+    "mod a { pub fn shout() {} } mod b { pub fn shout() {} } \
+     use a::*; use b::*; fn main() { shout(); }",
+    "E0136"
+)]
+#[case(
+    "mod a { pub const X: u32 = 1; } const X: u32 = 2; fn main() {}",
+    "E0130"
+)]
+fn test_module_errors(#[case] source: &str, #[case] code: &str) {
+    assert_eq!(diagnostic_for(source).code, code);
+}
+
+#[test]
+fn test_module_functions_are_named_after_their_module() {
+    let source = r#"
+        mod a {
+            pub fn run() { helper(); }
+            pub fn shout() { let w = 0; }
+            fn helper() { let x = 1; }
+        }
+        mod b {
+            pub fn walk() { self::helper(); }
+            pub fn shout() { let v = 4; }
+            fn helper() { let y = 2; }
+            fn git() {}
+        }
+        use a::run;
+        use b::walk;
+        fn helper() { let z = 3; }
+        fn main() { run(); walk(); helper(); a::shout(); b::shout(); }
+    "#;
+    let ast = parse(source).unwrap();
+
+    let names: Vec<&str> = ast.functions.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "helper",
+            "main",
+            "a__run",
+            "a__shout",
+            "a__helper",
+            "b__walk",
+            "b__shout",
+            "b__helper"
+        ]
+    );
+    for (caller, callee) in [
+        ("main", "a__run"),
+        ("a__run", "a__helper"),
+        ("b__walk", "b__helper"),
+    ] {
+        let function = ast.functions.iter().find(|f| f.name == caller).unwrap();
+        assert!(
+            matches!(
+                &function.body[0],
+                Stmt::Expr(Expr::FunctionCall { name, .. }) if name == callee
+            ),
+            "{function:?}"
+        );
+    }
+    for (stmt, callee) in ast.functions[1].body[3..]
+        .iter()
+        .zip(["a__shout", "b__shout"])
+    {
+        assert!(
+            matches!(stmt, Stmt::Expr(Expr::FunctionCall { name, .. }) if name == callee),
+            "{stmt:?}"
+        );
+    }
+}
+
+#[test]
+fn test_glob_imports_are_ambiguous_only_where_used() {
+    let source = r#"
+        mod a {
+            pub fn shout() { let x = 1; }
+            pub fn hum() { let y = 2; }
+        }
+        mod b {
+            pub fn shout() { let z = 3; }
+        }
+        use a::*;
+        use b::*;
+        use b::shout;
+        fn main() { hum(); shout(); }
+    "#;
+    let ast = parse(source).unwrap();
+
+    let main = ast.functions.iter().find(|f| f.name == "main").unwrap();
+    let called: Vec<&str> = main
+        .body
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Expr(Expr::FunctionCall { name, .. }) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(called, ["a__hum", "b__shout"]);
+
+    let diagnostic = diagnostic_for(
+        "mod a { pub fn shout() {} }\nmod b { pub fn shout() {} }\n\
+         use a::*;\nuse b::*;\nfn main() { shout(); }",
+    );
+    assert_eq!(diagnostic.code, "E0136");
+    assert_eq!(diagnostic.primary.unwrap().span.line, 5);
+    let lines: Vec<usize> = diagnostic.secondary.iter().map(|l| l.span.line).collect();
+    assert_eq!(lines, [3, 4]);
+}
+
+#[test]
+fn test_import_conflicts_point_at_both_names() {
+    let twice = diagnostic_for(
+        "mod util { pub fn shout() {} }\nmod other { pub fn shout() {} }\n\
+         use util::shout;\nuse other::shout;\nfn main() {}",
+    );
+    assert_eq!(twice.code, "E0135");
+    assert_eq!(twice.primary.unwrap().span.line, 4);
+    assert_eq!(twice.secondary[0].span.line, 3);
+
+    let declared = diagnostic_for(
+        "mod util { pub fn shout() {} }\nuse util::shout;\nfn shout() {}\nfn main() {}",
+    );
+    assert_eq!(declared.code, "E0135");
+    assert_eq!(declared.primary.unwrap().span.line, 2);
+    assert_eq!(declared.secondary[0].span.line, 3);
+}
+
+#[test]
+fn test_redefinition_points_at_first_definition() {
+    let diagnostic = diagnostic_for("fn f() {}\nfn f() {}\nfn main() {}");
+
+    assert_eq!(diagnostic.code, "E0119");
+    assert_eq!(diagnostic.primary.unwrap().span.line, 2);
+    assert_eq!(diagnostic.secondary.len(), 1);
+    assert_eq!(diagnostic.secondary[0].span.line, 1);
+    assert_eq!(diagnostic.secondary[0].message, "first defined here");
+}
+
+#[test]
+fn test_collision_across_files_names_first_definition() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("main.rs"), "mod a;\nmod b;\nfn main() {}\n").unwrap();
+    std::fs::write(dir.path().join("a.rs"), "pub const URL: &str = \"a\";\n").unwrap();
+    std::fs::write(dir.path().join("b.rs"), "pub const URL: &str = \"b\";\n").unwrap();
+
+    let mut files = modules::SourceFiles::default();
+    let error = modules::load(&dir.path().join("main.rs"), &mut files).unwrap_err();
+    let crate::models::Error::InFile { path, inner } = files.attribute(error) else {
+        panic!("Expected an error in a file");
+    };
+    assert_eq!(path, dir.path().join("b.rs"));
+    let diagnostic = inner.to_diagnostic().unwrap();
+    assert_eq!(diagnostic.code, "E0130");
+    assert!(diagnostic.secondary.is_empty());
+    let help = diagnostic.help.unwrap();
+    assert!(
+        help.starts_with(&format!(
+            "first definition at {}:1:1",
+            dir.path().join("a.rs").display()
+        )),
+        "{help}"
+    );
+}
+
+#[test]
+fn test_modules_load_from_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(src.join("net")).unwrap();
+    std::fs::write(
+        dir.path().join("Rash.toml"),
+        "[package]\nentry = \"src/main.rs\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("main.rs"),
+        "mod net;\nuse net::fetch;\nfn main() { fetch(\"x\"); }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("net/mod.rs"),
+        "mod http;\npub fn fetch(url: &str) { http::get(url); }\n",
+    )
+    .unwrap();
+    std::fs::write(
+        src.join("net/http.rs"),
+        "pub fn get(url: &str) {\n    let pair = (url, 1);\n}\n",
+    )
+    .unwrap();
+
+    let entry = modules::project_entry(dir.path()).unwrap();
+    assert_eq!(entry, src.join("main.rs"));
+
+    let mut files = modules::SourceFiles::default();
+    let ast = modules::load(&entry, &mut files).unwrap_err();
+    match files.attribute(ast) {
+        crate::models::Error::InFile { path, inner } => {
+            assert_eq!(path, src.join("net/http.rs"));
+            let span = inner.span().unwrap();
+            assert_eq!((span.line, span.column), (2, 16));
+            assert_eq!(span.start, "pub fn get(url: &str) {\n    let pair = ".len());
+        }
+        other => panic!("Expected an error in http.rs, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_result_and_try_parsing() {
+    let source = r#"
+        fn check(x: &str) -> Result<String, String> {
+            return Ok(x);
+        }
+        fn main() -> Result<(), String> {
+            let v = check("a")?;
+            return Ok(());
+        }
+    "#;
+    let ast = parse(source).unwrap();
+
+    let check = &ast.functions[0];
+    assert!(matches!(
+        &check.return_type,
+        Type::Result { ok_type, err_type }
+            if matches!(ok_type.as_ref(), Type::Str) && matches!(err_type.as_ref(), Type::Str)
+    ));
+    let main = &ast.functions[1];
+    assert!(matches!(
+        &main.return_type,
+        Type::Result { ok_type, .. } if matches!(ok_type.as_ref(), Type::Void)
+    ));
+    assert!(matches!(
+        &main.body[0],
+        Stmt::Let { value: Expr::Try { expr }, .. }
+            if matches!(expr.as_ref(), Expr::FunctionCall { name, .. } if name == "check")
+    ));
+    // The unit in `Ok(())` carries nothing
+    assert!(matches!(
+        &main.body[1],
+        Stmt::Return(Some(Expr::FunctionCall { name, args })) if name == "Ok" && args.is_empty()
+    ));
+}
+
+#[test]
+fn test_option_parsing() {
+    let source = r#"
+        fn lookup(key: &str) -> Option<String> {
+            return None;
+        }
+        fn main() {
+            if let Some(prefix) = lookup("prefix") {
+                let p = prefix;
+            }
+            match lookup("mode") {
+                Some(_) => {}
+                None => {}
+            }
+            let dir = lookup("dir").unwrap_or_else(|| "/usr/local");
+        }
+    "#;
+    let ast = parse(source).unwrap();
+
+    assert!(matches!(
+        &ast.functions[0].return_type,
+        Type::Option { inner_type } if matches!(inner_type.as_ref(), Type::Str)
+    ));
+    let body = &ast.functions[1].body;
+    // `if let` becomes a match with a fallback arm
+    match body[0].unspanned() {
+        Stmt::Match { arms, .. } => {
+            assert!(matches!(
+                &arms[0].pattern,
+                Pattern::Some(inner) if matches!(inner.as_ref(), Pattern::Variable(name) if name == "prefix")
+            ));
+            assert!(matches!(arms[1].pattern, Pattern::Wildcard));
+            assert!(arms[1].body.is_empty());
+        }
+        other => panic!("Expected a match, got {other:?}"),
+    }
+    match body[1].unspanned() {
+        Stmt::Match { arms, .. } => assert!(matches!(arms[1].pattern, Pattern::None)),
+        other => panic!("Expected a match, got {other:?}"),
+    }
+    // The closure's body is the fallback value
+    assert!(matches!(
+        body[2].unspanned(),
+        Stmt::Let { value: Expr::MethodCall { method, args, .. }, .. }
+            if method == "unwrap_or_else" && matches!(args[0], Expr::Literal(Literal::Str(_)))
+    ));
+}
+
+#[test]
+fn test_option_of_list_is_rejected() {
+    let err = parse("fn f() -> Option<Vec<String>> { return None; } fn main() {}").unwrap_err();
+    assert!(err.to_string().contains("options can only hold"), "{err}");
+}
+
+#[test]
+fn test_assignment_parsing() {
+    let source = r#"
+        fn main() {
+            let mut total = 0;
+            let step = 2;
+            total = step;
+            total += 1;
+        }
+    "#;
+    let ast = parse(source).unwrap();
+    let body = &ast.functions[0].body;
+
+    assert!(matches!(
+        body[0].unspanned(),
+        Stmt::Let { mutable: true, .. }
+    ));
+    assert!(matches!(
+        body[1].unspanned(),
+        Stmt::Let { mutable: false, .. }
+    ));
+    assert!(matches!(
+        body[2].unspanned(),
+        Stmt::Assign { name, value: Expr::Variable(v) } if name == "total" && v == "step"
+    ));
+    // `+=` reads the variable it assigns
+    assert!(matches!(
+        body[3].unspanned(),
+        Stmt::Assign { name, value: Expr::Binary { op: BinaryOp::Add, left, .. } }
+            if name == "total" && matches!(left.as_ref(), Expr::Variable(v) if v == "total")
+    ));
+}
+
+#[test]
+fn test_assignment_to_non_variable_is_rejected() {
+    let err = parse("fn main() { let mut v = vec![1]; v[0] = 2; }").unwrap_err();
+    assert!(
+        err.to_string().contains("only variables can be assigned"),
+        "{err}"
+    );
+}
@@ -77,6 +77,7 @@ impl CrossValidationTester {
                 optimize: true,
                 strict_mode: false,
                 validation_level: None,
+                effects: crate::ir::EffectPolicy::default(),
             },
             Config {
                 target: ShellDialect::Bash,
@@ -85,6 +86,7 @@ impl CrossValidationTester {
                 optimize: true,
                 strict_mode: false,
                 validation_level: None,
+                effects: crate::ir::EffectPolicy::default(),
             },
             Config {
                 target: ShellDialect::Dash,
@@ -93,6 +95,7 @@ impl CrossValidationTester {
                 optimize: false,
                 strict_mode: false,
                 validation_level: None,
+                effects: crate::ir::EffectPolicy::default(),
             },
         ];
 
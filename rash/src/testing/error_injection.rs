@@ -171,13 +171,13 @@ impl ErrorInjectionTester {
             "fn main() { let x = y }",                 // Undefined variable
             "struct Foo {}",                           // Unsupported construct
             "impl Foo {}",                             // Unsupported construct
-            "fn main() { loop {} }",                   // Unsupported loop
-            "fn main() { while true {} }",             // Unsupported while
-            "fn main() { for i in 0..10 {} }",         // Unsupported for
+            "fn main() { 'a: loop {} }",               // Unsupported labeled loop
+            "fn main() { while let x = y {} }",        // Unsupported while let
+            "fn main() { for i in 0.. {} }",           // Unbounded range
             "fn main() { match x {} }",                // Unsupported match
             "use std::collections::HashMap;",          // Unsupported use
-            "fn main() { let x: Vec<u32> = vec![]; }", // Unsupported types
-            "fn main() { println!(\"hello\"); }",      // Unsupported macros
+            "fn main() { let x: HashSet<u32> = s; }",  // Unsupported types
+            "fn main() { assert!(true); }",            // Unsupported macros
             "fn main() { unsafe { } }",                // Unsupported unsafe
             "async fn main() {}",                      // Unsupported async
             "fn main<T>() {}",                         // Unsupported generics
@@ -191,7 +191,12 @@ impl ErrorInjectionTester {
 
             // All of these should fail gracefully with parse/validation errors
             match result {
-                Err(Error::Parse(_)) | Err(Error::Validation(_)) => {
+                Err(e)
+                    if matches!(
+                        e.unlocated(),
+                        Error::Parse(_) | Error::Validation(_) | Error::Diagnostic(_)
+                    ) =>
+                {
                     results.graceful_failures += 1;
                 }
                 Ok(_) => {
@@ -242,7 +247,7 @@ impl ErrorInjectionTester {
             results.total_injections += 1;
 
             match result {
-                Err(Error::Validation(_)) => {
+                Err(Error::Diagnostic(_)) => {
                     results.graceful_failures += 1;
                 }
                 Ok(_) => {
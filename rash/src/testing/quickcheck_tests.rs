@@ -113,8 +113,11 @@ pub mod generators {
 
     pub fn simple_stmt() -> impl Strategy<Value = Stmt> {
         prop_oneof![
-            (any_valid_identifier(), simple_expr())
-                .prop_map(|(name, value)| Stmt::Let { name, value }),
+            (any_valid_identifier(), simple_expr()).prop_map(|(name, value)| Stmt::Let {
+                name,
+                value,
+                mutable: false
+            }),
             simple_expr().prop_map(Stmt::Expr),
             prop::option::of(simple_expr()).prop_map(Stmt::Return),
         ]
@@ -131,6 +134,7 @@ pub mod generators {
                 params: vec![], // Keep params simple for now
                 return_type,
                 body,
+                effects: None,
             })
     }
 
@@ -150,6 +154,7 @@ pub mod generators {
             RestrictedAst {
                 functions,
                 entry_point: "main".to_string(),
+                constants: vec![],
             }
         })
     }
@@ -177,6 +182,7 @@ pub mod generators {
                 optimize,
                 validation_level: Some(crate::validation::ValidationLevel::Minimal),
                 strict_mode: false,
+                effects: crate::ir::EffectPolicy::default(),
             })
     }
 }
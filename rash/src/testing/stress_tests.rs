@@ -11,6 +11,7 @@ fn get_test_config() -> Config {
         optimize: true,
         strict_mode: false,
         validation_level: None,
+        effects: crate::ir::EffectPolicy::default(),
     }
 }
 
@@ -110,6 +111,7 @@ fn test_stress_tester_with_different_configs() {
             optimize: true,
             strict_mode: false,
             validation_level: None,
+            effects: crate::ir::EffectPolicy::default(),
         },
         Config {
             target: ShellDialect::Bash,
@@ -118,6 +120,7 @@ fn test_stress_tester_with_different_configs() {
             optimize: false,
             strict_mode: false,
             validation_level: None,
+            effects: crate::ir::EffectPolicy::default(),
         },
         Config {
             target: ShellDialect::Dash,
@@ -126,6 +129,7 @@ fn test_stress_tester_with_different_configs() {
             optimize: true,
             strict_mode: false,
             validation_level: None,
+            effects: crate::ir::EffectPolicy::default(),
         },
     ];
 
@@ -1,12 +1,14 @@
 use super::{ValidationError, ValidationLevel};
 use crate::ast::RestrictedAst;
 use crate::ir::ShellIR;
-use crate::models::config::Config;
+use crate::models::config::{Config, ShellDialect};
 use crate::models::error::{RashError, RashResult};
+use std::process::{Command, Stdio};
 
 pub struct ValidationPipeline {
     pub(crate) level: ValidationLevel,
     pub(crate) strict_mode: bool,
+    pub(crate) target: ShellDialect,
 }
 
 impl ValidationPipeline {
@@ -14,6 +16,7 @@ impl ValidationPipeline {
         Self {
             level: config.validation_level.unwrap_or_default(),
             strict_mode: config.strict_mode,
+            target: config.target,
         }
     }
 
@@ -39,17 +42,58 @@ impl ValidationPipeline {
         self.validate_ir_recursive(ir)
     }
 
-    pub fn validate_output(&self, _shell_script: &str) -> RashResult<()> {
+    pub fn validate_output(&self, shell_script: &str) -> RashResult<()> {
         if self.level == ValidationLevel::None {
             return Ok(());
         }
 
         #[cfg(debug_assertions)]
-        self.verify_with_embedded_rules(_shell_script)?;
+        self.verify_with_embedded_rules(shell_script)?;
+
+        if self.level == ValidationLevel::Paranoid {
+            self.verify_with_shellcheck(shell_script)?;
+        }
 
         Ok(())
     }
 
+    /// `shellcheck` for the target dialect, reading the script from stdin
+    pub(crate) fn shellcheck_command(&self) -> Command {
+        let mut command = Command::new("shellcheck");
+        command
+            .args(["-s", self.target.shellcheck_shell(), "-S", "warning", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        command
+    }
+
+    /// Run the script through the `shellcheck` binary, when one is installed
+    fn verify_with_shellcheck(&self, script: &str) -> RashResult<()> {
+        let checked = self.shellcheck_command().spawn().and_then(|mut child| {
+            use std::io::Write;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(script.as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+        let output = match checked {
+            Ok(output) => output,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("shellcheck not installed; skipping external validation");
+                return Ok(());
+            }
+            Err(error) => return Err(error.into()),
+        };
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(RashError::ValidationError(format!(
+            "shellcheck -s {} rejected the generated script:\n{}",
+            self.target.shellcheck_shell(),
+            String::from_utf8_lossy(&output.stdout)
+        )))
+    }
+
     fn validate_stmt(&self, stmt: &crate::ast::Stmt) -> RashResult<()> {
         use crate::ast::Stmt;
 
@@ -75,6 +119,29 @@ impl ValidationPipeline {
                     }
                 }
             }
+            Stmt::For { iter, body, .. } => {
+                self.validate_expr(iter)?;
+                self.validate_block_statements(body)?;
+            }
+            Stmt::While {
+                condition, body, ..
+            } => {
+                self.validate_expr(condition)?;
+                self.validate_block_statements(body)?;
+            }
+            Stmt::Match { scrutinee, arms } => {
+                self.validate_expr(scrutinee)?;
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.validate_expr(guard)?;
+                    }
+                    self.validate_block_statements(&arm.body)?;
+                }
+            }
+            Stmt::Block(stmts) => self.validate_block_statements(stmts)?,
+            Stmt::Spanned { stmt, .. } => {
+                self.validate_stmt(stmt)?;
+            }
             _ => {}
         }
         Ok(())
@@ -98,6 +165,9 @@ impl ValidationPipeline {
             Expr::Index { object, index } => self.validate_index_expr(object, index),
             Expr::Try { expr } => self.validate_expr(expr),
             Expr::Block(stmts) => self.validate_block_statements(stmts),
+            Expr::Range { start, end, .. } => self.validate_binary_expr(start, end),
+            Expr::Format(parts) => self.validate_array_items(parts),
+            Expr::Print { message, .. } => self.validate_expr(message),
         }
     }
 
@@ -180,7 +250,13 @@ impl ValidationPipeline {
 
     fn validate_ir_recursive(&self, ir: &ShellIR) -> RashResult<()> {
         match ir {
-            ShellIR::Let { value, .. } => {
+            ShellIR::Let { value, .. }
+            | ShellIR::Assign { value, .. }
+            | ShellIR::Global { value, .. }
+            | ShellIR::Try { call: value, .. }
+            | ShellIR::LetOption {
+                value: Some(value), ..
+            } => {
                 self.validate_shell_value(value)?;
             }
             ShellIR::Exec { cmd, .. } => {
@@ -188,6 +264,11 @@ impl ValidationPipeline {
                     self.validate_shell_value(arg)?;
                 }
             }
+            ShellIR::Printf { args, .. } => {
+                for arg in args {
+                    self.validate_shell_value(arg)?;
+                }
+            }
             ShellIR::If {
                 test,
                 then_branch,
@@ -199,12 +280,55 @@ impl ValidationPipeline {
                     self.validate_ir_recursive(else_b)?;
                 }
             }
+            ShellIR::For { iter, body, .. } => {
+                match iter {
+                    crate::ir::ForIter::Range { start, end, .. } => {
+                        self.validate_shell_value(start)?;
+                        self.validate_shell_value(end)?;
+                    }
+                    crate::ir::ForIter::Words(words) => {
+                        for word in words {
+                            self.validate_shell_value(word)?;
+                        }
+                    }
+                }
+                self.validate_ir_recursive(body)?;
+            }
+            ShellIR::While { test, body, .. } => {
+                self.validate_shell_value(test)?;
+                self.validate_ir_recursive(body)?;
+            }
+            ShellIR::Function { body, .. } => {
+                self.validate_ir_recursive(body)?;
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                self.validate_shell_value(scrutinee)?;
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.validate_shell_value(guard)?;
+                    }
+                    self.validate_ir_recursive(&arm.body)?;
+                }
+            }
             ShellIR::Sequence(irs) => {
                 for ir in irs {
                     self.validate_ir_recursive(ir)?;
                 }
             }
-            ShellIR::Exit { .. } | ShellIR::Noop => {}
+            ShellIR::Spanned { inner, .. } => {
+                self.validate_ir_recursive(inner)?;
+            }
+            ShellIR::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.validate_shell_value(value)?;
+                }
+            }
+            ShellIR::UnpackList { .. }
+            | ShellIR::LetOption { value: None, .. }
+            | ShellIR::Exit { .. }
+            | ShellIR::Break
+            | ShellIR::Continue
+            | ShellIR::Noop => {}
         }
         Ok(())
     }
@@ -219,18 +343,37 @@ impl ValidationPipeline {
                     // This is a simplified check - real implementation would check context
                 }
             }
-            ShellValue::CommandSubst(cmd) => {
+            ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => {
                 if cmd.program.contains('`') && self.level >= ValidationLevel::Minimal {
                     return Err(RashError::ValidationError(
                         "Use $(...) instead of backticks (SC2006)".to_string(),
                     ));
                 }
             }
-            ShellValue::Concat(parts) => {
+            ShellValue::Concat(parts) | ShellValue::List(parts) => {
                 for part in parts {
                     self.validate_shell_value(part)?;
                 }
             }
+            ShellValue::FunctionCall { args, .. } => {
+                for arg in args {
+                    self.validate_shell_value(arg)?;
+                }
+            }
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::Comparison { left, right, .. }
+            | ShellValue::Logical { left, right, .. }
+            | ShellValue::UnwrapOr {
+                option: left,
+                default: right,
+            } => {
+                self.validate_shell_value(left)?;
+                self.validate_shell_value(right)?;
+            }
+            ShellValue::Not(operand)
+            | ShellValue::ChangeCase { value: operand, .. }
+            | ShellValue::ListItem { index: operand, .. }
+            | ShellValue::IsSome(operand) => self.validate_shell_value(operand)?,
             _ => {}
         }
         Ok(())
@@ -28,6 +28,7 @@ mod tests {
         let ast = RestrictedAst {
             functions: vec![],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
@@ -44,15 +45,38 @@ mod tests {
                     Stmt::Let {
                         name: "x".to_string(),
                         value: Expr::Literal(Literal::U32(42)),
+                        mutable: false,
                     },
                     Stmt::Expr(Expr::Variable("x".to_string())),
                 ],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
 
+    #[test]
+    fn test_shellcheck_runs_for_target_dialect() {
+        for (target, shell) in [
+            (crate::models::ShellDialect::Posix, "sh"),
+            (crate::models::ShellDialect::Bash, "bash"),
+        ] {
+            let config = Config {
+                target,
+                ..Config::default()
+            };
+            let pipeline = pipeline::ValidationPipeline::new(&config);
+            let command = pipeline.shellcheck_command();
+
+            assert_eq!(command.get_program(), "shellcheck");
+            let args: Vec<_> = command.get_args().collect();
+            assert_eq!(args[..2], ["-s", shell]);
+            assert_eq!(args.last().copied(), Some(std::ffi::OsStr::new("-")));
+        }
+    }
+
     #[test]
     fn test_validate_ir_none_level() {
         let pipeline = create_test_pipeline(ValidationLevel::None, false);
@@ -210,14 +234,18 @@ mod tests {
                     then_block: vec![Stmt::Let {
                         name: "x".to_string(),
                         value: Expr::Literal(Literal::U32(1)),
+                        mutable: false,
                     }],
                     else_block: Some(vec![Stmt::Let {
                         name: "y".to_string(),
                         value: Expr::Literal(Literal::U32(2)),
+                        mutable: false,
                     }]),
                 }],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
@@ -377,8 +405,10 @@ mod tests {
                 params: vec![],
                 return_type: crate::ast::Type::Void,
                 body: vec![Stmt::Expr(Expr::Variable("".to_string()))],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         let result = pipeline.validate_ast(&ast);
         assert!(result.is_err());
@@ -397,8 +427,10 @@ mod tests {
                 params: vec![],
                 return_type: crate::ast::Type::Void,
                 body: vec![Stmt::Expr(Expr::Variable("var name".to_string()))],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         let result = pipeline.validate_ast(&ast);
         assert!(result.is_err());
@@ -420,8 +452,10 @@ mod tests {
                     name: "".to_string(),
                     args: vec![],
                 })],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         let result = pipeline.validate_ast(&ast);
         assert!(result.is_err());
@@ -444,8 +478,10 @@ mod tests {
                     method: "".to_string(),
                     args: vec![],
                 })],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         let result = pipeline.validate_ast(&ast);
         assert!(result.is_err());
@@ -474,9 +510,12 @@ mod tests {
                             right: Box::new(Expr::Variable("x".to_string())),
                         }),
                     },
+                    mutable: false,
                 }],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
@@ -498,9 +537,12 @@ mod tests {
                             operand: Box::new(Expr::Variable("x".to_string())),
                         }),
                     },
+                    mutable: false,
                 }],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
@@ -521,8 +563,10 @@ mod tests {
                         Expr::Variable("arg2".to_string()),
                     ],
                 })],
+                effects: None,
             }],
             entry_point: "main".to_string(),
+            constants: vec![],
         };
         assert!(pipeline.validate_ast(&ast).is_ok());
     }
@@ -225,8 +225,10 @@ fn test_validation_pipeline_integration() {
                 )],
             })],
             return_type: crate::ast::Type::Void,
+            effects: None,
         }],
         entry_point: "main".to_string(),
+        constants: vec![],
     };
     assert!(pipeline.validate_ast(&ast).is_ok());
 
@@ -0,0 +1,567 @@
+//! Idempotency of scripts that run more than once
+//!
+//! Installers get re-run, so a command that fails when its work is already
+//! done (`mkdir` of an existing directory, `rm` of a removed file) must either
+//! take the flag that makes it succeed anyway (`mkdir -p`, `rm -f`, `ln -sf`)
+//! or only run where a test has shown it can: inside an `if` on the path, or
+//! after an `if` that leaves the script otherwise. The analysis walks the IR
+//! in execution order, tracking which paths are known to exist or to be
+//! absent at each point.
+
+use crate::ast::Span;
+use crate::ir::{Command, ComparisonOp, LogicalOp, ShellIR, ShellValue};
+use crate::models::{Diagnostic, Error, Result};
+use std::collections::{HashMap, HashSet};
+
+/// A path as the script spells it, with `${name}` for each variable
+type Path = String;
+
+/// What a test has established about a path
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Fact {
+    Exists(Path),
+    Absent(Path),
+}
+
+/// Whether a command needs its paths to exist or to be absent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Requirement {
+    Exists,
+    Absent,
+}
+
+/// Which operands a command fails on
+#[derive(Debug, Clone, Copy)]
+enum Targets {
+    All,
+    Last,
+    AllButLast,
+}
+
+/// A command that fails when its work is already done
+struct Operation {
+    program: &'static str,
+    /// The short and long spelling of the flag that makes it succeed anyway
+    flag: Option<(char, &'static str)>,
+    /// Short options that take a value
+    valued: &'static str,
+    targets: Targets,
+    requires: Requirement,
+}
+
+const OPERATIONS: &[Operation] = &[
+    Operation {
+        program: "mkdir",
+        flag: Some(('p', "--parents")),
+        valued: "m",
+        targets: Targets::All,
+        requires: Requirement::Absent,
+    },
+    Operation {
+        program: "rm",
+        flag: Some(('f', "--force")),
+        valued: "",
+        targets: Targets::All,
+        requires: Requirement::Exists,
+    },
+    Operation {
+        program: "rmdir",
+        flag: None,
+        valued: "",
+        targets: Targets::All,
+        requires: Requirement::Exists,
+    },
+    Operation {
+        program: "ln",
+        flag: Some(('f', "--force")),
+        valued: "tS",
+        targets: Targets::Last,
+        requires: Requirement::Absent,
+    },
+    Operation {
+        program: "mv",
+        flag: None,
+        valued: "tS",
+        targets: Targets::AllButLast,
+        requires: Requirement::Exists,
+    },
+];
+
+/// Verify that every command that fails when repeated is made safe to repeat
+pub fn verify(ir: &ShellIR) -> Result<()> {
+    let mut functions = HashSet::new();
+    collect_functions(ir, &mut functions);
+    let mut analysis = Analysis {
+        functions,
+        conditions: HashMap::new(),
+        span: None,
+        loop_exits: Vec::new(),
+    };
+    analysis.statement(ir, Some(HashSet::new()))?;
+    Ok(())
+}
+
+fn collect_functions(ir: &ShellIR, functions: &mut HashSet<String>) {
+    match ir {
+        ShellIR::Function { name, .. } => {
+            functions.insert(name.clone());
+        }
+        ShellIR::Sequence(items) => {
+            for item in items {
+                collect_functions(item, functions);
+            }
+        }
+        ShellIR::Spanned { inner, .. } => collect_functions(inner, functions),
+        _ => {}
+    }
+}
+
+/// Facts holding at a point of the script, or `None` where it is unreachable
+type State = Option<HashSet<Fact>>;
+
+struct Analysis {
+    /// Functions the script defines, whose calls may change any path
+    functions: HashSet<String>,
+    /// Conditions bound to variables, which `if` tests through the variable
+    conditions: HashMap<String, ShellValue>,
+    /// Source location of the statement being analyzed
+    span: Option<Span>,
+    /// For each enclosing loop, the facts holding where `break` and
+    /// `continue` leave its body
+    loop_exits: Vec<Vec<HashSet<Fact>>>,
+}
+
+impl Analysis {
+    /// The facts holding after `ir` runs with `state` holding before it
+    fn statement(&mut self, ir: &ShellIR, state: State) -> Result<State> {
+        let Some(mut facts) = state else {
+            return Ok(None);
+        };
+        match ir {
+            ShellIR::Exec { cmd, .. } => {
+                if self.functions.contains(&cmd.program) {
+                    self.forget_everything(&mut facts);
+                } else {
+                    if let Some(violation) = check(cmd, &facts) {
+                        return Err(self.report(violation));
+                    }
+                    self.record(cmd, &mut facts);
+                }
+            }
+            ShellIR::Let { name, value, .. } => {
+                self.evaluate(value, &mut facts);
+                forget_variable(name, &mut facts);
+                self.conditions.insert(name.clone(), value.clone());
+            }
+            ShellIR::Assign { name, value, .. } => {
+                self.evaluate(value, &mut facts);
+                forget_variable(name, &mut facts);
+                self.conditions.remove(name);
+            }
+            ShellIR::LetOption { name, value, .. } => {
+                if let Some(value) = value {
+                    self.evaluate(value, &mut facts);
+                }
+                forget_variable(name, &mut facts);
+                self.conditions.remove(name);
+            }
+            ShellIR::Try { binding, call, .. } => {
+                self.evaluate(call, &mut facts);
+                if let Some(name) = binding {
+                    forget_variable(name, &mut facts);
+                    self.conditions.remove(name);
+                }
+            }
+            ShellIR::Printf { args, .. } => {
+                for arg in args {
+                    self.evaluate(arg, &mut facts);
+                }
+            }
+            ShellIR::If {
+                test,
+                then_branch,
+                else_branch,
+            } => {
+                self.evaluate(test, &mut facts);
+                let mut then_facts = facts.clone();
+                then_facts.extend(self.test_facts(test, true));
+                let mut else_facts = facts;
+                else_facts.extend(self.test_facts(test, false));
+
+                let then_out = self.statement(then_branch, Some(then_facts))?;
+                let else_out = match else_branch {
+                    Some(else_branch) => self.statement(else_branch, Some(else_facts))?,
+                    None => Some(else_facts),
+                };
+                return Ok(join(then_out, else_out));
+            }
+            ShellIR::For { var, body, .. } => {
+                forget_variable(var, &mut facts);
+                return self.repeat(body, facts, None);
+            }
+            ShellIR::While { test, body, .. } => {
+                self.evaluate(test, &mut facts);
+                return self.repeat(body, facts, Some(test));
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                self.evaluate(scrutinee, &mut facts);
+                let mut out = Some(facts.clone());
+                for arm in arms {
+                    let arm_out = self.statement(&arm.body, Some(facts.clone()))?;
+                    out = join(out, arm_out);
+                }
+                return Ok(out);
+            }
+            ShellIR::Function { body, .. } => {
+                // Functions run wherever they are called, knowing nothing
+                let conditions = std::mem::take(&mut self.conditions);
+                self.statement(body, Some(HashSet::new()))?;
+                self.conditions = conditions;
+            }
+            ShellIR::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.evaluate(value, &mut facts);
+                }
+                return Ok(None);
+            }
+            ShellIR::Break | ShellIR::Continue => {
+                if let Some(exits) = self.loop_exits.last_mut() {
+                    exits.push(facts);
+                }
+                return Ok(None);
+            }
+            ShellIR::Exit { .. } => return Ok(None),
+            ShellIR::Sequence(items) => {
+                let mut state = Some(facts);
+                for item in items {
+                    state = self.statement(item, state)?;
+                }
+                return Ok(state);
+            }
+            ShellIR::Spanned { span, inner } => {
+                let outer = self.span.replace(*span);
+                let state = self.statement(inner, Some(facts));
+                self.span = outer;
+                return state;
+            }
+            ShellIR::Global { .. } | ShellIR::UnpackList { .. } | ShellIR::Noop => {}
+        }
+        Ok(Some(facts))
+    }
+
+    /// The facts holding after a loop, whose body sees only the facts that
+    /// survive every iteration
+    fn repeat(
+        &mut self,
+        body: &ShellIR,
+        entry: HashSet<Fact>,
+        test: Option<&ShellValue>,
+    ) -> Result<State> {
+        let mut head = entry;
+        loop {
+            let mut body_facts = head.clone();
+            if let Some(test) = test {
+                body_facts.extend(self.test_facts(test, true));
+            }
+            self.loop_exits.push(Vec::new());
+            let out = self.statement(body, Some(body_facts));
+            let exits = self.loop_exits.pop().unwrap_or_default();
+            let mut next = head.clone();
+            for facts in out?.iter().chain(&exits) {
+                next.retain(|fact| facts.contains(fact));
+            }
+            if next.len() == head.len() {
+                return Ok(Some(head));
+            }
+            head = next;
+        }
+    }
+
+    /// Account for the calls evaluating `value` makes
+    fn evaluate(&mut self, value: &ShellValue, facts: &mut HashSet<Fact>) {
+        let mut commands = Vec::new();
+        value.collect_commands(&mut commands);
+        if commands.iter().any(|name| self.functions.contains(name)) {
+            self.forget_everything(facts);
+        }
+    }
+
+    /// The facts `test` establishes when it evaluates to `outcome`
+    fn test_facts(&self, test: &ShellValue, outcome: bool) -> Vec<Fact> {
+        match test {
+            ShellValue::Not(inner) => self.test_facts(inner, !outcome),
+            ShellValue::Logical { op, left, right } => match (op, outcome) {
+                (LogicalOp::And, true) | (LogicalOp::Or, false) => {
+                    let mut facts = self.test_facts(left, outcome);
+                    facts.extend(self.test_facts(right, outcome));
+                    facts
+                }
+                _ => Vec::new(),
+            },
+            ShellValue::Variable(name) => match self.conditions.get(name) {
+                Some(condition) => self.test_facts(condition, outcome),
+                None => Vec::new(),
+            },
+            ShellValue::Comparison {
+                op: ComparisonOp::StrEq,
+                left,
+                right,
+            } if matches!(&**right, ShellValue::String(s) if s == "true") => {
+                self.test_facts(left, outcome)
+            }
+            ShellValue::FunctionCall { name, args, .. } => match (name.as_str(), &args[..]) {
+                ("rash_file_exists", [path]) => path_test("-f", path, outcome),
+                ("rash_dir_exists", [path]) => path_test("-d", path, outcome),
+                _ => Vec::new(),
+            },
+            ShellValue::CommandStatus(cmd) => {
+                let mut args: Vec<&ShellValue> = cmd.args.iter().collect();
+                match cmd.program.as_str() {
+                    "test" => {}
+                    "[" if args.last().is_some_and(|last| is_literal(last, "]")) => {
+                        args.pop();
+                    }
+                    _ => return Vec::new(),
+                }
+                let mut outcome = outcome;
+                if args.first().is_some_and(|first| is_literal(first, "!")) {
+                    args.remove(0);
+                    outcome = !outcome;
+                }
+                match args[..] {
+                    [ShellValue::String(operator), path] => path_test(operator, path, outcome),
+                    _ => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Update `facts` with the paths `cmd` creates or removes
+    fn record(&mut self, cmd: &Command, facts: &mut HashSet<Fact>) {
+        let invocation = Invocation::parse(cmd, "mtS");
+        let paths: Vec<Option<Path>> = invocation.operands.iter().map(|v| path(v)).collect();
+        let (created, removed): (&[Option<Path>], &[Option<Path>]) = match cmd.program.as_str() {
+            "mkdir" | "rash_mkdir_p" | "touch" => (&paths, &[]),
+            "rm" | "rmdir" => (&[], &paths),
+            "ln" | "cp" => (last(&paths), &[]),
+            "mv" => match paths.split_last() {
+                Some((_, sources)) => (last(&paths), sources),
+                None => (&[], &[]),
+            },
+            _ => return,
+        };
+        for path in created.iter().flatten() {
+            facts.remove(&Fact::Absent(path.clone()));
+            facts.insert(Fact::Exists(path.clone()));
+        }
+        for path in removed.iter().flatten() {
+            facts.remove(&Fact::Exists(path.clone()));
+            facts.insert(Fact::Absent(path.clone()));
+        }
+        // Conditions tested earlier may no longer hold
+        self.conditions.clear();
+    }
+
+    fn forget_everything(&mut self, facts: &mut HashSet<Fact>) {
+        facts.clear();
+        self.conditions.clear();
+    }
+
+    fn report(&self, violation: Violation) -> Error {
+        let diagnostic = Diagnostic::new("E0400", violation.message).with_help(violation.help);
+        match self.span {
+            Some(span) => diagnostic.with_primary(span, violation.label).into(),
+            None => diagnostic.into(),
+        }
+    }
+}
+
+/// Facts holding where two paths of execution meet
+fn join(first: State, second: State) -> State {
+    match (first, second) {
+        (Some(first), Some(second)) => Some(first.intersection(&second).cloned().collect()),
+        (Some(facts), None) | (None, Some(facts)) => Some(facts),
+        (None, None) => None,
+    }
+}
+
+fn forget_variable(name: &str, facts: &mut HashSet<Fact>) {
+    let variable = format!("${{{name}}}");
+    facts.retain(|fact| match fact {
+        Fact::Exists(path) | Fact::Absent(path) => !path.contains(&variable),
+    });
+}
+
+fn last<T>(items: &[T]) -> &[T] {
+    &items[items.len().saturating_sub(1)..]
+}
+
+/// The facts a `test` of `path` with `operator` establishes. Any passing test
+/// shows the path exists; only a failing type test shows it is absent.
+fn path_test(operator: &str, value: &ShellValue, outcome: bool) -> Vec<Fact> {
+    let Some(path) = path(value) else {
+        return Vec::new();
+    };
+    match (operator, outcome) {
+        ("-e" | "-f" | "-d" | "-L" | "-h" | "-s" | "-r" | "-w" | "-x", true) => {
+            vec![Fact::Exists(path)]
+        }
+        ("-e" | "-f" | "-d" | "-L" | "-h", false) => vec![Fact::Absent(path)],
+        _ => Vec::new(),
+    }
+}
+
+/// The path `value` spells, if it is made of literals and variables
+fn path(value: &ShellValue) -> Option<Path> {
+    let spelled = match value {
+        ShellValue::String(s) => s.clone(),
+        ShellValue::Variable(name) | ShellValue::EnvVar(name) => format!("${{{name}}}"),
+        ShellValue::Arg(n) => format!("${{{n}}}"),
+        ShellValue::Concat(parts) => parts.iter().map(path).collect::<Option<String>>()?,
+        _ => return None,
+    };
+    // `dir/` and `dir` are the same path
+    match spelled.strip_suffix('/') {
+        Some(trimmed) if !trimmed.is_empty() => Some(trimmed.to_string()),
+        _ => Some(spelled),
+    }
+}
+
+fn is_literal(value: &ShellValue, literal: &str) -> bool {
+    matches!(value, ShellValue::String(s) if s == literal)
+}
+
+/// A command's options and operands
+struct Invocation<'a> {
+    options: Vec<&'a str>,
+    operands: Vec<&'a ShellValue>,
+}
+
+impl<'a> Invocation<'a> {
+    /// Split `cmd`'s arguments, skipping the values of the short options in `valued`
+    fn parse(cmd: &'a Command, valued: &str) -> Self {
+        let mut options = Vec::new();
+        let mut operands = Vec::new();
+        let mut args = cmd.args.iter();
+        while let Some(arg) = args.next() {
+            match arg {
+                ShellValue::String(s) if s == "--" => {
+                    operands.extend(args.by_ref());
+                }
+                ShellValue::String(s) if s.starts_with('-') && s.len() > 1 => {
+                    options.push(s.as_str());
+                    // A valued option at the end of a cluster takes the next argument
+                    if !s.starts_with("--") && s.ends_with(|c| valued.contains(c)) {
+                        args.next();
+                    }
+                }
+                _ => operands.push(arg),
+            }
+        }
+        Self { options, operands }
+    }
+
+    fn has_flag(&self, (short, long): (char, &str)) -> bool {
+        self.options
+            .iter()
+            .any(|option| match option.strip_prefix("--") {
+                Some(_) => *option == long,
+                None => option[1..].contains(short),
+            })
+    }
+}
+
+/// A command that is not safe to repeat
+struct Violation {
+    message: String,
+    /// Explanation shown under the command's location
+    label: String,
+    help: String,
+}
+
+/// The violation, if `cmd` is an operation that fails when repeated, lacks
+/// its idempotent flag, and runs where one of its targets is not known to be
+/// as it requires
+fn check(cmd: &Command, facts: &HashSet<Fact>) -> Option<Violation> {
+    let operation = OPERATIONS.iter().find(|op| op.program == cmd.program)?;
+    let invocation = Invocation::parse(cmd, operation.valued);
+    if operation.flag.is_some_and(|flag| invocation.has_flag(flag)) {
+        return None;
+    }
+    let targets = match operation.targets {
+        Targets::All => &invocation.operands[..],
+        Targets::Last => last(&invocation.operands),
+        Targets::AllButLast => &invocation.operands[..invocation.operands.len().saturating_sub(1)],
+    };
+    let target = targets.iter().find(|target| {
+        let Some(path) = path(target) else {
+            return true;
+        };
+        let fact = match operation.requires {
+            Requirement::Exists => Fact::Exists(path),
+            Requirement::Absent => Fact::Absent(path),
+        };
+        !facts.contains(&fact)
+    })?;
+
+    let shown = display_command(cmd, None);
+    let target = display_word(target);
+    let (label, test) = match operation.requires {
+        Requirement::Exists => (
+            format!("fails once {target} is gone"),
+            format!("[ -e {target} ]"),
+        ),
+        Requirement::Absent => (
+            format!("fails once {target} exists"),
+            format!("! [ -e {target} ]"),
+        ),
+    };
+    let help = match operation.flag {
+        Some((short, _)) => format!(
+            "use `{}`, or guard it with `if {test}; then ...; fi`",
+            display_command(cmd, Some(short))
+        ),
+        None => format!("guard it: `if {test}; then {shown}; fi`"),
+    };
+    Some(Violation {
+        message: format!("`{shown}` is not idempotent, so the script cannot safely run again"),
+        label,
+        help,
+    })
+}
+
+/// `cmd` as the script runs it, with `flag` added to its options if given
+fn display_command(cmd: &Command, flag: Option<char>) -> String {
+    let mut words = vec![cmd.program.clone()];
+    words.extend(cmd.args.iter().map(display_word));
+    if let Some(flag) = flag {
+        // Join a leading cluster of short options, as in `ln -sf`
+        match words.get_mut(1) {
+            Some(first) if first.starts_with('-') && !first.starts_with("--") => {
+                first.push(flag);
+            }
+            _ => words.insert(1, format!("-{flag}")),
+        }
+    }
+    words.join(" ")
+}
+
+/// `value` as one shell word
+fn display_word(value: &ShellValue) -> String {
+    let is_plain = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_-./=:+,@%".contains(c))
+    };
+    match value {
+        ShellValue::String(s) if is_plain(s) => s.clone(),
+        ShellValue::String(s) => format!("'{}'", s.replace('\'', r"'\''")),
+        ShellValue::Variable(name) | ShellValue::EnvVar(name) => format!("\"${name}\""),
+        ShellValue::Arg(n) if *n < 10 => format!("\"${n}\""),
+        _ => match path(value) {
+            Some(path) => format!("\"{path}\""),
+            None => "...".to_string(),
+        },
+    }
+}
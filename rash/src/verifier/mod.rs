@@ -1,4 +1,6 @@
+mod idempotency;
 pub mod properties;
+mod taint;
 
 #[cfg(kani)]
 pub mod kani_harnesses;
@@ -22,6 +24,22 @@ pub fn verify(ir: &ShellIR, level: VerificationLevel) -> Result<()> {
     }
 }
 
+/// Verify the properties the compiler checks on every script it emits: from
+/// `Strict` on, that no external input reaches a position where the shell
+/// interprets it, and at `Paranoid` that running the script twice does what
+/// running it once does. The other checks of [`verify`] are heuristics for
+/// callers that opt in.
+pub fn verify_script(ir: &ShellIR, level: VerificationLevel) -> Result<()> {
+    match level {
+        VerificationLevel::None | VerificationLevel::Basic => Ok(()),
+        VerificationLevel::Strict => properties::verify_no_tainted_input(ir),
+        VerificationLevel::Paranoid => {
+            properties::verify_no_tainted_input(ir)?;
+            properties::verify_idempotency(ir)
+        }
+    }
+}
+
 fn verify_basic(ir: &ShellIR) -> Result<()> {
     // Basic verification: check for obvious safety issues
     properties::verify_no_command_injection(ir)?;
@@ -32,6 +50,7 @@ fn verify_strict(ir: &ShellIR) -> Result<()> {
     // Strict verification: all basic checks plus determinism
     verify_basic(ir)?;
     properties::verify_deterministic(ir)?;
+    properties::verify_no_tainted_input(ir)?;
     Ok(())
 }
 
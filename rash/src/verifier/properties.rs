@@ -8,7 +8,26 @@ pub fn verify_no_command_injection(ir: &ShellIR) -> Result<()> {
             ShellIR::Exec { cmd, .. } => {
                 check_command_safety(cmd)?;
             }
-            ShellIR::Let { value, .. } => {
+            ShellIR::Let { value, .. }
+            | ShellIR::Assign { value, .. }
+            | ShellIR::Global { value, .. }
+            | ShellIR::Try { call: value, .. }
+            | ShellIR::LetOption {
+                value: Some(value), ..
+            } => {
+                check_value_safety(value)?;
+            }
+            ShellIR::Printf { args, .. } => {
+                for arg in args {
+                    check_value_safety(arg)?;
+                }
+            }
+            ShellIR::Case { scrutinee, .. } => {
+                check_value_safety(scrutinee)?;
+            }
+            ShellIR::Return {
+                value: Some(value), ..
+            } => {
                 check_value_safety(value)?;
             }
             _ => {}
@@ -17,6 +36,13 @@ pub fn verify_no_command_injection(ir: &ShellIR) -> Result<()> {
     })
 }
 
+/// Verify that no environment variable, script argument or command output
+/// reaches a position where the shell runs or evaluates it, unless it went
+/// through a `sanitize` intrinsic first
+pub fn verify_no_tainted_input(ir: &ShellIR) -> Result<()> {
+    super::taint::verify(ir)
+}
+
 /// Verify that the IR is deterministic (same inputs produce same outputs)
 pub fn verify_deterministic(ir: &ShellIR) -> Result<()> {
     walk_ir(ir, &mut |node| {
@@ -29,28 +55,37 @@ pub fn verify_deterministic(ir: &ShellIR) -> Result<()> {
                     )));
                 }
             }
-            ShellIR::Let { value, .. } => {
+            ShellIR::Let { value, .. }
+            | ShellIR::Assign { value, .. }
+            | ShellIR::Global { value, .. }
+            | ShellIR::Try { call: value, .. }
+            | ShellIR::LetOption {
+                value: Some(value), ..
+            }
+            | ShellIR::Case {
+                scrutinee: value, ..
+            }
+            | ShellIR::Return {
+                value: Some(value), ..
+            } => {
                 check_value_determinism(value)?;
             }
+            ShellIR::Printf { args, .. } => {
+                for arg in args {
+                    check_value_determinism(arg)?;
+                }
+            }
             _ => {}
         }
         Ok(())
     })
 }
 
-/// Verify that the IR represents idempotent operations
+/// Verify that the IR can run again after a complete or interrupted run:
+/// every command that fails when repeated takes its idempotent flag or is
+/// guarded by a test of the paths it touches
 pub fn verify_idempotency(ir: &ShellIR) -> Result<()> {
-    // Check for operations that could fail on second run
-    walk_ir(ir, &mut |node| {
-        if let ShellIR::Exec { cmd, .. } = node {
-            if requires_idempotency_check(&cmd.program) {
-                // Look for corresponding existence checks
-                // This is simplified - real implementation would be more sophisticated
-                check_has_idempotency_guard(ir, cmd)?;
-            }
-        }
-        Ok(())
-    })
+    super::idempotency::verify(ir)
 }
 
 /// Verify that the IR doesn't use excessive resources
@@ -97,11 +132,24 @@ where
                 walk_ir(else_ir, visitor)?;
             }
         }
+        ShellIR::For { body, .. }
+        | ShellIR::While { body, .. }
+        | ShellIR::Function { body, .. } => {
+            walk_ir(body, visitor)?;
+        }
+        ShellIR::Case { arms, .. } => {
+            for arm in arms {
+                walk_ir(&arm.body, visitor)?;
+            }
+        }
         ShellIR::Sequence(items) => {
             for item in items {
                 walk_ir(item, visitor)?;
             }
         }
+        ShellIR::Spanned { inner, .. } => {
+            walk_ir(inner, visitor)?;
+        }
         _ => {}
     }
 
@@ -134,14 +182,34 @@ fn check_value_safety(value: &ShellValue) -> Result<()> {
                 )));
             }
         }
-        ShellValue::Concat(parts) => {
+        ShellValue::Concat(parts) | ShellValue::List(parts) => {
             for part in parts {
                 check_value_safety(part)?;
             }
         }
-        ShellValue::CommandSubst(cmd) => {
+        ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => {
             check_command_safety(cmd)?;
         }
+        ShellValue::FunctionCall { args, .. } => {
+            // The callee's body is verified with the function definition
+            for arg in args {
+                check_value_safety(arg)?;
+            }
+        }
+        ShellValue::Arithmetic { left, right, .. }
+        | ShellValue::Comparison { left, right, .. }
+        | ShellValue::Logical { left, right, .. }
+        | ShellValue::UnwrapOr {
+            option: left,
+            default: right,
+        } => {
+            check_value_safety(left)?;
+            check_value_safety(right)?;
+        }
+        ShellValue::Not(operand)
+        | ShellValue::ChangeCase { value: operand, .. }
+        | ShellValue::ListItem { index: operand, .. }
+        | ShellValue::IsSome(operand) => check_value_safety(operand)?,
         _ => {}
     }
     Ok(())
@@ -149,7 +217,7 @@ fn check_value_safety(value: &ShellValue) -> Result<()> {
 
 fn check_value_determinism(value: &ShellValue) -> Result<()> {
     match value {
-        ShellValue::CommandSubst(cmd) => {
+        ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => {
             if is_nondeterministic_command(&cmd.program) {
                 return Err(Error::Verification(format!(
                     "Non-deterministic command substitution: {}",
@@ -157,11 +225,30 @@ fn check_value_determinism(value: &ShellValue) -> Result<()> {
                 )));
             }
         }
-        ShellValue::Concat(parts) => {
+        ShellValue::Concat(parts) | ShellValue::List(parts) => {
             for part in parts {
                 check_value_determinism(part)?;
             }
         }
+        ShellValue::FunctionCall { args, .. } => {
+            for arg in args {
+                check_value_determinism(arg)?;
+            }
+        }
+        ShellValue::Arithmetic { left, right, .. }
+        | ShellValue::Comparison { left, right, .. }
+        | ShellValue::Logical { left, right, .. }
+        | ShellValue::UnwrapOr {
+            option: left,
+            default: right,
+        } => {
+            check_value_determinism(left)?;
+            check_value_determinism(right)?;
+        }
+        ShellValue::Not(operand)
+        | ShellValue::ChangeCase { value: operand, .. }
+        | ShellValue::ListItem { index: operand, .. }
+        | ShellValue::IsSome(operand) => check_value_determinism(operand)?,
         _ => {}
     }
     Ok(())
@@ -213,13 +300,6 @@ fn is_nondeterministic_command(cmd: &str) -> bool {
     )
 }
 
-fn requires_idempotency_check(cmd: &str) -> bool {
-    matches!(
-        cmd,
-        "mkdir" | "cp" | "mv" | "ln" | "touch" | "curl" | "wget"
-    )
-}
-
 fn is_network_command(cmd: &str) -> bool {
     matches!(
         cmd,
@@ -245,24 +325,6 @@ fn is_file_operation(cmd: &str) -> bool {
     )
 }
 
-fn check_has_idempotency_guard(_ir: &ShellIR, cmd: &Command) -> Result<()> {
-    // This is a simplified check - real implementation would analyze the IR structure
-    // to ensure that potentially non-idempotent operations have appropriate guards
-
-    match cmd.program.as_str() {
-        "mkdir" => {
-            // Should have a test -d check
-            // For now, just warn
-            Ok(())
-        }
-        "curl" | "wget" => {
-            // Should have a test -f check for the output file
-            Ok(())
-        }
-        _ => Ok(()),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,564 @@
+//! Taint tracking from external input to positions the shell interprets
+//!
+//! Environment variables, script arguments and the output of commands
+//! (downloaded content included) are controlled by whoever runs the script.
+//! Quoting keeps them from being split or globbed, but not from being run:
+//! as code by `eval` or `sh -c`, as the program of `env`, `sudo` and the
+//! like, as an expression by `$((...))`, or as the tree `rm -r` deletes.
+//! Values are followed through bindings, function parameters and return
+//! values; the `sanitize` intrinsics clear them.
+
+use crate::ast::Span;
+use crate::ir::{Command, ForIter, ShellIR, ShellValue};
+use crate::models::{Diagnostic, Error, Result};
+use std::collections::HashMap;
+
+/// Where tainted data comes from and the bindings it went through
+#[derive(Debug, Clone)]
+struct Taint {
+    /// Description such as "the environment variable `PREFIX`"
+    source: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    span: Option<Span>,
+    label: String,
+    /// The binding the data flowed into, if any
+    binding: Option<String>,
+}
+
+impl Taint {
+    fn through(&self, span: Option<Span>, label: String, binding: Option<&str>) -> Self {
+        let mut taint = self.clone();
+        // Variables the compiler introduces are not part of the user's story
+        if binding.is_some_and(|binding| binding.starts_with("_rash_")) {
+            return taint;
+        }
+        taint.steps.push(Step {
+            span,
+            label,
+            binding: binding.map(str::to_string),
+        });
+        taint
+    }
+}
+
+/// A position where the shell interprets a value rather than passing it on
+#[derive(Debug, Clone)]
+enum Sink {
+    /// The program run by a command that runs another, such as `sudo`
+    Program(String),
+    /// Code run by `eval`, `sh -c` or `.`
+    Code(String),
+    /// An operand of `$((...))`, which the shell evaluates as an expression
+    Arithmetic,
+    /// A path removed by `rm` with a recursive flag
+    RemovedTree(String),
+}
+
+impl Sink {
+    fn description(&self) -> String {
+        match self {
+            Sink::Program(program) => format!("the program `{program}` runs"),
+            Sink::Code(runner) => format!("code run by `{runner}`"),
+            Sink::Arithmetic => "an arithmetic expansion".to_string(),
+            Sink::RemovedTree(command) => format!("the path of `{command}`"),
+        }
+    }
+
+    fn sanitizer(&self) -> &'static str {
+        match self {
+            Sink::RemovedTree(_) => "sanitize::path",
+            _ => "sanitize::word",
+        }
+    }
+}
+
+/// Commands whose first operand is another program to run, with their
+/// short options that take a value
+const PROGRAM_RUNNERS: &[(&str, &str)] = &[
+    ("env", "uCS"),
+    ("exec", ""),
+    ("command", ""),
+    ("nohup", ""),
+    ("sudo", "ugCDhprtU"),
+    ("doas", "uC"),
+    ("xargs", "aEeIiLlnPsd"),
+    ("nice", "n"),
+    ("time", ""),
+];
+
+/// Shells that run their `-c` operand as code
+const SHELLS: &[&str] = &["sh", "bash", "dash", "ash", "ksh", "zsh"];
+
+/// Refuse tainted data at any position where the shell would interpret it
+pub fn verify(ir: &ShellIR) -> Result<()> {
+    let mut analysis = Analysis::default();
+    collect_functions(ir, &mut analysis.parameters);
+    // Callers reach function parameters only once the functions have been
+    // seen, so repeat until what flows into them stops growing
+    loop {
+        analysis.changed = false;
+        analysis.statement(ir, &mut HashMap::new())?;
+        if !analysis.changed {
+            return Ok(());
+        }
+    }
+}
+
+fn collect_functions(ir: &ShellIR, parameters: &mut HashMap<String, Vec<(String, Option<Taint>)>>) {
+    match ir {
+        ShellIR::Function { name, params, .. } => {
+            let params = params.iter().map(|param| (param.clone(), None)).collect();
+            parameters.insert(name.clone(), params);
+        }
+        ShellIR::Sequence(items) => {
+            for item in items {
+                collect_functions(item, parameters);
+            }
+        }
+        ShellIR::Spanned { inner, .. } => collect_functions(inner, parameters),
+        _ => {}
+    }
+}
+
+/// Tainted variables at a point of the script
+type Variables = HashMap<String, Taint>;
+
+#[derive(Default)]
+struct Analysis {
+    /// Each function's parameters, with the taint callers pass them
+    parameters: HashMap<String, Vec<(String, Option<Taint>)>>,
+    /// Taint of the values functions return
+    returns: HashMap<String, Taint>,
+    /// Whether this pass found new taint flowing into a function
+    changed: bool,
+    /// Function whose body is being analyzed
+    function: Option<String>,
+    /// Source location of the statement being analyzed
+    span: Option<Span>,
+}
+
+impl Analysis {
+    fn statement(&mut self, ir: &ShellIR, variables: &mut Variables) -> Result<()> {
+        match ir {
+            ShellIR::Let { name, value, .. }
+            | ShellIR::Assign { name, value, .. }
+            | ShellIR::Global { name, value }
+            | ShellIR::LetOption {
+                name,
+                value: Some(value),
+                ..
+            } => {
+                self.check_value(value, variables)?;
+                self.bind(name, value, variables);
+            }
+            ShellIR::LetOption {
+                name, value: None, ..
+            } => {
+                variables.remove(name);
+            }
+            ShellIR::Try { binding, call, .. } => {
+                self.check_value(call, variables)?;
+                if let Some(name) = binding {
+                    self.bind(name, call, variables);
+                }
+            }
+            ShellIR::Exec { cmd, .. } => self.check_command(cmd, variables)?,
+            ShellIR::Printf { args, .. } => {
+                for arg in args {
+                    self.check_value(arg, variables)?;
+                }
+            }
+            ShellIR::If {
+                test,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_value(test, variables)?;
+                let mut else_variables = variables.clone();
+                self.statement(then_branch, variables)?;
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch, &mut else_variables)?;
+                }
+                merge(variables, else_variables);
+            }
+            ShellIR::For {
+                var, iter, body, ..
+            } => {
+                let mut element = None;
+                match iter {
+                    // The loop counts with `$((...))` from the bounds
+                    ForIter::Range { start, end, .. } => {
+                        for bound in [start, end] {
+                            self.refuse(bound, Sink::Arithmetic, variables)?;
+                            self.check_value(bound, variables)?;
+                        }
+                    }
+                    ForIter::Words(words) => {
+                        for word in words {
+                            self.check_value(word, variables)?;
+                            element = element.or_else(|| self.taint(word, variables));
+                        }
+                    }
+                }
+                let element = element.map(|taint| {
+                    taint.through(self.span, format!("flows into `{var}`"), Some(var))
+                });
+                self.repeat(body, variables, |variables| match &element {
+                    Some(taint) => {
+                        variables.insert(var.clone(), taint.clone());
+                    }
+                    None => {
+                        variables.remove(var);
+                    }
+                })?;
+            }
+            ShellIR::While { test, body, .. } => {
+                self.check_value(test, variables)?;
+                self.repeat(body, variables, |_| {})?;
+            }
+            ShellIR::Case { scrutinee, arms } => {
+                self.check_value(scrutinee, variables)?;
+                let entry = variables.clone();
+                for arm in arms {
+                    let mut arm_variables = entry.clone();
+                    self.statement(&arm.body, &mut arm_variables)?;
+                    merge(variables, arm_variables);
+                }
+            }
+            ShellIR::Function { name, body, .. } => {
+                let mut parameters = Variables::new();
+                for (param, taint) in &self.parameters[name] {
+                    if let Some(taint) = taint {
+                        parameters.insert(param.clone(), taint.clone());
+                    }
+                }
+                let outer = self.function.replace(name.clone());
+                let result = self.statement(body, &mut parameters);
+                self.function = outer;
+                result?;
+            }
+            ShellIR::Return {
+                value: Some(value), ..
+            } => {
+                self.check_value(value, variables)?;
+                if let (Some(function), Some(taint)) =
+                    (&self.function, self.taint(value, variables))
+                {
+                    if !self.returns.contains_key(function) {
+                        let label = format!("returned from `{function}`");
+                        let taint = taint.through(self.span, label, None);
+                        self.returns.insert(function.clone(), taint);
+                        self.changed = true;
+                    }
+                }
+            }
+            ShellIR::Sequence(items) => {
+                for item in items {
+                    self.statement(item, variables)?;
+                }
+            }
+            ShellIR::Spanned { span, inner } => {
+                let outer = self.span.replace(*span);
+                let result = self.statement(inner, variables);
+                self.span = outer;
+                result?;
+            }
+            ShellIR::Return { value: None, .. }
+            | ShellIR::UnpackList { .. }
+            | ShellIR::Break
+            | ShellIR::Continue
+            | ShellIR::Exit { .. }
+            | ShellIR::Noop => {}
+        }
+        Ok(())
+    }
+
+    /// Analyze a loop body until the variables it taints stop growing
+    fn repeat(
+        &mut self,
+        body: &ShellIR,
+        variables: &mut Variables,
+        enter: impl Fn(&mut Variables),
+    ) -> Result<()> {
+        loop {
+            let mut body_variables = variables.clone();
+            enter(&mut body_variables);
+            self.statement(body, &mut body_variables)?;
+            let tainted = variables.len();
+            merge(variables, body_variables);
+            if variables.len() == tainted {
+                return Ok(());
+            }
+        }
+    }
+
+    fn bind(&self, name: &str, value: &ShellValue, variables: &mut Variables) {
+        match self.taint(value, variables) {
+            Some(taint) => {
+                let taint = taint.through(self.span, format!("flows into `{name}`"), Some(name));
+                variables.insert(name.to_string(), taint);
+            }
+            None => {
+                variables.remove(name);
+            }
+        }
+    }
+
+    /// The taint of `value`, if it carries external input
+    fn taint(&self, value: &ShellValue, variables: &Variables) -> Option<Taint> {
+        let source = |source: String| {
+            Some(Taint {
+                steps: vec![Step {
+                    span: self.span,
+                    label: format!("{source} is read here"),
+                    binding: None,
+                }],
+                source,
+            })
+        };
+        match value {
+            ShellValue::EnvVar(name) => source(format!("the environment variable `{name}`")),
+            ShellValue::Arg(position) => source(format!("script argument {position}")),
+            ShellValue::Args => source("the script arguments".to_string()),
+            ShellValue::CommandSubst(cmd) => source(format!("the output of `{}`", cmd.program)),
+            ShellValue::Variable(name)
+            | ShellValue::ListElements(name)
+            | ShellValue::ListItem { list: name, .. } => variables.get(name).cloned(),
+            // Sanitizers and the other runtime helpers return clean values
+            ShellValue::FunctionCall { name, .. } => self.returns.get(name).cloned(),
+            ShellValue::Concat(parts) | ShellValue::List(parts) => {
+                parts.iter().find_map(|part| self.taint(part, variables))
+            }
+            ShellValue::Arithmetic { left, right, .. }
+            | ShellValue::UnwrapOr {
+                option: left,
+                default: right,
+            } => self
+                .taint(left, variables)
+                .or_else(|| self.taint(right, variables)),
+            ShellValue::ChangeCase { value, .. } => self.taint(value, variables),
+            // Conditions and counts are clean whatever they are computed from
+            ShellValue::String(_)
+            | ShellValue::Bool(_)
+            | ShellValue::CommandStatus(_)
+            | ShellValue::Comparison { .. }
+            | ShellValue::Logical { .. }
+            | ShellValue::Not(_)
+            | ShellValue::IsSome(_)
+            | ShellValue::ListLength(_) => None,
+        }
+    }
+
+    /// Refuse taint at the sinks inside `value`, and follow its calls
+    fn check_value(&mut self, value: &ShellValue, variables: &Variables) -> Result<()> {
+        match value {
+            ShellValue::Arithmetic { left, right, .. } => {
+                for operand in [left, right] {
+                    self.refuse(operand, Sink::Arithmetic, variables)?;
+                    self.check_value(operand, variables)?;
+                }
+            }
+            ShellValue::CommandSubst(cmd) | ShellValue::CommandStatus(cmd) => {
+                self.check_command(cmd, variables)?;
+            }
+            ShellValue::FunctionCall { name, args, .. } => {
+                for arg in args {
+                    self.check_value(arg, variables)?;
+                }
+                self.pass_arguments(name, args, variables);
+            }
+            ShellValue::Concat(parts) | ShellValue::List(parts) => {
+                for part in parts {
+                    self.check_value(part, variables)?;
+                }
+            }
+            ShellValue::Comparison { left, right, .. }
+            | ShellValue::Logical { left, right, .. }
+            | ShellValue::UnwrapOr {
+                option: left,
+                default: right,
+            } => {
+                self.check_value(left, variables)?;
+                self.check_value(right, variables)?;
+            }
+            // The index is expanded inside `$(( ... ))`
+            ShellValue::ListItem { index, .. } => {
+                self.refuse(index, Sink::Arithmetic, variables)?;
+                self.check_value(index, variables)?;
+            }
+            ShellValue::Not(operand)
+            | ShellValue::ChangeCase { value: operand, .. }
+            | ShellValue::IsSome(operand) => self.check_value(operand, variables)?,
+            ShellValue::String(_)
+            | ShellValue::Bool(_)
+            | ShellValue::Variable(_)
+            | ShellValue::ListElements(_)
+            | ShellValue::ListLength(_)
+            | ShellValue::EnvVar(_)
+            | ShellValue::Arg(_)
+            | ShellValue::Args => {}
+        }
+        Ok(())
+    }
+
+    fn check_command(&mut self, cmd: &Command, variables: &Variables) -> Result<()> {
+        for arg in &cmd.args {
+            self.check_value(arg, variables)?;
+        }
+        self.pass_arguments(&cmd.program, &cmd.args, variables);
+
+        let program = cmd.program.as_str();
+        let runner = PROGRAM_RUNNERS
+            .iter()
+            .find(|(runner, _)| *runner == program)
+            .map(|(_, valued)| *valued);
+        let mut options = Vec::new();
+        let mut operands = Vec::new();
+        let mut args = cmd.args.iter();
+        while let Some(arg) = args.next() {
+            match arg {
+                ShellValue::String(s) if s == "--" => break,
+                ShellValue::String(s) if s.starts_with('-') && s.len() > 1 => {
+                    options.push(s.as_str());
+                    if runner.is_some_and(|valued| {
+                        !s.starts_with("--") && s.ends_with(|c| valued.contains(c))
+                    }) {
+                        args.next();
+                    }
+                }
+                _ => {
+                    operands.push(arg);
+                    // Options after the first operand belong to the program it runs
+                    if runner.is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+        operands.extend(args);
+
+        match program {
+            "eval" => {
+                for arg in &cmd.args {
+                    self.refuse(arg, Sink::Code("eval".to_string()), variables)?;
+                }
+            }
+            "." | "source" => {
+                if let Some(script) = cmd.args.first() {
+                    self.refuse(script, Sink::Code(program.to_string()), variables)?;
+                }
+            }
+            _ if SHELLS.contains(&program) => {
+                let code = cmd
+                    .args
+                    .iter()
+                    .skip_while(|arg| !matches!(arg, ShellValue::String(s) if s == "-c"))
+                    .nth(1);
+                if let Some(code) = code {
+                    self.refuse(code, Sink::Code(format!("{program} -c")), variables)?;
+                }
+            }
+            // `command -v name` only looks the name up
+            "command" if options.iter().any(|option| option.contains(['v', 'V'])) => {}
+            _ if runner.is_some() => {
+                // `env NAME=value program`: the assignments come first
+                let run = operands.iter().find(|operand| {
+                    !matches!(operand, ShellValue::String(s) if program == "env" && s.contains('='))
+                });
+                if let Some(run) = run {
+                    self.refuse(run, Sink::Program(program.to_string()), variables)?;
+                }
+            }
+            "rm" => {
+                let recursive = options
+                    .iter()
+                    .any(|option| match option.strip_prefix("--") {
+                        Some(long) => long == "recursive",
+                        None => option.contains(['r', 'R']),
+                    });
+                if recursive {
+                    let shown = format!("rm {}", options.join(" "));
+                    for operand in operands {
+                        self.refuse(operand, Sink::RemovedTree(shown.clone()), variables)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Record the taint a call passes to a script function's parameters
+    fn pass_arguments(&mut self, function: &str, args: &[ShellValue], variables: &Variables) {
+        let taints: Vec<Option<Taint>> =
+            args.iter().map(|arg| self.taint(arg, variables)).collect();
+        let span = self.span;
+        let Some(parameters) = self.parameters.get_mut(function) else {
+            return;
+        };
+        for ((param, known), taint) in parameters.iter_mut().zip(taints) {
+            if let (None, Some(taint)) = (&known, taint) {
+                let label = format!("passed to `{function}` as `{param}`");
+                *known = Some(taint.through(span, label, Some(param)));
+                self.changed = true;
+            }
+        }
+    }
+
+    fn refuse(&self, value: &ShellValue, sink: Sink, variables: &Variables) -> Result<()> {
+        match self.taint(value, variables) {
+            Some(taint) => Err(self.report(taint, sink)),
+            None => Ok(()),
+        }
+    }
+
+    fn report(&self, taint: Taint, sink: Sink) -> Error {
+        let bindings: Vec<String> = taint
+            .steps
+            .iter()
+            .filter_map(|step| step.binding.as_ref())
+            .map(|binding| format!("`{binding}`"))
+            .collect();
+        let mut message = format!("{} reaches {}", taint.source, sink.description());
+        if !bindings.is_empty() {
+            message.push_str(&format!(" through {}", bindings.join(", ")));
+        }
+        let mut diagnostic = Diagnostic::new("E0401", message).with_help(format!(
+            "check the value with `{}(...)` first, which stops the script on unsafe input",
+            sink.sanitizer()
+        ));
+        // One label per statement, so that a value read and bound at once
+        // reads as one step
+        let mut labels: Vec<(Span, String)> = Vec::new();
+        for step in &taint.steps {
+            let Some(span) = step.span else { continue };
+            match labels.last_mut() {
+                Some((last, label)) if *last == span => {
+                    label.push_str(" and ");
+                    label.push_str(&step.label);
+                }
+                _ => labels.push((span, step.label.clone())),
+            }
+        }
+        for (span, label) in labels {
+            diagnostic = diagnostic.with_secondary(span, label);
+        }
+        match self.span {
+            Some(span) => diagnostic
+                .with_primary(span, "used here without being sanitized")
+                .into(),
+            None => diagnostic.into(),
+        }
+    }
+}
+
+/// Taint in either of two paths of execution
+fn merge(variables: &mut Variables, other: Variables) {
+    for (name, taint) in other {
+        variables.entry(name).or_insert(taint);
+    }
+}
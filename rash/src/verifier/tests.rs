@@ -1,6 +1,6 @@
 use crate::ir::{Command, Effect, EffectSet, ShellIR, ShellValue};
 use crate::models::VerificationLevel;
-use crate::verifier::verify;
+use crate::verifier::{verify, verify_script};
 
 #[test]
 fn test_verify_basic() {
@@ -154,3 +154,39 @@ fn test_verify_none_level() {
     let result = verify(&ir, VerificationLevel::None);
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_verify_script_checks_taint_from_strict_on() {
+    let source =
+        r#"fn main() { let d = env::var_or("DIR", ""); rm("-rf", d); } fn rm(a: &str, b: &str) {}"#;
+    let ast = crate::services::parse(source).unwrap();
+    let ir = crate::ir::from_ast(&ast).unwrap();
+
+    assert!(verify_script(&ir, VerificationLevel::None).is_ok());
+    assert!(verify_script(&ir, VerificationLevel::Basic).is_ok());
+    assert!(verify_script(&ir, VerificationLevel::Strict).is_err());
+    assert!(verify_script(&ir, VerificationLevel::Paranoid).is_err());
+}
+
+#[test]
+fn test_transpile_checks_idempotency_when_paranoid() {
+    let source = r#"fn main() { mkdir("/opt/app"); } fn mkdir(path: &str) {}"#;
+    let config = |verify| crate::models::Config {
+        verify,
+        ..crate::models::Config::default()
+    };
+
+    assert!(crate::transpile(source, config(VerificationLevel::Strict)).is_ok());
+    let diagnostic = crate::transpile(source, config(VerificationLevel::Paranoid))
+        .unwrap_err()
+        .to_diagnostic()
+        .unwrap();
+    assert_eq!(diagnostic.code, "E0400");
+    assert!(
+        diagnostic.message.contains("`mkdir /opt/app`"),
+        "{diagnostic:?}"
+    );
+
+    let guarded = r#"fn main() { mkdir("-p", "/opt/app"); } fn mkdir(a: &str, b: &str) {}"#;
+    assert!(crate::transpile(guarded, config(VerificationLevel::Paranoid)).is_ok());
+}
@@ -1,5 +1,5 @@
-use rash::models::config::{ShellDialect, VerificationLevel};
-use rash::{check, transpile, Config};
+use bashrs::models::config::{ShellDialect, VerificationLevel};
+use bashrs::{check, transpile, Config};
 use std::fs;
 use std::process::Command;
 use tempfile::TempDir;
@@ -145,8 +145,13 @@ fn echo(msg: &str) {}
         assert!(result.is_ok(), "Failed for dialect: {dialect:?}");
 
         let script = result.unwrap();
-        assert!(script.contains("#!/bin/sh"));
-        assert!(script.contains("readonly msg='testing dialects'"));
+        if *dialect == ShellDialect::Bash {
+            assert!(script.starts_with("#!/bin/bash"));
+            assert!(script.contains("local -r msg='testing dialects'"));
+        } else {
+            assert!(script.starts_with("#!/bin/sh"));
+            assert!(script.contains("readonly msg='testing dialects'"));
+        }
     }
 }
 
@@ -336,14 +341,10 @@ fn main() {
     let config = Config::default();
     let result = transpile(source, config).unwrap();
 
-    // Verify runtime functions are included
-    assert!(result.contains("rash_require()"));
-    assert!(result.contains("rash_download_verified()"));
-
-    // Verify they contain expected functionality
-    assert!(result.contains("curl"));
-    assert!(result.contains("sha256sum"));
-    assert!(result.contains("wget"));
+    // A script that calls no runtime functions carries none
+    assert!(!result.contains("rash_require()"));
+    assert!(!result.contains("rash_download_verified()"));
+    assert!(!result.contains("sha256sum"));
 }
 
 #[test]